@@ -1,7 +1,10 @@
 use std::borrow::Borrow;
 use std::fmt;
+use std::sync::Arc;
+use std::sync::Mutex;
 
 use elp_syntax::SmolStr;
+use fxhash::FxHashSet;
 
 use serde::{Deserialize, Serialize};
 
@@ -23,6 +26,26 @@ impl AtomName {
         Self(name.into())
     }
 
+    /// Like `new`, but names which are too long to be stored inline
+    /// share their storage with every other copy of the same name
+    /// interned in `interner`. Use this where the same names are
+    /// created many times over, such as when converting eqWAlizer
+    /// stubs.
+    pub fn interned(name: &str, interner: &AtomNameInterner) -> Self {
+        if name.len() <= AtomNameInterner::MAX_INLINE_LEN {
+            return Self(name.into());
+        }
+        let mut pool = interner.0.lock().unwrap();
+        if let Some(interned) = pool.names.get(name) {
+            let interned = interned.clone();
+            pool.saved_bytes += name.len();
+            return Self(interned.into());
+        }
+        let name: Arc<str> = Arc::from(name);
+        pool.insert(name.clone());
+        Self(name.into())
+    }
+
     /// Returns the unquoted name as a `str`.
     ///
     /// The `Display` implementation and `ToString` should be preferred. Only use this function
@@ -96,3 +119,85 @@ impl AsRef<std::ffi::OsStr> for AtomName {
         self.0.as_ref()
     }
 }
+
+/// The shared storage of interned atom names. It belongs to the
+/// database using it rather than to the process, and names which are
+/// no longer used outside of it are dropped as it grows.
+#[derive(Debug, Default)]
+pub struct AtomNameInterner(Mutex<InternedNames>);
+
+impl AtomNameInterner {
+    /// The longest name `SmolStr` stores inline, which is not worth
+    /// sharing.
+    const MAX_INLINE_LEN: usize = 22;
+
+    /// How many bytes of names were shared rather than allocated again.
+    pub fn saved_bytes(&self) -> usize {
+        self.0.lock().unwrap().saved_bytes
+    }
+}
+
+#[derive(Debug, Default)]
+struct InternedNames {
+    names: FxHashSet<Arc<str>>,
+    /// Once this many names are held, the ones only held here are
+    /// dropped.
+    prune_at: usize,
+    saved_bytes: usize,
+}
+
+impl InternedNames {
+    const MIN_PRUNE_AT: usize = 1024;
+
+    fn insert(&mut self, name: Arc<str>) {
+        if self.names.len() >= self.prune_at.max(Self::MIN_PRUNE_AT) {
+            self.names.retain(|name| Arc::strong_count(name) > 1);
+            self.prune_at = 2 * self.names.len();
+        }
+        self.names.insert(name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AtomName;
+    use super::AtomNameInterner;
+    use super::InternedNames;
+
+    #[test]
+    fn interned_names_share_storage() {
+        let name = "a_module_name_too_long_to_be_inlined";
+        let interner = AtomNameInterner::default();
+        let a = AtomName::interned(name, &interner);
+        let b = AtomName::interned(&name.to_string(), &interner);
+        assert_eq!(a, b);
+        assert_eq!(a.as_unquoted_str().as_ptr(), b.as_unquoted_str().as_ptr());
+        assert_ne!(
+            AtomName::new(name).as_unquoted_str().as_ptr(),
+            a.as_unquoted_str().as_ptr()
+        );
+        assert_eq!(interner.saved_bytes(), name.len());
+    }
+
+    #[test]
+    fn unused_interned_names_are_pruned() {
+        let interner = AtomNameInterner::default();
+        let kept = AtomName::interned("a_module_name_which_is_still_in_use", &interner);
+        for i in 0..10_000 {
+            AtomName::interned(
+                &format!("a_module_name_too_long_to_be_inlined_{i}"),
+                &interner,
+            );
+        }
+        let names = interner.0.lock().unwrap().names.len();
+        assert!(
+            names <= InternedNames::MIN_PRUNE_AT,
+            "{names} names are held"
+        );
+        let again = AtomName::interned(kept.as_unquoted_str(), &interner);
+        assert_eq!(
+            kept.as_unquoted_str().as_ptr(),
+            again.as_unquoted_str().as_ptr()
+        );
+    }
+}
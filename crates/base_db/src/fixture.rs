@@ -32,6 +32,8 @@ use elp_project_model::test_fixture::DiagnosticsEnabled;
 use elp_project_model::test_fixture::FixtureWithProjectMeta;
 use elp_project_model::AppName;
 use elp_project_model::AppType;
+use elp_project_model::EqwalizerConfig;
+use elp_project_model::EqwalizerConfigFile;
 use elp_project_model::Project;
 use elp_project_model::ProjectAppData;
 use elp_project_model::ProjectBuildData;
@@ -207,6 +209,7 @@ impl ChangeFixture {
                     otp = Some(otp_extra);
                 }
             }
+            app_map.add_deps(&app_name, entry.deps);
             app_map.combine(entry.app_data);
 
             change.change_file(file_id, Some(Arc::from(text)));
@@ -252,6 +255,7 @@ impl ChangeFixture {
         let root = AbsPathBuf::assert("/".into());
         let apps = app_map.all_apps().cloned().collect();
         let apps_with_includes = RebarProject::add_app_includes(apps, &vec![], &otp.lib_dir);
+        let apps_with_includes = app_map.add_dep_includes(apps_with_includes);
         let rebar_project = RebarProject::new(root, Default::default());
         let mut project = Project::otp(otp, app_map.otp_apps().cloned().collect());
         project.add_apps(apps_with_includes);
@@ -279,14 +283,14 @@ impl ChangeFixture {
             }
 
             let json_config_file = format!("{}/build_info.json", project_dir_str);
+            let mut json_config = project.as_json(AbsPathBuf::assert(project_dir.to_path_buf()));
 
-            let mut writer = File::create(&json_config_file).unwrap();
+            if diagnostics_enabled.needs_build_info() {
+                let mut writer = File::create(&json_config_file).unwrap();
 
-            let json_str = serde_json::to_string_pretty::<JsonConfig>(
-                &project.as_json(AbsPathBuf::assert(project_dir.to_path_buf())),
-            )
-            .unwrap();
-            writer.write_all(json_str.as_bytes()).unwrap();
+                let json_str = serde_json::to_string_pretty::<JsonConfig>(&json_config).unwrap();
+                writer.write_all(json_str.as_bytes()).unwrap();
+            }
 
             let first_fixture = &fixture_with_meta.fixture[0];
 
@@ -297,10 +301,17 @@ impl ChangeFixture {
             } else {
                 project_dir.join(first_fixture.path.clone())
             };
-            let (elp_config, manifest) =
-                ProjectManifest::discover(&AbsPathBuf::assert(json_config_file.into())).unwrap();
+            // The manifest is built in memory, as the build_info file
+            // is only written out for eqWAlizer.
+            json_config.config_path = Some(AbsPathBuf::assert(json_config_file.into()));
+            let manifest = ProjectManifest::Json(json_config);
+            let eqwalizer_config = EqwalizerConfig::resolve(
+                &AbsPathBuf::assert(project_dir.to_path_buf()),
+                &EqwalizerConfigFile::default(),
+            )
+            .unwrap();
             let loaded_project =
-                Project::load(&manifest, elp_config.eqwalizer, &BuckQueryConfig::Original).unwrap();
+                Project::load(&manifest, eqwalizer_config, &BuckQueryConfig::Original).unwrap();
             project = loaded_project;
         }
 
@@ -425,6 +436,7 @@ fn inc_file_id(file_id: &mut FileId) {
 #[derive(Debug, Clone, Default)]
 pub struct AppMap {
     app_map: FxHashMap<AppName, ProjectAppData>,
+    deps: FxHashMap<AppName, Vec<AppName>>,
 }
 
 impl AppMap {
@@ -439,6 +451,29 @@ impl AppMap {
         }
     }
 
+    fn add_deps(&mut self, app_name: &AppName, deps: Vec<AppName>) {
+        let app_deps = self.deps.entry(app_name.clone()).or_default();
+        app_deps.extend(deps);
+        app_deps.dedup();
+    }
+
+    /// Make the include directories of the declared dependencies of
+    /// each app available to it, after its own include path.
+    fn add_dep_includes(&self, mut apps: Vec<ProjectAppData>) -> Vec<ProjectAppData> {
+        for app in &mut apps {
+            for dep in self.deps.get(&app.name).into_iter().flatten() {
+                if let Some(dep_data) = self.app_map.get(dep) {
+                    for include_dir in &dep_data.include_dirs {
+                        if !app.include_path.contains(include_dir) {
+                            app.include_path.push(include_dir.clone());
+                        }
+                    }
+                }
+            }
+        }
+        apps
+    }
+
     fn otp_apps(&self) -> impl Iterator<Item = &ProjectAppData> + '_ {
         self.app_map
             .values()
@@ -791,6 +826,30 @@ mod tests {
     use crate::fixture::extract_annotations;
     use crate::fixture::remove_annotations;
 
+    #[test]
+    fn test_build_info_only_written_for_eqwalizer() {
+        let (fixture, _change) = ChangeFixture::parse(
+            r#"
+//- common_test
+//- /my_app/src/a.erl
+-module(a).
+"#,
+        );
+        let (_projects, dir) = fixture.diagnostics_enabled.tmp_dir.as_ref().unwrap();
+        assert!(!dir.path().join("build_info.json").exists());
+
+        let (fixture, _change) = ChangeFixture::parse(
+            r#"
+//- common_test
+//- eqwalizer
+//- /my_app/src/a.erl
+-module(a).
+"#,
+        );
+        let (_projects, dir) = fixture.diagnostics_enabled.tmp_dir.as_ref().unwrap();
+        assert!(dir.path().join("build_info.json").exists());
+    }
+
     #[test]
     fn test_extract_annotations_1() {
         let text = stdx::trim_indent(
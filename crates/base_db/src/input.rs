@@ -223,6 +223,7 @@ impl AppStructure {
             db.set_app_data_by_id(app_data_id, arc_data);
             db.set_app_data_id(source_root_id, app_data_id);
             applicable_files.map(|files| {
+                app_index.restricted.insert(app_data_id);
                 files.iter().for_each(|path| {
                     if let Some(file_id) = resolve_file_id(path) {
                         app_index.map.insert(file_id, app_data_id);
@@ -18,6 +18,7 @@ use elp_syntax::SmolStr;
 use elp_syntax::TextRange;
 use elp_syntax::TextSize;
 use fxhash::FxHashMap;
+use fxhash::FxHashSet;
 use lazy_static::lazy_static;
 
 mod change;
@@ -33,6 +34,7 @@ pub mod fixture;
 mod atom_name;
 pub mod test_utils;
 pub use atom_name::AtomName;
+pub use atom_name::AtomNameInterner;
 pub use change::Change;
 pub use elp_project_model::AppType;
 pub use include::IncludeCtx;
@@ -168,8 +170,19 @@ pub trait SourceDatabase: FileLoader + salsa::Database {
     /// Returns a map from module name to FileId of the containing file.
     fn module_index(&self, project_id: ProjectId) -> Arc<ModuleIndex>;
 
+    /// Returns the FileId of the module, from the `module_index`.
+    /// Queries using this are not invalidated when other modules are
+    /// added to or removed from the project.
+    fn module_file(&self, project_id: ProjectId, module: ModuleName) -> Option<FileId>;
+
     fn include_file_index(&self, project_id: ProjectId) -> Arc<IncludeFileIndex>;
 
+    /// Returns a map from the FileId of each module owned by the
+    /// project to its app type, and whether it belongs to OTP.
+    /// Computed once per project, so that bulk operations over all
+    /// modules can filter them with a cheap lookup.
+    fn module_app_types(&self, project_id: ProjectId) -> Arc<FxHashMap<FileId, (AppType, bool)>>;
+
     /// Returns a map from FileId to the AppDataId of the app the file
     /// belongs to.
     #[salsa::input]
@@ -180,6 +193,11 @@ pub trait SourceDatabase: FileLoader + salsa::Database {
 
     fn is_generated(&self, file_id: FileId) -> bool;
 
+    /// Whether the file should be processed. An app can restrict its
+    /// files to a set of applicable files, such as those of a Buck
+    /// target, in which case other files in its directories are not.
+    fn is_applicable_file(&self, file_id: FileId) -> bool;
+
     fn is_otp(&self, file_id: FileId) -> Option<bool>;
 
     fn is_test_suite_or_test_helper(&self, file_id: FileId) -> Option<bool>;
@@ -256,6 +274,31 @@ fn module_index(db: &dyn SourceDatabase, project_id: ProjectId) -> Arc<ModuleInd
     builder.build()
 }
 
+fn module_file(
+    db: &dyn SourceDatabase,
+    project_id: ProjectId,
+    module: ModuleName,
+) -> Option<FileId> {
+    db.module_index(project_id).file_for_module(&module)
+}
+
+fn module_app_types(
+    db: &dyn SourceDatabase,
+    project_id: ProjectId,
+) -> Arc<FxHashMap<FileId, (AppType, bool)>> {
+    let module_index = db.module_index(project_id);
+    let map = module_index
+        .iter_own()
+        .filter_map(|(_name, _source, file_id)| {
+            let app_data = db.file_app_data(file_id)?;
+            let is_otp =
+                db.project_data(app_data.project_id).otp_project_id == Some(app_data.project_id);
+            Some((file_id, (app_data.app_type, is_otp)))
+        })
+        .collect();
+    Arc::new(map)
+}
+
 /// A map from file path to `FileId` for each `.hrl` file we have
 /// loaded.
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
@@ -326,6 +369,9 @@ fn include_file_id(
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub struct AppDataIndex {
     pub map: FxHashMap<FileId, AppDataId>,
+    /// Apps whose files are restricted to their applicable files,
+    /// which are the ones recorded in `map`.
+    pub restricted: FxHashSet<AppDataId>,
 }
 
 fn app_data_id_by_file(db: &dyn SourceDatabase, file_id: FileId) -> Option<AppDataId> {
@@ -363,6 +409,11 @@ fn is_generated(db: &dyn SourceDatabase, file_id: FileId) -> bool {
     RE.is_match(&contents.as_bytes()[0..(2001.min(contents.len()))])
 }
 
+fn is_applicable_file(db: &dyn SourceDatabase, file_id: FileId) -> bool {
+    let app_data_id = db.app_data_id(db.file_source_root(file_id));
+    !db.app_index().restricted.contains(&app_data_id) || db.app_data_id_by_file(file_id).is_some()
+}
+
 fn is_otp(db: &dyn SourceDatabase, file_id: FileId) -> Option<bool> {
     let app_data = db.file_app_data(file_id)?;
     let project_id = app_data.project_id;
@@ -92,12 +92,12 @@ pub struct Eqwalize {
     /// Rebar3 profile to pickup (default is test)
     #[bpaf(long("as"), argument("PROFILE"), fallback("test".to_string()))]
     pub profile: String,
-    /// Show diagnostics in JSON format
+    /// Show diagnostics in JSON format, or as GitHub Actions annotations with `github`
     #[bpaf(
         argument("FORMAT"),
-        complete(format_completer),
+        complete(eqwalize_format_completer),
         fallback(None),
-        guard(format_guard, "Please use json")
+        guard(eqwalize_format_guard, "Please use json or github")
     )]
     pub format: Option<String>,
     /// Run with rebar
@@ -106,6 +106,12 @@ pub struct Eqwalize {
     pub clause_coverage: bool,
     /// Exit with a non-zero status code if any errors are found
     pub bail_on_error: bool,
+    /// Stop checking modules once N diagnostics are found, and report only those
+    #[bpaf(argument("N"))]
+    pub max_diagnostics: Option<usize>,
+    /// Read module ASTs from a directory written by `elp parse-all`
+    #[bpaf(argument("AST_DIR"))]
+    pub ast_dir: Option<PathBuf>,
     /// Eqwalize specified modules
     #[bpaf(
         positional("MODULES"),
@@ -122,12 +128,12 @@ pub struct EqwalizeAll {
     /// Rebar3 profile to pickup (default is test)
     #[bpaf(long("as"), argument("PROFILE"), fallback("test".to_string()))]
     pub profile: String,
-    /// Show diagnostics in JSON format
+    /// Show diagnostics in JSON format, or as GitHub Actions annotations with `github`
     #[bpaf(
         argument("FORMAT"),
-        complete(format_completer),
+        complete(eqwalize_format_completer),
         fallback(None),
-        guard(format_guard, "Please use json")
+        guard(eqwalize_format_guard, "Please use json or github")
     )]
     pub format: Option<String>,
     /// Run with rebar
@@ -138,10 +144,19 @@ pub struct EqwalizeAll {
     pub clause_coverage: bool,
     /// Exit with a non-zero status code if any errors are found
     pub bail_on_error: bool,
+    /// Stop checking modules once N diagnostics are found, and report only those
+    #[bpaf(argument("N"))]
+    pub max_diagnostics: Option<usize>,
     /// Print statistics when done
     pub stats: bool,
     /// When printing statistics, include the list of modules parsed
     pub list_modules: bool,
+    /// Report no diagnostics for modules eqWAlizer fails on, instead of failing the run
+    pub skip_failed_modules: bool,
+    /// Print how long eqWAlizer took to check each module, slowest first
+    pub timings: bool,
+    /// Print the eqWAlizer configuration as JSON, without checking any module
+    pub print_config: bool,
 }
 
 #[derive(Clone, Debug, Bpaf)]
@@ -195,6 +210,20 @@ pub struct EqwalizeStats {
     pub include_generated: bool,
 }
 
+#[derive(Clone, Debug, Bpaf)]
+pub struct CheckStubs {
+    /// Path to directory with project, or to a JSON file (defaults to `.`)
+    #[bpaf(argument("PROJECT"), fallback(PathBuf::from(".")))]
+    pub project: PathBuf,
+    /// Rebar3 profile to pickup (default is test)
+    #[bpaf(long("as"), argument("PROFILE"), fallback("test".to_string()))]
+    pub profile: String,
+    /// Run with rebar
+    pub rebar: bool,
+    /// Exit with a non-zero status code if any errors are found
+    pub bail_on_error: bool,
+}
+
 #[derive(Clone, Debug, Bpaf)]
 pub struct DialyzeAll {}
 
@@ -363,6 +392,7 @@ pub enum Command {
     EqwalizeTarget(EqwalizeTarget),
     EqwalizeApp(EqwalizeApp),
     EqwalizeStats(EqwalizeStats),
+    CheckStubs(CheckStubs),
     DialyzeAll(DialyzeAll),
     BuildInfo(BuildInfo),
     GenerateCompletions(GenerateCompletions),
@@ -448,6 +478,12 @@ pub fn command() -> impl Parser<Command> {
         .command("eqwalize-stats")
         .help("Return statistics about code quality for eqWAlizer");
 
+    let check_stubs = check_stubs()
+        .map(Command::CheckStubs)
+        .to_options()
+        .command("check-stubs")
+        .help("Check that the eqWAlizer stubs of all modules in a project are valid");
+
     let dialyze_all = dialyze_all()
         .map(Command::DialyzeAll)
         .to_options()
@@ -530,6 +566,7 @@ pub fn command() -> impl Parser<Command> {
         version,
         shell,
         eqwalize_stats,
+        check_stubs,
         explain,
         project_info,
         glean,
@@ -594,6 +631,18 @@ fn format_guard(format: &Option<String>) -> bool {
     }
 }
 
+fn eqwalize_format_completer(_: &Option<String>) -> Vec<(String, Option<String>)> {
+    vec![("json".to_string(), None), ("github".to_string(), None)]
+}
+
+fn eqwalize_format_guard(format: &Option<String>) -> bool {
+    match format {
+        None => true,
+        Some(f) if f == "json" || f == "github" => true,
+        _ => false,
+    }
+}
+
 fn at_least_1(data: &Vec<String>) -> bool {
     data.len() >= 1
 }
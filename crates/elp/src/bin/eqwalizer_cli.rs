@@ -7,8 +7,11 @@
  * of this source tree.
  */
 
+use std::collections::BTreeMap;
 use std::path::Path;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::bail;
 use anyhow::Context;
@@ -20,6 +23,8 @@ use elp::build::load;
 use elp::build::types::LoadResult;
 use elp::cli::Cli;
 use elp::convert;
+use elp_eqwalizer::ast::db::EqwalizerASTDatabase;
+use elp_eqwalizer::ast::Error;
 use elp_eqwalizer::EqwalizerConfig;
 use elp_eqwalizer::EqwalizerDiagnosticsDatabase;
 use elp_eqwalizer::Mode;
@@ -31,6 +36,7 @@ use elp_ide::diagnostics_collection::DiagnosticCollection;
 use elp_ide::elp_ide_db::elp_base_db::FileId;
 use elp_ide::elp_ide_db::elp_base_db::IncludeOtp;
 use elp_ide::elp_ide_db::elp_base_db::ModuleName;
+use elp_ide::elp_ide_db::elp_base_db::ProjectId;
 use elp_ide::elp_ide_db::elp_base_db::VfsPath;
 use elp_ide::elp_ide_db::EqwalizerDiagnostics;
 use elp_ide::elp_ide_db::LineIndex;
@@ -41,12 +47,17 @@ use elp_project_model::buck::BuckQueryConfig;
 use elp_project_model::AppName;
 use elp_project_model::DiscoverConfig;
 use elp_project_model::ProjectBuildData;
+use elp_types_db::eqwalizer::form::InvalidForm;
+use elp_types_db::eqwalizer::invalid_diagnostics::Invalid;
 use fxhash::FxHashMap;
 use indicatif::ParallelProgressIterator;
 use itertools::Itertools;
 use lazy_static::lazy_static;
+use parking_lot::Mutex;
 use rayon::prelude::*;
+use serde::Serialize;
 
+use crate::args::CheckStubs;
 use crate::args::Eqwalize;
 use crate::args::EqwalizeAll;
 use crate::args::EqwalizeApp;
@@ -55,6 +66,7 @@ use crate::args::EqwalizeTarget;
 use crate::reporting;
 use crate::reporting::add_stat;
 use crate::reporting::dump_stats;
+use crate::reporting::dump_timings;
 use crate::reporting::ParseDiagnostic;
 use crate::reporting::Reporter;
 
@@ -64,6 +76,7 @@ struct EqwalizerInternalArgs<'a> {
     file_ids: Vec<FileId>,
     reporter: &'a mut dyn reporting::Reporter,
     bail_on_error: bool,
+    max_diagnostics: Option<usize>,
 }
 
 pub fn eqwalize_module(
@@ -89,7 +102,7 @@ pub fn do_eqwalize_module(
     loaded: &mut LoadResult,
     cli: &mut dyn Cli,
 ) -> Result<()> {
-    set_eqwalizer_config(loaded, args.clause_coverage);
+    set_eqwalizer_config(loaded, args.clause_coverage, args.ast_dir.clone(), false);
     let analysis = &loaded.analysis();
     let mut file_ids = vec![];
     for module in &args.modules {
@@ -108,19 +121,25 @@ pub fn do_eqwalize_module(
     }
 
     let mut json_reporter;
+    let mut github_reporter;
     let mut pretty_reporter;
 
-    let reporter: &mut dyn Reporter = match args.format {
+    let reporter: &mut dyn Reporter = match args.format.as_deref() {
         None => {
             pretty_reporter = reporting::PrettyReporter::new(analysis, loaded, cli);
             &mut pretty_reporter
         }
+        Some("github") => {
+            github_reporter = reporting::GithubReporter::new(analysis, loaded, cli);
+            &mut github_reporter
+        }
         Some(_) => {
             json_reporter = reporting::JsonReporter::new(analysis, loaded, cli);
             &mut json_reporter
         }
     };
     let bail_on_error = args.bail_on_error;
+    let max_diagnostics = args.max_diagnostics;
 
     eqwalize(EqwalizerInternalArgs {
         analysis,
@@ -128,6 +147,7 @@ pub fn do_eqwalize_module(
         file_ids,
         reporter,
         bail_on_error,
+        max_diagnostics,
     })
 }
 
@@ -150,6 +170,15 @@ pub fn eqwalize_all(
         Mode::Cli,
         query_config,
     )?;
+    if args.print_config {
+        set_eqwalizer_config(
+            &mut loaded,
+            args.clause_coverage,
+            None,
+            args.skip_failed_modules,
+        );
+        return print_eqwalizer_config(&loaded, cli);
+    }
     build::compile_deps(&loaded, cli)?;
     do_eqwalize_all(args, &mut loaded, cli)
 }
@@ -159,7 +188,17 @@ pub fn do_eqwalize_all(
     loaded: &mut LoadResult,
     cli: &mut dyn Cli,
 ) -> Result<()> {
-    set_eqwalizer_config(loaded, args.clause_coverage);
+    set_eqwalizer_config(loaded, args.clause_coverage, None, args.skip_failed_modules);
+    let timings: Arc<Mutex<Vec<(String, Duration)>>> = Default::default();
+    if args.timings {
+        let sink = timings.clone();
+        loaded
+            .analysis_host
+            .raw_database_mut()
+            .set_eqwalizer_timing_sink(Some(Arc::new(move |module, time| {
+                sink.lock().push((module.to_string(), time))
+            })));
+    }
     let analysis = &loaded.analysis();
     let module_index = analysis.module_index(loaded.project_id)?;
     let include_generated = args.include_generated.into();
@@ -186,19 +225,25 @@ pub fn do_eqwalize_all(
     pb.finish();
 
     let mut json_reporter;
+    let mut github_reporter;
     let mut pretty_reporter;
 
-    let reporter: &mut dyn Reporter = match args.format {
+    let reporter: &mut dyn Reporter = match args.format.as_deref() {
         None => {
             pretty_reporter = reporting::PrettyReporter::new(analysis, loaded, cli);
             &mut pretty_reporter
         }
+        Some("github") => {
+            github_reporter = reporting::GithubReporter::new(analysis, loaded, cli);
+            &mut github_reporter
+        }
         Some(_) => {
             json_reporter = reporting::JsonReporter::new(analysis, loaded, cli);
             &mut json_reporter
         }
     };
     let bail_on_error = args.bail_on_error;
+    let max_diagnostics = args.max_diagnostics;
 
     eqwalize(EqwalizerInternalArgs {
         analysis,
@@ -206,10 +251,18 @@ pub fn do_eqwalize_all(
         file_ids,
         reporter,
         bail_on_error,
+        max_diagnostics,
     })?;
     if args.stats {
         dump_stats(cli, args.list_modules);
     }
+    if args.timings {
+        loaded
+            .analysis_host
+            .raw_database_mut()
+            .set_eqwalizer_timing_sink(None);
+        dump_timings(cli, &timings.lock());
+    }
     Ok(())
 }
 
@@ -236,7 +289,7 @@ pub fn do_eqwalize_app(
     loaded: &mut LoadResult,
     cli: &mut dyn Cli,
 ) -> Result<()> {
-    set_eqwalizer_config(loaded, args.clause_coverage);
+    set_eqwalizer_config(loaded, args.clause_coverage, None, false);
     let analysis = &loaded.analysis();
     let module_index = analysis.module_index(loaded.project_id)?;
     let include_generated = args.include_generated.into();
@@ -263,6 +316,7 @@ pub fn do_eqwalize_app(
         file_ids,
         reporter: &mut reporter,
         bail_on_error,
+        max_diagnostics: None,
     })
 }
 
@@ -281,7 +335,7 @@ pub fn eqwalize_target(
         query_config,
     )?;
 
-    set_eqwalizer_config(&mut loaded, args.clause_coverage);
+    set_eqwalizer_config(&mut loaded, args.clause_coverage, None, false);
 
     let buck = match &loaded.project.project_build_data {
         ProjectBuildData::Buck(buck) => buck,
@@ -343,6 +397,7 @@ elp eqwalize-target erl/chatd #same as //erl/chatd/... but enables shell complet
         file_ids,
         reporter: &mut reporter,
         bail_on_error,
+        max_diagnostics: None,
     })
 }
 
@@ -406,6 +461,101 @@ pub fn eqwalize_stats(
     Ok(())
 }
 
+pub fn check_stubs(
+    args: &CheckStubs,
+    cli: &mut dyn Cli,
+    query_config: &BuckQueryConfig,
+) -> Result<()> {
+    let config = DiscoverConfig::new(args.rebar, &args.profile);
+    let loaded = load::load_project_at(
+        cli,
+        &args.project,
+        config,
+        IncludeOtp::Yes,
+        Mode::Cli,
+        query_config,
+    )?;
+    build::compile_deps(&loaded, cli)?;
+    let analysis = &loaded.analysis();
+    let module_index = analysis.module_index(loaded.project_id)?;
+    let project_id = loaded.project_id;
+    let db = loaded.analysis_host.raw_database();
+    let modules: Vec<ModuleName> = module_index
+        .iter_own()
+        .map(|(name, _source, _file_id)| name.clone())
+        .sorted()
+        .collect();
+    let pb = cli.progress(modules.len() as u64, "Checking stubs");
+    let mut problems = vec![];
+    let mut error_count = 0;
+    let mut invalid_count = 0;
+    for module in &modules {
+        match db.transitive_stub(project_id, module.clone()) {
+            Ok(stub) => {
+                for form in &stub.invalid_forms {
+                    if let Some(reason) = invalid_type_decl_reason(form) {
+                        invalid_count += 1;
+                        problems.push(format!("{}: {}", module, reason));
+                    }
+                }
+            }
+            Err(err) => {
+                error_count += 1;
+                problems.push(format!("{}: {}", module, stub_error_reason(&err)));
+            }
+        }
+        pb.inc(1);
+    }
+    pb.finish();
+    for problem in &problems {
+        writeln!(cli, "{}", problem)?;
+    }
+    writeln!(
+        cli,
+        "Checked {} modules: {} invalid type declarations, {} errors",
+        modules.len(),
+        invalid_count,
+        error_count
+    )?;
+    if args.bail_on_error && !problems.is_empty() {
+        bail!("Invalid stubs found")
+    }
+    Ok(())
+}
+
+/// Why the stub of a module could not be built.
+fn stub_error_reason(err: &Error) -> String {
+    match err {
+        Error::TypeConversionError(err) => err.to_string(),
+        Error::ContractivityError(err) => err.to_string(),
+        Error::VarianceCheckError(err) => err.to_string(),
+        Error::TransitiveCheckError(err) => err.to_string(),
+        err => format!("eqWAlizer stub construction failed with {:?}", err),
+    }
+}
+
+/// Why a type declaration was rejected by the stub validation, if it was.
+fn invalid_type_decl_reason(form: &InvalidForm) -> Option<String> {
+    let InvalidForm::InvalidTypeDecl(decl) = form else {
+        return None;
+    };
+    match &decl.te {
+        Invalid::NonProductiveRecursiveTypeAlias(invalid) => {
+            Some(format!("type {} is not contractive", invalid.name))
+        }
+        Invalid::AliasWithNonCovariantParam(invalid) => Some(format!(
+            "parameter {} of opaque type {} is not covariant",
+            invalid.type_var, invalid.name
+        )),
+        Invalid::TransitiveInvalid(invalid) => Some(format!(
+            "type {} refers to invalid types {}",
+            decl.id,
+            invalid.references.join(", ")
+        )),
+        _ => None,
+    }
+}
+
 fn print_diagnostic_json(
     diagnostic: &Diagnostic,
     line_index: &LineIndex,
@@ -431,6 +581,7 @@ fn eqwalize(
         file_ids,
         reporter,
         bail_on_error,
+        max_diagnostics,
     }: EqwalizerInternalArgs,
 ) -> Result<()> {
     if file_ids.is_empty() {
@@ -441,33 +592,51 @@ fn eqwalize(
 
     let files_count = file_ids.len();
     let pb = reporter.progress(files_count as u64, "EqWAlizing");
-    let output = loaded.with_eqwalizer_progress_bar(pb.clone(), move |analysis| {
-        let project_id = loaded.project_id;
-        let max_tasks = loaded.project.eqwalizer_config.max_tasks;
-        let chunk_size = (files_count + max_tasks - 1) / max_tasks;
-        file_ids
-            .chunks(chunk_size)
-            .par_bridge()
-            .map_with(analysis, move |analysis, file_ids| {
-                analysis
-                    .eqwalizer_diagnostics(project_id, file_ids.to_vec())
-                    .expect("cancelled")
-            })
-            .fold(EqwalizerDiagnostics::default, |acc, output| {
-                acc.combine((*output).clone())
-            })
-            .reduce(EqwalizerDiagnostics::default, |acc, other| {
-                acc.combine(other)
+    let project_id = loaded.project_id;
+    let (output, unchecked) = match max_diagnostics {
+        None => loaded.with_eqwalizer_progress_bar(pb.clone(), move |analysis| {
+            let max_tasks = loaded.project.eqwalizer_config.max_tasks;
+            let chunk_size = (files_count + max_tasks - 1) / max_tasks;
+            let output = file_ids
+                .chunks(chunk_size)
+                .par_bridge()
+                .map_with(analysis, move |analysis, file_ids| {
+                    analysis
+                        .eqwalizer_diagnostics(project_id, file_ids.to_vec())
+                        .expect("cancelled")
+                })
+                .fold(EqwalizerDiagnostics::default, |acc, output| {
+                    acc.combine((*output).clone())
+                })
+                .reduce(EqwalizerDiagnostics::default, |acc, other| {
+                    acc.combine(other)
+                });
+            (output, 0)
+        }),
+        Some(limit) => {
+            let mut file_ids = file_ids;
+            file_ids.sort_by_cached_key(|file_id| analysis.module_name(*file_id).ok().flatten());
+            loaded.with_eqwalizer_progress_bar(pb.clone(), move |analysis| {
+                eqwalize_until_limit(&analysis, project_id, &file_ids, limit)
             })
-    });
+        }
+    };
     let eqwalized = pb.position();
     pb.finish();
     let mut has_errors = false;
     match output {
         EqwalizerDiagnostics::Diagnostics {
             errors: diagnostics_by_module,
+            no_ast,
             ..
         } => {
+            for module in no_ast.iter().sorted() {
+                let parse_diagnostics = no_ast_parse_diagnostics(analysis, loaded, module)?;
+                reporter.write_parse_diagnostics(&parse_diagnostics)?;
+                has_errors = true;
+            }
+            let mut remaining = max_diagnostics.unwrap_or(usize::MAX);
+            let mut omitted = 0;
             for (module, diagnostics) in diagnostics_by_module
                 .into_iter()
                 .sorted_by(|(name1, _), (name2, _)| Ord::cmp(name1, name2))
@@ -476,11 +645,18 @@ fn eqwalize(
                     .module_index(loaded.project_id)?
                     .file_for_module(&module)
                     .with_context(|| format!("module {} not found", module))?;
-                reporter.write_eqwalizer_diagnostics(file_id, &diagnostics)?;
+                // Once the cap is reached, only count what is left over
+                let shown = diagnostics.len().min(remaining);
+                remaining -= shown;
+                omitted += diagnostics.len() - shown;
+                reporter.write_eqwalizer_diagnostics(file_id, &diagnostics[..shown])?;
                 if !diagnostics.is_empty() {
                     has_errors = true;
                 }
             }
+            if omitted > 0 || unchecked > 0 {
+                reporter.write_omitted_count(omitted, unchecked)?;
+            }
             if analysis.eqwalizer().mode == Mode::Shell {
                 reporter.write_stats(eqwalized, files_count as u64)?;
             }
@@ -492,66 +668,12 @@ fn eqwalize(
             }
         }
         EqwalizerDiagnostics::NoAst { module } => {
-            if let Some(file_id) =
-                analysis.module_file_id(loaded.project_id, module.as_unquoted_str())?
-            {
-                let config = DiagnosticsConfig::default();
-                let erlang_service_diagnostics =
-                    analysis.erlang_service_diagnostics(file_id, &config, RemoveElpReported::No)?;
-                let erlang_service = erlang_service_diagnostics
-                    .into_iter()
-                    .find(|(f, _diags)| f == &file_id)
-                    .map(|(_, diags)| diags)
-                    .unwrap_or(LabeledDiagnostics::default());
-                let mut diagnostics = DiagnosticCollection::default();
-                diagnostics.set_erlang_service(file_id, erlang_service);
-                // `diagnostics_for` will also combine related diagnostics
-                let diags = diagnostics.diagnostics_for(file_id);
-                let mut parse_diagnostics: Vec<ParseDiagnostic> = Vec::default();
-                let line_index = analysis.with_db(|db| db.file_line_index(file_id))?;
-                for diag in diags {
-                    let vfs_path = loaded.vfs.file_path(file_id);
-                    let analysis = loaded.analysis();
-                    let root_path = &analysis
-                        .project_data(file_id)
-                        .unwrap_or_else(|_err| panic!("could not find project data"))
-                        .unwrap_or_else(|| panic!("could not find project data"))
-                        .root_dir;
-                    let relative_path = reporting::get_relative_path(root_path, &vfs_path);
-
-                    let line_num = convert::position(&line_index, diag.range.start()).line + 1;
-                    parse_diagnostics.push(ParseDiagnostic {
-                        file_id,
-                        relative_path: relative_path.to_path_buf(),
-                        line_num,
-                        msg: diag.message,
-                        range: Some(diag.range),
-                    });
-                    has_errors = true;
-                }
-                // The cached parse errors must be non-empty otherwise we wouldn't have `NoAst`
-                assert!(
-                    !parse_diagnostics.is_empty(),
-                    "Expecting erlang service diagnostics, but none found, for '{}'",
-                    module
-                );
-                let parse_diagnostics: Vec<_> = parse_diagnostics
-                    .into_iter()
-                    .sorted_by(|d1, d2| {
-                        Ord::cmp(&d1.range.map(|r| r.start()), &d2.range.map(|r| r.start()))
-                    })
-                    .collect();
-                reporter.write_parse_diagnostics(&parse_diagnostics)?;
-                if bail_on_error && has_errors {
-                    bail!("Eqwalizer parse errors found.")
-                } else {
-                    Ok(())
-                }
+            let parse_diagnostics = no_ast_parse_diagnostics(analysis, loaded, &module)?;
+            reporter.write_parse_diagnostics(&parse_diagnostics)?;
+            if bail_on_error {
+                bail!("Eqwalizer parse errors found.")
             } else {
-                bail!(
-                    "Could not type-check because module {} was not found",
-                    module
-                )
+                Ok(())
             }
         }
         EqwalizerDiagnostics::Error(error) => {
@@ -560,6 +682,103 @@ fn eqwalize(
     }
 }
 
+/// The number of modules type-checked at a time when there is a limit
+/// on the number of diagnostics to report.
+const LIMITED_CHUNK_SIZE: usize = 20;
+
+/// Type-check `file_ids` in order, a batch at a time, and stop once
+/// `limit` diagnostics have been found. Returns the diagnostics and
+/// the number of files which were not checked.
+fn eqwalize_until_limit(
+    analysis: &Analysis,
+    project_id: ProjectId,
+    file_ids: &[FileId],
+    limit: usize,
+) -> (EqwalizerDiagnostics, usize) {
+    let mut output = EqwalizerDiagnostics::default();
+    let mut checked = 0;
+    for chunk in file_ids.chunks(LIMITED_CHUNK_SIZE) {
+        if diagnostics_count(&output) >= limit {
+            break;
+        }
+        let diagnostics = analysis
+            .eqwalizer_diagnostics(project_id, chunk.to_vec())
+            .expect("cancelled");
+        output = output.combine((*diagnostics).clone());
+        checked += chunk.len();
+    }
+    (output, file_ids.len() - checked)
+}
+
+fn diagnostics_count(output: &EqwalizerDiagnostics) -> usize {
+    match output {
+        EqwalizerDiagnostics::Diagnostics { errors, .. } => errors.values().map(Vec::len).sum(),
+        EqwalizerDiagnostics::NoAst { .. } | EqwalizerDiagnostics::Error(_) => 0,
+    }
+}
+
+/// The parse errors explaining why eqWAlizer could not get an AST
+/// for `module`.
+fn no_ast_parse_diagnostics(
+    analysis: &Analysis,
+    loaded: &LoadResult,
+    module: &ModuleName,
+) -> Result<Vec<ParseDiagnostic>> {
+    let Some(file_id) = analysis.module_file_id(loaded.project_id, module.as_unquoted_str())?
+    else {
+        bail!(
+            "Could not type-check because module {} was not found",
+            module
+        )
+    };
+    let config = DiagnosticsConfig::default();
+    let erlang_service_diagnostics =
+        analysis.erlang_service_diagnostics(file_id, &config, RemoveElpReported::No)?;
+    let erlang_service = erlang_service_diagnostics
+        .into_iter()
+        .find(|(f, _diags)| f == &file_id)
+        .map(|(_, diags)| diags)
+        .unwrap_or(LabeledDiagnostics::default());
+    let mut diagnostics = DiagnosticCollection::default();
+    diagnostics.set_erlang_service(file_id, erlang_service);
+    // `diagnostics_for` will also combine related diagnostics
+    let diags = diagnostics.diagnostics_for(file_id);
+    let mut parse_diagnostics: Vec<ParseDiagnostic> = Vec::default();
+    let line_index = analysis.with_db(|db| db.file_line_index(file_id))?;
+    for diag in diags {
+        let vfs_path = loaded.vfs.file_path(file_id);
+        let analysis = loaded.analysis();
+        let root_path = &analysis
+            .project_data(file_id)
+            .unwrap_or_else(|_err| panic!("could not find project data"))
+            .unwrap_or_else(|| panic!("could not find project data"))
+            .root_dir;
+        let relative_path = reporting::get_relative_path(root_path, &vfs_path);
+
+        let line_num = convert::position(&line_index, diag.range.start()).line + 1;
+        parse_diagnostics.push(ParseDiagnostic {
+            file_id,
+            relative_path: relative_path.to_path_buf(),
+            line_num,
+            msg: diag.message,
+            range: Some(diag.range),
+            code: diag.code,
+            severity: diag.severity,
+        });
+    }
+    // The cached parse errors must be non-empty otherwise we wouldn't have `NoAst`
+    assert!(
+        !parse_diagnostics.is_empty(),
+        "Expecting erlang service diagnostics, but none found, for '{}'",
+        module
+    );
+    let parse_diagnostics: Vec<_> = parse_diagnostics
+        .into_iter()
+        .sorted_by(|d1, d2| Ord::cmp(&d1.range.map(|r| r.start()), &d2.range.map(|r| r.start())))
+        .collect();
+    Ok(parse_diagnostics)
+}
+
 fn pre_parse_for_speed(reporter: &dyn Reporter, analysis: Analysis, file_ids: &[FileId]) {
     let pb = reporter.progress(file_ids.len() as u64, "Parsing modules");
     file_ids
@@ -571,17 +790,85 @@ fn pre_parse_for_speed(reporter: &dyn Reporter, analysis: Analysis, file_ids: &[
     pb.finish();
 }
 
-fn set_eqwalizer_config(loaded: &mut LoadResult, clause_coverage: bool) -> () {
-    let config = EqwalizerConfig {
-        clause_coverage: clause_coverage.then_some(true),
+fn eqwalizer_config(
+    clause_coverage: bool,
+    ast_dir: Option<PathBuf>,
+    skip_failed_modules: bool,
+) -> EqwalizerConfig {
+    EqwalizerConfig {
+        clause_coverage: clause_coverage.then_some(true).into(),
+        ast_dir,
+        skip_failed_modules: skip_failed_modules.then_some(true).into(),
         ..EqwalizerConfig::default()
-    };
+    }
+}
+
+fn set_eqwalizer_config(
+    loaded: &mut LoadResult,
+    clause_coverage: bool,
+    ast_dir: Option<PathBuf>,
+    skip_failed_modules: bool,
+) -> () {
+    let config = eqwalizer_config(clause_coverage, ast_dir, skip_failed_modules);
     let db = loaded.analysis_host.raw_database_mut();
     if config != *db.eqwalizer_config() {
         db.set_eqwalizer_config(Arc::new(config));
     }
 }
 
+/// The eqWAlizer settings of a run, as printed by `--print-config`.
+#[derive(Serialize)]
+struct RunConfig<'a> {
+    /// From the config files of the project.
+    project: &'a elp_project_model::EqwalizerConfig,
+    #[serde(flatten)]
+    eqwalizer: ProcessConfig,
+    /// The modules checked with a different config.
+    modules: BTreeMap<String, ProcessConfig>,
+}
+
+/// The config of an eqWAlizer process, with the environment it is
+/// started with.
+#[derive(Serialize)]
+struct ProcessConfig {
+    eqwalizer: EqwalizerConfig,
+    env: BTreeMap<String, String>,
+}
+
+impl ProcessConfig {
+    fn new(loaded: &LoadResult, config: &EqwalizerConfig) -> ProcessConfig {
+        let config = config.resolve_env();
+        let env = loaded
+            .analysis_host
+            .raw_database()
+            .eqwalizer()
+            .process_env(&config);
+        ProcessConfig {
+            eqwalizer: config,
+            env,
+        }
+    }
+}
+
+fn print_eqwalizer_config(loaded: &LoadResult, cli: &mut dyn Cli) -> Result<()> {
+    let db = loaded.analysis_host.raw_database();
+    let modules = db
+        .eqwalizer_module_configs()
+        .keys()
+        .map(|module| {
+            let config = db.eqwalizer_module_config(module.clone());
+            (module.to_string(), ProcessConfig::new(loaded, &config))
+        })
+        .collect();
+    let run_config = RunConfig {
+        project: &loaded.project.eqwalizer_config,
+        eqwalizer: ProcessConfig::new(loaded, &db.eqwalizer_config()),
+        modules,
+    };
+    writeln!(cli, "{}", serde_json::to_string_pretty(&run_config)?)?;
+    Ok(())
+}
+
 lazy_static! {
     static ref YELLOW_COLOR_SPEC: ColorSpec = {
         let mut spec = ColorSpec::default();
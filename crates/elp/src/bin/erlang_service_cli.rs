@@ -7,8 +7,12 @@
  * of this source tree.
  */
 
+use std::fmt;
 use std::fs;
 use std::path::Path;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
 
 use anyhow::Context;
 use anyhow::Error;
@@ -20,6 +24,8 @@ use elp::cli::Cli;
 use elp::convert;
 use elp::otp_file_to_ignore;
 use elp_eqwalizer::Mode;
+use elp_ide::diagnostics::DiagnosticCode;
+use elp_ide::diagnostics::Severity;
 use elp_ide::elp_ide_db::elp_base_db::AtomName;
 use elp_ide::elp_ide_db::elp_base_db::FileId;
 use elp_ide::elp_ide_db::elp_base_db::IncludeOtp;
@@ -31,6 +37,7 @@ use elp_project_model::buck::BuckQueryConfig;
 use elp_project_model::AppType;
 use elp_project_model::DiscoverConfig;
 use indicatif::ParallelProgressIterator;
+use lazy_static::lazy_static;
 use rayon::prelude::*;
 
 use crate::args::ParseAll;
@@ -53,7 +60,16 @@ pub fn parse_all(args: &ParseAll, cli: &mut dyn Cli, query_config: &BuckQueryCon
     fs::create_dir_all(&args.to)?;
     let format = erlang_service::Format::OffsetEtf;
 
-    let parse_diagnostics = do_parse_all(cli, &loaded, &args.to, format, &args.module, args.buck)?;
+    let cancel = interrupt_token();
+    let parse_diagnostics = do_parse_all(
+        cli,
+        &loaded,
+        &args.to,
+        format,
+        &args.module,
+        args.buck,
+        &cancel,
+    )?;
     if args.stats {
         dump_stats(cli, args.list_modules);
     }
@@ -69,6 +85,48 @@ pub fn parse_all(args: &ParseAll, cli: &mut dyn Cli, query_config: &BuckQueryCon
     Ok(())
 }
 
+/// The error of a run which the user interrupted before every module
+/// was parsed.
+#[derive(Debug)]
+pub struct Interrupted;
+
+impl fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Interrupted, not every module was parsed")
+    }
+}
+
+impl std::error::Error for Interrupted {}
+
+/// A token which is set when the user interrupts the run with Ctrl-C,
+/// so that parsing stops without leaving partially written files.
+/// Only the first Ctrl-C is caught: a second one kills the process as
+/// usual.
+fn interrupt_token() -> Arc<AtomicBool> {
+    lazy_static! {
+        static ref INTERRUPTED: Arc<AtomicBool> = {
+            let interrupted = Arc::new(AtomicBool::new(false));
+            let handler_interrupted = interrupted.clone();
+            if let Err(err) = ctrlc::set_handler(move || {
+                handler_interrupted.store(true, Ordering::Relaxed);
+                // The handler runs on a thread of its own rather than
+                // in the signal handler, so this is safe.
+                unsafe {
+                    libc::signal(libc::SIGINT, libc::SIG_DFL);
+                }
+            }) {
+                log::warn!("Could not install the interrupt handler: {}", err);
+            }
+            interrupted
+        };
+    }
+    INTERRUPTED.clone()
+}
+
+/// Parse all modules in the project, writing the resulting ASTs to `to`.
+///
+/// Setting `cancel` stops any further modules from being parsed, and
+/// the run fails with `Interrupted`.
 pub fn do_parse_all(
     cli: &dyn Cli,
     loaded: &LoadResult,
@@ -76,10 +134,29 @@ pub fn do_parse_all(
     format: erlang_service::Format,
     module: &Option<String>,
     buck: bool,
+    cancel: &AtomicBool,
+) -> Result<Vec<ParseDiagnostic>> {
+    do_parse_all_with(cli, loaded, to, format, module, buck, cancel, &|_| {})
+}
+
+/// Like `do_parse_all`, calling `parsed` with the name of each module
+/// once it has been parsed.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn do_parse_all_with(
+    cli: &dyn Cli,
+    loaded: &LoadResult,
+    to: &Path,
+    format: erlang_service::Format,
+    module: &Option<String>,
+    buck: bool,
+    cancel: &AtomicBool,
+    parsed: &(dyn Fn(&AtomName) + Sync),
 ) -> Result<Vec<ParseDiagnostic>> {
     let module_index = loaded.analysis().module_index(loaded.project_id)?;
     let file_cnt = module_index.len_own();
     let _timer = timeit!("parse {} files", file_cnt);
+    let app_types = loaded.analysis().module_app_types(loaded.project_id)?;
+    let app_types = &app_types;
 
     let pb = cli.progress(file_cnt as u64, "Parsing modules");
     let mut result = module_index
@@ -90,27 +167,37 @@ pub fn do_parse_all(
             loaded.analysis(),
             move |db, (name, _, file_id)| -> Result<Vec<ParseDiagnostic>> {
                 let empty = Ok(vec![]);
+                if cancel.load(Ordering::Relaxed) {
+                    return empty;
+                }
                 match module {
                     Some(module) if name != &module.as_str() => {
                         return empty;
                     }
                     _ => {}
                 }
-                if !buck && db.file_app_type(file_id).ok() == Some(Some(AppType::Dep)) {
-                    return empty;
+                match app_types.get(&file_id) {
+                    Some((AppType::Dep, _)) if !buck => return empty,
+                    Some((_, true)) => return empty,
+                    _ => {}
                 }
-                if db.is_otp(file_id).ok() == Some(Some(true)) {
+                if !db.is_applicable_file(file_id)? {
                     return empty;
                 }
 
-                do_parse_one(db, Some((name, to)), file_id, format)
-                    .with_context(|| format!("Failed to parse module {}", name))
+                let diagnostics = do_parse_one(db, Some((name, to)), file_id, format)
+                    .with_context(|| format!("Failed to parse module {}", name))?;
+                parsed(name);
+                Ok(diagnostics)
             },
         )
         .try_reduce(Vec::new, |mut acc, diagnostics| {
             acc.extend(diagnostics);
             Ok(acc)
         })?;
+    if cancel.load(Ordering::Relaxed) {
+        return Err(Interrupted.into());
+    }
     result.sort_by(|f, l| f.relative_path.cmp(&l.relative_path));
     Ok(result)
 }
@@ -145,8 +232,9 @@ pub fn do_parse_one(
         let diagnostic = result
             .errors
             .iter()
-            .chain(result.warnings.iter())
-            .map(|err| {
+            .map(|err| (err, Severity::Error))
+            .chain(result.warnings.iter().map(|err| (err, Severity::Warning)))
+            .map(|(err, severity)| {
                 let relative_path: &Path = err.path.strip_prefix(root_dir).unwrap_or(&err.path);
                 let (range, line_num) = match err.location {
                     None => (None, convert::position(&line_index, 0.into()).line + 1),
@@ -168,6 +256,8 @@ pub fn do_parse_one(
                     line_num,
                     msg: err.msg.to_owned(),
                     range,
+                    code: DiagnosticCode::ErlangService(err.code.to_owned()),
+                    severity,
                 }
             })
             .collect();
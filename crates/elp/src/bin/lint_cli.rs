@@ -938,12 +938,14 @@ mod tests {
             },
             enabled_lints: vec![DiagnosticCode::HeadMismatch],
             disabled_lints: vec![],
+            safe_functions: vec![],
         })
         .unwrap();
 
         expect![[r#"
             enabled_lints = ["P1700"]
             disabled_lints = []
+            safe_functions = []
             [[ad_hoc_lints.lints]]
             type = "ReplaceCall"
 
@@ -975,6 +977,7 @@ mod tests {
                     TrivialMatch,
                 ],
                 disabled_lints: [],
+                safe_functions: [],
                 ad_hoc_lints: LintsFromConfig {
                     lints: [],
                 },
@@ -73,7 +73,12 @@ fn main() {
 fn handle_res(result: Result<()>, stderr: &mut dyn Write) -> i32 {
     if let Err(err) = result {
         writeln!(stderr, "{:#}", err).unwrap();
-        101
+        if err.is::<erlang_service_cli::Interrupted>() {
+            // The conventional exit code of a process stopped by SIGINT
+            130
+        } else {
+            101
+        }
     } else {
         0
     }
@@ -115,6 +120,7 @@ fn try_main(cli: &mut dyn Cli, args: Args) -> Result<()> {
         args::Command::EqwalizeStats(args) => {
             eqwalizer_cli::eqwalize_stats(&args, cli, &query_config)?
         }
+        args::Command::CheckStubs(args) => eqwalizer_cli::check_stubs(&args, cli, &query_config)?,
         args::Command::EqwalizeTarget(args) => {
             eqwalizer_cli::eqwalize_target(&args, cli, &query_config)?
         }
@@ -194,6 +200,8 @@ mod tests {
     use std::ffi::OsString;
     use std::path::Path;
     use std::str;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::atomic::Ordering;
     use std::sync::Arc;
 
     use anyhow::Context;
@@ -201,6 +209,7 @@ mod tests {
     use elp::build;
     use elp::build::load;
     use elp::cli::Fake;
+    use elp_eqwalizer::ConfigValue;
     use elp_eqwalizer::EqwalizerConfig;
     use elp_eqwalizer::EqwalizerDiagnostics;
     use elp_eqwalizer::EqwalizerDiagnosticsDatabase;
@@ -270,6 +279,49 @@ mod tests {
         assert!(stderr.is_empty());
     }
 
+    #[test]
+    fn parse_all_stops_when_cancelled() {
+        let mut cli = Fake::default();
+        let project_path = Path::new("../../test_projects/standard");
+        let loaded = load::load_project_at(
+            &mut cli,
+            project_path,
+            DiscoverConfig::new(true, "test"),
+            IncludeOtp::Yes,
+            Mode::Cli,
+            &BUCK_QUERY_CONFIG,
+        )
+        .unwrap();
+        let tmp = Builder::new().prefix("elp_").tempdir().unwrap();
+        let outdir = PathBuf::from(tmp.path());
+        let cancel = AtomicBool::new(false);
+        // A single thread parses the modules one after the other, so
+        // exactly one is parsed before the run is cancelled.
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap();
+        let result = pool.install(|| {
+            erlang_service_cli::do_parse_all_with(
+                &cli,
+                &loaded,
+                &outdir,
+                elp_ide::erlang_service::Format::OffsetEtf,
+                &None,
+                false,
+                &cancel,
+                &|_| cancel.store(true, Ordering::Relaxed),
+            )
+        });
+        let err = result.unwrap_err();
+        assert!(err.is::<erlang_service_cli::Interrupted>());
+        let written: Vec<_> = fs::read_dir(&outdir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(written.len(), 1, "{:?}", written);
+    }
+
     fn parse_all_complete(project: &str) -> Result<i32> {
         // Just check the command returns.
         let project_path = format!("../../test_projects/{}", project);
@@ -531,6 +583,34 @@ mod tests {
         }
     }
 
+    #[test_case(false ; "rebar")]
+    #[test_case(true  ; "buck")]
+    fn eqwalize_all_max_diagnostics_truncates_output(buck: bool) {
+        if otp_supported_by_eqwalizer() {
+            simple_snapshot(
+                args_vec!["eqwalize-all", "--max-diagnostics", "3"],
+                "standard",
+                expect_file!("../resources/test/standard/eqwalize_all_diagnostics_max.pretty"),
+                buck,
+                None,
+            );
+        }
+    }
+
+    #[test_case(false ; "rebar")]
+    #[test_case(true  ; "buck")]
+    fn eqwalize_all_max_diagnostics_truncates_output_jsonl(buck: bool) {
+        if otp_supported_by_eqwalizer() {
+            simple_snapshot(
+                args_vec!["eqwalize-all", "--format", "json", "--max-diagnostics", "3"],
+                "standard",
+                expect_file!("../resources/test/standard/eqwalize_all_diagnostics_max.jsonl"),
+                buck,
+                None,
+            );
+        }
+    }
+
     #[test_case(false ; "rebar")]
     #[test_case(true  ; "buck")]
     fn eqwalize_app_diagnostics_match_snapshot_pretty(buck: bool) {
@@ -600,6 +680,17 @@ mod tests {
         );
     }
 
+    #[test]
+    fn check_stubs_reports_non_contractive_type() {
+        simple_snapshot(
+            args_vec!["check-stubs"],
+            "check_stubs",
+            expect_file!("../resources/test/check_stubs/check_stubs.stdout"),
+            false,
+            None,
+        );
+    }
+
     #[ignore]
     #[test]
     fn dialyzer_cli() {
@@ -1696,10 +1787,10 @@ mod tests {
             "options",
             buck,
             EqwalizerConfig {
-                fault_tolerance: Some(true),
-                clause_coverage: Some(true),
-                report_bad_maps: Some(true),
-                report_dynamic_lambdas: Some(true),
+                fault_tolerance: ConfigValue::On,
+                clause_coverage: ConfigValue::On,
+                report_bad_maps: ConfigValue::On,
+                report_dynamic_lambdas: ConfigValue::On,
                 ..EqwalizerConfig::default_test()
             },
         );
@@ -1713,8 +1804,8 @@ mod tests {
             "fault_tolerance",
             buck,
             EqwalizerConfig {
-                occurrence_typing: Some(true),
-                fault_tolerance: Some(true),
+                occurrence_typing: ConfigValue::On,
+                fault_tolerance: ConfigValue::On,
                 ..EqwalizerConfig::default_test()
             },
         );
@@ -1752,6 +1843,34 @@ mod tests {
         }
     }
 
+    #[test_case(false ; "rebar")]
+    #[test_case(true  ; "buck")]
+    fn eqwalize_all_print_config(buck: bool) {
+        if !buck || cfg!(feature = "buck") {
+            let (mut args, _path) = add_project(
+                args_vec!["eqwalize-all", "--print-config", "--clause-coverage"],
+                "standard",
+                None,
+                None,
+            );
+            if !buck {
+                args.push("--rebar".into());
+            }
+            let (stdout, stderr, code) = elp(args);
+            assert_eq!(code, 0, "stdout:\n{}\nstderr:\n{}", stdout, stderr);
+            let printed: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+            let config = EqwalizerConfig {
+                clause_coverage: ConfigValue::On,
+                ..EqwalizerConfig::default()
+            }
+            .resolve_env();
+            assert_eq!(printed["eqwalizer"], serde_json::to_value(&config).unwrap());
+            assert_eq!(printed["env"]["EQWALIZER_CLAUSE_COVERAGE"], "true");
+            assert_eq!(printed["env"]["EQWALIZER_MODE"], "elp_cli");
+            assert_eq!(printed["modules"], serde_json::json!({}));
+        }
+    }
+
     #[test]
     fn help() {
         let args = args::args().run_inner(Args::from(&["--help"])).unwrap_err();
@@ -12,6 +12,7 @@ use std::path::Path;
 use std::path::PathBuf;
 use std::str;
 use std::sync::Arc;
+use std::time::Duration;
 use std::time::Instant;
 
 use anyhow::Context;
@@ -27,10 +28,14 @@ use elp::build::types::LoadResult;
 use elp::cli::Cli;
 use elp::convert;
 use elp_eqwalizer::IncludeGenerated;
+use elp_ide::diagnostics::Diagnostic;
+use elp_ide::diagnostics::DiagnosticCode;
+use elp_ide::diagnostics::Severity;
 use elp_ide::elp_ide_db::elp_base_db::AbsPath;
 use elp_ide::elp_ide_db::elp_base_db::FileId;
 use elp_ide::elp_ide_db::elp_base_db::VfsPath;
 use elp_ide::elp_ide_db::EqwalizerDiagnostic;
+use elp_ide::elp_ide_db::LineIndex;
 use elp_ide::Analysis;
 use elp_ide::TextRange;
 use indicatif::ProgressBar;
@@ -47,6 +52,10 @@ pub trait Reporter {
     fn write_parse_diagnostics(&mut self, diagnostics: &[ParseDiagnostic]) -> Result<()>;
     #[allow(unused)]
     fn write_file_advice(&mut self, file_id: FileId, description: String) -> Result<()>;
+    /// Report the number of diagnostics which were found but not
+    /// shown, and of modules which were not checked, once the limit
+    /// on the number of diagnostics is reached.
+    fn write_omitted_count(&mut self, count: usize, unchecked_modules: usize) -> Result<()>;
     fn write_error_count(&mut self) -> Result<()>;
     fn write_stats(&mut self, count: u64, total: u64) -> Result<()>;
 
@@ -60,6 +69,20 @@ pub struct ParseDiagnostic {
     pub line_num: u32,
     pub msg: String,
     pub range: Option<TextRange>,
+    pub code: DiagnosticCode,
+    pub severity: Severity,
+}
+
+impl From<ParseDiagnostic> for Diagnostic {
+    fn from(diagnostic: ParseDiagnostic) -> Diagnostic {
+        let mut d = Diagnostic::new(
+            diagnostic.code,
+            diagnostic.msg,
+            diagnostic.range.unwrap_or_default(),
+        );
+        d.severity = diagnostic.severity;
+        d
+    }
 }
 
 pub struct PrettyReporter<'a> {
@@ -76,6 +99,15 @@ pub struct JsonReporter<'a> {
     cli: &'a mut dyn Cli,
 }
 
+/// Report diagnostics as GitHub Actions workflow commands, which GitHub
+/// shows inline on pull requests.
+/// See https://docs.github.com/en/actions/using-workflow-commands-for-github-actions
+pub struct GithubReporter<'a> {
+    analysis: &'a Analysis,
+    loaded: &'a LoadResult,
+    cli: &'a mut dyn Cli,
+}
+
 impl<'a> PrettyReporter<'a> {
     pub fn new(analysis: &'a Analysis, loaded: &'a LoadResult, cli: &'a mut dyn Cli) -> Self {
         Self {
@@ -163,6 +195,20 @@ impl<'a> Reporter for PrettyReporter<'a> {
         Ok(())
     }
 
+    fn write_omitted_count(&mut self, count: usize, unchecked_modules: usize) -> Result<()> {
+        if count > 0 {
+            writeln!(self.cli, "+{} more", count)?;
+        }
+        if unchecked_modules > 0 {
+            writeln!(
+                self.cli,
+                "Stopped at the diagnostics limit, {} modules were not checked",
+                unchecked_modules
+            )?;
+        }
+        Ok(())
+    }
+
     fn write_error_count(&mut self) -> Result<()> {
         if self.error_count == 0 {
             self.cli.set_color(&GREEN_COLOR_SPEC)?;
@@ -288,6 +334,15 @@ impl<'a> Reporter for JsonReporter<'a> {
         Ok(())
     }
 
+    fn write_omitted_count(&mut self, count: usize, unchecked_modules: usize) -> Result<()> {
+        let omitted = serde_json::json!({
+            "omitted_diagnostics": count,
+            "unchecked_modules": unchecked_modules,
+        });
+        writeln!(self.cli, "{}", omitted)?;
+        Ok(())
+    }
+
     fn write_error_count(&mut self) -> Result<()> {
         Ok(())
     }
@@ -301,6 +356,141 @@ impl<'a> Reporter for JsonReporter<'a> {
     }
 }
 
+impl<'a> GithubReporter<'a> {
+    pub fn new(analysis: &'a Analysis, loaded: &'a LoadResult, cli: &'a mut dyn Cli) -> Self {
+        Self {
+            analysis,
+            loaded,
+            cli,
+        }
+    }
+
+    fn relative_path(&self, file_id: FileId) -> Result<PathBuf> {
+        let file_path = &self.loaded.vfs.file_path(file_id);
+        let root_path = &self
+            .analysis
+            .project_data(file_id)?
+            .with_context(|| "could not find project data")?
+            .root_dir;
+        Ok(get_relative_path(root_path, file_path).to_path_buf())
+    }
+}
+
+impl<'a> Reporter for GithubReporter<'a> {
+    fn write_eqwalizer_diagnostics(
+        &mut self,
+        file_id: FileId,
+        diagnostics: &[EqwalizerDiagnostic],
+    ) -> Result<()> {
+        let line_index = self.analysis.line_index(file_id)?;
+        let relative_path = self.relative_path(file_id)?;
+        for diagnostic in diagnostics {
+            let annotation =
+                eqwalizer_to_github_annotation(diagnostic, &line_index, &relative_path);
+            writeln!(self.cli, "{}", annotation)?;
+        }
+        Ok(())
+    }
+
+    fn write_parse_diagnostics(&mut self, diagnostics: &[ParseDiagnostic]) -> Result<()> {
+        for diagnostic in diagnostics {
+            let annotation = github_annotation(
+                "error",
+                &diagnostic.relative_path,
+                diagnostic.line_num,
+                None,
+                &diagnostic.msg,
+            );
+            writeln!(self.cli, "{}", annotation)?;
+        }
+        Ok(())
+    }
+
+    fn write_file_advice(&mut self, file_id: FileId, description: String) -> Result<()> {
+        let relative_path = self.relative_path(file_id)?;
+        let annotation = github_annotation("notice", &relative_path, 1, None, &description);
+        writeln!(self.cli, "{}", annotation)?;
+        Ok(())
+    }
+
+    fn write_omitted_count(&mut self, count: usize, unchecked_modules: usize) -> Result<()> {
+        writeln!(
+            self.cli,
+            "::notice::{} more diagnostics not shown, {} modules not checked",
+            count, unchecked_modules
+        )?;
+        Ok(())
+    }
+
+    fn write_error_count(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn write_stats(&mut self, _count: u64, _total: u64) -> Result<()> {
+        Ok(())
+    }
+
+    fn progress(&self, len: u64, prefix: &'static str) -> ProgressBar {
+        self.cli.progress(len, prefix)
+    }
+}
+
+fn eqwalizer_to_github_annotation(
+    diagnostic: &EqwalizerDiagnostic,
+    line_index: &LineIndex,
+    relative_path: &Path,
+) -> String {
+    let pos = convert::position(line_index, diagnostic.range.start());
+    let mut message = format!(
+        "{}: {}{}",
+        diagnostic.code,
+        diagnostic.expr_string(),
+        diagnostic.message
+    );
+    if let Some(explanation) = &diagnostic.explanation {
+        message.push_str(&format!("\n\n{}", explanation));
+    }
+    message.push_str(&format!("\nSee {}", diagnostic.uri));
+    github_annotation(
+        "error",
+        relative_path,
+        pos.line + 1,
+        Some(pos.character + 1),
+        &message,
+    )
+}
+
+/// Format a workflow command such as `::error file=f.erl,line=1::msg`.
+fn github_annotation(
+    level: &str,
+    relative_path: &Path,
+    line: u32,
+    col: Option<u32>,
+    message: &str,
+) -> String {
+    let file = escape_github_property(&relative_path.display().to_string());
+    let col = match col {
+        Some(col) => format!(",col={}", col),
+        None => "".to_string(),
+    };
+    format!(
+        "::{level} file={file},line={line}{col}::{}",
+        escape_github_data(message)
+    )
+}
+
+fn escape_github_data(s: &str) -> String {
+    s.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn escape_github_property(s: &str) -> String {
+    escape_github_data(s)
+        .replace(':', "%3A")
+        .replace(',', "%2C")
+}
+
 pub fn format_raw_parse_error(errs: &[ParseDiagnostic]) -> String {
     errs.iter()
         .map(|err| {
@@ -364,6 +554,17 @@ pub(crate) fn dump_stats(cli: &mut dyn Cli, list_modules: bool) {
     writeln!(cli, "{} modules processed", stats.len()).ok();
 }
 
+/// Print how long each module took to check, slowest first.
+pub(crate) fn dump_timings(cli: &mut dyn Cli, timings: &[(String, Duration)]) {
+    writeln!(cli, "{:>10}  module", "time (ms)").ok();
+    for (module, time) in timings
+        .iter()
+        .sorted_by(|(m1, t1), (m2, t2)| t2.cmp(t1).then_with(|| m1.cmp(m2)))
+    {
+        writeln!(cli, "{:>10}  {}", time.as_millis(), module).ok();
+    }
+}
+
 lazy_static! {
     static ref STATS: Mutex<Vec<String>> = {
         let stats = Vec::new();
@@ -375,3 +576,64 @@ pub(crate) fn add_stat(stat: String) {
     let mut stats = STATS.lock();
     stats.push(stat);
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use elp_ide::diagnostics::Diagnostic;
+    use elp_ide::diagnostics::DiagnosticCode;
+    use elp_ide::diagnostics::Severity;
+    use elp_ide::elp_ide_db::elp_base_db::FileId;
+    use elp_ide::elp_ide_db::EqwalizerDiagnostic;
+    use elp_ide::elp_ide_db::LineIndex;
+    use elp_ide::TextRange;
+
+    use super::eqwalizer_to_github_annotation;
+    use super::ParseDiagnostic;
+
+    #[test]
+    fn parse_diagnostic_to_diagnostic() {
+        let range = TextRange::new(10.into(), 15.into());
+        let parse_diagnostic = ParseDiagnostic {
+            file_id: FileId::from_raw(0),
+            relative_path: PathBuf::from("src/main.erl"),
+            line_num: 2,
+            msg: "syntax error before: ')'".to_string(),
+            range: Some(range),
+            code: DiagnosticCode::ErlangService("P1711".to_string()),
+            severity: Severity::Error,
+        };
+        let diagnostic = Diagnostic::from(parse_diagnostic);
+        assert_eq!(
+            diagnostic.code,
+            DiagnosticCode::ErlangService("P1711".to_string())
+        );
+        assert_eq!(diagnostic.message, "syntax error before: ')'");
+        assert_eq!(diagnostic.range, range);
+        assert_eq!(diagnostic.severity, Severity::Error);
+    }
+
+    #[test]
+    fn eqwalizer_diagnostic_to_github_annotation() {
+        let text = "-module(main).\nfoo() -> ok.\n";
+        let diagnostic = EqwalizerDiagnostic {
+            range: TextRange::new(24.into(), 26.into()),
+            message: "Expression has type:   'ok'\nContext expected type: number()".to_string(),
+            uri: "https://fb.me/eqwalizer_errors#incompatible_types".to_string(),
+            code: "incompatible_types".to_string(),
+            expression: Some("'ok'".to_string()),
+            explanation: None,
+            diagnostic: None,
+        };
+        let annotation = eqwalizer_to_github_annotation(
+            &diagnostic,
+            &LineIndex::new(text),
+            &PathBuf::from("src/main,1.erl"),
+        );
+        assert_eq!(
+            annotation,
+            "::error file=src/main%2C1.erl,line=2,col=10::incompatible_types: `'ok'`.%0AExpression has type:   'ok'%0AContext expected type: number()%0ASee https://fb.me/eqwalizer_errors#incompatible_types"
+        );
+    }
+}
@@ -151,6 +151,8 @@ impl ShellCommand {
                             modules: args.iter().map(|s| s.to_string()).collect(),
                             clause_coverage,
                             bail_on_error: false,
+                            max_diagnostics: None,
+                            ast_dir: None,
                         })));
                     }
                     return Err(ShellError::MissingArg("eqwalize".into()));
@@ -206,8 +208,12 @@ impl ShellCommand {
                         include_generated,
                         clause_coverage,
                         bail_on_error: false,
+                        max_diagnostics: None,
                         stats: false,
                         list_modules: false,
+                        skip_failed_modules: false,
+                        timings: false,
+                        print_config: false,
                     })));
                 }
                 "exit" | "quit" => return Ok(Some(ShellCommand::Quit)),
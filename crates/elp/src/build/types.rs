@@ -7,11 +7,13 @@
  * of this source tree.
  */
 
+use elp_ide::elp_ide_db::elp_base_db::AbsPath;
 use elp_ide::elp_ide_db::elp_base_db::FileId;
 use elp_ide::elp_ide_db::elp_base_db::FileSetConfig;
 use elp_ide::elp_ide_db::elp_base_db::ModuleName;
 use elp_ide::elp_ide_db::elp_base_db::ProjectId;
 use elp_ide::elp_ide_db::elp_base_db::Vfs;
+use elp_ide::elp_ide_db::elp_base_db::VfsPath;
 use elp_ide::elp_ide_db::EqwalizerProgressReporter;
 use elp_ide::Analysis;
 use elp_ide::AnalysisHost;
@@ -103,6 +105,11 @@ impl LoadResult {
         self.analysis_host.analysis()
     }
 
+    /// The id of a file in the loaded project, given its path on disk.
+    pub fn file_id(&self, path: &AbsPath) -> Option<FileId> {
+        self.vfs.file_id(&VfsPath::from(path.to_path_buf()))
+    }
+
     pub fn update_erlang_service_paths(&self) {
         self.analysis_host
             .raw_database()
@@ -162,6 +162,7 @@ mod tests {
     use elp_ide::diagnostics::ReplaceCallAction;
     use elp_ide::diagnostics::Replacement;
     use elp_ide::FunctionMatch;
+    use elp_ide::MFA;
     use expect_test::expect;
 
     use crate::LintConfig;
@@ -171,6 +172,7 @@ mod tests {
         let lint_config = LintConfig {
             enabled_lints: vec![DiagnosticCode::ApplicationGetEnv],
             disabled_lints: vec![],
+            safe_functions: vec![MFA::new("logger", "info", 1)],
             ad_hoc_lints: LintsFromConfig {
                 lints: vec![
                     Lint::ReplaceCall(ReplaceCall {
@@ -189,6 +191,7 @@ mod tests {
         expect![[r#"
             enabled_lints = ["W0011"]
             disabled_lints = []
+            safe_functions = ["logger:info/1"]
             [[ad_hoc_lints.lints]]
             type = "ReplaceCall"
 
@@ -205,7 +205,7 @@ impl StubContractivityChecker<'_> {
         stub: &mut ModuleStub,
         t: &TypeDecl,
     ) -> Result<(), ContractivityCheckError> {
-        if !self.is_contractive(&t.body)? {
+        if !self.is_contractive_decl(t)? {
             stub.types.remove(&t.id);
             stub.invalid_forms.push(self.to_invalid(t));
         }
@@ -217,7 +217,7 @@ impl StubContractivityChecker<'_> {
         stub: &mut ModuleStub,
         t: &TypeDecl,
     ) -> Result<(), ContractivityCheckError> {
-        if !self.is_contractive(&t.body)? {
+        if !self.is_contractive_decl(t)? {
             stub.private_opaques.remove(&t.id);
             stub.public_opaques.remove(&t.id);
             stub.invalid_forms.push(self.to_invalid(t));
@@ -238,6 +238,12 @@ impl StubContractivityChecker<'_> {
         })
     }
 
+    fn is_contractive_decl(&self, t: &TypeDecl) -> Result<bool, ContractivityCheckError> {
+        self.is_contractive(&t.body).map_err(|err| {
+            ContractivityCheckError::InTypeDecl(t.id.clone(), t.location.clone(), Box::new(err))
+        })
+    }
+
     fn is_contractive(&self, t: &Type) -> Result<bool, ContractivityCheckError> {
         self.is_foldable(t, &[])
     }
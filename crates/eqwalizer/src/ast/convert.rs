@@ -10,6 +10,7 @@
 use eetf;
 use eetf::Term;
 use elp_base_db::AtomName;
+use elp_base_db::AtomNameInterner;
 use elp_syntax::SmolStr;
 use elp_types_db::eqwalizer::binary_specifier::Specifier;
 use elp_types_db::eqwalizer::expr::AtomLit;
@@ -164,12 +165,13 @@ use super::Id;
 use super::RemoteId;
 use crate::ast;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-struct Converter {
+#[derive(Debug)]
+struct Converter<'a> {
     no_auto_imports: FxHashSet<ast::Id>,
     from_beam: bool,
     filter_stub: bool,
     current_file: Option<SmolStr>,
+    atoms: &'a AtomNameInterner,
 }
 
 fn get_specifier(name: &str) -> Option<Specifier> {
@@ -188,7 +190,7 @@ fn get_specifier(name: &str) -> Option<Specifier> {
     }
 }
 
-impl Converter {
+impl Converter<'_> {
     fn make_pos(&self, start: u32, end: u32) -> ast::Pos {
         if self.from_beam {
             ast::Pos::LineAndColumn(LineAndColumn {
@@ -631,7 +633,7 @@ impl Converter {
         if let Term::Tuple(data) = atom {
             if let [Term::Atom(kind), _, Term::Atom(val)] = &data.elements[..] {
                 if kind.name == "atom" {
-                    return Ok(val.name.clone().into());
+                    return Ok(AtomName::interned(&val.name, self.atoms));
                 }
             }
         }
@@ -1885,7 +1887,7 @@ impl Converter {
                     ("atom", [Term::Atom(val)]) => {
                         return Ok(ExtType::AtomLitExtType(AtomLitExtType {
                             location,
-                            atom: val.name.clone().into(),
+                            atom: AtomName::interned(&val.name, self.atoms),
                         }));
                     }
                     ("type", [Term::Atom(fun), Term::List(ty)])
@@ -2170,6 +2172,7 @@ pub fn convert_forms(
     term: &eetf::Term,
     from_beam: bool,
     filter_stub: bool,
+    atoms: &AtomNameInterner,
 ) -> Result<Vec<ExternalForm>, ConversionError> {
     if let Term::List(forms) = term {
         let dummy_converter = Converter {
@@ -2177,6 +2180,7 @@ pub fn convert_forms(
             from_beam,
             filter_stub,
             current_file: None,
+            atoms,
         };
         let no_auto_imports: FxHashSet<ast::Id> = forms
             .elements
@@ -2189,6 +2193,7 @@ pub fn convert_forms(
             from_beam,
             filter_stub,
             current_file: None,
+            atoms,
         };
         return Ok(forms
             .elements
@@ -11,17 +11,21 @@ use std::sync::Arc;
 
 use elp_base_db::AbsPathBuf;
 use elp_base_db::AppType;
+use elp_base_db::AtomNameInterner;
 use elp_base_db::ModuleName;
 use elp_base_db::ProjectId;
 use elp_base_db::SourceDatabase;
 use elp_types_db::eqwalizer::form::ExternalForm;
+use fxhash::FxHashMap;
 use fxhash::FxHashSet;
+use parking_lot::Mutex;
 
 use super::contractivity::StubContractivityChecker;
 use super::expand::StubExpander;
 use super::stub::ModuleStub;
 use super::trans_valid::TransitiveChecker;
 use super::variance_check::VarianceChecker;
+use super::AstShape;
 use super::Error;
 use super::Id;
 use super::AST;
@@ -32,6 +36,53 @@ pub trait EqwalizerErlASTStorage {
         project_id: ProjectId,
         module: ModuleName,
     ) -> Result<Arc<Vec<u8>>, Error>;
+
+    /// Whether the preprocessor should rewrite predicates passed to
+    /// functions such as `lists:partition/2`.
+    fn preprocess_predicates(&self) -> bool;
+
+    /// The pool of atom names shared by every converted module.
+    fn atom_interner(&self) -> &AtomNameInterner;
+
+    /// Serialized transitive stubs, shared across revisions.
+    fn stub_bytes_cache(&self) -> &StubBytesCache;
+}
+
+/// The serialized transitive stubs of each module, keyed by the stub
+/// itself. Salsa only reuses `transitive_stub_bytes` if the stub did
+/// not change since the previous revision, so without this an edit
+/// which is then undone, or which changes the stub of a module and
+/// then changes it back, serializes the stub again.
+#[derive(Debug, Default)]
+pub struct StubBytesCache {
+    stubs: Mutex<FxHashMap<(ProjectId, ModuleName), Vec<(Arc<ModuleStub>, Arc<Vec<u8>>)>>>,
+}
+
+impl StubBytesCache {
+    /// How many versions of the stub of a module are kept.
+    const VERSIONS: usize = 4;
+
+    fn get_or_serialize(
+        &self,
+        project_id: ProjectId,
+        module: ModuleName,
+        stub: Arc<ModuleStub>,
+    ) -> Arc<Vec<u8>> {
+        let mut stubs = self.stubs.lock();
+        let versions = stubs.entry((project_id, module)).or_default();
+        if let Some((_, bytes)) = versions
+            .iter()
+            .find(|(cached, _)| Arc::ptr_eq(cached, &stub) || **cached == *stub)
+        {
+            return bytes.clone();
+        }
+        let bytes = Arc::new(stub.to_bytes());
+        if versions.len() == Self::VERSIONS {
+            versions.remove(0);
+        }
+        versions.push((stub, bytes.clone()));
+        bytes
+    }
 }
 
 #[salsa::query_group(EqwalizerASTDatabaseStorage)]
@@ -44,6 +95,11 @@ pub trait EqwalizerASTDatabase: EqwalizerErlASTStorage + SourceDatabase {
         project_id: ProjectId,
         module: ModuleName,
     ) -> Result<Arc<Vec<u8>>, Error>;
+    fn converted_ast_shape(
+        &self,
+        project_id: ProjectId,
+        module: ModuleName,
+    ) -> Result<Arc<AstShape>, Error>;
     fn converted_stub(&self, project_id: ProjectId, module: ModuleName) -> Result<Arc<AST>, Error>;
 
     fn type_ids(
@@ -88,7 +144,7 @@ pub trait EqwalizerASTDatabase: EqwalizerErlASTStorage + SourceDatabase {
 }
 
 fn from_beam(db: &dyn EqwalizerASTDatabase, project_id: ProjectId, module: ModuleName) -> bool {
-    if let Some(file_id) = db.module_index(project_id).file_for_module(&module) {
+    if let Some(file_id) = db.module_file(project_id, module) {
         // Context for T171541590
         let _ = stdx::panic_context::enter(format!("\nfrom_beam: {:?}", file_id));
         if let Some(app) = db.file_app_data(file_id) {
@@ -104,7 +160,7 @@ fn converted_ast(
     module: ModuleName,
 ) -> Result<Arc<AST>, Error> {
     let ast = db.get_erl_ast_bytes(project_id, module)?;
-    super::from_bytes(&ast, false).map(Arc::new)
+    super::from_bytes(&ast, false, db.preprocess_predicates(), db.atom_interner()).map(Arc::new)
 }
 
 fn converted_ast_bytes(
@@ -119,6 +175,15 @@ fn converted_ast_bytes(
     })
 }
 
+fn converted_ast_shape(
+    db: &dyn EqwalizerASTDatabase,
+    project_id: ProjectId,
+    module: ModuleName,
+) -> Result<Arc<AstShape>, Error> {
+    db.converted_ast(project_id, module)
+        .map(|ast| Arc::new(super::shape(&ast.iter().filter(is_non_stub_form).collect())))
+}
+
 fn is_non_stub_form(form: &&ExternalForm) -> bool {
     match form {
         ExternalForm::Module(_) => true,
@@ -140,7 +205,7 @@ fn converted_stub(
     if db.from_beam(project_id, module.to_owned()) {
         if let Some(beam_path) = beam_path(db, project_id, module.to_owned()) {
             if let Ok(beam_contents) = std::fs::read(&beam_path) {
-                super::from_beam(&beam_contents).map(Arc::new)
+                super::from_beam(&beam_contents, db.atom_interner()).map(Arc::new)
             } else {
                 Err(Error::BEAMNotFound(beam_path.into()))
             }
@@ -149,16 +214,17 @@ fn converted_stub(
         }
     } else {
         let ast = db.get_erl_ast_bytes(project_id, module)?;
-        super::from_bytes(&ast, true).map(Arc::new)
+        super::from_bytes(&ast, true, db.preprocess_predicates(), db.atom_interner()).map(Arc::new)
     }
 }
 
-fn beam_path(
+/// The `.beam` file of `module`, in the `ebin` directory of its app.
+pub fn beam_path(
     db: &dyn EqwalizerASTDatabase,
     project_id: ProjectId,
     module: ModuleName,
 ) -> Option<AbsPathBuf> {
-    let file_id = db.module_index(project_id).file_for_module(&module)?;
+    let file_id = db.module_file(project_id, module.clone())?;
     // Context for T171541590
     let _ = stdx::panic_context::enter(format!("\nbeam_path: {:?}", file_id));
     let app = db.file_app_data(file_id)?;
@@ -243,6 +309,8 @@ fn transitive_stub_bytes(
     project_id: ProjectId,
     module: ModuleName,
 ) -> Result<Arc<Vec<u8>>, Error> {
-    db.transitive_stub(project_id, module)
-        .map(|stub| Arc::new(stub.to_bytes()))
+    let stub = db.transitive_stub(project_id, module.clone())?;
+    Ok(db
+        .stub_bytes_cache()
+        .get_or_serialize(project_id, module, stub))
 }
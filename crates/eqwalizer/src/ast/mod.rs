@@ -8,6 +8,8 @@
  */
 
 use std::fmt;
+use std::hash::Hash;
+use std::hash::Hasher;
 use std::io::BufRead;
 use std::io::Cursor;
 use std::io::Read;
@@ -15,14 +17,17 @@ use std::path::PathBuf;
 
 use eetf;
 use eetf::Term;
+use elp_base_db::AtomNameInterner;
 use elp_syntax::SmolStr;
 use elp_types_db::eqwalizer::form::ExternalForm;
 use elp_types_db::eqwalizer::invalid_diagnostics::Invalid;
 pub use elp_types_db::eqwalizer::Id;
 pub use elp_types_db::eqwalizer::Pos;
 pub use elp_types_db::eqwalizer::RemoteId;
+use elp_types_db::eqwalizer::TextRange;
 use elp_types_db::eqwalizer::AST;
 use fxhash::FxHashSet;
+use fxhash::FxHasher;
 
 pub mod auto_import;
 pub mod compiler_macro;
@@ -159,6 +164,19 @@ pub enum ContractivityCheckError {
     UnexpectedType,
     ErrorExpandingID(RemoteId, Box<Error>),
     NonEmptyForall,
+    /// A failure while checking the declaration of the given type,
+    /// located at the given position.
+    InTypeDecl(Id, Pos, Box<ContractivityCheckError>),
+}
+
+impl ContractivityCheckError {
+    /// The type declaration whose check failed, if known.
+    pub fn type_decl(&self) -> Option<(&Id, &Pos)> {
+        match self {
+            ContractivityCheckError::InTypeDecl(id, pos, _) => Some((id, pos)),
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Display for ContractivityCheckError {
@@ -167,6 +185,9 @@ impl fmt::Display for ContractivityCheckError {
             ContractivityCheckError::ErrorExpandingID(rid, err) => {
                 format!("error when expanding ID {}\n{}", rid, err)
             }
+            ContractivityCheckError::InTypeDecl(id, _, err) => {
+                return write!(f, "{} in type {}", err, id);
+            }
             err => format!("{:?}", err),
         };
         write!(f, "eqWAlizer contractivity check failed with {}", message)
@@ -176,6 +197,19 @@ impl fmt::Display for ContractivityCheckError {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum VarianceCheckError {
     ErrorExpandingID(RemoteId, Box<Error>),
+    /// A failure while checking the declaration of the given opaque
+    /// type, located at the given position.
+    InTypeDecl(Id, Pos, Box<VarianceCheckError>),
+}
+
+impl VarianceCheckError {
+    /// The type declaration whose check failed, if known.
+    pub fn type_decl(&self) -> Option<(&Id, &Pos)> {
+        match self {
+            VarianceCheckError::InTypeDecl(id, pos, _) => Some((id, pos)),
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Display for VarianceCheckError {
@@ -184,6 +218,9 @@ impl fmt::Display for VarianceCheckError {
             VarianceCheckError::ErrorExpandingID(rid, err) => {
                 format!("error when expanding ID {}\n{}", rid, err)
             }
+            VarianceCheckError::InTypeDecl(id, _, err) => {
+                return write!(f, "{} in type {}", err, id);
+            }
         };
         write!(f, "eqWAlizer variance check failed with {}", message)
     }
@@ -201,20 +238,46 @@ impl fmt::Display for TransitiveCheckError {
     }
 }
 
-pub fn from_bytes(bytes: &Vec<u8>, filter_stub: bool) -> Result<AST, Error> {
+pub fn from_bytes(
+    bytes: &Vec<u8>,
+    filter_stub: bool,
+    preprocess_predicates: bool,
+    atoms: &AtomNameInterner,
+) -> Result<AST, Error> {
     let term = eetf::Term::decode(Cursor::new(bytes))?;
     if let Term::Tuple(res) = term {
         if let [Term::Atom(ok), forms, _] = &res.elements[..] {
             if ok.name == "ok" {
-                let converted_forms = convert::convert_forms(forms, false, filter_stub)?;
-                return Ok(preprocess::preprocess(converted_forms));
+                let converted_forms = convert::convert_forms(forms, false, filter_stub, atoms)?;
+                return Ok(preprocess::preprocess(
+                    converted_forms,
+                    preprocess_predicates,
+                ));
             }
         }
     }
     Err(Error::ConversionError(ConversionError::InvalidDecode))
 }
 
-pub fn from_beam(bytes: &Vec<u8>) -> Result<AST, Error> {
+/// Decode a module as `from_bytes` does, returning the preprocessing
+/// rules that fired for it. Only available to tests, through the
+/// `testing` feature.
+#[cfg(any(test, feature = "testing"))]
+pub fn preprocessing_log(bytes: &Vec<u8>) -> Result<Vec<preprocess::PreprocessEvent>, Error> {
+    let term = eetf::Term::decode(Cursor::new(bytes))?;
+    if let Term::Tuple(res) = term {
+        if let [Term::Atom(ok), forms, _] = &res.elements[..] {
+            if ok.name == "ok" {
+                let converted_forms =
+                    convert::convert_forms(forms, false, false, &AtomNameInterner::default())?;
+                return Ok(preprocess::preprocess_with_log(converted_forms, true).1);
+            }
+        }
+    }
+    Err(Error::ConversionError(ConversionError::InvalidDecode))
+}
+
+pub fn from_beam(bytes: &Vec<u8>, atoms: &AtomNameInterner) -> Result<AST, Error> {
     let mut cursor = Cursor::new(bytes);
     let mut buf: [u8; 4] = [0; 4];
     let mut tag: [u8; 4] = [0; 4];
@@ -240,7 +303,7 @@ pub fn from_beam(bytes: &Vec<u8>) -> Result<AST, Error> {
             if let Term::Tuple(terms) = t1 {
                 if let Term::Tuple(terms) = &terms.elements[2] {
                     let ast = &terms.elements[0];
-                    return Ok(convert::convert_forms(ast, true, true)?);
+                    return Ok(convert::convert_forms(ast, true, true, atoms)?);
                 }
             }
         } else {
@@ -272,3 +335,47 @@ pub fn exported_type_ids(ast: &AST) -> FxHashSet<Id> {
 pub fn to_bytes(ast: &Vec<&ExternalForm>) -> Vec<u8> {
     serde_json::to_vec(ast).unwrap()
 }
+
+/// An AST with the positions of its nodes taken out, so that edits to
+/// whitespace and comments, which only move nodes, keep the same hash.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AstShape {
+    /// A hash of the AST, without positions.
+    pub hash: u64,
+    /// The `start_byte` and `end_byte` of each `Pos::TextRange` in
+    /// the AST, in the order they appear in it.
+    pub positions: Vec<(u32, u32)>,
+}
+
+pub fn shape(ast: &Vec<&ExternalForm>) -> AstShape {
+    fn strip_positions(value: &mut serde_json::Value, positions: &mut Vec<(u32, u32)>) {
+        match value {
+            serde_json::Value::Array(values) => values
+                .iter_mut()
+                .for_each(|value| strip_positions(value, positions)),
+            serde_json::Value::Object(fields) => {
+                if let Some(range) = fields.get("TextRange").filter(|_| fields.len() == 1) {
+                    if let Ok(range) = serde_json::from_value::<TextRange>(range.clone()) {
+                        positions.push((range.start_byte, range.end_byte));
+                        *value = serde_json::Value::Null;
+                        return;
+                    }
+                }
+                fields
+                    .values_mut()
+                    .for_each(|value| strip_positions(value, positions));
+            }
+            _ => (),
+        }
+    }
+
+    let mut value = serde_json::to_value(ast).unwrap();
+    let mut positions = vec![];
+    strip_positions(&mut value, &mut positions);
+    let mut hasher = FxHasher::default();
+    value.to_string().hash(&mut hasher);
+    AstShape {
+        hash: hasher.finish(),
+        positions,
+    }
+}
@@ -9,11 +9,16 @@
 
 use elp_base_db::AtomName;
 use elp_syntax::SmolStr;
+use elp_types_db::eqwalizer::expr::Block;
 use elp_types_db::eqwalizer::expr::Body;
+use elp_types_db::eqwalizer::expr::Case;
 use elp_types_db::eqwalizer::expr::Clause;
 use elp_types_db::eqwalizer::expr::Expr;
 use elp_types_db::eqwalizer::expr::Lambda;
+use elp_types_db::eqwalizer::expr::Maybe;
+use elp_types_db::eqwalizer::expr::MaybeMatch;
 use elp_types_db::eqwalizer::expr::RemoteCall;
+use elp_types_db::eqwalizer::expr::Var;
 use elp_types_db::eqwalizer::guard::Guard;
 use elp_types_db::eqwalizer::guard::Test;
 use elp_types_db::eqwalizer::guard::TestAtom;
@@ -24,6 +29,7 @@ use elp_types_db::eqwalizer::guard::TestTuple;
 use elp_types_db::eqwalizer::guard::TestUnOp;
 use elp_types_db::eqwalizer::guard::TestVar;
 use elp_types_db::eqwalizer::pat::Pat;
+use elp_types_db::eqwalizer::pat::PatMatch;
 use elp_types_db::eqwalizer::pat::PatVar;
 use elp_types_db::eqwalizer::transformer;
 use elp_types_db::eqwalizer::transformer::Transformer;
@@ -184,11 +190,41 @@ fn as_tests(exprs: Vec<Expr>) -> Option<Vec<Test>> {
     Some(exprs_test)
 }
 
+/// A rewrite performed by the preprocessor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreprocessRule {
+    /// A predicate passed to `lists:partition/2` as `fun M:F/1` was
+    /// eta-expanded into a lambda with a guard.
+    ListsPartitionEtaExpansion,
+    /// A single-clause lambda passed to `lists:partition/2` had its
+    /// body moved into a guard.
+    ListsPartitionLambdaToGuard,
+    /// A `maybe` block without an `else` was desugared into nested
+    /// `case` expressions.
+    MaybeToCase,
+}
+
+/// Records that a preprocessing rule fired, and where.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreprocessEvent {
+    pub rule: PreprocessRule,
+    pub location: Pos,
+}
+
 struct Preprocessor {
     var: u32,
+    predicates: bool,
+    log: Vec<PreprocessEvent>,
 }
 
 impl Preprocessor {
+    fn record(&mut self, rule: PreprocessRule, location: &Pos) {
+        self.log.push(PreprocessEvent {
+            rule,
+            location: location.clone(),
+        });
+    }
+
     fn fresh_var(&mut self) -> SmolStr {
         let var = self.var;
         self.var += 1;
@@ -227,11 +263,31 @@ impl Preprocessor {
         }
     }
 
+    /// Like `preprocess_lists_partition_arg_fun`, but checks in debug
+    /// builds that a predicate no rule applies to is returned unchanged,
+    /// positions included.
+    fn rewrite_lists_partition_arg_fun(&mut self, location: &Pos, expr: Expr) -> Expr {
+        if !cfg!(debug_assertions) {
+            return self.preprocess_lists_partition_arg_fun(location, expr);
+        }
+        let fired = self.log.len();
+        let original = expr.clone();
+        let res = self.preprocess_lists_partition_arg_fun(location, expr);
+        if self.log.len() == fired {
+            assert_eq!(
+                res, original,
+                "declined preprocessing rule changed its input"
+            );
+        }
+        res
+    }
+
     fn preprocess_lists_partition_arg_fun(&mut self, location: &Pos, expr: Expr) -> Expr {
         match expr {
             Expr::RemoteFun(rfun)
                 if PREDICATES.contains(&rfun.id.clone().into()) && rfun.id.arity == 1 =>
             {
+                self.record(PreprocessRule::ListsPartitionEtaExpansion, location);
                 Expr::Lambda(self.eta_expand_unary_predicate(location, rfun.id.name.clone()))
             }
             Expr::Lambda(lambda) if lambda.clauses.len() == 1 => {
@@ -239,6 +295,7 @@ impl Preprocessor {
                 if let [body] = &clause.body.exprs[..] {
                     if let [pat] = &clause.pats[..] {
                         if let Some(test) = as_test(body.clone()) {
+                            self.record(PreprocessRule::ListsPartitionLambdaToGuard, location);
                             return Expr::Lambda(Lambda {
                                 location: lambda.location.clone(),
                                 name: lambda.name.clone(),
@@ -274,6 +331,70 @@ impl Preprocessor {
     }
 }
 
+impl Preprocessor {
+    /// Desugar the body of an else-less `maybe` block. Each
+    /// `Pat ?= Expr` becomes a `case` whose first clause continues with
+    /// the rest of the block, and whose second clause returns the
+    /// non-matching value, which is what the `maybe` block evaluates to.
+    fn desugar_maybe_body(&mut self, mut exprs: Vec<Expr>) -> Vec<Expr> {
+        let index = match exprs
+            .iter()
+            .position(|expr| matches!(expr, Expr::MaybeMatch(_)))
+        {
+            Some(index) => index,
+            None => return exprs,
+        };
+        let rest = exprs.split_off(index + 1);
+        let MaybeMatch { location, pat, arg } = match exprs.pop() {
+            Some(Expr::MaybeMatch(maybe_match)) => maybe_match,
+            _ => unreachable!(),
+        };
+        let var = self.fresh_var();
+        let var_expr = Expr::Var(Var {
+            location: location.clone(),
+            n: var.clone(),
+        });
+        let matched = if rest.is_empty() {
+            // The value of a trailing `Pat ?= Expr` is the value of `Expr`
+            Clause {
+                location: location.clone(),
+                pats: vec![Pat::PatMatch(PatMatch {
+                    location: location.clone(),
+                    pat: Box::new(Pat::pat_var(location.clone(), var.clone())),
+                    arg: Box::new(pat),
+                })],
+                guards: vec![],
+                body: Body {
+                    exprs: vec![var_expr.clone()],
+                },
+            }
+        } else {
+            Clause {
+                location: location.clone(),
+                pats: vec![pat],
+                guards: vec![],
+                body: Body {
+                    exprs: self.desugar_maybe_body(rest),
+                },
+            }
+        };
+        let unmatched = Clause {
+            location: location.clone(),
+            pats: vec![Pat::pat_var(location.clone(), var)],
+            guards: vec![],
+            body: Body {
+                exprs: vec![var_expr],
+            },
+        };
+        exprs.push(Expr::Case(Case {
+            location,
+            expr: arg,
+            clauses: vec![matched, unmatched],
+        }));
+        exprs
+    }
+}
+
 impl Transformer<()> for Preprocessor {
     fn transform_expr(&mut self, expr: Expr) -> Result<Expr, ()> {
         match expr {
@@ -286,9 +407,9 @@ impl Transformer<()> for Preprocessor {
                         arity: 2,
                     },
                 args,
-            }) if module == "lists" && name == "partition" => {
+            }) if self.predicates && module == "lists" && name == "partition" => {
                 let [arg_fun, arg_list] = args.try_into().unwrap();
-                let arg_trans = self.preprocess_lists_partition_arg_fun(&location, arg_fun);
+                let arg_trans = self.rewrite_lists_partition_arg_fun(&location, arg_fun);
                 Ok(Expr::RemoteCall(RemoteCall {
                     location,
                     id: RemoteId {
@@ -299,12 +420,38 @@ impl Transformer<()> for Preprocessor {
                     args: vec![arg_trans, arg_list],
                 }))
             }
+            Expr::Maybe(maybe) => {
+                let Maybe { location, body } = maybe;
+                let body = self.transform_body(body)?;
+                self.record(PreprocessRule::MaybeToCase, &location);
+                let exprs = self.desugar_maybe_body(body.exprs);
+                match <[Expr; 1]>::try_from(exprs) {
+                    Ok([expr]) => Ok(expr),
+                    Err(exprs) => Ok(Expr::Block(Block {
+                        location,
+                        body: Body { exprs },
+                    })),
+                }
+            }
             e => transformer::walk_expr(self, e),
         }
     }
 }
 
-pub(crate) fn preprocess(ast: AST) -> AST {
-    let mut preprocessor = Preprocessor { var: 0 };
-    preprocessor.transform_ast(ast).unwrap()
+/// Rewrite the given forms. When `predicates` is false, predicates
+/// passed to `lists:partition/2` are left unchanged.
+pub(crate) fn preprocess(ast: AST, predicates: bool) -> AST {
+    preprocess_with_log(ast, predicates).0
+}
+
+/// Like `preprocess`, but also returns the rules that fired, in the
+/// order they were applied.
+pub(crate) fn preprocess_with_log(ast: AST, predicates: bool) -> (AST, Vec<PreprocessEvent>) {
+    let mut preprocessor = Preprocessor {
+        var: 0,
+        predicates,
+        log: vec![],
+    };
+    let ast = preprocessor.transform_ast(ast).unwrap();
+    (ast, preprocessor.log)
 }
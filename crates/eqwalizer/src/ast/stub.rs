@@ -19,11 +19,19 @@ use elp_types_db::eqwalizer::form::RecDecl;
 use elp_types_db::eqwalizer::form::TypeDecl;
 use fxhash::FxHashMap;
 use fxhash::FxHashSet;
+use serde::Deserialize;
 use serde::Serialize;
 
+use super::Error;
 use super::Id;
 
-#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+/// Marks the start of the bytes produced by `ModuleStub::to_versioned_bytes`.
+pub const STUB_MAGIC: &[u8] = b"ELPSTUB";
+/// Bump whenever the serialized form of a `ModuleStub` changes, so that
+/// stubs persisted by another version of ELP are not misread.
+pub const STUB_FORMAT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct ModuleStub {
     pub module: ModuleName,
     pub exports: FxHashSet<Id>,
@@ -59,7 +67,39 @@ impl ModuleStub {
         }
     }
 
+    /// The stub as sent to eqWAlizer.
     pub fn to_bytes(&self) -> Vec<u8> {
         serde_json::to_vec(self).unwrap()
     }
+
+    /// The stub prefixed with a header recording the format version, for
+    /// storing it outside of a single ELP session.
+    pub fn to_versioned_bytes(&self) -> Vec<u8> {
+        let mut bytes = STUB_MAGIC.to_vec();
+        bytes.extend_from_slice(&STUB_FORMAT_VERSION.to_be_bytes());
+        bytes.extend(self.to_bytes());
+        bytes
+    }
+
+    /// Read a stub written by `to_versioned_bytes`, failing if it was
+    /// written with a different format version.
+    pub fn from_versioned_bytes(bytes: &[u8]) -> Result<ModuleStub, Error> {
+        let rest = bytes
+            .strip_prefix(STUB_MAGIC)
+            .ok_or_else(|| Error::DecodeError("missing module stub header".to_string()))?;
+        if rest.len() < 4 {
+            return Err(Error::DecodeError(
+                "truncated module stub header".to_string(),
+            ));
+        }
+        let (version, json) = rest.split_at(4);
+        let version = u32::from_be_bytes(version.try_into().unwrap());
+        if version != STUB_FORMAT_VERSION {
+            return Err(Error::DecodeError(format!(
+                "module stub has format version {}, expected {}",
+                version, STUB_FORMAT_VERSION
+            )));
+        }
+        serde_json::from_slice(json).map_err(|err| Error::DecodeError(err.to_string()))
+    }
 }
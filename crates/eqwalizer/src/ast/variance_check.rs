@@ -72,7 +72,10 @@ impl VarianceChecker<'_> {
         stub: &mut ModuleStub,
         t: &TypeDecl,
     ) -> Result<(), VarianceCheckError> {
-        if let Some((ty_var, expansion)) = self.expands_to_contravariant(t)? {
+        let contravariant = self.expands_to_contravariant(t).map_err(|err| {
+            VarianceCheckError::InTypeDecl(t.id.clone(), t.location.clone(), Box::new(err))
+        })?;
+        if let Some((ty_var, expansion)) = contravariant {
             let invalid = self.to_invalid(t, &ty_var, expansion);
             stub.invalid_forms.push(invalid);
             stub.private_opaques.remove(&t.id);
@@ -7,12 +7,17 @@
  * of this source tree.
  */
 
+use std::borrow::Cow;
 use std::fs;
+use std::fs::File;
+use std::fs::OpenOptions;
 use std::io::BufRead;
 use std::io::BufReader;
 use std::io::BufWriter;
 use std::io::ErrorKind;
+use std::io::Read;
 use std::io::Write;
+use std::path::Path;
 use std::process::Child;
 use std::process::ChildStdin;
 use std::process::ChildStdout;
@@ -28,6 +33,9 @@ use elp_base_db::limit_logged_string;
 use elp_base_db::ModuleName;
 use elp_types_db::eqwalizer::types::Type;
 use elp_types_db::eqwalizer::EqwalizerDiagnostic;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
 use fxhash::FxHashMap;
 use serde::Deserialize;
 use serde::Serialize;
@@ -36,6 +44,7 @@ use timeout_readwrite::TimeoutReader;
 use timeout_readwrite::TimeoutWriter;
 
 use crate::ast::Pos;
+use crate::ConfigValue;
 
 #[derive(Deserialize, Debug)]
 pub enum EqWAlizerASTFormat {
@@ -48,6 +57,10 @@ pub enum EqWAlizerASTFormat {
 pub enum MsgFromEqWAlizer {
     EnteringModule {
         module: ModuleName,
+        /// Whether eqWAlizer accepts compressed AST payloads. Older
+        /// versions do not send the flag, and only get uncompressed ones.
+        #[serde(default)]
+        compression: bool,
     },
     GetAstBytes {
         module: ModuleName,
@@ -71,20 +84,78 @@ pub enum MsgFromEqWAlizer {
 #[derive(Serialize, Debug)]
 #[serde(tag = "tag", content = "content")]
 pub enum MsgToEqWAlizer {
-    ELPEnteringModule,
+    ELPEnteringModule {
+        options: ModuleOptions,
+    },
+    /// Only check the function `name/arity` of the module being
+    /// entered. Sent right after `ELPEnteringModule`.
+    ELPScopeFunction {
+        name: String,
+        arity: u32,
+    },
     ELPExitingModule,
-    GetAstBytesReply { ast_bytes_len: u32 },
+    GetAstBytesReply {
+        ast_bytes_len: u32,
+        // Omitted when false, so that the reply is unchanged for
+        // versions of eqWAlizer which do not support compression.
+        #[serde(skip_serializing_if = "std::ops::Not::not")]
+        compressed: bool,
+    },
     CannotCompleteRequest,
 }
 
+/// The flags eqWAlizer checks a module with, sent with
+/// `ELPEnteringModule`. They take precedence over the ones eqWAlizer
+/// read from the environment when it started, so that modules with
+/// different configs are checked by the same process. A flag sent as
+/// `null` keeps its value from the environment.
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModuleOptions {
+    pub fault_tolerance: ConfigValue,
+    pub occurrence_typing: ConfigValue,
+    pub clause_coverage: ConfigValue,
+    pub report_bad_maps: ConfigValue,
+    pub overloaded_spec_dynamic_result: ConfigValue,
+    pub report_dynamic_lambdas: ConfigValue,
+}
+
+/// Environment variable naming a file to which the messages exchanged
+/// with eqWAlizer are appended, for debugging the protocol.
+pub const TRACE_ENV_VAR: &str = "ELP_EQWALIZER_TRACE";
+
 pub struct IpcHandle {
     writer: BufWriter<TimeoutWriter<ChildStdin>>,
     reader: BufReader<TimeoutReader<ChildStdout>>,
-    _child_for_drop: JodChild,
+    trace: Option<BufWriter<File>>,
+    compression: bool,
+    /// Time spent by ELP producing the ASTs eqWAlizer requested, since
+    /// the last call to `take_ast_time`.
+    ast_time: Duration,
+    child: JodChild,
+}
+
+/// AST payloads smaller than this are always sent uncompressed, as
+/// compressing them costs more than it saves.
+pub const COMPRESSION_THRESHOLD: usize = 65_536;
+
+/// Compress an AST payload with gzip.
+pub fn compress_bytes(bytes: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+    encoder.write_all(bytes).context("compressing bytes")?;
+    encoder.finish().context("compressing bytes")
 }
 
-const WRITE_TIMEOUT: Duration = Duration::from_secs(240);
-const READ_TIMEOUT: Duration = Duration::from_secs(240);
+/// Decompress an AST payload compressed by `compress_bytes`.
+pub fn decompress_bytes(bytes: &[u8]) -> Result<Vec<u8>> {
+    let mut decoded = Vec::new();
+    GzDecoder::new(bytes)
+        .read_to_end(&mut decoded)
+        .context("decompressing bytes")?;
+    Ok(decoded)
+}
+
+pub const WRITE_TIMEOUT: Duration = Duration::from_secs(240);
+pub const READ_TIMEOUT: Duration = Duration::from_secs(240);
 
 impl IpcHandle {
     fn spawn_cmd(cmd: &mut Command) -> Result<Child> {
@@ -116,6 +187,16 @@ impl IpcHandle {
     }
 
     pub fn from_command(cmd: &mut Command) -> Result<Self> {
+        Self::from_command_with_timeouts(cmd, READ_TIMEOUT, WRITE_TIMEOUT)
+    }
+
+    /// Like `from_command`, but a read or write taking longer than the
+    /// given timeout fails with an error.
+    pub fn from_command_with_timeouts(
+        cmd: &mut Command,
+        read_timeout: Duration,
+        write_timeout: Duration,
+    ) -> Result<Self> {
         cmd.stdin(Stdio::piped())
             .stdout(Stdio::piped())
             // for debugging purposes
@@ -131,19 +212,84 @@ impl IpcHandle {
             .take()
             .context("failed to get stdout for eqwalizer process")?;
 
-        let _child_for_drop = JodChild(child);
-        let writer = BufWriter::new(TimeoutWriter::new(stdin, WRITE_TIMEOUT));
-        let reader = BufReader::new(TimeoutReader::new(stdout, READ_TIMEOUT));
+        let child = JodChild(child);
+        let writer = BufWriter::new(TimeoutWriter::new(stdin, write_timeout));
+        let reader = BufReader::new(TimeoutReader::new(stdout, read_timeout));
 
         Ok(Self {
             writer,
             reader,
-            _child_for_drop,
+            trace: None,
+            compression: false,
+            ast_time: Duration::ZERO,
+            child,
         })
     }
 
+    /// Whether the eqWAlizer process is still running. Unlike
+    /// `exit_code`, this does not wait for the process to exit.
+    pub fn is_alive(&mut self) -> bool {
+        matches!(self.child.0.try_wait(), Ok(None))
+    }
+
+    /// The exit code of the eqWAlizer process, if it has exited. The
+    /// code is `None` if the process was killed by a signal.
+    pub fn exit_code(&mut self) -> Option<Option<i32>> {
+        // The process can close its stdout shortly before it exits
+        for _ in 0..10 {
+            if let Ok(Some(status)) = self.child.0.try_wait() {
+                return Some(status.code());
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        None
+    }
+
+    /// Append every message exchanged with eqWAlizer to the file at
+    /// `path`, one per line. Messages read are prefixed with `<-` and
+    /// messages written with `->`. AST payloads are summarized by their
+    /// length. If the file cannot be opened, the error is logged and
+    /// messages are not traced.
+    pub fn with_trace(mut self, path: &Path) -> Self {
+        match OpenOptions::new().create(true).append(true).open(path) {
+            Ok(file) => self.trace = Some(BufWriter::new(file)),
+            Err(err) => log::error!(
+                "Could not open eqWAlizer trace file {}, not tracing: {}",
+                path.display(),
+                err
+            ),
+        }
+        self
+    }
+
+    /// Compress AST payloads larger than `COMPRESSION_THRESHOLD`. Only
+    /// enable this if eqWAlizer advertised support for it.
+    pub fn set_compression(&mut self, enabled: bool) {
+        self.compression = enabled;
+    }
+
+    pub fn add_ast_time(&mut self, time: Duration) {
+        self.ast_time += time;
+    }
+
+    /// The time spent producing ASTs since the last call, so that it
+    /// can be told apart from the time eqWAlizer spent checking.
+    pub fn take_ast_time(&mut self) -> Duration {
+        std::mem::take(&mut self.ast_time)
+    }
+
+    fn trace(&mut self, direction: &str, msg: &str) {
+        if let Some(trace) = &mut self.trace {
+            // Tracing is for debugging only, so failing to write the
+            // trace must not fail the session.
+            let _ = writeln!(trace, "{} {}", direction, limit_logged_string(msg));
+            let _ = trace.flush();
+        }
+    }
+
     pub fn receive(&mut self) -> Result<MsgFromEqWAlizer> {
         let buf = self.receive_line().context("receiving message")?;
+        self.trace("<-", buf.trim_end());
         let deserialized = serde_json::from_str(&buf)
             .with_context(|| format!("parsing for eqwalizer: {buf:?}"))?;
         Ok(deserialized)
@@ -156,6 +302,7 @@ impl IpcHandle {
 
     pub fn send(&mut self, msg: &MsgToEqWAlizer) -> Result<()> {
         let msg = serde_json::to_string(msg).expect("failed to serialize msg to eqwalizer");
+        self.trace("->", &msg);
         writeln!(self.writer, "{}", msg).with_context(|| format!("writing message: {:?}", msg))?;
         self.writer
             .flush()
@@ -167,6 +314,7 @@ impl IpcHandle {
         // Don't exceed pipe buffer size on Mac or Linux
         // https://unix.stackexchange.com/a/11954/147568
         let chunk_size = 65_536;
+        self.trace("->", &format!("<{} bytes>", msg.len()));
         for (idx, chunk) in msg.chunks(chunk_size).enumerate() {
             self.writer
                 .write_all(chunk)
@@ -178,6 +326,27 @@ impl IpcHandle {
         Ok(())
     }
 
+    /// Reply to a `GetAstBytes` request with the given AST, compressing
+    /// it if compression is enabled and the AST is large enough.
+    pub fn send_ast_bytes(&mut self, ast_bytes: &[u8]) -> Result<()> {
+        let (payload, compressed) = self.encode_payload(ast_bytes)?;
+        let ast_bytes_len = payload.len().try_into()?;
+        self.send(&MsgToEqWAlizer::GetAstBytesReply {
+            ast_bytes_len,
+            compressed,
+        })?;
+        self.receive_newline()?;
+        self.send_bytes(&payload)
+    }
+
+    fn encode_payload<'a>(&self, ast_bytes: &'a [u8]) -> Result<(Cow<'a, [u8]>, bool)> {
+        if self.compression && ast_bytes.len() > COMPRESSION_THRESHOLD {
+            Ok((Cow::Owned(compress_bytes(ast_bytes)?), true))
+        } else {
+            Ok((Cow::Borrowed(ast_bytes), false))
+        }
+    }
+
     fn receive_line(&mut self) -> Result<String> {
         let mut buf = String::new();
         self.reader
@@ -7,28 +7,39 @@
  * of this source tree.
  */
 
+use std::collections::BTreeMap;
 use std::env;
 use std::ffi::OsString;
+use std::fmt;
 use std::fs;
+use std::hash::Hash;
+use std::hash::Hasher;
 use std::io::Write;
 use std::os::unix::prelude::PermissionsExt;
+use std::path::Path;
 use std::path::PathBuf;
 use std::process::Command;
 use std::sync::Arc;
+use std::time::Duration;
 use std::time::Instant;
 
 use anyhow::Context;
 use anyhow::Result;
+use ast::AstShape;
 use ast::Error;
 use ast::Pos;
 use elp_base_db::limit_logged_string;
 use elp_base_db::ModuleName;
 use elp_base_db::ProjectId;
+use elp_types_db::eqwalizer::form::ExternalForm;
 use elp_types_db::eqwalizer::types::Type;
 pub use elp_types_db::eqwalizer::EqwalizerDiagnostic;
 use fxhash::FxHashMap;
+use fxhash::FxHasher;
 use lazy_static::lazy_static;
 use parking_lot::Mutex;
+use serde::Deserialize;
+use serde::Serialize;
 use tempfile::Builder;
 use tempfile::TempPath;
 
@@ -50,8 +61,11 @@ pub enum Mode {
     Server,
     Shell,
 }
+/// The environment variable eqWAlizer reads the `Mode` from.
+const MODE_ENV_VAR: &str = "EQWALIZER_MODE";
+
 impl Mode {
-    fn to_env_var(&self) -> &str {
+    fn to_env_var(&self) -> &'static str {
         match self {
             Mode::Cli => "elp_cli",
             Mode::Server => "elp_ide",
@@ -60,55 +74,465 @@ impl Mode {
     }
 }
 
-#[derive(Clone, Debug, Default, Eq, PartialEq)]
+/// The value of an eqWAlizer flag. `Inherit` leaves the flag to the
+/// config it is layered on, see `EqwalizerConfig::inherit`, and in the
+/// end to the eqWAlizer default. It is serialized as an optional
+/// boolean, with `Inherit` as `null`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(from = "Option<bool>", into = "Option<bool>")]
+pub enum ConfigValue {
+    On,
+    Off,
+    #[default]
+    Inherit,
+}
+
+impl ConfigValue {
+    pub fn is_on(self) -> bool {
+        self == ConfigValue::On
+    }
+
+    /// This value, or `default` if it is `Inherit`.
+    pub fn or(self, default: ConfigValue) -> ConfigValue {
+        match self {
+            ConfigValue::Inherit => default,
+            value => value,
+        }
+    }
+
+    /// The value of the environment variable for this flag, if it is
+    /// set at all.
+    fn env_value(self) -> Option<&'static str> {
+        match self {
+            ConfigValue::On => Some("true"),
+            ConfigValue::Off => Some("false"),
+            ConfigValue::Inherit => None,
+        }
+    }
+}
+
+impl From<bool> for ConfigValue {
+    fn from(value: bool) -> Self {
+        if value {
+            ConfigValue::On
+        } else {
+            ConfigValue::Off
+        }
+    }
+}
+
+impl From<Option<bool>> for ConfigValue {
+    fn from(value: Option<bool>) -> Self {
+        value.map_or(ConfigValue::Inherit, ConfigValue::from)
+    }
+}
+
+impl From<ConfigValue> for Option<bool> {
+    fn from(value: ConfigValue) -> Self {
+        match value {
+            ConfigValue::On => Some(true),
+            ConfigValue::Off => Some(false),
+            ConfigValue::Inherit => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
 pub struct EqwalizerConfig {
-    pub fault_tolerance: Option<bool>,
-    pub occurrence_typing: Option<bool>,
-    pub clause_coverage: Option<bool>,
-    pub report_bad_maps: Option<bool>,
-    pub overloaded_spec_dynamic_result: Option<bool>,
-    pub report_dynamic_lambdas: Option<bool>,
+    pub fault_tolerance: ConfigValue,
+    pub occurrence_typing: ConfigValue,
+    pub clause_coverage: ConfigValue,
+    pub report_bad_maps: ConfigValue,
+    pub overloaded_spec_dynamic_result: ConfigValue,
+    pub report_dynamic_lambdas: ConfigValue,
+    pub preprocess_predicates: ConfigValue,
+    pub related_information: ConfigValue,
+    /// Read module ASTs from `<module>.etf` files in this directory, as
+    /// written by `elp parse-all`, rather than parsing the modules.
+    /// Modules without such a file are parsed as usual.
+    pub ast_dir: Option<PathBuf>,
+    /// Restricts which modules are checked. Modules it excludes are
+    /// skipped and produce no diagnostics.
+    pub module_filter: Option<ModuleFilter>,
+    /// Append the messages exchanged with eqWAlizer to this file. See
+    /// also `ipc::TRACE_ENV_VAR`.
+    pub ipc_trace: Option<PathBuf>,
+    /// How long to wait for eqWAlizer to read or write a message before
+    /// giving up. Defaults to `ipc::READ_TIMEOUT` and
+    /// `ipc::WRITE_TIMEOUT`.
+    pub ipc_read_timeout: Option<Duration>,
+    pub ipc_write_timeout: Option<Duration>,
+    /// When eqWAlizer fails on a module, log a warning and report no
+    /// diagnostics for it, rather than failing the whole run.
+    pub skip_failed_modules: ConfigValue,
 }
 impl EqwalizerConfig {
+    pub fn builder() -> EqwalizerConfigBuilder {
+        EqwalizerConfigBuilder::default()
+    }
+
+    /// This config, with the flags it leaves as `Inherit`, and the
+    /// settings it leaves unset, taken from `defaults`.
+    pub fn inherit(&self, defaults: &EqwalizerConfig) -> EqwalizerConfig {
+        EqwalizerConfig {
+            fault_tolerance: self.fault_tolerance.or(defaults.fault_tolerance),
+            occurrence_typing: self.occurrence_typing.or(defaults.occurrence_typing),
+            clause_coverage: self.clause_coverage.or(defaults.clause_coverage),
+            report_bad_maps: self.report_bad_maps.or(defaults.report_bad_maps),
+            overloaded_spec_dynamic_result: self
+                .overloaded_spec_dynamic_result
+                .or(defaults.overloaded_spec_dynamic_result),
+            report_dynamic_lambdas: self
+                .report_dynamic_lambdas
+                .or(defaults.report_dynamic_lambdas),
+            preprocess_predicates: self
+                .preprocess_predicates
+                .or(defaults.preprocess_predicates),
+            related_information: self.related_information.or(defaults.related_information),
+            ast_dir: self.ast_dir.clone().or_else(|| defaults.ast_dir.clone()),
+            module_filter: self
+                .module_filter
+                .clone()
+                .or_else(|| defaults.module_filter.clone()),
+            ipc_trace: self
+                .ipc_trace
+                .clone()
+                .or_else(|| defaults.ipc_trace.clone()),
+            ipc_read_timeout: self.ipc_read_timeout.or(defaults.ipc_read_timeout),
+            ipc_write_timeout: self.ipc_write_timeout.or(defaults.ipc_write_timeout),
+            skip_failed_modules: self.skip_failed_modules.or(defaults.skip_failed_modules),
+        }
+    }
+
+    /// This config, with the settings which can also be given in the
+    /// environment taken from it when they are not set. Only the IPC
+    /// trace file can be given this way, see `ipc::TRACE_ENV_VAR`.
+    pub fn resolve_env(&self) -> EqwalizerConfig {
+        EqwalizerConfig {
+            ipc_trace: self
+                .ipc_trace
+                .clone()
+                .or_else(|| env::var_os(ipc::TRACE_ENV_VAR).map(PathBuf::from)),
+            ..self.clone()
+        }
+    }
+
+    /// The environment variables eqWAlizer is started with for this
+    /// config. Flags left as `Inherit` are not set, so eqWAlizer uses
+    /// its own defaults for them.
+    pub fn cmd_env(&self) -> Vec<(&'static str, &'static str)> {
+        [
+            ("EQWALIZER_TOLERATE_ERRORS", self.fault_tolerance),
+            ("EQWALIZER_EQWATER", self.occurrence_typing),
+            ("EQWALIZER_CLAUSE_COVERAGE", self.clause_coverage),
+            ("EQWALIZER_REPORT_BAD_MAPS", self.report_bad_maps),
+            (
+                "EQWALIZER_OVERLOADED_SPEC_DYNAMIC_RESULT",
+                self.overloaded_spec_dynamic_result,
+            ),
+            (
+                "EQWALIZER_REPORT_DYNAMIC_LAMBDAS",
+                self.report_dynamic_lambdas,
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(var, value)| Some((var, value.env_value()?)))
+        .collect()
+    }
+
     fn set_cmd_env(&self, cmd: &mut Command) {
-        self.fault_tolerance
-            .map(|cfg| cmd.env("EQWALIZER_TOLERATE_ERRORS", cfg.to_string()));
-        self.occurrence_typing
-            .map(|cfg| cmd.env("EQWALIZER_EQWATER", cfg.to_string()));
-        self.clause_coverage
-            .map(|cfg| cmd.env("EQWALIZER_CLAUSE_COVERAGE", cfg.to_string()));
-        self.report_bad_maps
-            .map(|cfg| cmd.env("EQWALIZER_REPORT_BAD_MAPS", cfg.to_string()));
-        self.overloaded_spec_dynamic_result
-            .map(|cfg| cmd.env("EQWALIZER_OVERLOADED_SPEC_DYNAMIC_RESULT", cfg.to_string()));
-        self.report_dynamic_lambdas
-            .map(|cfg| cmd.env("EQWALIZER_REPORT_DYNAMIC_LAMBDAS", cfg.to_string()));
+        cmd.envs(self.cmd_env());
+    }
+
+    /// The flags of this config, as sent to eqWAlizer with each module.
+    pub fn module_options(&self) -> ipc::ModuleOptions {
+        ipc::ModuleOptions {
+            fault_tolerance: self.fault_tolerance,
+            occurrence_typing: self.occurrence_typing,
+            clause_coverage: self.clause_coverage,
+            report_bad_maps: self.report_bad_maps,
+            overloaded_spec_dynamic_result: self.overloaded_spec_dynamic_result,
+            report_dynamic_lambdas: self.report_dynamic_lambdas,
+        }
     }
 
     pub fn default_test() -> EqwalizerConfig {
         EqwalizerConfig {
-            fault_tolerance: Some(false),
-            occurrence_typing: Some(true),
-            clause_coverage: Some(false),
-            report_bad_maps: Some(false),
-            overloaded_spec_dynamic_result: Some(false),
-            report_dynamic_lambdas: Some(false),
+            fault_tolerance: ConfigValue::Off,
+            occurrence_typing: ConfigValue::On,
+            clause_coverage: ConfigValue::Off,
+            report_bad_maps: ConfigValue::Off,
+            overloaded_spec_dynamic_result: ConfigValue::Off,
+            report_dynamic_lambdas: ConfigValue::Off,
+            preprocess_predicates: ConfigValue::On,
+            related_information: ConfigValue::Off,
+            ast_dir: None,
+            module_filter: None,
+            ipc_trace: None,
+            ipc_read_timeout: None,
+            ipc_write_timeout: None,
+            skip_failed_modules: ConfigValue::Inherit,
         }
     }
 }
 
+/// Allow and deny lists of module-name globs, such as `my_app_*`.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct ModuleFilter {
+    /// If non-empty, only modules matching one of these globs are checked.
+    pub allow: Vec<String>,
+    /// Modules matching one of these globs are never checked, even if
+    /// they are allowed.
+    pub deny: Vec<String>,
+}
+
+impl ModuleFilter {
+    pub fn is_allowed(&self, module: &str) -> bool {
+        let matches = |globs: &[String]| {
+            globs.iter().any(|glob| {
+                glob::Pattern::new(glob).map_or(false, |pattern| pattern.matches(module))
+            })
+        };
+        (self.allow.is_empty() || matches(&self.allow)) && !matches(&self.deny)
+    }
+}
+
+/// Builds an `EqwalizerConfig` one option at a time. Options which
+/// are not set are left to the eqWAlizer default.
+#[derive(Debug, Default)]
+pub struct EqwalizerConfigBuilder(EqwalizerConfig);
+
+impl EqwalizerConfigBuilder {
+    pub fn fault_tolerance(mut self, value: bool) -> Self {
+        self.0.fault_tolerance = value.into();
+        self
+    }
+
+    pub fn occurrence_typing(mut self, value: bool) -> Self {
+        self.0.occurrence_typing = value.into();
+        self
+    }
+
+    pub fn clause_coverage(mut self, value: bool) -> Self {
+        self.0.clause_coverage = value.into();
+        self
+    }
+
+    pub fn report_bad_maps(mut self, value: bool) -> Self {
+        self.0.report_bad_maps = value.into();
+        self
+    }
+
+    pub fn overloaded_spec_dynamic_result(mut self, value: bool) -> Self {
+        self.0.overloaded_spec_dynamic_result = value.into();
+        self
+    }
+
+    pub fn report_dynamic_lambdas(mut self, value: bool) -> Self {
+        self.0.report_dynamic_lambdas = value.into();
+        self
+    }
+
+    pub fn preprocess_predicates(mut self, value: bool) -> Self {
+        self.0.preprocess_predicates = value.into();
+        self
+    }
+
+    pub fn related_information(mut self, value: bool) -> Self {
+        self.0.related_information = value.into();
+        self
+    }
+
+    pub fn ast_dir(mut self, value: PathBuf) -> Self {
+        self.0.ast_dir = Some(value);
+        self
+    }
+
+    pub fn module_filter(mut self, value: ModuleFilter) -> Self {
+        self.0.module_filter = Some(value);
+        self
+    }
+
+    pub fn ipc_trace(mut self, value: PathBuf) -> Self {
+        self.0.ipc_trace = Some(value);
+        self
+    }
+
+    pub fn ipc_read_timeout(mut self, value: Duration) -> Self {
+        self.0.ipc_read_timeout = Some(value);
+        self
+    }
+
+    pub fn ipc_write_timeout(mut self, value: Duration) -> Self {
+        self.0.ipc_write_timeout = Some(value);
+        self
+    }
+
+    pub fn skip_failed_modules(mut self, value: bool) -> Self {
+        self.0.skip_failed_modules = value.into();
+        self
+    }
+
+    pub fn build(self) -> EqwalizerConfig {
+        self.0
+    }
+}
+
 // Bundle file with command to make sure it's not removed too early
 #[derive(Clone)]
 pub struct Eqwalizer {
     pub mode: Mode,
+    cache: ResultCache,
+    exe: Option<Arc<Mutex<EqwalizerExe>>>,
+    timing_sink: Option<TimingSink>,
+}
+
+/// Called with the name of each module eqWAlizer checked, and how long
+/// it took, not counting the time ELP spent producing the ASTs
+/// eqWAlizer requested.
+pub type TimingSink = Arc<dyn Fn(&str, Duration) + Send + Sync>;
+
+/// The results of previous eqWAlizer runs, so that a module is only
+/// checked again if its AST, the stubs of the modules it depends on, or
+/// the eqWAlizer config have changed. The AST is compared without the
+/// positions of its nodes, so an edit which only moves code, such as
+/// to whitespace or comments, reuses the result with its ranges moved.
+#[derive(Clone, Default)]
+struct ResultCache(Arc<Mutex<CachedResults>>);
+
+#[derive(Default)]
+struct CachedResults {
+    results: FxHashMap<(ProjectId, ModuleName), CachedResult>,
+    /// Incremented on each lookup and insertion, to tell which
+    /// results were used least recently.
+    clock: u64,
+}
+
+struct CachedResult {
+    config: Arc<EqwalizerConfig>,
+    fingerprint: u64,
+    /// The positions of the nodes in the AST that was checked.
+    shape: Arc<AstShape>,
+    dependencies: Arc<Vec<ModuleName>>,
+    diagnostics: Arc<EqwalizerDiagnostics>,
+    last_used: u64,
+}
+
+impl ResultCache {
+    /// How many results are kept. Once full, the quarter used least
+    /// recently is dropped.
+    const CAPACITY: usize = 4096;
+
+    fn get(
+        &self,
+        db: &dyn EqwalizerDiagnosticsDatabase,
+        project_id: ProjectId,
+        module: &ModuleName,
+    ) -> Option<EqwalizerDiagnostics> {
+        let mut cache = self.0.lock();
+        cache.clock += 1;
+        let clock = cache.clock;
+        let key = (project_id, module.clone());
+        let cached = cache.results.get_mut(&key)?;
+        let current = fingerprint(db, project_id, module, &cached.dependencies);
+        let shape = match current {
+            Some((fingerprint, shape))
+                if fingerprint == cached.fingerprint
+                    && *cached.config == *db.eqwalizer_module_config(module.clone()) =>
+            {
+                shape
+            }
+            _ => {
+                cache.results.remove(&key);
+                return None;
+            }
+        };
+        if shape.positions != cached.shape.positions {
+            match cached
+                .diagnostics
+                .moved(module, &cached.shape.positions, &shape.positions)
+            {
+                Some(moved) => {
+                    cached.diagnostics = Arc::new(moved);
+                    cached.shape = shape;
+                }
+                None => {
+                    cache.results.remove(&key);
+                    return None;
+                }
+            }
+        }
+        cached.last_used = clock;
+        Some((*cached.diagnostics).clone())
+    }
+
+    fn insert(
+        &self,
+        db: &dyn EqwalizerDiagnosticsDatabase,
+        project_id: ProjectId,
+        module: ModuleName,
+        dependencies: &Arc<Vec<ModuleName>>,
+        diagnostics: &Arc<EqwalizerDiagnostics>,
+    ) {
+        // Only complete results are worth reusing
+        if !matches!(**diagnostics, EqwalizerDiagnostics::Diagnostics { .. }) {
+            return;
+        }
+        if let Some((fingerprint, shape)) = fingerprint(db, project_id, &module, dependencies) {
+            let mut cache = self.0.lock();
+            if cache.results.len() >= Self::CAPACITY {
+                cache.evict_least_recently_used(Self::CAPACITY / 4);
+            }
+            cache.clock += 1;
+            let cached = CachedResult {
+                config: db.eqwalizer_module_config(module.clone()),
+                fingerprint,
+                shape,
+                dependencies: dependencies.clone(),
+                diagnostics: diagnostics.clone(),
+                last_used: cache.clock,
+            };
+            cache.results.insert((project_id, module), cached);
+        }
+    }
+}
+
+impl CachedResults {
+    fn evict_least_recently_used(&mut self, count: usize) {
+        let mut last_used: Vec<u64> = self.results.values().map(|r| r.last_used).collect();
+        last_used.sort_unstable();
+        if let Some(&threshold) = last_used.get(count) {
+            self.results
+                .retain(|_, result| result.last_used >= threshold);
+        }
+    }
+}
+
+/// A hash of the inputs eqWAlizer used to check `module`, except for
+/// the positions of the nodes in its AST, which are returned alongside.
+fn fingerprint(
+    db: &dyn EqwalizerDiagnosticsDatabase,
+    project_id: ProjectId,
+    module: &ModuleName,
+    dependencies: &[ModuleName],
+) -> Option<(u64, Arc<AstShape>)> {
+    let mut hasher = FxHasher::default();
+    let shape = db.converted_ast_shape(project_id, module.clone()).ok()?;
+    shape.hash.hash(&mut hasher);
+    for dependency in dependencies {
+        db.transitive_stub_bytes(project_id, dependency.clone())
+            .ok()
+            .hash(&mut hasher);
+    }
+    Some((hasher.finish(), shape))
 }
 
 #[derive(Clone)]
 pub struct EqwalizerExe {
     cmd: PathBuf,
     args: Vec<OsString>,
-    // Used only for the Drop implementation
-    _file: Option<Arc<TempPath>>,
+    // Kept so that the Drop implementation deletes the file
+    file: Option<Arc<TempPath>>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -116,11 +540,49 @@ pub enum EqwalizerDiagnostics {
     Diagnostics {
         errors: FxHashMap<ModuleName, Vec<EqwalizerDiagnostic>>,
         type_info: FxHashMap<ModuleName, Vec<(Pos, Type)>>,
+        /// Modules that could not be checked because their AST
+        /// could not be produced, usually due to a parse error.
+        no_ast: Vec<ModuleName>,
     },
     NoAst {
         module: ModuleName,
     },
-    Error(String),
+    Error(EqwalizerError),
+}
+
+/// Why eqWAlizer could not produce diagnostics.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum EqwalizerError {
+    /// The eqWAlizer process could not be started.
+    Spawn(String),
+    /// The eqWAlizer process exited before the session was complete,
+    /// with the error the session failed with. The code is `None` if it
+    /// was killed by a signal.
+    ProcessExited { code: Option<i32>, message: String },
+    /// Any other failure, such as an unexpected message, or a module
+    /// which could not be sent to eqWAlizer.
+    Protocol(String),
+}
+
+impl fmt::Display for EqwalizerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EqwalizerError::Spawn(err) => write!(f, "{}", err),
+            EqwalizerError::ProcessExited {
+                code: Some(code),
+                message,
+            } => {
+                write!(f, "eqWAlizer process exited with code {code}: {message}")
+            }
+            EqwalizerError::ProcessExited {
+                code: None,
+                message,
+            } => {
+                write!(f, "eqWAlizer process was terminated by a signal: {message}")
+            }
+            EqwalizerError::Protocol(err) => write!(f, "{}", err),
+        }
+    }
 }
 
 impl Default for EqwalizerDiagnostics {
@@ -128,47 +590,145 @@ impl Default for EqwalizerDiagnostics {
         EqwalizerDiagnostics::Diagnostics {
             errors: Default::default(),
             type_info: Default::default(),
+            no_ast: Default::default(),
         }
     }
 }
 
 impl EqwalizerDiagnostics {
+    /// Combine the results of two eqWAlizer runs. An `Error` is fatal
+    /// and takes precedence, whereas modules without an AST are
+    /// recorded alongside the diagnostics of the other modules.
     pub fn combine(mut self, other: Self) -> Self {
         match &mut self {
-            EqwalizerDiagnostics::NoAst {
-                module: self_module,
-            } => match &other {
-                EqwalizerDiagnostics::NoAst {
-                    module: other_module,
-                } => {
-                    if other_module > self_module {
-                        self
-                    } else {
-                        other
-                    }
-                }
-                _ => self,
-            },
+            EqwalizerDiagnostics::NoAst { module } => {
+                let mut diagnostics = EqwalizerDiagnostics::default();
+                diagnostics.record_no_ast(module.clone());
+                diagnostics.combine(other)
+            }
             EqwalizerDiagnostics::Error(_) => self,
-            EqwalizerDiagnostics::Diagnostics { errors, type_info } => match other {
+            EqwalizerDiagnostics::Diagnostics {
+                errors,
+                type_info,
+                no_ast,
+            } => match other {
                 EqwalizerDiagnostics::Diagnostics {
                     errors: other_errors,
                     type_info: other_type_info,
+                    no_ast: other_no_ast,
                 } => {
                     errors.extend(other_errors.into_iter());
                     type_info.extend(other_type_info.into_iter());
+                    no_ast.extend(other_no_ast.into_iter());
+                    self
+                }
+                EqwalizerDiagnostics::Error(_) => other,
+                EqwalizerDiagnostics::NoAst { module } => {
+                    self.record_no_ast(module);
                     self
                 }
-                EqwalizerDiagnostics::Error(_) => other.clone(),
-                EqwalizerDiagnostics::NoAst { .. } => other.clone(),
             },
         }
     }
+
+    /// Only keep the diagnostics and types of `module` which are within
+    /// `range`. Results of the other modules are dropped.
+    fn restrict_to(self, module: &ModuleName, range: elp_syntax::TextRange) -> Self {
+        match self {
+            EqwalizerDiagnostics::Diagnostics {
+                mut errors,
+                mut type_info,
+                no_ast,
+            } => {
+                let contains = |pos: &elp_syntax::TextRange| range.contains_range(*pos);
+                let module_errors: Vec<EqwalizerDiagnostic> = errors
+                    .remove(module)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|diagnostic| contains(&diagnostic.range))
+                    .collect();
+                let module_type_info: Vec<(Pos, Type)> = type_info
+                    .remove(module)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|(pos, _)| match pos {
+                        Pos::TextRange(pos) => contains(&pos.clone().into()),
+                        Pos::LineAndColumn(_) => false,
+                    })
+                    .collect();
+                EqwalizerDiagnostics::Diagnostics {
+                    errors: FxHashMap::from_iter([(module.clone(), module_errors)]),
+                    type_info: FxHashMap::from_iter([(module.clone(), module_type_info)]),
+                    no_ast: no_ast.into_iter().filter(|m| m == module).collect(),
+                }
+            }
+            other => other,
+        }
+    }
+
+    /// The diagnostics and types of `module`, whose AST had its nodes
+    /// at `from`, moved to where the same nodes are in `to`. None if
+    /// one of them is not at the position of a node, so that it cannot
+    /// be told where it moved to.
+    fn moved(&self, module: &ModuleName, from: &[(u32, u32)], to: &[(u32, u32)]) -> Option<Self> {
+        let mut moves: FxHashMap<(u32, u32), (u32, u32)> = FxHashMap::default();
+        for (old, new) in from.iter().zip(to) {
+            if *moves.entry(*old).or_insert(*new) != *new {
+                return None;
+            }
+        }
+        let move_range = |range: &elp_syntax::TextRange| {
+            let (start, end) = moves.get(&(range.start().into(), range.end().into()))?;
+            Some(elp_syntax::TextRange::new((*start).into(), (*end).into()))
+        };
+        match self {
+            EqwalizerDiagnostics::Diagnostics {
+                errors,
+                type_info,
+                no_ast,
+            } => {
+                let mut errors = errors.clone();
+                let mut type_info = type_info.clone();
+                if let Some(module_errors) = errors.get_mut(module) {
+                    for diagnostic in module_errors {
+                        diagnostic.range = move_range(&diagnostic.range)?;
+                    }
+                }
+                if let Some(module_type_info) = type_info.get_mut(module) {
+                    for (pos, _) in module_type_info {
+                        if let Pos::TextRange(range) = pos {
+                            let (start_byte, end_byte) =
+                                moves.get(&(range.start_byte, range.end_byte))?;
+                            range.start_byte = *start_byte;
+                            range.end_byte = *end_byte;
+                        }
+                    }
+                }
+                Some(EqwalizerDiagnostics::Diagnostics {
+                    errors,
+                    type_info,
+                    no_ast: no_ast.clone(),
+                })
+            }
+            other => Some(other.clone()),
+        }
+    }
+
+    fn record_no_ast(&mut self, module: ModuleName) {
+        if let EqwalizerDiagnostics::Diagnostics { no_ast, .. } = self {
+            if !no_ast.contains(&module) {
+                no_ast.push(module);
+            }
+        }
+    }
 }
 
 pub trait DbApi {
     fn eqwalizing_start(&self, module: ModuleName);
     fn eqwalizing_done(&self, module: &ModuleName);
+    /// Called with the diagnostics of each module as soon as they are
+    /// available, before the whole batch of modules has been checked.
+    fn eqwalizing_result(&self, module: &ModuleName, diagnostics: &EqwalizerDiagnostics);
     fn set_module_ipc_handle(&self, module: &ModuleName, handle: Option<Arc<Mutex<IpcHandle>>>);
     fn module_ipc_handle<'a>(&'a self, module: &'a ModuleName) -> Option<Arc<Mutex<IpcHandle>>>;
 }
@@ -177,17 +737,23 @@ pub trait DbApi {
 pub trait EqwalizerDiagnosticsDatabase: ast::db::EqwalizerASTDatabase + DbApi {
     #[salsa::input]
     fn eqwalizer_config(&self) -> Arc<EqwalizerConfig>;
-
-    fn module_diagnostics(
-        &self,
-        project_id: ProjectId,
-        module: ModuleName,
-    ) -> (Arc<EqwalizerDiagnostics>, Instant);
+    /// Modules checked with a different config than `eqwalizer_config`,
+    /// for example legacy code which needs fault tolerance.
+    #[salsa::input]
+    fn eqwalizer_module_configs(&self) -> Arc<FxHashMap<ModuleName, Arc<EqwalizerConfig>>>;
+    /// The config `module` is checked with. Changing the override of
+    /// one module does not invalidate the results of the others.
+    fn eqwalizer_module_config(&self, module: ModuleName) -> Arc<EqwalizerConfig>;
 }
 
 impl Default for Eqwalizer {
     fn default() -> Self {
-        Self { mode: Mode::Server }
+        Self {
+            mode: Mode::Server,
+            cache: ResultCache::default(),
+            exe: EQWALIZER_EXE.clone(),
+            timing_sink: None,
+        }
     }
 }
 
@@ -205,9 +771,14 @@ lazy_static! {
 }
 
 impl EqwalizerExe {
-    // Identify the required Eqwalizer executable, and ensure it is
-    // available on the file system
     fn ensure_exe() -> Option<Arc<Mutex<Self>>> {
+        Self::new().map(|exe| Arc::new(Mutex::new(exe)))
+    }
+
+    /// Identify the required Eqwalizer executable, and ensure it is
+    /// available on the file system. The bundled executable is
+    /// extracted to a new temporary file each time.
+    pub fn new() -> Option<Self> {
         let env = env::var("ELP_EQWALIZER_PATH");
         let (path, ext, temp_file) = if let Ok(path) = env {
             let path = PathBuf::from(path);
@@ -252,11 +823,39 @@ impl EqwalizerExe {
             _ => panic!("Unknown eqwalizer executable {:?}", path),
         };
 
-        Some(Arc::new(Mutex::new(Self {
+        Some(Self {
+            cmd,
+            args,
+            file: temp_file.map(Arc::new),
+        })
+    }
+
+    /// Extract the bundled executable again if the temporary file it was
+    /// extracted to has been deleted, as the operating system may do
+    /// for a long-running server.
+    pub fn reensure(&mut self) {
+        let deleted = self.file.as_ref().map_or(false, |file| !file.exists());
+        if deleted {
+            log::info!("eqWAlizer executable was deleted, extracting it again");
+            if let Some(exe) = Self::new() {
+                *self = exe;
+            }
+        }
+    }
+
+    /// The temporary file the bundled executable was extracted to, if any.
+    pub fn temp_path(&self) -> Option<&Path> {
+        self.file.as_deref().map(|file| file.as_ref())
+    }
+
+    /// An executable run as `cmd` with `args`, rather than the one
+    /// configured for the process.
+    pub fn from_command(cmd: PathBuf, args: Vec<OsString>) -> Self {
+        Self {
             cmd,
             args,
-            _file: temp_file.map(Arc::new),
-        })))
+            file: None,
+        }
     }
 
     pub fn cmd(&self) -> Command {
@@ -267,61 +866,318 @@ impl EqwalizerExe {
 }
 
 impl Eqwalizer {
+    /// An `Eqwalizer` using `exe`, rather than the executable shared by
+    /// the whole process.
+    pub fn with_exe(exe: EqwalizerExe) -> Self {
+        Self {
+            exe: Some(Arc::new(Mutex::new(exe))),
+            ..Default::default()
+        }
+    }
+
     fn cmd(&self) -> Option<Command> {
-        let exe = EQWALIZER_EXE.as_ref()?.lock();
+        let exe = self.exe.as_ref()?.lock();
         Some(exe.cmd())
     }
 
+    pub fn set_timing_sink(&mut self, sink: Option<TimingSink>) {
+        self.timing_sink = sink;
+    }
+
+    fn reensure_exe(&self) {
+        if let Some(exe) = &self.exe {
+            exe.lock().reensure();
+        }
+    }
+
+    /// Whether an eqWAlizer executable is configured. When it is not,
+    /// `typecheck` returns no diagnostics without checking anything.
+    pub fn is_available(&self) -> bool {
+        self.exe.is_some()
+    }
+
+    /// The `EQWALIZER_` environment variables of the process checking
+    /// modules with `config`. Those set from the config and the mode
+    /// take precedence over the ones inherited from ELP.
+    pub fn process_env(&self, config: &EqwalizerConfig) -> BTreeMap<String, String> {
+        let mut vars: BTreeMap<String, String> = env::vars_os()
+            .filter_map(|(var, value)| Some((var.into_string().ok()?, value.into_string().ok()?)))
+            .filter(|(var, _)| var.starts_with("EQWALIZER_"))
+            .collect();
+        for (var, value) in config.cmd_env() {
+            vars.insert(var.to_string(), value.to_string());
+        }
+        vars.insert(MODE_ENV_VAR.to_string(), self.mode.to_env_var().to_string());
+        vars
+    }
+
+    /// Behave as if no eqWAlizer executable was configured.
+    pub fn disable_exe(&mut self) {
+        self.exe = None;
+    }
+
     pub fn typecheck(
         &self,
         db: &dyn EqwalizerDiagnosticsDatabase,
         project_id: ProjectId,
         modules: Vec<&ModuleName>,
     ) -> EqwalizerDiagnostics {
-        let Some(mut cmd) = self.cmd() else {
-            return EqwalizerDiagnostics::Diagnostics {
-                errors: Default::default(),
-                type_info: Default::default(),
+        let config = db.eqwalizer_config();
+        let modules: Vec<&ModuleName> = match &config.module_filter {
+            Some(filter) => modules
+                .into_iter()
+                .filter(|module| filter.is_allowed(module.as_unquoted_str()))
+                .collect(),
+            None => modules,
+        };
+        let mut cached = EqwalizerDiagnostics::default();
+        let mut modules: Vec<&ModuleName> = modules
+            .into_iter()
+            .filter(|module| match self.cache.get(db, project_id, module) {
+                Some(diags) => {
+                    db.eqwalizing_start((*module).clone());
+                    db.eqwalizing_done(module);
+                    db.eqwalizing_result(module, &diags);
+                    cached = std::mem::take(&mut cached).combine(diags);
+                    false
+                }
+                None => true,
+            })
+            .collect();
+        if modules.is_empty() {
+            return cached;
+        }
+        // Modules with a config of their own are checked by the same
+        // process, which gets their flags with `ELPEnteringModule`.
+        let mut diagnostics = cached;
+        let mut retried = false;
+        let mut restarted = false;
+        let mut timings = vec![];
+        let mut finished = vec![];
+        let result = loop {
+            if modules.is_empty() {
+                break Ok(EqwalizerDiagnostics::default());
+            }
+            let Some(mut cmd) = self.cmd() else {
+                return EqwalizerDiagnostics::default();
             };
+            config.set_cmd_env(&mut cmd);
+            cmd.arg("ipc");
+            cmd.args(&modules);
+            cmd.env(MODE_ENV_VAR, self.mode.to_env_var());
+
+            match do_typecheck(
+                cmd,
+                db,
+                project_id,
+                &config,
+                None,
+                &self.cache,
+                &mut timings,
+                &mut finished,
+            ) {
+                // The executable may have been deleted since it was
+                // extracted. No module was checked, so it is safe to
+                // start again.
+                Err(EqwalizerError::Spawn(err)) if !retried => {
+                    log::warn!(
+                        "could not start eqWAlizer, retrying: {}",
+                        limit_logged_string(&err)
+                    );
+                    self.reensure_exe();
+                    retried = true;
+                }
+                // The process died while checking a module. Start it
+                // again once, for the modules it did not finish. Those
+                // it did were already reported, so only their results
+                // are kept.
+                Err(EqwalizerError::ProcessExited { code, .. }) if !restarted => {
+                    log::warn!("eqWAlizer process exited with code {:?}, restarting", code);
+                    modules.retain(|module| !finished.iter().any(|(done, _)| done == *module));
+                    for (_, diags) in finished.drain(..) {
+                        diagnostics = std::mem::take(&mut diagnostics).combine(diags);
+                    }
+                    restarted = true;
+                }
+                result => break result,
+            }
         };
-        db.eqwalizer_config().set_cmd_env(&mut cmd);
-        cmd.arg("ipc");
-        cmd.args(modules);
-        cmd.env("EQWALIZER_MODE", self.mode.to_env_var());
+        if let Some(sink) = &self.timing_sink {
+            for (module, time) in &timings {
+                sink(module, *time);
+            }
+        }
+        match result {
+            Ok(diags) => diags.combine(diagnostics),
+            Err(err) => EqwalizerDiagnostics::Error(err),
+        }
+    }
 
-        match do_typecheck(cmd, db, project_id) {
-            Ok(diags) => diags,
-            Err(err) => EqwalizerDiagnostics::Error(format!("{:?}", err)),
+    /// Like `typecheck` for the single module `module`, but eqWAlizer
+    /// is told with `ELPScopeFunction` to only check the function
+    /// `function`, and only the diagnostics and types within it are
+    /// returned. Returns `None` if `module` does not define `function`.
+    ///
+    /// The result only covers part of the module, so it is not cached.
+    /// If the whole module was already checked, its cached result is
+    /// used instead of starting eqWAlizer.
+    pub fn typecheck_function(
+        &self,
+        db: &dyn EqwalizerDiagnosticsDatabase,
+        project_id: ProjectId,
+        module: &ModuleName,
+        function: &ast::Id,
+    ) -> Option<EqwalizerDiagnostics> {
+        let range = function_range(db, project_id, module, function)?;
+        if let Some(diagnostics) = self.cache.get(db, project_id, module) {
+            return Some(diagnostics.restrict_to(module, range));
         }
+        let Some(mut cmd) = self.cmd() else {
+            return Some(EqwalizerDiagnostics::default());
+        };
+        let config = db.eqwalizer_config();
+        config.set_cmd_env(&mut cmd);
+        cmd.arg("ipc");
+        cmd.arg(module);
+        cmd.env(MODE_ENV_VAR, self.mode.to_env_var());
+        let diagnostics = match do_typecheck(
+            cmd,
+            db,
+            project_id,
+            &config,
+            Some(function),
+            &self.cache,
+            &mut vec![],
+            &mut vec![],
+        ) {
+            Ok(diagnostics) => diagnostics,
+            Err(err) => return Some(EqwalizerDiagnostics::Error(err)),
+        };
+        Some(diagnostics.restrict_to(module, range))
     }
 }
 
+/// Run an eqWAlizer session with `cmd`. If `scope` is set, only that
+/// function of the modules is checked, and their results are not
+/// cached.
+#[allow(clippy::too_many_arguments)]
 fn do_typecheck(
     mut cmd: Command,
     db: &dyn EqwalizerDiagnosticsDatabase,
     project_id: ProjectId,
-) -> Result<EqwalizerDiagnostics, anyhow::Error> {
+    config: &EqwalizerConfig,
+    scope: Option<&ast::Id>,
+    cache: &ResultCache,
+    timings: &mut Vec<(String, Duration)>,
+    finished: &mut Vec<(ModuleName, EqwalizerDiagnostics)>,
+) -> Result<EqwalizerDiagnostics, EqwalizerError> {
     // Never cache the results of this function
     db.salsa_runtime().report_untracked_read();
-    let handle = Arc::new(Mutex::new(
-        IpcHandle::from_command(&mut cmd)
-            .with_context(|| format!("starting eqWAlizer process: {:?}", cmd))?,
-    ));
+    let trace = config.resolve_env().ipc_trace;
+    let handle = IpcHandle::from_command_with_timeouts(
+        &mut cmd,
+        config.ipc_read_timeout.unwrap_or(ipc::READ_TIMEOUT),
+        config.ipc_write_timeout.unwrap_or(ipc::WRITE_TIMEOUT),
+    )
+    .map(|handle| match &trace {
+        Some(path) => handle.with_trace(path),
+        None => handle,
+    })
+    .map_err(|err| {
+        EqwalizerError::Spawn(format!("starting eqWAlizer process: {:?}\n{:?}", cmd, err))
+    })?;
+    let handle = Arc::new(Mutex::new(handle));
+    match run_session(&handle, db, project_id, scope, cache, timings, finished) {
+        // A module which failed may have done so because the process died
+        Ok(EqwalizerDiagnostics::Error(EqwalizerError::Protocol(err))) => {
+            Err(session_error(&handle, err))
+        }
+        Ok(diagnostics) => Ok(diagnostics),
+        Err(err) => Err(session_error(&handle, format!("{:?}", err))),
+    }
+}
+
+/// Tell apart the eqWAlizer process exiting, which may be transient,
+/// from other failures of the session.
+fn session_error(handle: &Mutex<IpcHandle>, err: String) -> EqwalizerError {
+    match handle.lock().exit_code() {
+        Some(code) => {
+            log::error!("eqWAlizer process exited: {}", limit_logged_string(&err));
+            EqwalizerError::ProcessExited { code, message: err }
+        }
+        None => EqwalizerError::Protocol(err),
+    }
+}
+
+/// Check the modules eqWAlizer asks for in turn. The result of each
+/// module the session finishes is also recorded in `finished`, so
+/// that it is kept if the process exits before the session is done.
+fn run_session(
+    handle: &Arc<Mutex<IpcHandle>>,
+    db: &dyn EqwalizerDiagnosticsDatabase,
+    project_id: ProjectId,
+    scope: Option<&ast::Id>,
+    cache: &ResultCache,
+    timings: &mut Vec<(String, Duration)>,
+    finished: &mut Vec<(ModuleName, EqwalizerDiagnostics)>,
+) -> Result<EqwalizerDiagnostics, anyhow::Error> {
     let mut diagnostics = EqwalizerDiagnostics::default();
     loop {
         db.unwind_if_cancelled();
         let msg = handle.lock().receive()?;
         match msg {
-            MsgFromEqWAlizer::EnteringModule { module } => {
+            MsgFromEqWAlizer::EnteringModule {
+                module,
+                compression,
+            } => {
+                let entered = Instant::now();
+                handle.lock().set_compression(compression);
                 db.set_module_ipc_handle(&module, Some(handle.clone()));
-                let diags = db.module_diagnostics(project_id, module.clone()).0;
+                let (diags, dependencies) =
+                    module_diagnostics(db, project_id, module.clone(), scope);
                 db.set_module_ipc_handle(&module, None);
-                diagnostics = diagnostics.combine((*diags).clone());
-                match diagnostics {
-                    EqwalizerDiagnostics::Error(_) | EqwalizerDiagnostics::NoAst { .. } => {
-                        return Ok(diagnostics);
+                let ast_time = handle.lock().take_ast_time();
+                timings.push((
+                    module.to_string(),
+                    entered.elapsed().saturating_sub(ast_time),
+                ));
+                // If the process died, the module is not to blame, so it
+                // must not be skipped, and the session cannot go on.
+                if matches!(*diags, EqwalizerDiagnostics::Error(_)) && !handle.lock().is_alive() {
+                    anyhow::bail!("eqWAlizer process exited while checking {}", module);
+                }
+                // The results of a single function are not those of
+                // the module
+                if scope.is_none() {
+                    cache.insert(db, project_id, module.clone(), &dependencies, &diags);
+                    db.eqwalizing_result(&module, &diags);
+                }
+                // A module without an AST is recorded, and the session
+                // moves on to the next module. Only an `Error` aborts it,
+                // unless failed modules are skipped.
+                let skip_failed = db
+                    .eqwalizer_module_config(module.clone())
+                    .skip_failed_modules
+                    .is_on();
+                let diags = match &*diags {
+                    EqwalizerDiagnostics::Error(err) if skip_failed => {
+                        log::warn!(
+                            "skipping module {} which eqWAlizer failed on: {}",
+                            module,
+                            limit_logged_string(&err.to_string())
+                        );
+                        EqwalizerDiagnostics::default()
                     }
-                    EqwalizerDiagnostics::Diagnostics { .. } => (),
+                    diags => diags.clone(),
+                };
+                // A module whose check aborts the session is not
+                // finished, since the process may have exited during it.
+                if !matches!(diags, EqwalizerDiagnostics::Error(_)) {
+                    finished.push((module.clone(), diags.clone()));
+                }
+                diagnostics = diagnostics.combine(diags);
+                if let EqwalizerDiagnostics::Error(_) = diagnostics {
+                    return Ok(diagnostics);
                 }
                 handle.lock().send(&MsgToEqWAlizer::ELPExitingModule)?;
             }
@@ -338,35 +1194,66 @@ fn do_typecheck(
     }
 }
 
+/// The range of the declaration of `function` in `module`, if it is
+/// defined there.
+fn function_range(
+    db: &dyn EqwalizerDiagnosticsDatabase,
+    project_id: ProjectId,
+    module: &ModuleName,
+    function: &ast::Id,
+) -> Option<elp_syntax::TextRange> {
+    let ast = db.converted_ast(project_id, module.clone()).ok()?;
+    ast.iter().find_map(|form| match form {
+        ExternalForm::FunDecl(decl) if &decl.id == function => match &decl.location {
+            Pos::TextRange(range) => Some(range.clone().into()),
+            Pos::LineAndColumn(_) => None,
+        },
+        _ => None,
+    })
+}
+
+fn eqwalizer_module_config(
+    db: &dyn EqwalizerDiagnosticsDatabase,
+    module: ModuleName,
+) -> Arc<EqwalizerConfig> {
+    let project_config = db.eqwalizer_config();
+    match db.eqwalizer_module_configs().get(&module) {
+        // Flags the module leaves to inherit come from the project config
+        Some(config) => Arc::new(config.inherit(&project_config)),
+        None => project_config,
+    }
+}
+
+/// Check `module` with the eqWAlizer process which asked for it. This
+/// is not a query: every call must talk to the process, including when
+/// a module is checked again by a restarted process in the same
+/// revision. Results are reused through the `ResultCache` instead.
 fn module_diagnostics(
     db: &dyn EqwalizerDiagnosticsDatabase,
     project_id: ProjectId,
     module: ModuleName,
-) -> (Arc<EqwalizerDiagnostics>, Instant) {
-    // A timestamp is added to the return value to force Salsa to store new
-    // diagnostics, and not attempt to back-date them if they are equal to
-    // the memoized ones.
-    let timestamp = Instant::now();
-    // Dummy read eqWAlizer config for Salsa
-    // Ideally, the config should be passed per module to eqWAlizer instead
-    // of being set in the command's environment
-    let _ = db.eqwalizer_config();
-    match get_module_diagnostics(db, project_id, &module) {
-        Ok(diag) => (Arc::new(diag), timestamp),
-        Err(err) => (
-            Arc::new(EqwalizerDiagnostics::Error(format!(
+    scope: Option<&ast::Id>,
+) -> (Arc<EqwalizerDiagnostics>, Arc<Vec<ModuleName>>) {
+    let mut dependencies = vec![];
+    let diagnostics =
+        match get_module_diagnostics(db, project_id, &module, scope, &mut dependencies) {
+            Ok(diag) => diag,
+            Err(err) => EqwalizerDiagnostics::Error(EqwalizerError::Protocol(format!(
                 "eqWAlizing module {}:\n{}",
                 module, err
             ))),
-            timestamp,
-        ),
-    }
+        };
+    (Arc::new(diagnostics), Arc::new(dependencies))
 }
 
+/// Check a single module, recording in `dependencies` the modules
+/// whose stubs eqWAlizer requested to do so.
 fn get_module_diagnostics(
     db: &dyn EqwalizerDiagnosticsDatabase,
     project_id: ProjectId,
     module: &ModuleName,
+    scope: Option<&ast::Id>,
+    dependencies: &mut Vec<ModuleName>,
 ) -> Result<EqwalizerDiagnostics, anyhow::Error> {
     let handle_mutex = db
         .module_ipc_handle(module)
@@ -375,16 +1262,27 @@ fn get_module_diagnostics(
             module
         )))?;
     let mut handle = handle_mutex.lock();
-    handle.send(&MsgToEqWAlizer::ELPEnteringModule)?;
+    let options = db.eqwalizer_module_config(module.clone()).module_options();
+    handle.send(&MsgToEqWAlizer::ELPEnteringModule { options })?;
+    if let Some(function) = scope {
+        handle.send(&MsgToEqWAlizer::ELPScopeFunction {
+            name: function.name.as_unquoted_str().to_string(),
+            arity: function.arity,
+        })?;
+    }
     loop {
         db.unwind_if_cancelled();
         match handle.receive()? {
             MsgFromEqWAlizer::GetAstBytes { module, format } => {
+                let requested = Instant::now();
                 log::debug!(
                     "received from eqwalizer: GetAstBytes for module {} (format = {:?})",
                     module,
                     format
                 );
+                if let EqWAlizerASTFormat::TransitiveStub = format {
+                    dependencies.push(module.clone());
+                }
                 let ast = {
                     match format {
                         EqWAlizerASTFormat::ConvertedForms => {
@@ -401,11 +1299,7 @@ fn get_module_diagnostics(
                             "sending to eqwalizer: GetAstBytesReply for module {}",
                             module
                         );
-                        let ast_bytes_len = ast_bytes.len().try_into()?;
-                        let reply = &MsgToEqWAlizer::GetAstBytesReply { ast_bytes_len };
-                        handle.send(reply)?;
-                        handle.receive_newline()?;
-                        handle.send_bytes(&ast_bytes).with_context(|| {
+                        handle.send_ast_bytes(&ast_bytes).with_context(|| {
                             format!(
                                 "sending to eqwalizer: bytes for module {} (format = {:?})",
                                 module, format
@@ -417,8 +1311,10 @@ fn get_module_diagnostics(
                             "module not found, sending to eqwalizer: empty GetAstBytesReply for module {}",
                             module
                         );
-                        let ast_bytes_len = 0;
-                        let reply = &MsgToEqWAlizer::GetAstBytesReply { ast_bytes_len };
+                        let reply = &MsgToEqWAlizer::GetAstBytesReply {
+                            ast_bytes_len: 0,
+                            compressed: false,
+                        };
                         handle.send(reply)?;
                         handle.receive_newline()?;
                     }
@@ -439,9 +1335,12 @@ fn get_module_diagnostics(
                         );
                         let reply = &MsgToEqWAlizer::CannotCompleteRequest;
                         handle.send(reply)?;
-                        return Ok(EqwalizerDiagnostics::Error(err.to_string()));
+                        return Ok(EqwalizerDiagnostics::Error(EqwalizerError::Protocol(
+                            err.to_string(),
+                        )));
                     }
                 }
+                handle.add_ast_time(requested.elapsed());
             }
             MsgFromEqWAlizer::EqwalizingStart { module } => db.eqwalizing_start(module),
             MsgFromEqWAlizer::EqwalizingDone { module } => db.eqwalizing_done(&module),
@@ -456,12 +1355,14 @@ fn get_module_diagnostics(
                 return Ok(EqwalizerDiagnostics::Diagnostics {
                     errors: diagnostics,
                     type_info,
+                    no_ast: vec![],
                 });
             }
             MsgFromEqWAlizer::Dependencies { modules } => {
                 modules.iter().for_each(|module| {
                     _ = db.transitive_stub_bytes(project_id, module.clone());
                 });
+                dependencies.extend(modules);
             }
             msg => {
                 log::warn!(
@@ -239,13 +239,14 @@ pub struct SsrBody {
     pub when: Option<Vec<Vec<ExprId>>>,
 }
 
-/// We lower a SSR pattern and template as both an Expr and a Pat, as
-/// we do not know how it will be matched when used.
-/// Note: extend to Types and Terms when needed.
+/// We lower a SSR pattern and template as an Expr, a Pat and a
+/// TypeExpr, as we do not know how it will be matched when used.
+/// Note: extend to Terms when needed.
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct SsrPatternIds {
     pub expr: ExprId,
     pub pat: PatId,
+    pub type_expr: TypeExprId,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -404,27 +405,25 @@ impl Body {
     ) -> Option<AnyExprId> {
         let key = path.get(0)?;
         match map_id {
-            AnyExprId::Expr(id) => {
-                match &self[id] {
-                    Expr::Map { fields } => fields.iter().find_map(|(key_id, val_id)| match &self
-                        [*key_id]
-                    {
-                        Expr::Literal(Literal::Atom(atom)) => {
-                            if &atom.as_string(db) == key {
-                                if path.len() == 1 {
-                                    Some(AnyExprId::Expr(*val_id))
-                                } else {
-                                    self.lookup_map_path(db, AnyExprId::Expr(*val_id), &path[1..])
-                                }
+            AnyExprId::Expr(id) => match &self[id] {
+                Expr::Map { fields } => fields.iter().find_map(|(key_id, _op, val_id)| match &self
+                    [*key_id]
+                {
+                    Expr::Literal(Literal::Atom(atom)) => {
+                        if &atom.as_string(db) == key {
+                            if path.len() == 1 {
+                                Some(AnyExprId::Expr(*val_id))
                             } else {
-                                None
+                                self.lookup_map_path(db, AnyExprId::Expr(*val_id), &path[1..])
                             }
+                        } else {
+                            None
                         }
-                        _ => None,
-                    }),
+                    }
                     _ => None,
-                }
-            }
+                }),
+                _ => None,
+            },
             AnyExprId::Pat(id) => match &self[id] {
                 Pat::Map { fields } => {
                     fields
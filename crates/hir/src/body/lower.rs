@@ -485,10 +485,12 @@ impl<'a> Ctx<'a> {
         let lhs_ast = ssr.lhs()?;
         let lhs_expr = self.lower_expr(&lhs_ast);
         let lhs_pat = self.lower_pat(&lhs_ast);
+        let lhs_type_expr = self.lower_type_expr(&lhs_ast);
         let rhs = ssr.rhs().and_then(|rhs| {
             rhs.expr().map(|rhs_ast| SsrPatternIds {
                 expr: self.lower_expr(&rhs_ast),
                 pat: self.lower_pat(&rhs_ast),
+                type_expr: self.lower_type_expr(&rhs_ast),
             })
         });
         let when = ssr.when().and_then(|w| Some(self.lower_guards(w.guard())));
@@ -500,6 +502,7 @@ impl<'a> Ctx<'a> {
                 pattern: SsrPatternIds {
                     expr: lhs_expr,
                     pat: lhs_pat,
+                    type_expr: lhs_type_expr,
                 },
                 template: rhs,
                 when,
@@ -960,11 +963,7 @@ impl<'a> Ctx<'a> {
                     .flat_map(|field| {
                         let key = self.lower_optional_expr(field.key());
                         let value = self.lower_optional_expr(field.value());
-                        if let Some((ast::MapOp::Assoc, _)) = field.op() {
-                            Some((key, value))
-                        } else {
-                            None
-                        }
+                        Some((key, field.op()?.0, value))
                     })
                     .collect();
                 self.alloc_expr(Expr::Map { fields }, Some(expr))
@@ -421,9 +421,9 @@ impl<'a> Printer<'a> {
                 write!(self, ")")
             }
             Expr::Map { fields } => {
-                self.print_seq(fields, None, "#{", "}", ",", |this, (key, value)| {
+                self.print_seq(fields, None, "#{", "}", ",", |this, (key, op, value)| {
                     this.print_expr(&this.body[*key])?;
-                    write!(this, " => ")?;
+                    write!(this, " {} ", op)?;
                     this.print_expr(&this.body[*value])
                 })
             }
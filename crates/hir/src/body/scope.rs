@@ -438,7 +438,7 @@ fn compute_expr_scopes(
             compute_expr_scopes(*expr, body, scopes, scope, vt);
         }
         crate::Expr::Map { fields } => {
-            for (lhs, rhs) in fields {
+            for (lhs, _, rhs) in fields {
                 compute_expr_scopes(*lhs, body, scopes, scope, vt);
                 compute_expr_scopes(*rhs, body, scopes, scope, vt);
             }
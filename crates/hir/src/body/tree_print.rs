@@ -387,11 +387,12 @@ impl<'a> Printer<'a> {
             }
             Expr::Map { fields } => {
                 self.print_herald("Expr::Map", &mut |this| {
-                    fields.iter().for_each(|(name, value)| {
+                    fields.iter().for_each(|(name, op, value)| {
                         writeln!(this, "{{").ok();
                         this.indent();
                         this.print_expr(name);
                         writeln!(this, ",").ok();
+                        writeln!(this, "{:?},", op).ok();
                         this.print_expr(value);
                         writeln!(this, ",").ok();
                         this.dedent();
@@ -1642,6 +1643,7 @@ mod tests {
                         Expr<7>:Expr::Map {
                             {
                                 Expr<1>:Literal(Atom('foo')),
+                                Assoc,
                                 Expr<4>:Expr::BinaryOp {
                                     lhs
                                         Expr<2>:Literal(Atom('a'))
@@ -1653,6 +1655,7 @@ mod tests {
                             },
                             {
                                 Expr<5>:Literal(Atom('bar')),
+                                Assoc,
                                 Expr<6>:Literal(Char($v)),
                             },
                         },
@@ -1677,6 +1680,7 @@ mod tests {
                                 Expr<3>:Expr::Map {
                                     {
                                         Expr<1>:Literal(Atom('a')),
+                                        Assoc,
                                         Expr<2>:Literal(Atom('b')),
                                     },
                                 }
@@ -2536,6 +2540,7 @@ mod tests {
                         Expr<6>:Expr::Map {
                             {
                                 Expr<4>:Literal(Atom('a')),
+                                Assoc,
                                 Expr<5>:Literal(Atom('b')),
                             },
                         },
@@ -249,7 +249,7 @@ pub enum Expr {
         field: Atom,
     },
     Map {
-        fields: Vec<(ExprId, ExprId)>,
+        fields: Vec<(ExprId, MapOp, ExprId)>,
     },
     MapUpdate {
         expr: ExprId,
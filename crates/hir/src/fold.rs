@@ -725,7 +725,7 @@ impl<'a, T> FoldCtx<'a, T> {
                 name: _,
                 field: _,
             } => self.do_fold_expr(*expr, acc),
-            crate::Expr::Map { fields } => fields.iter().fold(acc, |acc, (k, v)| {
+            crate::Expr::Map { fields } => fields.iter().fold(acc, |acc, (k, _op, v)| {
                 let r = self.do_fold_expr(*k, acc);
                 self.do_fold_expr(*v, r)
             }),
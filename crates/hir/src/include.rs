@@ -122,4 +122,18 @@ mod tests {
             "#]],
         )
     }
+
+    #[test]
+    fn include_path_of_dependency() {
+        check(
+            r#"
+//- /app_b/src/module.erl app:app_b deps:app_a
+-include("header.hrl").
+//- /app_a/include/header.hrl app:app_a include_path:/app_a/include
+"#,
+            expect![[r#"
+                -include("header.hrl"). % => /app_a/include/header.hrl
+            "#]],
+        )
+    }
 }
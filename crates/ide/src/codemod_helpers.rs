@@ -12,6 +12,7 @@ use elp_syntax::ast::in_erlang_module;
 use elp_syntax::AstNode;
 use elp_syntax::SmolStr;
 use elp_syntax::SyntaxKind;
+use elp_syntax::SyntaxNode;
 use elp_syntax::TextRange;
 use elp_types_db::eqwalizer;
 use fxhash::FxHashMap;
@@ -51,7 +52,12 @@ use crate::FileId;
 // We typically want to have the statement range in order to be able to delete the statement,
 // remove an element from a list, etc.
 pub(crate) fn statement_range(expr: &ast::Expr) -> TextRange {
-    let node = expr.syntax();
+    element_range(expr.syntax())
+}
+
+// Like `statement_range`, for any comma-separated element, such as an
+// entry of an export attribute.
+pub(crate) fn element_range(node: &SyntaxNode) -> TextRange {
     let node_range = node.text_range();
 
     let mut right = node.last_token().and_then(|tok| tok.next_token());
@@ -39,7 +39,9 @@ use fxhash::FxHashMap;
 use fxhash::FxHashSet;
 use hir::known;
 use hir::DefMap;
+use hir::Expr;
 use hir::FunctionDef;
+use hir::Literal;
 use hir::Name;
 use hir::NameArity;
 use hir::Semantic;
@@ -92,6 +94,55 @@ pub fn unreachable_test(
     }
 }
 
+/// Report the literal test names returned by `all/0` which have no
+/// exported function of arity 1, since Common Test fails to run them.
+/// Unlike `unreachable_test`, this does not need the Erlang Service to
+/// evaluate `all/0`, so entries computed at runtime are not checked.
+pub fn missing_test_function(res: &mut Vec<Diagnostic>, sema: &Semantic, file_id: FileId) {
+    if !sema
+        .module_name(file_id)
+        .map_or(false, |name| is_suite(&name))
+    {
+        return;
+    }
+    let def_map = sema.def_map(file_id);
+    let all = match def_map.get_function(&NameArity::new(known::all, 0)) {
+        Some(def) if def.file.file_id == file_id => def,
+        _ => return,
+    };
+    let def_fb = all.in_function_body(sema, all);
+    for (clause_id, clause) in def_fb.clauses() {
+        let body = &clause.body;
+        let exprs = match clause.clause.exprs.last().map(|expr| &body[*expr]) {
+            Some(Expr::List { exprs, tail: None }) => exprs,
+            _ => continue,
+        };
+        for expr in exprs {
+            let name = match &body[*expr] {
+                Expr::Literal(Literal::Atom(atom)) => atom.as_name(sema.db.upcast()),
+                _ => continue,
+            };
+            let name_arity = NameArity::new(name, 1);
+            if def_map
+                .get_function(&name_arity)
+                .map_or(false, |def| def.exported)
+            {
+                continue;
+            }
+            if let Some(range) = def_fb.range_for_expr(clause_id, *expr) {
+                let d = Diagnostic::new(
+                    DiagnosticCode::MissingTestFunction,
+                    format!("Test case listed in `all/0` has no exported function ({name_arity})"),
+                    range,
+                )
+                .with_severity(Severity::Error)
+                .with_ignore_fix(sema, file_id);
+                res.push(d);
+            }
+        }
+    }
+}
+
 pub fn ct_info_eval_error(res: &mut Vec<Diagnostic>, sema: &Semantic, file_id: FileId) {
     let def_map = sema.db.def_map(file_id);
     eval_error_diagnostic(res, sema, &def_map, &NameArity::new(known::all, 0));
@@ -348,6 +399,46 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_missing_test_function() {
+        check_ct_diagnostics(
+            r#"
+//- common_test
+//- /my_app/test/missing1_SUITE.erl
+   -module(missing1_SUITE).~
+   -export([all/0]).
+   -export([a/1, c/1]).
+   all() -> [a, b, c, d, {group, g}].
+%%              ^ 💡 error: Test case listed in `all/0` has no exported function (b/1)
+%%                    ^ 💡 error: Test case listed in `all/0` has no exported function (d/1)
+   a(_Config) ->
+     ok.
+   c(_Config) ->
+     ok.
+   d(_Config) ->
+     ok.
+            "#,
+        );
+    }
+
+    #[test]
+    fn test_missing_test_function_complete_suite() {
+        check_ct_diagnostics(
+            r#"
+//- common_test
+//- /my_app/test/complete_SUITE.erl
+   -module(complete_SUITE).~
+   -export([all/0]).
+   -export([a/1, b/1]).
+   all() -> [a, b].
+   a(_Config) ->
+     ok.
+   b(_Config) ->
+     ok.
+            "#,
+        );
+    }
+
     #[test]
     fn test_unreachable_test_init_end() {
         check_ct_diagnostics(
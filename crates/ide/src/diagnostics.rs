@@ -59,13 +59,19 @@ use elp_syntax::SyntaxKind;
 use elp_syntax::SyntaxNode;
 use elp_syntax::TextRange;
 use elp_syntax::TextSize;
+use elp_types_db::eqwalizer::tc_diagnostics::ExpectedSubtype;
+use elp_types_db::eqwalizer::tc_diagnostics::TypeError;
+use elp_types_db::eqwalizer::Pos;
+use elp_types_db::eqwalizer::StructuredDiagnostic;
 use elp_types_db::IncludeGenerated;
 use elp_types_db::TypedSemantic;
 use erlang_service::CompileOption;
 use fxhash::FxHashMap;
 use fxhash::FxHashSet;
 use hir::db::DefDatabase;
+use hir::DefMap;
 use hir::InFile;
+use hir::NameArity;
 use hir::Semantic;
 use itertools::Itertools;
 use lazy_static::lazy_static;
@@ -74,27 +80,37 @@ use serde::Deserialize;
 use serde::Serialize;
 use text_edit::TextEdit;
 
+use crate::codemod_helpers::MFA;
 use crate::common_test;
 use crate::RootDatabase;
 use crate::SourceDatabase;
 
+mod after_may_raise;
 mod application_env;
 mod atoms_exhaustion;
 mod boolean_precedence;
+mod case_missing_catch_all;
+mod case_on_boolean;
+mod comprehension_to_lists_call;
 mod cross_node_eval;
 mod dependent_header;
 mod deprecated_function;
+mod duplicate_clause;
 mod effect_free_statement;
 mod eqwalizer_assists;
 mod expression_can_be_simplified;
 mod from_config;
+mod gen_server_call_timeout;
 mod head_mismatch;
 mod helpers;
+mod if_to_case;
 mod inefficient_enumerate;
 mod inefficient_flatlength;
 mod inefficient_last;
+mod literal_return_contradicts_spec;
 mod map_find_to_syntax;
 mod map_insertion_to_syntax;
+mod maps_get_without_default;
 mod meck;
 // @fb-only
 mod missing_compile_warn_missing_spec;
@@ -102,16 +118,28 @@ mod missing_separator;
 mod misspelled_attribute;
 mod module_mismatch;
 mod mutable_variable;
+mod non_contractive_type;
+mod non_covariant_opaque_param;
+mod quadratic_append;
 mod record_tuple_match;
 mod redundant_assignment;
+mod redundant_block;
 mod replace_call;
 mod replace_in_spec;
+mod sibling_arity_missing_spec;
 mod slow_functions;
+mod spawn_capturing_closure;
+mod spawn_undefined_function;
+mod spec_with_any_argument;
 mod trivial_match;
+mod undefined_export_type;
 mod undefined_function;
+mod unexported_spec_type;
+mod unhandled_error_return;
 mod unnecessary_fold_to_build_map;
 mod unnecessary_map_from_list_around_comprehension;
 mod unnecessary_map_to_list_in_comprehension;
+mod unreachable_code;
 mod unspecific_include;
 mod unused_function_args;
 mod unused_include;
@@ -539,6 +567,9 @@ pub struct DiagnosticsConfig {
     /// Used in `elp lint` to request erlang service diagnostics if
     /// needed.
     pub request_erlang_service_diagnostics: bool,
+    /// Functions which the `after_may_raise` lint treats as never
+    /// raising an exception, on top of its built-in list.
+    pub safe_functions: Vec<MFA>,
 }
 
 impl DiagnosticsConfig {
@@ -590,6 +621,7 @@ impl DiagnosticsConfig {
             self.enabled = EnabledDiagnostics::from_set(allowed_diagnostics);
         }
         self.lints_from_config = lint_config.ad_hoc_lints.clone();
+        self.safe_functions = lint_config.safe_functions.clone();
         self.request_erlang_service_diagnostics = self.request_erlang_service_diagnostics();
         Ok(self)
     }
@@ -625,6 +657,11 @@ impl DiagnosticsConfig {
         self
     }
 
+    pub fn set_safe_functions(mut self, safe_functions: Vec<MFA>) -> DiagnosticsConfig {
+        self.safe_functions = safe_functions;
+        self
+    }
+
     pub fn enable(mut self, code: DiagnosticCode) -> DiagnosticsConfig {
         self.enabled.enable(code);
         self
@@ -664,6 +701,10 @@ pub struct LintConfig {
     pub enabled_lints: Vec<DiagnosticCode>,
     #[serde(default)]
     pub disabled_lints: Vec<DiagnosticCode>,
+    /// Functions, given as `module:name/arity`, which never raise an
+    /// exception. See `DiagnosticsConfig::safe_functions`.
+    #[serde(default)]
+    pub safe_functions: Vec<MFA>,
     #[serde(default)]
     pub ad_hoc_lints: LintsFromConfig,
 }
@@ -769,6 +810,48 @@ pub fn eqwalizer_to_diagnostic(
     diagnostic
 }
 
+/// Related information for an eqWAlizer type mismatch: the type found
+/// at the mismatched expression, and the expected type, at the location
+/// eqWAlizer gives for it. If that is not a range in the file, fall
+/// back to the spec of the enclosing function.
+pub fn eqwalizer_related_info(
+    db: &RootDatabase,
+    file_id: FileId,
+    d: &EqwalizerDiagnostic,
+) -> Option<Vec<RelatedInformation>> {
+    let ExpectedSubtype {
+        location,
+        expected,
+        got,
+    } = match &d.diagnostic {
+        Some(StructuredDiagnostic::TypeError(TypeError::ExpectedSubtype(subtype))) => subtype,
+        _ => return None,
+    };
+    let expected_range = match location {
+        Pos::TextRange(range) => Some(range.clone().into()),
+        Pos::LineAndColumn(_) => db
+            .def_map(file_id)
+            .get_functions()
+            .find(|(_, def)| {
+                def.file.file_id == file_id
+                    && def
+                        .range(db)
+                        .map_or(false, |range| range.contains_range(d.range))
+            })
+            .and_then(|(_, def)| Some(def.spec.as_ref()?.source(db).syntax().text_range())),
+    };
+    Some(vec![
+        RelatedInformation {
+            range: d.range,
+            message: format!("Got type: {got}"),
+        },
+        RelatedInformation {
+            range: expected_range.unwrap_or(d.range),
+            message: format!("Expected type: {expected}"),
+        },
+    ])
+}
+
 /// Main entry point to calculate ELP-native diagnostics for a file
 pub fn native_diagnostics(
     db: &RootDatabase,
@@ -797,6 +880,16 @@ pub fn native_diagnostics(
             no_module_definition_diagnostic(&mut res, &parse);
             if config.include_generated || !db.is_generated(file_id) {
                 unused_include::unused_includes(&sema, db, &mut res, file_id);
+                unexported_spec_type::unexported_spec_types(&sema, db, &mut res, file_id);
+                undefined_export_type::undefined_export_types(&sema, &mut res, file_id);
+                unhandled_error_return::unhandled_error_returns(&sema, db, &mut res, file_id);
+                case_missing_catch_all::case_missing_catch_alls(&sema, db, &mut res, file_id);
+                case_on_boolean::case_on_booleans(&sema, db, &mut res, file_id, config);
+                non_contractive_type::non_contractive_types(&sema, db, &mut res, file_id);
+                non_covariant_opaque_param::non_covariant_opaque_params(
+                    &sema, db, &mut res, file_id,
+                );
+                after_may_raise::after_may_raise(&mut res, &sema, file_id, config);
             }
         }
 
@@ -884,6 +977,18 @@ pub fn diagnostics_descriptors<'a>() -> Vec<&'a DiagnosticDescriptor<'a>> {
         &boolean_precedence::DESCRIPTOR,
         &record_tuple_match::DESCRIPTOR,
         &unspecific_include::DESCRIPTOR,
+        &if_to_case::DESCRIPTOR,
+        &spawn_capturing_closure::DESCRIPTOR,
+        &quadratic_append::DESCRIPTOR,
+        &redundant_block::DESCRIPTOR,
+        &maps_get_without_default::DESCRIPTOR,
+        &duplicate_clause::DESCRIPTOR,
+        &spec_with_any_argument::DESCRIPTOR,
+        &sibling_arity_missing_spec::DESCRIPTOR,
+        &spawn_undefined_function::DESCRIPTOR,
+        &unreachable_code::DESCRIPTOR,
+        &comprehension_to_lists_call::DESCRIPTOR,
+        &literal_return_contradicts_spec::DESCRIPTOR,
     ]
 }
 
@@ -1319,6 +1424,31 @@ fn label_erlang_service_diagnostics(
         .collect_vec()
 }
 
+/// The outcome of asking eqWAlizer to check a file, distinguishing a
+/// file without errors from one which could not be checked at all.
+#[derive(Debug)]
+pub enum EqwalizerStatus {
+    /// No eqWAlizer executable is configured.
+    Unavailable,
+    /// eqWAlizer is not enabled for the file.
+    Disabled,
+    Checked(Vec<Diagnostic>),
+}
+
+pub fn eqwalizer_status(
+    db: &RootDatabase,
+    file_id: FileId,
+    include_generated: IncludeGenerated,
+) -> EqwalizerStatus {
+    if !db.eqwalizer().is_available() {
+        return EqwalizerStatus::Unavailable;
+    }
+    match eqwalizer_diagnostics(db, file_id, include_generated) {
+        Some(diagnostics) => EqwalizerStatus::Checked(diagnostics),
+        None => EqwalizerStatus::Disabled,
+    }
+}
+
 pub fn eqwalizer_diagnostics(
     db: &RootDatabase,
     file_id: FileId,
@@ -1328,15 +1458,64 @@ pub fn eqwalizer_diagnostics(
     // Because of the way db.eqwalizer_diagnostics() is implemented,
     // we only get diagnostics if it is enabled.
     let eqwalizer_enabled = true;
+    let related_information = db.eqwalizer_config().related_information.is_on();
     let sema = Semantic::new(db);
     Some(
         eqwalizer_diagnostics
             .iter()
-            .map(|d| eqwalizer_to_diagnostic(&sema, file_id, d, eqwalizer_enabled))
+            .map(|d| {
+                let diagnostic = eqwalizer_to_diagnostic(&sema, file_id, d, eqwalizer_enabled);
+                if related_information {
+                    diagnostic.with_related(eqwalizer_related_info(db, file_id, d))
+                } else {
+                    diagnostic
+                }
+            })
             .collect(),
     )
 }
 
+/// Computes the eqwalizer diagnostics for the given file which overlap
+/// the given range, so that only the errors relevant to a selection are
+/// shown.
+pub fn eqwalizer_diagnostics_in_range(
+    db: &RootDatabase,
+    file_id: FileId,
+    range: TextRange,
+) -> Vec<EqwalizerDiagnostic> {
+    db.eqwalizer_diagnostics(file_id, IncludeGenerated::Yes)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|d| d.range.intersect(range).is_some())
+        .collect()
+}
+
+/// Groups the given eqwalizer diagnostics for a module by the function
+/// containing them, using the function ranges from its `DefMap`.
+/// Diagnostics outside of any function, such as in specs, are dropped.
+pub fn eqwalizer_diagnostics_by_function(
+    db: &RootDatabase,
+    file_id: FileId,
+    def_map: &DefMap,
+    diagnostics: Vec<EqwalizerDiagnostic>,
+) -> FxHashMap<NameArity, Vec<EqwalizerDiagnostic>> {
+    let functions: Vec<(&NameArity, TextRange)> = def_map
+        .get_functions()
+        .filter(|(_, def)| def.file.file_id == file_id)
+        .filter_map(|(name, def)| Some((name, def.range(db)?)))
+        .collect();
+    let mut res: FxHashMap<NameArity, Vec<EqwalizerDiagnostic>> = FxHashMap::default();
+    for diagnostic in diagnostics {
+        if let Some((name, _)) = functions
+            .iter()
+            .find(|(_, range)| range.contains_range(diagnostic.range))
+        {
+            res.entry((*name).clone()).or_default().push(diagnostic);
+        }
+    }
+    res
+}
+
 pub fn to_standard_diagnostics(
     db: &RootDatabase,
     project_id: ProjectId,
@@ -1344,6 +1523,7 @@ pub fn to_standard_diagnostics(
 ) -> Option<Vec<(FileId, Vec<Diagnostic>)>> {
     let sema = Semantic::new(db);
     let module_index = db.module_index(project_id);
+    let related_information = db.eqwalizer_config().related_information.is_on();
 
     let mut res = FxHashMap::default();
     match diagnostics {
@@ -1353,7 +1533,12 @@ pub fn to_standard_diagnostics(
                 for d in ds {
                     if let Some(file_id) = module_index.file_for_module(module) {
                         let value = res.entry(file_id).or_insert(Vec::new());
-                        value.push(eqwalizer_to_diagnostic(&sema, file_id, d, true))
+                        let mut diagnostic = eqwalizer_to_diagnostic(&sema, file_id, d, true);
+                        if related_information {
+                            diagnostic =
+                                diagnostic.with_related(eqwalizer_related_info(db, file_id, d));
+                        }
+                        value.push(diagnostic)
                     }
                 }
             })
@@ -1516,6 +1701,8 @@ pub fn ct_diagnostics(
     let sema = Semantic::new(db);
 
     meck::missing_no_link_in_init_per_suite(&mut res, &sema, file_id);
+    common_test::missing_test_function(&mut res, &sema, file_id);
+    gen_server_call_timeout::gen_server_call_without_timeout(&mut res, &sema, file_id);
 
     match &*ct_info(db, file_id) {
         CommonTestInfo::Result { all, groups } => {
@@ -1868,6 +2055,7 @@ pub fn spec_for_undefined_function_from_message(s: &str) -> Option<String> {
 // cargo test --package elp_ide --lib
 #[cfg(test)]
 mod tests {
+    use elp_ide_db::elp_base_db::fixture::WithFixture;
     use elp_project_model::otp::otp_supported_by_eqwalizer;
     use elp_project_model::otp::supports_eep59_doc_attributes;
     use expect_test::expect;
@@ -1877,6 +2065,7 @@ mod tests {
     use crate::codemod_helpers::MFA;
     use crate::diagnostics::DiagnosticCode;
     use crate::tests::check_diagnostics;
+    use crate::tests::check_diagnostics_across_files;
     use crate::tests::check_diagnostics_with_config;
     use crate::tests::check_diagnostics_with_config_and_ad_hoc;
     use crate::tests::check_diagnostics_with_config_and_extra;
@@ -2405,6 +2594,169 @@ baz(1)->4.
         }
     }
 
+    #[test]
+    fn test_eqwalizer_status_without_exe() {
+        let fixture = r#"
+            //- eqwalizer
+            //- /play/src/bar1e.erl app:play
+                -module(bar1e).
+
+                -spec foo() -> ok.
+                foo() -> something_else.
+            "#;
+        let (mut db, file_id) = RootDatabase::with_single_file(fixture);
+        db.disable_eqwalizer_exe();
+        assert!(matches!(
+            eqwalizer_status(&db, file_id, IncludeGenerated::Yes),
+            EqwalizerStatus::Unavailable
+        ));
+
+        if otp_supported_by_eqwalizer() {
+            let (db, file_id) = RootDatabase::with_single_file(fixture);
+            match eqwalizer_status(&db, file_id, IncludeGenerated::Yes) {
+                EqwalizerStatus::Checked(diagnostics) => assert_eq!(diagnostics.len(), 1),
+                status => panic!("expected diagnostics, got {:?}", status),
+            }
+        }
+    }
+
+    #[test]
+    fn test_eqwalizer_diagnostics_in_range() {
+        if otp_supported_by_eqwalizer() {
+            let (db, fixture) = RootDatabase::with_fixture(
+                r#"
+            //- eqwalizer
+            //- /play/src/bar1e.erl app:play
+                -module(bar1e).
+
+                -spec foo() -> ok.
+                foo() -> ~something_else~.
+
+                -spec baz() -> ok.
+                baz() -> other.
+            "#,
+            );
+            let range = fixture.range();
+            let file_text = db.file_text(range.file_id);
+            let diagnostics = eqwalizer_diagnostics_in_range(&db, range.file_id, range.range);
+            let texts: Vec<&str> = diagnostics.iter().map(|d| &file_text[d.range]).collect();
+            assert_eq!(texts, vec!["something_else"]);
+        }
+    }
+
+    #[test]
+    fn test_eqwalizer_diagnostics_by_function() {
+        if otp_supported_by_eqwalizer() {
+            let (db, file_id) = RootDatabase::with_single_file(
+                r#"
+            //- eqwalizer
+            //- /play/src/bar1e.erl app:play
+                -module(bar1e).
+
+                -spec foo(boolean()) -> ok.
+                foo(true) -> one;
+                foo(false) -> two.
+
+                -spec bar() -> ok.
+                bar() -> ok.
+
+                -spec baz() -> ok.
+                baz() -> three.
+            "#,
+            );
+            let file_text = db.file_text(file_id);
+            let diagnostics = db
+                .eqwalizer_diagnostics(file_id, IncludeGenerated::Yes)
+                .unwrap_or_default();
+            let def_map = db.def_map(file_id);
+            let by_function =
+                eqwalizer_diagnostics_by_function(&db, file_id, &def_map, diagnostics);
+            let mut grouped: Vec<(String, Vec<&str>)> = by_function
+                .iter()
+                .map(|(name, diagnostics)| {
+                    let texts = diagnostics.iter().map(|d| &file_text[d.range]).collect();
+                    (name.to_string(), texts)
+                })
+                .collect();
+            grouped.sort();
+            assert_eq!(
+                grouped,
+                vec![
+                    ("baz/0".to_string(), vec!["three"]),
+                    ("foo/1".to_string(), vec!["one", "two"]),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn test_eqwalizer_related_info() {
+        let (db, file_id) = RootDatabase::with_single_file(
+            r#"
+            -module(main).
+
+            -spec foo() -> atom().
+            foo() -> 42.
+            "#,
+        );
+        let file_text = db.file_text(file_id);
+        let start = TextSize::from(file_text.find("42").unwrap() as u32);
+        let range = TextRange::at(start, TextSize::from(2));
+        let expected_start = file_text.find("atom()").unwrap() as u32;
+        let diagnostic = |location: Pos| EqwalizerDiagnostic {
+            range,
+            message: "Expression has type:   number()\nContext expected type: atom()".to_string(),
+            uri: "https://fb.me/eqwalizer_errors#incompatible_types".to_string(),
+            code: "incompatible_types".to_string(),
+            expression: Some("42".to_string()),
+            explanation: None,
+            diagnostic: Some(StructuredDiagnostic::TypeError(TypeError::ExpectedSubtype(
+                ExpectedSubtype {
+                    location,
+                    expected: elp_types_db::eqwalizer::types::Type::AtomType,
+                    got: elp_types_db::eqwalizer::types::Type::NumberType,
+                },
+            ))),
+        };
+        let related = |location: Pos| -> Vec<(String, String)> {
+            eqwalizer_related_info(&db, file_id, &diagnostic(location))
+                .unwrap()
+                .into_iter()
+                .map(|info| (file_text[info.range].to_string(), info.message))
+                .collect()
+        };
+        assert_eq!(
+            related(
+                elp_types_db::eqwalizer::TextRange {
+                    start_byte: expected_start,
+                    end_byte: expected_start + 6,
+                }
+                .into()
+            ),
+            vec![
+                ("42".to_string(), "Got type: number()".to_string()),
+                ("atom()".to_string(), "Expected type: atom()".to_string()),
+            ]
+        );
+        // Without a range from eqWAlizer, point at the enclosing spec
+        assert_eq!(
+            related(
+                elp_types_db::eqwalizer::LineAndColumn {
+                    line: 4,
+                    column: 22
+                }
+                .into()
+            ),
+            vec![
+                ("42".to_string(), "Got type: number()".to_string()),
+                (
+                    "-spec foo() -> atom().".to_string(),
+                    "Expected type: atom()".to_string()
+                ),
+            ]
+        );
+    }
+
     #[test]
     fn test_nested_syntax_errors() {
         check_diagnostics(
@@ -2555,6 +2907,25 @@ baz(1)->4.
         );
     }
 
+    #[test]
+    fn erlang_service_diagnostic_in_included_header() {
+        check_diagnostics_across_files(
+            r#"
+               //- erlang_service
+               //- /src/main.erl
+                   -module(main).
+                   -export([foo/0]).
+                   -include("header.hrl").
+
+                   foo() -> baz().
+
+               //- /src/header.hrl
+                   baz() -> bar().
+               %%           ^^^^^ error: function bar/0 undefined
+            "#,
+        );
+    }
+
     #[test]
     fn erlang_service_include_resolution_doc() {
         check_diagnostics(
@@ -0,0 +1,290 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+// Diagnostic: after-may-raise
+//
+// Return a weak warning if the `after` block of a `try` calls a function
+// which may raise an exception. An exception raised in an `after` block
+// replaces the one raised in the body of the `try`, hiding the original
+// error. Any call is assumed to be able to raise, except for a small set
+// of functions known to be safe, and those listed in the
+// `safe_functions` lint config. Disabled by default, it must be enabled
+// in `enabled_lints`.
+
+use elp_ide_db::elp_base_db::FileId;
+use elp_syntax::SmolStr;
+use elp_syntax::TextRange;
+use hir::fold::FoldCtx;
+use hir::fold::MacroStrategy;
+use hir::fold::ParenStrategy;
+use hir::AnyExpr;
+use hir::Body;
+use hir::Expr;
+use hir::ExprId;
+use hir::FunctionDef;
+use hir::Semantic;
+use hir::Strategy;
+use lazy_static::lazy_static;
+
+use super::Diagnostic;
+use super::DiagnosticsConfig;
+use super::Severity;
+use crate::codemod_helpers::FunctionMatch;
+use crate::codemod_helpers::FunctionMatcher;
+use crate::diagnostics::DiagnosticCode;
+
+const STRATEGY: Strategy = Strategy {
+    macros: MacroStrategy::DoNotExpand,
+    parens: ParenStrategy::InvisibleParens,
+};
+
+lazy_static! {
+    static ref SAFE_FUNCTIONS: Vec<FunctionMatch> = [
+        vec![
+            FunctionMatch::mfa("erlang", "self", 0),
+            FunctionMatch::mfa("erlang", "node", 0),
+            FunctionMatch::mfa("erlang", "make_ref", 0),
+            FunctionMatch::mfa("erlang", "get", 1),
+            FunctionMatch::mfa("erlang", "put", 2),
+            FunctionMatch::mfa("erlang", "unlink", 1),
+            FunctionMatch::mfa("erlang", "monotonic_time", 0),
+            FunctionMatch::mfa("erlang", "system_time", 0),
+            FunctionMatch::mf("logger", "debug"),
+            FunctionMatch::mf("logger", "info"),
+            FunctionMatch::mf("logger", "notice"),
+            FunctionMatch::mf("logger", "warning"),
+            FunctionMatch::mf("logger", "error"),
+        ],
+        FunctionMatch::mfas("erlang", "erase", vec![0, 1]),
+        FunctionMatch::mfas("erlang", "demonitor", vec![1, 2]),
+    ]
+    .concat();
+}
+
+pub(crate) fn after_may_raise(
+    diagnostics: &mut Vec<Diagnostic>,
+    sema: &Semantic,
+    file_id: FileId,
+    config: &DiagnosticsConfig,
+) {
+    if !config.enabled.contains(&DiagnosticCode::AfterMayRaise) {
+        return;
+    }
+    let is_test = sema
+        .db
+        .is_test_suite_or_test_helper(file_id)
+        .unwrap_or(false);
+    if is_test || sema.db.is_generated(file_id) {
+        return;
+    }
+    let configured: Vec<FunctionMatch> = config
+        .safe_functions
+        .iter()
+        .map(|mfa| FunctionMatch::MFA { mfa: mfa.clone() })
+        .collect();
+    let safe_functions: Vec<(&FunctionMatch, ())> = SAFE_FUNCTIONS
+        .iter()
+        .chain(configured.iter())
+        .map(|matcher| (matcher, ()))
+        .collect();
+    let matcher = FunctionMatcher::new(&safe_functions);
+    sema.for_each_function(file_id, |def| {
+        check_function(diagnostics, sema, def, &matcher)
+    });
+}
+
+fn check_function(
+    diagnostics: &mut Vec<Diagnostic>,
+    sema: &Semantic,
+    def: &FunctionDef,
+    matcher: &FunctionMatcher<()>,
+) {
+    let def_fb = def.in_function_body(sema, def);
+    def_fb
+        .clone()
+        .fold_function(STRATEGY, (), &mut |_acc, clause_id, ctx| {
+            let after = match &ctx.item {
+                AnyExpr::Expr(Expr::Try { after, .. }) if !after.is_empty() => after,
+                _ => return,
+            };
+            let body = def_fb.body(clause_id);
+            let callee = match risky_call(sema, &body, after, matcher) {
+                Some(callee) => callee,
+                None => return,
+            };
+            let start = def_fb.range_for_expr(clause_id, after[0]);
+            let end = def_fb.range_for_expr(clause_id, after[after.len() - 1]);
+            if let (Some(start), Some(end)) = (start, end) {
+                let range = TextRange::new(start.start(), end.end());
+                diagnostics.push(make_diagnostic(range, callee));
+            }
+        });
+}
+
+/// The first call in `exprs` which is not known to be safe, labelled
+/// with the function it calls if that is known statically. Statements
+/// which catch their own exceptions are skipped.
+fn risky_call(
+    sema: &Semantic,
+    body: &Body,
+    exprs: &[ExprId],
+    matcher: &FunctionMatcher<()>,
+) -> Option<Option<SmolStr>> {
+    let exprs: Vec<ExprId> = exprs
+        .iter()
+        .copied()
+        .filter(|expr| !catches_exceptions(&body[*expr]))
+        .collect();
+    FoldCtx::fold_exprs(STRATEGY, body, &exprs, None, &mut |acc, ctx| {
+        if acc.is_some() {
+            return acc;
+        }
+        match &ctx.item {
+            AnyExpr::Expr(Expr::Call { target, args }) => {
+                let arity = args.len() as u32;
+                match matcher.get_match(target, arity, Some(&args[..]), sema, body) {
+                    Some(_) => None,
+                    None => Some(target.label(arity, sema, body)),
+                }
+            }
+            _ => None,
+        }
+    })
+}
+
+fn catches_exceptions(expr: &Expr) -> bool {
+    match expr {
+        Expr::Catch { .. } => true,
+        Expr::Try { catch_clauses, .. } => !catch_clauses.is_empty(),
+        _ => false,
+    }
+}
+
+fn make_diagnostic(range: TextRange, callee: Option<SmolStr>) -> Diagnostic {
+    let call = match callee {
+        Some(label) => format!("calls `{label}`, which"),
+        None => "contains a call which".to_string(),
+    };
+    let message = format!(
+        "This `after` block {call} may raise an exception, hiding any exception raised by the `try`."
+    );
+    Diagnostic::new(DiagnosticCode::AfterMayRaise, message, range)
+        .with_severity(Severity::WeakWarning)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::codemod_helpers::MFA;
+    use crate::diagnostics::DiagnosticCode;
+    use crate::diagnostics::DiagnosticsConfig;
+    use crate::tests::check_diagnostics_with_config;
+
+    #[track_caller]
+    fn check_diagnostics(fixture: &str) {
+        let config = DiagnosticsConfig::default().enable(DiagnosticCode::AfterMayRaise);
+        check_diagnostics_with_config(config, fixture)
+    }
+
+    #[test]
+    fn disabled_by_default() {
+        check_diagnostics_with_config(
+            DiagnosticsConfig::default(),
+            r#"
+            -module(main).
+            f(Fd) ->
+                try
+                    read(Fd)
+                after
+                    close(Fd)
+                end.
+            read(_) -> ok.
+            close(_) -> ok.
+            "#,
+        );
+    }
+
+    #[test]
+    fn risky_after_is_reported() {
+        check_diagnostics(
+            r#"
+            -module(main).
+            f(Fd) ->
+                try
+                    read(Fd)
+                after
+                    close(Fd)
+            %%      ^^^^^^^^^ weak: This `after` block calls `close/1`, which may raise an exception, hiding any exception raised by the `try`.
+                end.
+            read(_) -> ok.
+            close(_) -> ok.
+            "#,
+        );
+    }
+
+    #[test]
+    fn trivial_after_is_not_reported() {
+        check_diagnostics(
+            r#"
+            -module(main).
+            f(Fd) ->
+                try
+                    read(Fd)
+                after
+                    ok
+                end.
+            g(Fd) ->
+                try
+                    read(Fd)
+                after
+                    erase(fd),
+                    self()
+                end.
+            h(Fd) ->
+                try
+                    read(Fd)
+                after
+                    try close(Fd)
+                    catch _:_ -> ok
+                    end
+                end.
+            read(_) -> ok.
+            close(_) -> ok.
+            "#,
+        );
+    }
+
+    #[test]
+    fn configured_safe_function_is_not_reported() {
+        let config = DiagnosticsConfig::default()
+            .enable(DiagnosticCode::AfterMayRaise)
+            .set_safe_functions(vec![MFA::new("main", "close", 1)]);
+        check_diagnostics_with_config(
+            config,
+            r#"
+            -module(main).
+            f(Fd) ->
+                try
+                    read(Fd)
+                after
+                    close(Fd)
+                end.
+            g(Fd) ->
+                try
+                    read(Fd)
+                after
+                    flush(Fd)
+            %%      ^^^^^^^^^ weak: This `after` block calls `flush/1`, which may raise an exception, hiding any exception raised by the `try`.
+                end.
+            read(_) -> ok.
+            close(_) -> ok.
+            flush(_) -> ok.
+            "#,
+        );
+    }
+}
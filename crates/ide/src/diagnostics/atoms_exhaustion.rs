@@ -7,11 +7,23 @@
  * of this source tree.
  */
 
+// Diagnostic: atoms-exhaustion
+//
+// Return a warning if an atom is created from a value which is not a
+// literal, since atoms are never garbage collected. Offer to use the
+// `_existing_` variant of the conversion function instead.
+
+use elp_ide_assists::Assist;
 use elp_ide_db::elp_base_db::FileId;
+use elp_ide_db::source_change::SourceChangeBuilder;
+use hir::Body;
+use hir::Expr;
+use hir::ExprId;
 use hir::FunctionDef;
 use hir::Semantic;
 use lazy_static::lazy_static;
 use text_edit::TextRange;
+use text_edit::TextSize;
 
 use super::DiagnosticConditions;
 use super::DiagnosticDescriptor;
@@ -22,6 +34,7 @@ use crate::codemod_helpers::MakeDiagCtx;
 use crate::diagnostics::Diagnostic;
 use crate::diagnostics::DiagnosticCode;
 use crate::diagnostics::Severity;
+use crate::fix;
 use crate::FunctionMatch;
 
 pub(crate) static DESCRIPTOR: DiagnosticDescriptor = DiagnosticDescriptor {
@@ -87,10 +100,14 @@ fn check_function(
             let is_safe;
             // @fb-only
             is_safe = false; // @oss-only
-            if !is_safe {
+            let body = in_clause.body();
+            let literal_arg = args
+                .as_vec()
+                .first()
+                .map_or(false, |arg| is_literal(&body, *arg));
+            if !is_safe && !literal_arg {
                 match args.as_vec()[..] {
                     [_, options] => {
-                        let body = in_clause.body();
                         match &body[options].literal_list_contains_atom(in_clause, "safe") {
                             Some(true) => None,
                             _ => Some(("".to_string(), "".to_string())),
@@ -102,22 +119,66 @@ fn check_function(
                 None
             }
         },
-        &move |MakeDiagCtx { sema, range, .. }| {
-            let diag = make_diagnostic(sema, def.file.file_id, range);
+        &move |ctx @ MakeDiagCtx { sema, range, .. }| {
+            let diag = make_diagnostic(sema, def.file.file_id, range, ctx.range_mf_only());
             Some(diag)
         },
     );
 }
 
-fn make_diagnostic(sema: &Semantic, file_id: FileId, range: TextRange) -> Diagnostic {
+/// Whether `expr` is a literal, or a binary built only from literals,
+/// so that converting it can only ever create a single atom.
+fn is_literal(body: &Body, expr: ExprId) -> bool {
+    match &body[expr] {
+        Expr::Literal(_) => true,
+        Expr::Binary { segs } => segs
+            .iter()
+            .all(|seg| matches!(body[seg.elem], Expr::Literal(_))),
+        _ => false,
+    }
+}
+
+fn make_diagnostic(
+    sema: &Semantic,
+    file_id: FileId,
+    range: TextRange,
+    range_mf: TextRange,
+) -> Diagnostic {
     let message = "Risk of atoms exhaustion.".to_string();
     Diagnostic::new(DiagnosticCode::AtomsExhaustion, message, range)
-        .with_severity(Severity::WeakWarning)
+        .with_severity(Severity::Warning)
+        .with_fixes(use_existing_atom_fix(sema, file_id, range, range_mf))
         .with_ignore_fix(sema, file_id)
 }
 
+/// Replace `binary_to_atom` or `list_to_atom` with its `_existing_`
+/// variant. No fix is offered if the call name is hidden in a macro.
+fn use_existing_atom_fix(
+    sema: &Semantic,
+    file_id: FileId,
+    range: TextRange,
+    range_mf: TextRange,
+) -> Option<Vec<Assist>> {
+    let file_text = sema.db.file_text(file_id);
+    let call_name = &file_text[range_mf];
+    let name = ["binary_to_atom", "list_to_atom"]
+        .into_iter()
+        .find(|name| call_name.ends_with(name))?;
+    let name_range = TextRange::new(range_mf.end() - TextSize::of(name), range_mf.end());
+    let replacement = name.replace("_to_atom", "_to_existing_atom");
+    let mut builder = SourceChangeBuilder::new(file_id);
+    builder.replace(name_range, replacement.clone());
+    Some(vec![fix(
+        "use_existing_atom",
+        &format!("Use `{replacement}`"),
+        builder.finish(),
+        range,
+    )])
+}
+
 #[cfg(test)]
 mod tests {
+    use expect_test::expect;
 
     use crate::tests;
 
@@ -127,12 +188,12 @@ mod tests {
             r#"
 //- /src/main.erl
    -module(main).
-   -export([main/0]).
-   main() ->
-     erlang:list_to_atom(foo),
-%%   ^^^^^^^^^^^^^^^^^^^^^^^^ 💡 weak: Risk of atoms exhaustion.
-     list_to_atom(foo).
-%%   ^^^^^^^^^^^^^^^^^ 💡 weak: Risk of atoms exhaustion.
+   -export([main/1]).
+   main(Foo) ->
+     erlang:list_to_atom(Foo),
+%%   ^^^^^^^^^^^^^^^^^^^^^^^^ 💡 warning: Risk of atoms exhaustion.
+     list_to_atom(Foo).
+%%   ^^^^^^^^^^^^^^^^^ 💡 warning: Risk of atoms exhaustion.
 
 //- /opt/lib/stdlib-3.17/src/erlang.erl otp_app:/opt/lib/stdlib-3.17
    -module(erlang).
@@ -148,12 +209,12 @@ mod tests {
             r#"
 //- /src/main.erl
    -module(main).
-   -export([main/0]).
-   main() ->
-     erlang:binary_to_atom(foo),
-%%   ^^^^^^^^^^^^^^^^^^^^^^^^^^ 💡 weak: Risk of atoms exhaustion.
-     binary_to_atom(foo).
-%%   ^^^^^^^^^^^^^^^^^^^ 💡 weak: Risk of atoms exhaustion.
+   -export([main/1]).
+   main(Foo) ->
+     erlang:binary_to_atom(Foo),
+%%   ^^^^^^^^^^^^^^^^^^^^^^^^^^ 💡 warning: Risk of atoms exhaustion.
+     binary_to_atom(Foo).
+%%   ^^^^^^^^^^^^^^^^^^^ 💡 warning: Risk of atoms exhaustion.
 
 //- /opt/lib/stdlib-3.17/src/erlang.erl otp_app:/opt/lib/stdlib-3.17
    -module(erlang).
@@ -229,4 +290,72 @@ mod tests {
             "#,
         )
     }
+
+    #[test]
+    fn test_literal_argument() {
+        tests::check_diagnostics(
+            r#"
+//- /src/main.erl
+   -module(main).
+   -export([main/0]).
+   main() ->
+     list_to_atom("foo"),
+     binary_to_atom(<<"foo">>),
+     erlang:binary_to_atom(<<"foo">>, utf8).
+
+//- /opt/lib/stdlib-3.17/src/erlang.erl otp_app:/opt/lib/stdlib-3.17
+   -module(erlang).
+   -export([binary_to_atom/1, binary_to_atom/2, list_to_atom/1]).
+   binary_to_atom(_) -> ok.
+   binary_to_atom(_, _) -> ok.
+   list_to_atom(_) -> ok.
+            "#,
+        )
+    }
+
+    #[test]
+    fn test_use_existing_atom() {
+        tests::check_fix(
+            r#"
+//- /src/main.erl
+   -module(main).
+   -export([main/1]).
+   main(Foo) ->
+     erlang:binary_to~_atom(Foo, utf8).
+//- /opt/lib/stdlib-3.17/src/erlang.erl otp_app:/opt/lib/stdlib-3.17
+   -module(erlang).
+   -export([binary_to_atom/2]).
+   binary_to_atom(_, _) -> ok.
+            "#,
+            expect![[r#"
+   -module(main).
+   -export([main/1]).
+   main(Foo) ->
+     erlang:binary_to_existing_atom(Foo, utf8).
+            "#]],
+        )
+    }
+
+    #[test]
+    fn test_use_existing_atom_local_call() {
+        tests::check_fix(
+            r#"
+//- /src/main.erl
+   -module(main).
+   -export([main/1]).
+   main(Foo) ->
+     list_to_~atom(Foo).
+//- /opt/lib/stdlib-3.17/src/erlang.erl otp_app:/opt/lib/stdlib-3.17
+   -module(erlang).
+   -export([list_to_atom/1]).
+   list_to_atom(_) -> ok.
+            "#,
+            expect![[r#"
+   -module(main).
+   -export([main/1]).
+   main(Foo) ->
+     list_to_existing_atom(Foo).
+            "#]],
+        )
+    }
 }
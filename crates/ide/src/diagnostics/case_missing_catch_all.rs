@@ -0,0 +1,292 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+// Diagnostic: case-missing-catch-all
+//
+// Return a warning if a `case` expression has no catch-all clause, and
+// the eqWAlizer type of its subject shows that its clauses do not
+// cover every possible value. Such a `case` can crash at runtime with
+// a `case_clause` error. Only enabled when clause coverage is enabled
+// in the eqWAlizer config.
+
+use elp_eqwalizer::EqwalizerDiagnosticsDatabase;
+use elp_ide_db::elp_base_db::FileId;
+use elp_ide_db::elp_base_db::FileRange;
+use elp_ide_db::source_change::SourceChangeBuilder;
+use elp_ide_db::EqwalizerDatabase;
+use elp_syntax::ast;
+use elp_syntax::ast::edit::IndentLevel;
+use elp_syntax::AstNode;
+use elp_syntax::SmolStr;
+use elp_syntax::TextRange;
+use elp_types_db::eqwalizer::types::Type;
+use elp_types_db::IncludeGenerated;
+use hir::fold::MacroStrategy;
+use hir::fold::ParenStrategy;
+use hir::AnyExpr;
+use hir::AnyExprId;
+use hir::Body;
+use hir::CRClause;
+use hir::Expr;
+use hir::Literal;
+use hir::Pat;
+use hir::PatId;
+use hir::Semantic;
+use hir::Strategy;
+
+use super::Diagnostic;
+use crate::diagnostics::DiagnosticCode;
+use crate::diagnostics::Severity;
+use crate::fix;
+use crate::RootDatabase;
+
+pub(crate) fn case_missing_catch_alls(
+    sema: &Semantic,
+    db: &RootDatabase,
+    diagnostics: &mut Vec<Diagnostic>,
+    file_id: FileId,
+) -> Option<()> {
+    if !db.eqwalizer_config().clause_coverage.is_on() {
+        return None;
+    }
+    if !db.is_eqwalizer_enabled(file_id, IncludeGenerated::No) {
+        return None;
+    }
+    let source_file = sema.parse(file_id);
+    sema.for_each_function(file_id, |def| {
+        let def_fb = def.in_function_body(sema, def);
+        def_fb.fold_function(
+            Strategy {
+                macros: MacroStrategy::Expand,
+                parens: ParenStrategy::InvisibleParens,
+            },
+            (),
+            &mut |_acc, clause_id, ctx| {
+                let expr_id = match ctx.item_id {
+                    AnyExprId::Expr(expr_id) => expr_id,
+                    _ => return,
+                };
+                let (subject, clauses) = match ctx.item {
+                    AnyExpr::Expr(Expr::Case { expr, clauses }) => (expr, clauses),
+                    _ => return,
+                };
+                let body = def_fb.body(clause_id);
+                if clauses.iter().any(|clause| is_catch_all(&body, clause)) {
+                    return;
+                }
+                let subject_range = match def_fb.range_for_expr(clause_id, subject) {
+                    Some(range) => range,
+                    None => return,
+                };
+                let subject_type = match db.type_at_position(FileRange {
+                    file_id,
+                    range: subject_range,
+                }) {
+                    Some(type_info) if type_info.1.range == subject_range => type_info,
+                    _ => return,
+                };
+                if !is_incomplete(&subject_type.0, &covered_atoms(sema, &body, &clauses)) {
+                    return;
+                }
+                let case_expr = match def_fb
+                    .get_body_map(clause_id)
+                    .expr(expr_id)
+                    .and_then(|ptr| ptr.to_node(&source_file))
+                {
+                    Some(ast::Expr::ExprMax(ast::ExprMax::CaseExpr(case_expr))) => case_expr,
+                    _ => return,
+                };
+                if let Some(diagnostic) = make_diagnostic(sema, file_id, &case_expr, subject_range)
+                {
+                    diagnostics.push(diagnostic);
+                }
+            },
+        );
+    });
+    Some(())
+}
+
+/// A clause without guards that matches any value, such as `_` or `X`.
+fn is_catch_all(body: &Body, clause: &CRClause) -> bool {
+    clause.guards.is_empty() && is_catch_all_pat(body, clause.pat)
+}
+
+fn is_catch_all_pat(body: &Body, pat: PatId) -> bool {
+    match &body[pat] {
+        Pat::Var(_) => true,
+        Pat::Match { lhs, rhs } => is_catch_all_pat(body, *lhs) || is_catch_all_pat(body, *rhs),
+        _ => false,
+    }
+}
+
+/// The atom literals matched by the unguarded clauses.
+fn covered_atoms(sema: &Semantic, body: &Body, clauses: &[CRClause]) -> Vec<SmolStr> {
+    clauses
+        .iter()
+        .filter(|clause| clause.guards.is_empty())
+        .filter_map(|clause| match &body[clause.pat] {
+            Pat::Literal(Literal::Atom(atom)) => Some(atom.as_string(sema.db.upcast()).into()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Whether a value of type `ty` may not match any of the clauses. An
+/// open type, such as `atom()`, can never be covered by literal
+/// patterns, and a union of atoms is only covered if every atom is
+/// matched. Other types are left to eqWAlizer.
+fn is_incomplete(ty: &Type, covered: &[SmolStr]) -> bool {
+    match ty {
+        Type::AtomType | Type::NumberType | Type::BinaryType | Type::AnyType => true,
+        Type::AtomLitType(lit) => !covered.contains(&lit.atom),
+        Type::UnionType(union) => union.tys.iter().any(|ty| is_incomplete(ty, covered)),
+        _ => false,
+    }
+}
+
+fn make_diagnostic(
+    sema: &Semantic,
+    file_id: FileId,
+    case_expr: &ast::CaseExpr,
+    range: TextRange,
+) -> Option<Diagnostic> {
+    let last_clause = case_expr.clauses().last()?;
+    let indent = IndentLevel::from_node(last_clause.syntax());
+    let mut builder = SourceChangeBuilder::new(file_id);
+    builder.insert(
+        last_clause.syntax().text_range().end(),
+        format!(";\n{indent}_ -> error(unexpected)"),
+    );
+    let fixes = vec![fix(
+        "case_add_catch_all",
+        "Add a catch-all clause",
+        builder.finish(),
+        range,
+    )];
+    let message = "The clauses of this `case` do not cover every value of its subject, and there is no catch-all clause.".to_string();
+    Some(
+        Diagnostic::new(DiagnosticCode::CaseMissingCatchAll, message, range)
+            .with_severity(Severity::Warning)
+            .with_ignore_fix(sema, file_id)
+            .with_fixes(Some(fixes)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use elp_eqwalizer::ConfigValue;
+    use elp_eqwalizer::EqwalizerConfig;
+    use elp_project_model::otp::otp_supported_by_eqwalizer;
+    use expect_test::expect;
+    use expect_test::Expect;
+
+    use crate::diagnostics::DiagnosticCode;
+    use crate::tests::check_filtered_diagnostics_with_eqwalizer_config;
+    use crate::tests::check_fix_with_eqwalizer_config;
+
+    fn eqwalizer_config() -> EqwalizerConfig {
+        EqwalizerConfig {
+            clause_coverage: ConfigValue::On,
+            ..EqwalizerConfig::default()
+        }
+    }
+
+    #[track_caller]
+    fn check_diagnostics(fixture: &str) {
+        check_filtered_diagnostics_with_eqwalizer_config(eqwalizer_config(), fixture, &|d| {
+            d.code == DiagnosticCode::CaseMissingCatchAll
+        })
+    }
+
+    #[track_caller]
+    fn check_fix(fixture_before: &str, fixture_after: Expect) {
+        check_fix_with_eqwalizer_config(eqwalizer_config(), fixture_before, fixture_after)
+    }
+
+    #[test]
+    fn incomplete_case() {
+        if otp_supported_by_eqwalizer() {
+            check_diagnostics(
+                r#"
+            //- eqwalizer
+            //- native
+            //- /play/src/main.erl app:play
+            -module(main).
+            -export([f/1]).
+            -spec f(atom()) -> ok.
+            f(X) ->
+                case X of
+            %%       ^ 💡 warning: The clauses of this `case` do not cover every value of its subject, and there is no catch-all clause.
+                    a -> ok;
+                    b -> ok
+                end.
+            "#,
+            )
+        }
+    }
+
+    #[test]
+    fn exhaustive_case() {
+        if otp_supported_by_eqwalizer() {
+            check_diagnostics(
+                r#"
+            //- eqwalizer
+            //- native
+            //- /play/src/main.erl app:play
+            -module(main).
+            -export([f/1, g/1]).
+            -spec f(a | b) -> ok.
+            f(X) ->
+                case X of
+                    a -> ok;
+                    b -> ok
+                end.
+            -spec g(atom()) -> ok.
+            g(X) ->
+                case X of
+                    a -> ok;
+                    _ -> ok
+                end.
+            "#,
+            )
+        }
+    }
+
+    #[test]
+    fn add_catch_all_clause() {
+        if otp_supported_by_eqwalizer() {
+            check_fix(
+                r#"
+            //- eqwalizer
+            //- native
+            //- /play/src/main.erl app:play
+            -module(main).
+            -export([f/1]).
+            -spec f(atom()) -> ok.
+            f(X) ->
+                case ~X of
+                    a -> ok;
+                    b -> ok
+                end.
+            "#,
+                expect![[r#"
+                    -module(main).
+                    -export([f/1]).
+                    -spec f(atom()) -> ok.
+                    f(X) ->
+                        case X of
+                            a -> ok;
+                            b -> ok;
+                            _ -> error(unexpected)
+                        end.
+                "#]],
+            )
+        }
+    }
+}
@@ -0,0 +1,440 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+// Diagnostic: case-on-boolean
+//
+// Return a weak warning if a `case` expression has exactly two
+// clauses, matching `true` and `false`, and the eqWAlizer type of its
+// subject is `boolean()`. Offer to use the subject directly when the
+// clauses just return a boolean, and otherwise to rewrite the `case`
+// as an `if` when the subject can be used as a guard.
+
+use elp_ide_db::elp_base_db::FileId;
+use elp_ide_db::elp_base_db::FileRange;
+use elp_ide_db::source_change::SourceChangeBuilder;
+use elp_ide_db::EqwalizerDatabase;
+use elp_syntax::ast;
+use elp_syntax::AstNode;
+use elp_syntax::SyntaxKind;
+use elp_syntax::TextRange;
+use elp_types_db::eqwalizer::types::Type;
+use elp_types_db::IncludeGenerated;
+use hir::fold::MacroStrategy;
+use hir::fold::ParenStrategy;
+use hir::AnyExpr;
+use hir::AnyExprId;
+use hir::Body;
+use hir::CRClause;
+use hir::ClauseId;
+use hir::Expr;
+use hir::ExprId;
+use hir::FunctionDef;
+use hir::InFunctionBody;
+use hir::Literal;
+use hir::Pat;
+use hir::PatId;
+use hir::Semantic;
+use hir::Strategy;
+
+use super::Diagnostic;
+use crate::diagnostics::DiagnosticCode;
+use crate::diagnostics::DiagnosticsConfig;
+use crate::diagnostics::Severity;
+use crate::fix;
+use crate::RootDatabase;
+
+pub(crate) fn case_on_booleans(
+    sema: &Semantic,
+    db: &RootDatabase,
+    diagnostics: &mut Vec<Diagnostic>,
+    file_id: FileId,
+    config: &DiagnosticsConfig,
+) -> Option<()> {
+    if !config.enabled.contains(&DiagnosticCode::CaseOnBoolean)
+        || !db.is_eqwalizer_enabled(file_id, IncludeGenerated::No)
+    {
+        return None;
+    }
+    let source_file = sema.parse(file_id);
+    sema.for_each_function(file_id, |def| {
+        let def_fb = def.in_function_body(sema, def);
+        def_fb.fold_function(
+            Strategy {
+                macros: MacroStrategy::Expand,
+                parens: ParenStrategy::InvisibleParens,
+            },
+            (),
+            &mut |_acc, clause_id, ctx| {
+                let expr_id = match ctx.item_id {
+                    AnyExprId::Expr(expr_id) => expr_id,
+                    _ => return,
+                };
+                let (subject, clauses) = match ctx.item {
+                    AnyExpr::Expr(Expr::Case { expr, clauses }) => (expr, clauses),
+                    _ => return,
+                };
+                let body = def_fb.body(clause_id);
+                let (true_clause, false_clause) = match boolean_clauses(sema, &body, &clauses) {
+                    Some(boolean_clauses) => boolean_clauses,
+                    None => return,
+                };
+                let subject_range = match def_fb.range_for_expr(clause_id, subject) {
+                    Some(range) => range,
+                    None => return,
+                };
+                match db.type_at_position(FileRange {
+                    file_id,
+                    range: subject_range,
+                }) {
+                    Some(type_info)
+                        if type_info.1.range == subject_range && is_boolean(&type_info.0) => {}
+                    _ => return,
+                };
+                let case_expr = match def_fb
+                    .get_body_map(clause_id)
+                    .expr(expr_id)
+                    .and_then(|ptr| ptr.to_node(&source_file))
+                {
+                    Some(ast::Expr::ExprMax(ast::ExprMax::CaseExpr(case_expr))) => case_expr,
+                    _ => return,
+                };
+                let case = BooleanCase {
+                    subject,
+                    subject_range,
+                    true_clause,
+                    false_clause,
+                    true_first: clauses[0].pat == true_clause.pat,
+                };
+                diagnostics.push(make_diagnostic(
+                    sema, file_id, &def_fb, clause_id, &body, &case_expr, &case,
+                ));
+            },
+        );
+    });
+    Some(())
+}
+
+/// A `case` whose clauses are exactly `true -> ...` and `false -> ...`.
+struct BooleanCase<'a> {
+    subject: ExprId,
+    subject_range: TextRange,
+    true_clause: &'a CRClause,
+    false_clause: &'a CRClause,
+    /// Whether the `true` clause comes before the `false` one.
+    true_first: bool,
+}
+
+/// The `true` and `false` clauses, if those are the only clauses and
+/// neither has guards.
+fn boolean_clauses<'a>(
+    sema: &Semantic,
+    body: &Body,
+    clauses: &'a [CRClause],
+) -> Option<(&'a CRClause, &'a CRClause)> {
+    let is_atom = |clause: &CRClause, name: &str| {
+        clause.guards.is_empty() && pat_atom(sema, body, clause.pat).as_deref() == Some(name)
+    };
+    match clauses {
+        [a, b] if is_atom(a, "true") && is_atom(b, "false") => Some((a, b)),
+        [a, b] if is_atom(a, "false") && is_atom(b, "true") => Some((b, a)),
+        _ => None,
+    }
+}
+
+fn pat_atom(sema: &Semantic, body: &Body, pat: PatId) -> Option<String> {
+    match &body[pat] {
+        Pat::Literal(Literal::Atom(atom)) => Some(atom.as_string(sema.db.upcast())),
+        _ => None,
+    }
+}
+
+/// `boolean()`, or a union of `true` and `false`.
+fn is_boolean(ty: &Type) -> bool {
+    match ty {
+        Type::UnionType(union) => {
+            union.tys.len() == 2
+                && union.tys.contains(&Type::TRUE_TYPE)
+                && union.tys.contains(&Type::FALSE_TYPE)
+        }
+        _ => *ty == Type::boolean_type(),
+    }
+}
+
+/// The atom returned by a clause whose body is a single atom literal.
+fn returned_atom(sema: &Semantic, body: &Body, clause: &CRClause) -> Option<String> {
+    match (
+        clause.exprs.as_slice(),
+        clause.exprs.first().map(|expr| &body[*expr]),
+    ) {
+        ([_], Some(Expr::Literal(Literal::Atom(atom)))) => Some(atom.as_string(sema.db.upcast())),
+        _ => None,
+    }
+}
+
+/// Whether `expr` is allowed in a guard, so that the `case` can be
+/// rewritten as an `if`. Calls are not considered, since only some of
+/// them are allowed.
+fn is_guard_expr(body: &Body, expr: ExprId) -> bool {
+    match &body[expr] {
+        Expr::Var(_) | Expr::Literal(_) => true,
+        Expr::UnaryOp { expr, .. } => is_guard_expr(body, *expr),
+        Expr::BinaryOp { lhs, rhs, op } => {
+            !matches!(op, ast::BinaryOp::ListOp(_) | ast::BinaryOp::Send)
+                && is_guard_expr(body, *lhs)
+                && is_guard_expr(body, *rhs)
+        }
+        _ => false,
+    }
+}
+
+fn make_diagnostic(
+    sema: &Semantic,
+    file_id: FileId,
+    def_fb: &InFunctionBody<&FunctionDef>,
+    clause_id: ClauseId,
+    body: &Body,
+    case_expr: &ast::CaseExpr,
+    case: &BooleanCase,
+) -> Diagnostic {
+    let file_text = sema.db.file_text(file_id);
+    let subject_text = &file_text[case.subject_range];
+    let negated_subject = if matches!(&body[case.subject], Expr::Var(_)) {
+        format!("not {subject_text}")
+    } else {
+        format!("not ({subject_text})")
+    };
+    let case_range = case_expr.syntax().text_range();
+    let mut builder = SourceChangeBuilder::new(file_id);
+    let fix_label = match (
+        returned_atom(sema, body, case.true_clause).as_deref(),
+        returned_atom(sema, body, case.false_clause).as_deref(),
+    ) {
+        (Some("true"), Some("false")) => {
+            builder.replace(case_range, subject_text.to_string());
+            Some("Use the boolean directly")
+        }
+        (Some("false"), Some("true")) => {
+            builder.replace(case_range, negated_subject);
+            Some("Use the negated boolean directly")
+        }
+        _ if is_guard_expr(body, case.subject) => {
+            let of_token = case_expr
+                .syntax()
+                .children_with_tokens()
+                .find(|token| token.kind() == SyntaxKind::ANON_OF);
+            let (first, second, guard) = if case.true_first {
+                (
+                    case.true_clause,
+                    case.false_clause,
+                    subject_text.to_string(),
+                )
+            } else {
+                (case.false_clause, case.true_clause, negated_subject)
+            };
+            let pat_range =
+                |clause: &CRClause| def_fb.range_for_any(clause_id, AnyExprId::Pat(clause.pat));
+            match (of_token, pat_range(first), pat_range(second)) {
+                (Some(of_token), Some(first_range), Some(second_range)) => {
+                    builder.replace(
+                        TextRange::new(case_range.start(), of_token.text_range().end()),
+                        "if".to_string(),
+                    );
+                    builder.replace(first_range, guard);
+                    builder.replace(second_range, "true".to_string());
+                    Some("Rewrite as an `if`")
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    };
+    let fixes = fix_label.map(|label| {
+        vec![fix(
+            "case_on_boolean",
+            label,
+            builder.finish(),
+            case.subject_range,
+        )]
+    });
+    Diagnostic::new(
+        DiagnosticCode::CaseOnBoolean,
+        "This `case` only matches `true` and `false` on a boolean subject.",
+        case.subject_range,
+    )
+    .with_severity(Severity::WeakWarning)
+    .with_ignore_fix(sema, file_id)
+    .with_fixes(fixes)
+}
+
+#[cfg(test)]
+mod tests {
+    use elp_project_model::otp::otp_supported_by_eqwalizer;
+    use expect_test::expect;
+    use expect_test::Expect;
+
+    use crate::diagnostics::DiagnosticCode;
+    use crate::diagnostics::DiagnosticsConfig;
+    use crate::tests::check_filtered_diagnostics_with_config;
+    use crate::tests::check_fix_with_config;
+
+    #[track_caller]
+    fn check_diagnostics(fixture: &str) {
+        check_diagnostics_with_config(
+            DiagnosticsConfig::default().enable(DiagnosticCode::CaseOnBoolean),
+            fixture,
+        )
+    }
+
+    #[track_caller]
+    fn check_diagnostics_with_config(config: DiagnosticsConfig, fixture: &str) {
+        check_filtered_diagnostics_with_config(config, &vec![], fixture, &|d| {
+            d.code == DiagnosticCode::CaseOnBoolean
+        })
+    }
+
+    #[track_caller]
+    fn check_fix(fixture_before: &str, fixture_after: Expect) {
+        check_fix_with_config(
+            DiagnosticsConfig::default().enable(DiagnosticCode::CaseOnBoolean),
+            fixture_before,
+            fixture_after,
+        )
+    }
+
+    #[test]
+    fn disabled_by_default() {
+        if otp_supported_by_eqwalizer() {
+            check_diagnostics_with_config(
+                DiagnosticsConfig::default(),
+                r#"
+            //- eqwalizer
+            //- native
+            //- /play/src/main.erl app:play
+            -module(main).
+            -export([f/1]).
+            -spec f(boolean()) -> ok | error.
+            f(X) ->
+                case X of
+                    true -> ok;
+                    false -> error
+                end.
+            "#,
+            )
+        }
+    }
+
+    #[test]
+    fn boolean_case() {
+        if otp_supported_by_eqwalizer() {
+            check_diagnostics(
+                r#"
+            //- eqwalizer
+            //- native
+            //- /play/src/main.erl app:play
+            -module(main).
+            -export([f/1]).
+            -spec f(boolean()) -> ok | error.
+            f(X) ->
+                case X of
+            %%       ^ 💡 weak: This `case` only matches `true` and `false` on a boolean subject.
+                    true -> ok;
+                    false -> error
+                end.
+            "#,
+            )
+        }
+    }
+
+    #[test]
+    fn non_boolean_case() {
+        if otp_supported_by_eqwalizer() {
+            check_diagnostics(
+                r#"
+            //- eqwalizer
+            //- native
+            //- /play/src/main.erl app:play
+            -module(main).
+            -export([f/1, g/1]).
+            -spec f(true | false | undefined) -> ok | error.
+            f(X) ->
+                case X of
+                    true -> ok;
+                    false -> error;
+                    undefined -> error
+                end.
+            -spec g(atom()) -> ok | error.
+            g(X) ->
+                case X of
+                    true -> ok;
+                    false -> error
+                end.
+            "#,
+            )
+        }
+    }
+
+    #[test]
+    fn rewrite_as_if() {
+        if otp_supported_by_eqwalizer() {
+            check_fix(
+                r#"
+            //- eqwalizer
+            //- native
+            //- /play/src/main.erl app:play
+            -module(main).
+            -export([f/1]).
+            -spec f(boolean()) -> ok | error.
+            f(X) ->
+                case ~X of
+                    false -> error;
+                    true -> ok
+                end.
+            "#,
+                expect![[r#"
+                    -module(main).
+                    -export([f/1]).
+                    -spec f(boolean()) -> ok | error.
+                    f(X) ->
+                        if
+                            not X -> error;
+                            true -> ok
+                        end.
+            "#]],
+            )
+        }
+    }
+
+    #[test]
+    fn use_boolean_directly() {
+        if otp_supported_by_eqwalizer() {
+            check_fix(
+                r#"
+            //- eqwalizer
+            //- native
+            //- /play/src/main.erl app:play
+            -module(main).
+            -export([f/1]).
+            -spec f(boolean()) -> boolean().
+            f(X) ->
+                case ~X of
+                    true -> false;
+                    false -> true
+                end.
+            "#,
+                expect![[r#"
+                    -module(main).
+                    -export([f/1]).
+                    -spec f(boolean()) -> boolean().
+                    f(X) ->
+                        not X.
+            "#]],
+            )
+        }
+    }
+}
@@ -0,0 +1,265 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+// Diagnostic: comprehension-to-lists-call
+//
+// Return a weak warning if a list comprehension with a single generator
+// binding a variable only maps over the list, as in `[f(X) || X <- L]`,
+// or only filters it, as in `[X || X <- L, pred(X)]`, and offer to
+// rewrite it as a call to `lists:map/2` or `lists:filter/2`. Disabled
+// by default.
+
+use elp_ide_db::elp_base_db::FileId;
+use elp_ide_db::source_change::SourceChangeBuilder;
+use elp_ide_db::DiagnosticCode;
+use hir::fold::MacroStrategy;
+use hir::fold::ParenStrategy;
+use hir::AnyExpr;
+use hir::ClauseId;
+use hir::ComprehensionBuilder;
+use hir::ComprehensionExpr;
+use hir::Expr;
+use hir::ExprId;
+use hir::FunctionDef;
+use hir::InFunctionBody;
+use hir::Pat;
+use hir::Semantic;
+use hir::Strategy;
+use text_edit::TextRange;
+
+use super::Diagnostic;
+use super::DiagnosticConditions;
+use super::DiagnosticDescriptor;
+use super::Severity;
+use crate::fix;
+
+pub(crate) static DESCRIPTOR: DiagnosticDescriptor = DiagnosticDescriptor {
+    conditions: DiagnosticConditions {
+        experimental: false,
+        include_generated: false,
+        include_tests: true,
+        default_disabled: true,
+    },
+    checker: &|diags, sema, file_id, _ext| {
+        comprehension_to_lists_call(diags, sema, file_id);
+    },
+};
+
+fn comprehension_to_lists_call(
+    diagnostics: &mut Vec<Diagnostic>,
+    sema: &Semantic,
+    file_id: FileId,
+) {
+    sema.for_each_function(file_id, |def| check_function(diagnostics, sema, def));
+}
+
+fn check_function(diagnostics: &mut Vec<Diagnostic>, sema: &Semantic, def: &FunctionDef) {
+    let def_fb = def.in_function_body(sema, def);
+    def_fb.clone().fold_function(
+        Strategy {
+            macros: MacroStrategy::DoNotExpand,
+            parens: ParenStrategy::InvisibleParens,
+        },
+        (),
+        &mut |_acc, clause_id, ctx| {
+            if let AnyExpr::Expr(Expr::Comprehension {
+                builder: ComprehensionBuilder::List(item),
+                exprs,
+            }) = &ctx.item
+            {
+                if let Some(call) = lists_call(sema, &def_fb, clause_id, *item, exprs) {
+                    if let Some(comprehension) = ctx.item_id.as_expr_id() {
+                        report(sema, &def_fb, clause_id, comprehension, call, diagnostics);
+                    }
+                }
+            }
+        },
+    )
+}
+
+/// The `lists` function equivalent to a comprehension.
+enum ListsCall {
+    /// `lists:map(fun(Var) -> Item end, List)`
+    Map {
+        var: String,
+        item: ExprId,
+        list: ExprId,
+    },
+    /// `lists:filter(fun(Var) -> Filter end, List)`
+    Filter {
+        var: String,
+        filter: ExprId,
+        list: ExprId,
+    },
+}
+
+fn lists_call(
+    sema: &Semantic,
+    def_fb: &InFunctionBody<&FunctionDef>,
+    clause_id: ClauseId,
+    item: ExprId,
+    exprs: &[ComprehensionExpr],
+) -> Option<ListsCall> {
+    let body = def_fb.body(clause_id);
+    let (pat, list, rest) = match exprs {
+        [ComprehensionExpr::ListGenerator {
+            pat,
+            expr,
+            strict: false,
+        }, rest @ ..] => (*pat, *expr, rest),
+        _ => return None,
+    };
+    // Any other pattern also filters out the elements it does not match
+    let var = match &body[pat] {
+        Pat::Var(var) => *var,
+        _ => return None,
+    };
+    let is_var = matches!(&body[item], Expr::Var(item_var) if *item_var == var);
+    let var = var.as_string(sema.db.upcast());
+    match rest {
+        // `[X || X <- L]` is just `L`
+        [] if !is_var => Some(ListsCall::Map { var, item, list }),
+        [ComprehensionExpr::Expr(filter)] if is_var => Some(ListsCall::Filter {
+            var,
+            filter: *filter,
+            list,
+        }),
+        _ => None,
+    }
+}
+
+fn report(
+    sema: &Semantic,
+    def_fb: &InFunctionBody<&FunctionDef>,
+    clause_id: ClauseId,
+    comprehension: ExprId,
+    call: ListsCall,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Option<()> {
+    let file_id = def_fb.file_id();
+    let file_text = sema.db.file_text(file_id);
+    let text = |expr: ExprId| -> Option<String> {
+        let range = def_fb.range_for_expr(clause_id, expr)?;
+        Some(file_text[range].to_string())
+    };
+    let (function, var, fun_body, list) = match call {
+        ListsCall::Map { var, item, list } => ("map", var, text(item)?, text(list)?),
+        ListsCall::Filter { var, filter, list } => ("filter", var, text(filter)?, text(list)?),
+    };
+    let range = def_fb.range_for_expr(clause_id, comprehension)?;
+    let mut builder = SourceChangeBuilder::new(file_id);
+    builder.replace(
+        range,
+        format!("lists:{function}(fun({var}) -> {fun_body} end, {list})"),
+    );
+    let diagnostic = make_diagnostic(range, function)
+        .with_ignore_fix(sema, file_id)
+        .with_fixes(Some(vec![fix(
+            "comprehension_to_lists_call",
+            &format!("Rewrite as a call to `lists:{function}/2`"),
+            builder.finish(),
+            range,
+        )]));
+    diagnostics.push(diagnostic);
+    Some(())
+}
+
+fn make_diagnostic(range: TextRange, function: &str) -> Diagnostic {
+    Diagnostic::new(
+        DiagnosticCode::ComprehensionToListsCall,
+        format!("This comprehension can be written as a call to `lists:{function}/2`."),
+        range,
+    )
+    .with_severity(Severity::WeakWarning)
+}
+
+#[cfg(test)]
+mod tests {
+    use elp_ide_db::DiagnosticCode;
+    use expect_test::expect;
+
+    use crate::diagnostics::DiagnosticsConfig;
+    use crate::tests::check_diagnostics_with_config;
+    use crate::tests::check_fix_with_config;
+
+    #[track_caller]
+    fn check_diagnostics(fixture: &str) {
+        let config = DiagnosticsConfig::default().enable(DiagnosticCode::ComprehensionToListsCall);
+        check_diagnostics_with_config(config, fixture)
+    }
+
+    #[track_caller]
+    fn check_fix(fixture_before: &str, fixture_after: expect_test::Expect) {
+        let config = DiagnosticsConfig::default().enable(DiagnosticCode::ComprehensionToListsCall);
+        check_fix_with_config(config, fixture_before, fixture_after)
+    }
+
+    #[test]
+    fn map_comprehension_is_reported() {
+        check_diagnostics(
+            r#"
+            -module(main).
+            f(L) -> [X + 1 || X <- L].
+            %%      ^^^^^^^^^^^^^^^^^ 💡 weak: This comprehension can be written as a call to `lists:map/2`.
+            "#,
+        );
+    }
+
+    #[test]
+    fn filter_comprehension_is_reported() {
+        check_diagnostics(
+            r#"
+            -module(main).
+            f(L) -> [X || X <- L, X > 1].
+            %%      ^^^^^^^^^^^^^^^^^^^^ 💡 weak: This comprehension can be written as a call to `lists:filter/2`.
+            "#,
+        );
+    }
+
+    #[test]
+    fn complex_comprehensions_are_not_reported() {
+        check_diagnostics(
+            r#"
+            -module(main).
+            f(L, M) -> [{X, Y} || X <- L, Y <- M].
+            g(L) -> [X || {ok, X} <- L].
+            h(L) -> [X + 1 || X <- L, X > 1].
+            i(L) -> [X || X <- L].
+            "#,
+        );
+    }
+
+    #[test]
+    fn rewrite_map_comprehension() {
+        check_fix(
+            r#"
+            -module(main).
+            f(L) -> [X + 1 |~| X <- L].
+            "#,
+            expect![[r#"
+            -module(main).
+            f(L) -> lists:map(fun(X) -> X + 1 end, L).
+            "#]],
+        );
+    }
+
+    #[test]
+    fn rewrite_filter_comprehension() {
+        check_fix(
+            r#"
+            -module(main).
+            f(L) -> [X |~| X <- L, is_atom(X)].
+            "#,
+            expect![[r#"
+            -module(main).
+            f(L) -> lists:filter(fun(X) -> is_atom(X) end, L).
+            "#]],
+        );
+    }
+}
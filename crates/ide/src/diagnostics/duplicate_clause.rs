@@ -0,0 +1,166 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+// Diagnostic: duplicate-clause
+//
+// Return a warning if a clause of a `case` or `receive` has the same
+// pattern and guards as an earlier clause. The later clause can never
+// be selected.
+
+use elp_ide_db::elp_base_db::FileId;
+use elp_syntax::TextRange;
+use fxhash::FxHashSet;
+use hir::db::InternDatabase;
+use hir::fold::MacroStrategy;
+use hir::fold::ParenStrategy;
+use hir::AnyExpr;
+use hir::AnyExprId;
+use hir::Body;
+use hir::CRClause;
+use hir::Expr;
+use hir::FunctionDef;
+use hir::Semantic;
+use hir::Strategy;
+
+use super::Diagnostic;
+use super::DiagnosticConditions;
+use super::DiagnosticDescriptor;
+use super::Severity;
+use crate::diagnostics::DiagnosticCode;
+
+pub(crate) static DESCRIPTOR: DiagnosticDescriptor = DiagnosticDescriptor {
+    conditions: DiagnosticConditions {
+        experimental: false,
+        include_generated: false,
+        include_tests: true,
+        default_disabled: false,
+    },
+    checker: &|diags, sema, file_id, _ext| {
+        duplicate_clause(diags, sema, file_id);
+    },
+};
+
+fn duplicate_clause(diagnostics: &mut Vec<Diagnostic>, sema: &Semantic, file_id: FileId) {
+    sema.for_each_function(file_id, |def| check_function(diagnostics, sema, def));
+}
+
+fn check_function(diagnostics: &mut Vec<Diagnostic>, sema: &Semantic, def: &FunctionDef) {
+    let def_fb = def.in_function_body(sema, def);
+    def_fb.clone().fold_function(
+        Strategy {
+            macros: MacroStrategy::Expand,
+            parens: ParenStrategy::InvisibleParens,
+        },
+        (),
+        &mut |_acc, clause_id, ctx| {
+            let clauses = match &ctx.item {
+                AnyExpr::Expr(Expr::Case { clauses, .. }) => clauses,
+                AnyExpr::Expr(Expr::Receive { clauses, .. }) => clauses,
+                _ => return,
+            };
+            let body = def_fb.body(clause_id);
+            let in_clause = def_fb.in_clause(clause_id);
+            let mut seen = FxHashSet::default();
+            for clause in clauses {
+                if seen.insert(clause_head(sema, &body, clause)) {
+                    continue;
+                }
+                let start = in_clause.range_for_pat(clause.pat);
+                let end = match clause.guards.last().and_then(|guard| guard.last()) {
+                    Some(guard) => in_clause.range_for_expr(*guard),
+                    None => start,
+                };
+                if let (Some(start), Some(end)) = (start, end) {
+                    let range = TextRange::new(start.start(), end.end());
+                    diagnostics.push(make_diagnostic(range));
+                }
+            }
+        },
+    )
+}
+
+/// The pattern and guards of a clause, printed without any location
+/// information, so that structurally identical clauses compare equal.
+fn clause_head(sema: &Semantic, body: &Body, clause: &CRClause) -> String {
+    let db: &dyn InternDatabase = sema.db.upcast();
+    let pat = body.print_any_expr(db, AnyExprId::Pat(clause.pat));
+    let guards: Vec<String> = clause
+        .guards
+        .iter()
+        .map(|guard| {
+            guard
+                .iter()
+                .map(|expr| body.print_any_expr(db, AnyExprId::Expr(*expr)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+        .collect();
+    format!("{} when {}", pat, guards.join("; "))
+}
+
+fn make_diagnostic(range: TextRange) -> Diagnostic {
+    Diagnostic::new(
+        DiagnosticCode::DuplicateClause,
+        "This clause has the same pattern and guards as an earlier clause, so it can never match.",
+        range,
+    )
+    .with_severity(Severity::Warning)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::check_diagnostics;
+
+    #[test]
+    fn duplicate_case_clause_is_reported() {
+        check_diagnostics(
+            r#"
+            -module(main).
+            f(X) ->
+                case X of
+                    {ok, Y} -> Y;
+                    error -> undefined;
+                    {ok, Y} -> Y + 1
+            %%      ^^^^^^^ warning: This clause has the same pattern and guards as an earlier clause, so it can never match.
+                end.
+            "#,
+        );
+    }
+
+    #[test]
+    fn duplicate_receive_clause_with_guard_is_reported() {
+        check_diagnostics(
+            r#"
+            -module(main).
+            f() ->
+                receive
+                    {msg, M} when is_atom(M) -> M;
+                    {msg, M} when is_atom(M) -> ok
+            %%      ^^^^^^^^^^^^^^^^^^^^^^^^ warning: This clause has the same pattern and guards as an earlier clause, so it can never match.
+                end.
+            "#,
+        );
+    }
+
+    #[test]
+    fn distinct_clauses_are_not_reported() {
+        check_diagnostics(
+            r#"
+            -module(main).
+            f(X) ->
+                case X of
+                    {ok, Y} when is_integer(Y) -> Y;
+                    {ok, Y} when is_atom(Y) -> Y;
+                    {ok, Y} -> Y;
+                    {error, _} -> undefined
+                end.
+            "#,
+        );
+    }
+}
@@ -105,7 +105,7 @@ fn has_no_effect(def_fb: &InFunctionClauseBody<&FunctionDef>, expr_id: &ExprId)
 
         Expr::Map { fields } => fields
             .iter()
-            .all(|(k, v)| has_no_effect(def_fb, k) && has_no_effect(def_fb, v)),
+            .all(|(k, _op, v)| has_no_effect(def_fb, k) && has_no_effect(def_fb, v)),
         Expr::MapUpdate { .. } => {
             // Side-effect: may throw if not a map
             false
@@ -160,7 +160,7 @@ fn has_no_effect(def_fb: &InFunctionClauseBody<&FunctionDef>, expr_id: &ExprId)
 }
 
 #[allow(clippy::match_like_matches_macro)]
-fn is_statement(expr: &ast::Expr) -> bool {
+pub(crate) fn is_statement(expr: &ast::Expr) -> bool {
     let syntax = expr.syntax();
     match syntax.parent() {
         Some(parent) => match parent.kind() {
@@ -180,7 +180,7 @@ fn is_macro_usage(expr: &ast::Expr) -> bool {
     syntax.kind() == SyntaxKind::MACRO_CALL_EXPR
 }
 
-fn is_followed_by(expected_kind: SyntaxKind, expr: &ast::Expr) -> bool {
+pub(crate) fn is_followed_by(expected_kind: SyntaxKind, expr: &ast::Expr) -> bool {
     let node = expr.syntax();
     let elements = iter::successors(node.next_sibling_or_token(), |n| {
         (*n).next_sibling_or_token()
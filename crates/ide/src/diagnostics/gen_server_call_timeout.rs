@@ -0,0 +1,231 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+// Diagnostic: gen-server-call-without-timeout
+//
+// Return a warning if a test suite calls `gen_server:call/2`, which
+// uses the default timeout of 5 seconds. On a loaded CI machine this
+// is a common source of flaky tests.
+
+use elp_ide_db::elp_base_db::FileId;
+use elp_ide_db::elp_base_db::FileKind;
+use elp_ide_db::source_change::SourceChangeBuilder;
+use hir::FunctionDef;
+use hir::Semantic;
+use text_edit::TextRange;
+use text_edit::TextSize;
+
+use crate::codemod_helpers::find_call_in_function;
+use crate::codemod_helpers::CheckCallCtx;
+use crate::codemod_helpers::MakeDiagCtx;
+use crate::diagnostics::Diagnostic;
+use crate::diagnostics::DiagnosticCode;
+use crate::diagnostics::Severity;
+use crate::fix;
+use crate::FunctionMatch;
+
+pub fn gen_server_call_without_timeout(
+    res: &mut Vec<Diagnostic>,
+    sema: &Semantic,
+    file_id: FileId,
+) {
+    if sema.db.file_kind(file_id) != FileKind::TestModule {
+        return;
+    }
+    sema.def_map(file_id)
+        .get_functions()
+        .for_each(|(_arity, def)| {
+            if def.file.file_id == file_id {
+                check_function(res, sema, def)
+            }
+        });
+}
+
+pub(crate) fn check_function(diags: &mut Vec<Diagnostic>, sema: &Semantic, def: &FunctionDef) {
+    find_call_in_function(
+        diags,
+        sema,
+        def,
+        &[(&FunctionMatch::mfa("gen_server", "call", 2), ())],
+        &move |CheckCallCtx { .. }: CheckCallCtx<'_, ()>| Some(()),
+        &move |MakeDiagCtx {
+                   sema,
+                   def_fb,
+                   args,
+                   range,
+                   ..
+               }| {
+            let last_arg = args.as_vec().last().copied()?;
+            let last_arg_range = def_fb.range_for_expr(last_arg)?;
+            let diag = make_diagnostic(sema, def.file.file_id, range, last_arg_range.end());
+            Some(diag)
+        },
+    );
+}
+
+fn make_diagnostic(
+    sema: &Semantic,
+    file_id: FileId,
+    range: TextRange,
+    end_of_args: TextSize,
+) -> Diagnostic {
+    let message =
+        "`gen_server:call/2` times out after 5 seconds, which can make tests flaky.\nConsider passing an explicit timeout.".to_string();
+    let mut builder = SourceChangeBuilder::new(file_id);
+    builder.insert(end_of_args, ", infinity");
+    let fixes = vec![fix(
+        "gen_server_call_add_timeout",
+        "Add an `infinity` timeout",
+        builder.finish(),
+        range,
+    )];
+    Diagnostic::new(DiagnosticCode::GenServerCallWithoutTimeout, message, range)
+        .with_severity(Severity::Warning)
+        .with_ignore_fix(sema, file_id)
+        .with_fixes(Some(fixes))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::diagnostics::Diagnostic;
+    use crate::diagnostics::DiagnosticCode;
+    use crate::tests;
+
+    fn filter(d: &Diagnostic) -> bool {
+        d.code == DiagnosticCode::GenServerCallWithoutTimeout
+    }
+
+    #[track_caller]
+    fn check_diagnostics(fixture: &str) {
+        tests::check_filtered_diagnostics(fixture, &filter)
+    }
+
+    #[track_caller]
+    fn check_fix(fixture_before: &str, fixture_after: &str) {
+        tests::check_filtered_ct_fix(fixture_before, fixture_after, &filter, &|a| {
+            a.id.0 == "gen_server_call_add_timeout"
+        })
+    }
+
+    #[test]
+    fn gen_server_call_2_in_suite() {
+        check_diagnostics(
+            r#"
+//- common_test
+//- /my_app/test/call_timeout1_SUITE.erl
+   -module(call_timeout1_SUITE).
+   -export([all/0]).
+   -export([a/1]).
+   all() -> [a].
+   a(_Config) ->
+     gen_server:call(my_server, ping).
+%%   ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^ 💡 warning: `gen_server:call/2` times out after 5 seconds, which can make tests flaky.
+%%                                  | Consider passing an explicit timeout.
+//- /my_app/src/gen_server.erl
+   -module(gen_server).
+   -export([call/2, call/3]).
+   call(_Server, _Request) -> ok.
+   call(_Server, _Request, _Timeout) -> ok.
+            "#,
+        )
+    }
+
+    #[test]
+    fn gen_server_call_2_in_test_target() {
+        check_diagnostics(
+            r#"
+//- common_test
+//- /my_app/test/call_timeout_checks_SUITE.erl app:my_app is_test_target:true
+   -module(call_timeout_checks_SUITE).
+   -export([all/0]).
+   -export([a/1]).
+   all() -> [a].
+   a(_Config) ->
+     gen_server:call(my_server, ping).
+%%   ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^ 💡 warning: `gen_server:call/2` times out after 5 seconds, which can make tests flaky.
+%%                                  | Consider passing an explicit timeout.
+//- /my_app/src/gen_server.erl
+   -module(gen_server).
+   -export([call/2, call/3]).
+   call(_Server, _Request) -> ok.
+   call(_Server, _Request, _Timeout) -> ok.
+            "#,
+        )
+    }
+
+    #[test]
+    fn gen_server_call_3_in_suite() {
+        check_diagnostics(
+            r#"
+//- common_test
+//- /my_app/test/call_timeout2_SUITE.erl
+   -module(call_timeout2_SUITE).
+   -export([all/0]).
+   -export([a/1]).
+   all() -> [a].
+   a(_Config) ->
+     gen_server:call(my_server, ping, 10000).
+//- /my_app/src/gen_server.erl
+   -module(gen_server).
+   -export([call/2, call/3]).
+   call(_Server, _Request) -> ok.
+   call(_Server, _Request, _Timeout) -> ok.
+            "#,
+        )
+    }
+
+    #[test]
+    fn gen_server_call_2_outside_suite() {
+        check_diagnostics(
+            r#"
+//- common_test
+//- /my_app/src/call_timeout3.erl
+   -module(call_timeout3).
+   -export([a/0]).
+   a() ->
+     gen_server:call(my_server, ping).
+//- /my_app/src/gen_server.erl
+   -module(gen_server).
+   -export([call/2, call/3]).
+   call(_Server, _Request) -> ok.
+   call(_Server, _Request, _Timeout) -> ok.
+            "#,
+        )
+    }
+
+    #[test]
+    fn gen_server_call_add_timeout() {
+        check_fix(
+            r#"
+//- common_test
+//- /my_app/test/call_timeout4_SUITE.erl
+-module(call_timeout4_SUITE).
+-export([all/0]).
+-export([a/1]).
+all() -> [a].
+a(_Config) ->
+  gen_s~erver:call(my_server, ping).
+//- /my_app/src/gen_server.erl
+-module(gen_server).
+-export([call/2, call/3]).
+call(_Server, _Request) -> ok.
+call(_Server, _Request, _Timeout) -> ok.
+            "#,
+            r#"
+-module(call_timeout4_SUITE).
+-export([all/0]).
+-export([a/1]).
+all() -> [a].
+a(_Config) ->
+  gen_server:call(my_server, ping, infinity).
+"#,
+        );
+    }
+}
@@ -0,0 +1,326 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+// Diagnostic: if-to-case
+//
+// Return a weak warning if every clause of an `if` expression compares
+// the same variable against a literal. eqWAlizer narrows the type of a
+// `case` subject in each clause, but not of a variable tested in an
+// `if` guard, so offer to rewrite it as an equivalent `case`.
+
+use elp_ide_db::elp_base_db::FileId;
+use elp_ide_db::source_change::SourceChangeBuilder;
+use elp_ide_db::DiagnosticCode;
+use elp_syntax::ast;
+use elp_syntax::ast::BinaryOp;
+use elp_syntax::ast::CompOp;
+use elp_syntax::AstNode;
+use hir::fold::MacroStrategy;
+use hir::fold::ParenStrategy;
+use hir::known;
+use hir::AnyExpr;
+use hir::AnyExprId;
+use hir::ClauseId;
+use hir::Expr;
+use hir::ExprId;
+use hir::FunctionDef;
+use hir::IfClause;
+use hir::InFile;
+use hir::InFunctionBody;
+use hir::Literal;
+use hir::Semantic;
+use hir::Strategy;
+use hir::Var;
+use text_edit::TextRange;
+
+use super::Diagnostic;
+use super::DiagnosticConditions;
+use super::DiagnosticDescriptor;
+use super::Severity;
+use crate::fix;
+
+pub(crate) static DESCRIPTOR: DiagnosticDescriptor = DiagnosticDescriptor {
+    conditions: DiagnosticConditions {
+        experimental: false,
+        include_generated: false,
+        include_tests: true,
+        default_disabled: false,
+    },
+    checker: &|diags, sema, file_id, _ext| {
+        if_to_case(diags, sema, file_id);
+    },
+};
+
+fn if_to_case(diagnostics: &mut Vec<Diagnostic>, sema: &Semantic, file_id: FileId) {
+    sema.for_each_function(file_id, |def| check_function(diagnostics, sema, def));
+}
+
+fn check_function(diagnostics: &mut Vec<Diagnostic>, sema: &Semantic, def: &FunctionDef) {
+    let def_fb = def.in_function_body(sema, def);
+    def_fb.clone().fold_function(
+        Strategy {
+            macros: MacroStrategy::DoNotExpand,
+            parens: ParenStrategy::InvisibleParens,
+        },
+        (),
+        &mut |_acc, clause_id, ctx| {
+            if let AnyExpr::Expr(Expr::If { clauses }) = &ctx.item {
+                if let Some((subject, case_clauses)) =
+                    case_clauses(sema, &def_fb, clause_id, clauses)
+                {
+                    report(
+                        sema,
+                        &def_fb,
+                        clause_id,
+                        ctx.item_id,
+                        subject,
+                        &case_clauses,
+                        diagnostics,
+                    );
+                }
+            }
+        },
+    )
+}
+
+/// What an `if` clause guard becomes in the equivalent `case`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CaseClause {
+    /// `Subject =:= Literal`, matched by the literal itself.
+    Literal(ExprId),
+    /// A final `true` clause, matched by `_`.
+    CatchAll,
+}
+
+/// Check that each clause consists of a single comparison of the
+/// same variable against a literal, optionally followed by a
+/// catch-all `true` clause.
+fn case_clauses(
+    sema: &Semantic,
+    def_fb: &InFunctionBody<&FunctionDef>,
+    clause_id: ClauseId,
+    clauses: &[IfClause],
+) -> Option<(Var, Vec<CaseClause>)> {
+    let body = def_fb.body(clause_id);
+    let mut subject: Option<Var> = None;
+    let mut res = Vec::default();
+    for (idx, clause) in clauses.iter().enumerate() {
+        let guard = match clause.guards.as_slice() {
+            [guard] => match guard.as_slice() {
+                [expr] => *expr,
+                _ => return None,
+            },
+            _ => return None,
+        };
+        match &body[guard] {
+            expr if idx + 1 == clauses.len() && sema.is_atom_named(expr, known::true_name) => {
+                res.push(CaseClause::CatchAll);
+            }
+            Expr::BinaryOp {
+                lhs,
+                rhs,
+                op:
+                    BinaryOp::CompOp(CompOp::Eq {
+                        strict,
+                        negated: false,
+                    }),
+            } => {
+                let (var, literal_id, literal) = match (&body[*lhs], &body[*rhs]) {
+                    (Expr::Var(var), Expr::Literal(literal)) => (*var, *rhs, literal),
+                    (Expr::Literal(literal), Expr::Var(var)) => (*var, *lhs, literal),
+                    _ => return None,
+                };
+                // `==` only agrees with pattern matching when comparing atoms
+                if !strict && !matches!(literal, Literal::Atom(_)) {
+                    return None;
+                }
+                if *subject.get_or_insert(var) != var {
+                    return None;
+                }
+                res.push(CaseClause::Literal(literal_id));
+            }
+            _ => return None,
+        }
+    }
+    Some((subject?, res))
+}
+
+fn report(
+    sema: &Semantic,
+    def_fb: &InFunctionBody<&FunctionDef>,
+    clause_id: ClauseId,
+    if_expr_id: AnyExprId,
+    subject: Var,
+    case_clauses: &[CaseClause],
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Option<()> {
+    let file_id = def_fb.file_id();
+    let source = sema.db.parse(file_id).tree();
+    let map = def_fb.get_body_map(clause_id);
+    let if_expr = map
+        .any(if_expr_id)?
+        .to_node(&InFile::new(file_id, source))?;
+    let if_expr = ast::IfExpr::cast(if_expr.syntax().clone())?;
+    let if_clauses: Vec<ast::IfClause> = if_expr.clauses().collect();
+    if if_clauses.len() != case_clauses.len() {
+        return None;
+    }
+    let file_text = sema.db.file_text(file_id);
+    let subject = subject.as_string(sema.db.upcast());
+
+    let mut builder = SourceChangeBuilder::new(file_id);
+    let if_keyword = if_expr.syntax().first_token()?;
+    builder.replace(if_keyword.text_range(), format!("case {subject} of"));
+    for (if_clause, case_clause) in if_clauses.iter().zip(case_clauses) {
+        let guard_range = if_clause.guard()?.syntax().text_range();
+        let pattern = match case_clause {
+            CaseClause::Literal(expr_id) => {
+                let range = def_fb.range_for_expr(clause_id, *expr_id)?;
+                file_text[range].to_string()
+            }
+            CaseClause::CatchAll => "_".to_string(),
+        };
+        builder.replace(guard_range, pattern);
+    }
+    let source_change = builder.finish();
+
+    let range = if_keyword.text_range();
+    let diagnostic = make_diagnostic(range, &subject).with_fixes(Some(vec![fix(
+        "convert_if_to_case",
+        &format!("Convert to `case {subject} of`"),
+        source_change,
+        range,
+    )]));
+    diagnostics.push(diagnostic);
+    Some(())
+}
+
+fn make_diagnostic(range: TextRange, subject: &str) -> Diagnostic {
+    let message = format!(
+        "Every clause of this `if` compares `{subject}` against a literal.\nConsider using `case {subject} of`, which eqWAlizer can narrow."
+    );
+    Diagnostic::new(DiagnosticCode::IfToCase, message, range).with_severity(Severity::WeakWarning)
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::expect;
+
+    use crate::tests::check_diagnostics;
+    use crate::tests::check_fix;
+
+    #[test]
+    fn if_on_same_var_is_reported() {
+        check_diagnostics(
+            r#"
+             -module(main).
+
+             f(X) ->
+                 if
+              %% ^^ 💡 weak: Every clause of this `if` compares `X` against a literal.
+              %%  | Consider using `case X of`, which eqWAlizer can narrow.
+                     X =:= 1 -> one;
+                     X == two -> two;
+                     true -> other
+                 end.
+            "#,
+        );
+    }
+
+    #[test]
+    fn if_on_different_vars_is_not_reported() {
+        check_diagnostics(
+            r#"
+             -module(main).
+
+             f(X, Y) ->
+                 if
+                     X =:= 1 -> one;
+                     Y =:= 2 -> two;
+                     true -> other
+                 end.
+            "#,
+        );
+    }
+
+    #[test]
+    fn if_with_non_literal_comparison_is_not_reported() {
+        check_diagnostics(
+            r#"
+             -module(main).
+
+             f(X, Y) ->
+                 if
+                     X =:= Y -> same;
+                     X > 2 -> big;
+                     true -> other
+                 end.
+            "#,
+        );
+    }
+
+    #[test]
+    fn if_with_loose_numeric_comparison_is_not_reported() {
+        check_diagnostics(
+            r#"
+             -module(main).
+
+             f(X) ->
+                 if
+                     X == 1 -> one;
+                     true -> other
+                 end.
+            "#,
+        );
+    }
+
+    #[test]
+    fn if_to_case_fix() {
+        check_fix(
+            r#"
+             -module(main).
+
+             f(X) ->
+                 i~f
+                     X =:= 1 -> one;
+                     two == X -> two;
+                     true -> other
+                 end.
+            "#,
+            expect![[r#"
+             -module(main).
+
+             f(X) ->
+                 case X of
+                     1 -> one;
+                     two -> two;
+                     _ -> other
+                 end.
+            "#]],
+        );
+    }
+
+    #[test]
+    fn if_to_case_fix_without_catch_all() {
+        check_fix(
+            r#"
+             -module(main).
+
+             f(X) ->
+                 i~f X =:= a -> 1; X =:= b -> 2 end.
+            "#,
+            expect![[r#"
+             -module(main).
+
+             f(X) ->
+                 case X of a -> 1; b -> 2 end.
+            "#]],
+        );
+    }
+}
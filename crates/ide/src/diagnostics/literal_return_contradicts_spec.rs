@@ -0,0 +1,228 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+// Diagnostic: literal-return-contradicts-spec
+//
+// Return a warning if a function with a single clause returns an atom
+// or number literal which cannot be a value of the return type in its
+// `-spec`, such as `ok` for `integer()`. This is a cheap check on the
+// spec as written, which does not need eqWAlizer.
+
+use elp_ide_db::elp_base_db::FileId;
+use hir::Atom;
+use hir::Body;
+use hir::Expr;
+use hir::InFile;
+use hir::Literal;
+use hir::Semantic;
+use hir::TypeExpr;
+use hir::TypeExprId;
+
+use super::Diagnostic;
+use super::DiagnosticConditions;
+use super::DiagnosticDescriptor;
+use crate::diagnostics::DiagnosticCode;
+use crate::diagnostics::Severity;
+
+pub(crate) static DESCRIPTOR: DiagnosticDescriptor = DiagnosticDescriptor {
+    conditions: DiagnosticConditions {
+        experimental: false,
+        include_generated: false,
+        include_tests: true,
+        default_disabled: false,
+    },
+    checker: &|diags, sema, file_id, _file_kind| {
+        literal_returns_contradicting_spec(diags, sema, file_id);
+    },
+};
+
+fn literal_returns_contradicting_spec(
+    diagnostics: &mut Vec<Diagnostic>,
+    sema: &Semantic,
+    file_id: FileId,
+) {
+    for (name, def) in sema.def_map(file_id).get_functions() {
+        if def.file.file_id != file_id || def.function_clauses.len() != 1 {
+            continue;
+        }
+        let spec = match &def.spec {
+            Some(spec) => spec,
+            None => continue,
+        };
+        let spec_body = sema
+            .db
+            .spec_body(InFile::new(spec.file.file_id, spec.spec_id));
+        let def_fb = def.in_function_body(sema, def);
+        for (clause_id, clause) in def_fb.clauses() {
+            let body = def_fb.body(clause_id);
+            let last = match clause.clause.exprs.last() {
+                Some(last) => *last,
+                None => continue,
+            };
+            let returned = match &body[last] {
+                Expr::Literal(Literal::Atom(atom)) => Returned::Atom(*atom),
+                Expr::Literal(Literal::Integer(_) | Literal::Float(_) | Literal::Char(_)) => {
+                    Returned::Number
+                }
+                _ => continue,
+            };
+            // A value compatible with any of the signatures is fine.
+            if spec_body
+                .sigs
+                .iter()
+                .any(|sig| is_compatible(sema, &spec_body.body, &returned, sig.result))
+            {
+                continue;
+            }
+            if let Some(range) = def_fb.range_for_expr(clause_id, last) {
+                let diagnostic = Diagnostic::new(
+                    DiagnosticCode::LiteralReturnContradictsSpec,
+                    format!(
+                        "This value is not compatible with the return type in the spec of `{name}`."
+                    ),
+                    range,
+                )
+                .with_severity(Severity::Warning)
+                .with_ignore_fix(sema, file_id);
+                diagnostics.push(diagnostic);
+            }
+        }
+    }
+}
+
+/// The kind of literal a function returns.
+enum Returned {
+    Atom(Atom),
+    Number,
+}
+
+/// Whether `returned` may be a value of the type `ty`. Types which
+/// are not resolved here, such as user-defined types, are assumed to
+/// be compatible.
+fn is_compatible(sema: &Semantic, body: &Body, returned: &Returned, ty: TypeExprId) -> bool {
+    match (&body[ty], returned) {
+        (TypeExpr::AnnType { ty, .. }, _) => is_compatible(sema, body, returned, *ty),
+        (TypeExpr::MacroCall { expansion, .. }, _) => {
+            is_compatible(sema, body, returned, *expansion)
+        }
+        (TypeExpr::Union { types }, _) => types
+            .iter()
+            .any(|ty| is_compatible(sema, body, returned, *ty)),
+        (TypeExpr::Literal(Literal::Atom(atom)), Returned::Atom(returned)) => atom == returned,
+        (
+            TypeExpr::Literal(Literal::Integer(_) | Literal::Float(_) | Literal::Char(_)),
+            Returned::Number,
+        ) => true,
+        (
+            TypeExpr::Range { .. } | TypeExpr::BinaryOp { .. } | TypeExpr::UnaryOp { .. },
+            Returned::Number,
+        ) => true,
+        (TypeExpr::Call { target, args }, _) => {
+            let label = match target.label(args.len() as u32, sema, body) {
+                Some(label) => label,
+                None => return true,
+            };
+            match (label.trim_start_matches("erlang:"), returned) {
+                ("atom/0" | "module/0" | "node/0", Returned::Atom(_)) => true,
+                ("boolean/0", Returned::Atom(atom)) => {
+                    matches!(sema.db.lookup_atom(*atom).as_str(), "true" | "false")
+                }
+                ("timeout/0", Returned::Atom(atom)) => {
+                    sema.db.lookup_atom(*atom).as_str() == "infinity"
+                }
+                (
+                    "integer/0" | "non_neg_integer/0" | "pos_integer/0" | "neg_integer/0"
+                    | "number/0" | "float/0" | "byte/0" | "char/0" | "arity/0" | "timeout/0",
+                    Returned::Number,
+                ) => true,
+                (
+                    "atom/0" | "module/0" | "node/0" | "boolean/0" | "integer/0"
+                    | "non_neg_integer/0" | "pos_integer/0" | "neg_integer/0" | "number/0"
+                    | "float/0" | "byte/0" | "char/0" | "arity/0" | "binary/0" | "bitstring/0"
+                    | "list/0" | "list/1" | "nonempty_list/0" | "nonempty_list/1" | "string/0"
+                    | "tuple/0" | "map/0" | "pid/0" | "port/0" | "reference/0" | "function/0"
+                    | "mfa/0" | "iodata/0" | "iolist/0",
+                    _,
+                ) => false,
+                _ => true,
+            }
+        }
+        (
+            TypeExpr::Literal(_)
+            | TypeExpr::Range { .. }
+            | TypeExpr::BinaryOp { .. }
+            | TypeExpr::UnaryOp { .. }
+            | TypeExpr::Fun(_)
+            | TypeExpr::List(_)
+            | TypeExpr::Map { .. }
+            | TypeExpr::Record { .. }
+            | TypeExpr::Tuple { .. },
+            _,
+        ) => false,
+        (TypeExpr::Var(_) | TypeExpr::Missing | TypeExpr::SsrPlaceholder(_), _) => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostics::DiagnosticCode;
+    use crate::tests::check_filtered_diagnostics;
+
+    #[track_caller]
+    fn check_diagnostics(fixture: &str) {
+        check_filtered_diagnostics(fixture, &|d| {
+            d.code == DiagnosticCode::LiteralReturnContradictsSpec
+        })
+    }
+
+    #[test]
+    fn contradicting_literal_return() {
+        check_diagnostics(
+            r#"
+            -module(main).
+            -export([f/0, g/1, h/0]).
+            -spec f() -> integer().
+            f() -> ok.
+            %%     ^^ 💡 warning: This value is not compatible with the return type in the spec of `f/0`.
+            -spec h() -> boolean().
+            h() -> yes.
+            %%     ^^^ 💡 warning: This value is not compatible with the return type in the spec of `h/0`.
+            -spec g(term()) -> ok | error.
+            g(X) ->
+                io:format("~p", [X]),
+                42.
+            %%  ^^ 💡 warning: This value is not compatible with the return type in the spec of `g/1`.
+            "#,
+        )
+    }
+
+    #[test]
+    fn compatible_literal_return() {
+        check_diagnostics(
+            r#"
+            -module(main).
+            -export([f/0, g/0, h/1, i/0, j/0, k/0]).
+            -spec f() -> ok | {error, term()}.
+            f() -> ok.
+            -spec g() -> boolean().
+            g() -> true.
+            -spec h(atom()) -> integer().
+            h(a) -> 1;
+            h(_) -> ok.
+            -type t() :: ok.
+            -spec i() -> t().
+            i() -> ok.
+            -spec j() -> erlang:timeout().
+            j() -> infinity.
+            -spec k() -> 1..10.
+            k() -> 5.
+            "#,
+        )
+    }
+}
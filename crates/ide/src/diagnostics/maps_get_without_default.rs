@@ -0,0 +1,336 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+// Diagnostic: maps-get-without-default
+//
+// Return a weak warning if `maps:get/2` is called, since it raises an
+// exception if the key is missing. Offer to add a default value, using
+// `maps:get/3`, which changes the behaviour of the call when the key is
+// missing. Calls guarded by an `is_map_key/2` check of the key are not
+// reported. Disabled by default, it must be enabled in `enabled_lints`.
+
+use elp_ide_db::elp_base_db::FileId;
+use elp_ide_db::source_change::SourceChangeBuilder;
+use hir::fold::ParentId;
+use hir::AnyExprId;
+use hir::Body;
+use hir::Expr;
+use hir::ExprId;
+use hir::FunctionDef;
+use hir::Semantic;
+use text_edit::TextRange;
+use text_edit::TextSize;
+
+use super::DiagnosticConditions;
+use super::DiagnosticDescriptor;
+use crate::codemod_helpers::find_call_in_function;
+use crate::codemod_helpers::CheckCallCtx;
+use crate::codemod_helpers::MakeDiagCtx;
+use crate::diagnostics::Diagnostic;
+use crate::diagnostics::DiagnosticCode;
+use crate::diagnostics::Severity;
+use crate::fix;
+use crate::FunctionMatch;
+
+pub(crate) static DESCRIPTOR: DiagnosticDescriptor = DiagnosticDescriptor {
+    conditions: DiagnosticConditions {
+        experimental: false,
+        include_generated: false,
+        include_tests: false,
+        default_disabled: true,
+    },
+    checker: &|diags, sema, file_id, _ext| {
+        maps_get_without_default(diags, sema, file_id);
+    },
+};
+
+fn maps_get_without_default(diags: &mut Vec<Diagnostic>, sema: &Semantic, file_id: FileId) {
+    sema.for_each_function(file_id, |def| check_function(diags, sema, def));
+}
+
+fn check_function(diags: &mut Vec<Diagnostic>, sema: &Semantic, def: &FunctionDef) {
+    find_call_in_function(
+        diags,
+        sema,
+        def,
+        &[(&FunctionMatch::mfa("maps", "get", 2), ())],
+        &move |CheckCallCtx {
+                   args,
+                   in_clause,
+                   parents,
+                   ..
+               }: CheckCallCtx<'_, ()>| {
+            let key = args.get(0)?;
+            let map = args.get(1)?;
+            let body = in_clause.body();
+            let checked = dominating_guards(&body, &in_clause.body.clause.guards, parents)
+                .any(|guards| has_is_map_key_check(sema, &body, guards, key, map));
+            if checked {
+                None
+            } else {
+                Some(())
+            }
+        },
+        &move |MakeDiagCtx {
+                   sema,
+                   def_fb,
+                   args,
+                   range,
+                   ..
+               }| {
+            let last_arg = args.as_vec().last().copied()?;
+            let last_arg_range = def_fb.range_for_expr(last_arg)?;
+            let diag = make_diagnostic(sema, def.file.file_id, range, last_arg_range.end());
+            Some(diag)
+        },
+    );
+}
+
+/// The guards which hold wherever the call is reached: those of the
+/// function clause, and of each `case`, `if`, `receive` or `try ... of`
+/// clause whose body contains the call.
+fn dominating_guards<'a>(
+    body: &'a Body,
+    function_guards: &'a [Vec<ExprId>],
+    parents: &[ParentId],
+) -> impl Iterator<Item = &'a [Vec<ExprId>]> {
+    let path: Vec<ExprId> = parents
+        .iter()
+        .filter_map(|parent| match parent {
+            ParentId::HirIdx(hir_idx) => match hir_idx.idx {
+                AnyExprId::Expr(expr_id) => Some(expr_id),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+    let clause_guards: Vec<&[Vec<ExprId>]> = path
+        .windows(2)
+        .filter_map(|pair| {
+            let (outer, inner) = (pair[0], pair[1]);
+            match &body[outer] {
+                Expr::Case { clauses, .. }
+                | Expr::Receive { clauses, .. }
+                | Expr::Try {
+                    of_clauses: clauses,
+                    ..
+                } => clauses
+                    .iter()
+                    .find(|clause| clause.exprs.contains(&inner))
+                    .map(|clause| clause.guards.as_slice()),
+                Expr::If { clauses } => clauses
+                    .iter()
+                    .find(|clause| clause.exprs.contains(&inner))
+                    .map(|clause| clause.guards.as_slice()),
+                _ => None,
+            }
+        })
+        .collect();
+    std::iter::once(function_guards).chain(clause_guards)
+}
+
+/// Whether every alternative of `guards` calls `is_map_key(Key, Map)`
+/// with the same key and map, so the key is known to be present.
+fn has_is_map_key_check(
+    sema: &Semantic,
+    body: &Body,
+    guards: &[Vec<ExprId>],
+    key: ExprId,
+    map: ExprId,
+) -> bool {
+    !guards.is_empty()
+        && guards.iter().all(|alternative| {
+            alternative.iter().any(|guard| match &body[*guard] {
+                Expr::Call { target, args } if args.len() == 2 => {
+                    matches!(
+                        target.label(2, sema, body).as_deref(),
+                        Some("is_map_key/2" | "erlang:is_map_key/2")
+                    ) && same_expr(body, args[0], key)
+                        && same_expr(body, args[1], map)
+                }
+                _ => false,
+            })
+        })
+}
+
+fn same_expr(body: &Body, a: ExprId, b: ExprId) -> bool {
+    match (&body[a], &body[b]) {
+        (Expr::Var(a), Expr::Var(b)) => a == b,
+        (Expr::Literal(a), Expr::Literal(b)) => a == b,
+        _ => false,
+    }
+}
+
+fn make_diagnostic(
+    sema: &Semantic,
+    file_id: FileId,
+    range: TextRange,
+    end_of_args: TextSize,
+) -> Diagnostic {
+    let message =
+        "`maps:get/2` raises an exception if the key is missing.\nConsider using `maps:get/3` with a default value.".to_string();
+    let mut builder = SourceChangeBuilder::new(file_id);
+    builder.insert(end_of_args, ", undefined");
+    let fixes = vec![fix(
+        "maps_get_add_default",
+        "Return `undefined` if the key is missing (changes behaviour)",
+        builder.finish(),
+        range,
+    )];
+    Diagnostic::new(DiagnosticCode::MapsGetWithoutDefault, message, range)
+        .with_severity(Severity::WeakWarning)
+        .with_ignore_fix(sema, file_id)
+        .with_fixes(Some(fixes))
+}
+
+#[cfg(test)]
+mod tests {
+    use elp_ide_db::DiagnosticCode;
+    use expect_test::expect;
+    use expect_test::Expect;
+
+    use crate::diagnostics::DiagnosticsConfig;
+    use crate::tests::check_diagnostics_with_config;
+    use crate::tests::check_fix_with_config;
+
+    #[track_caller]
+    fn check_diagnostics(fixture: &str) {
+        let config = DiagnosticsConfig::default().enable(DiagnosticCode::MapsGetWithoutDefault);
+        check_diagnostics_with_config(config, fixture)
+    }
+
+    #[track_caller]
+    fn check_fix(fixture_before: &str, fixture_after: Expect) {
+        let config = DiagnosticsConfig::default().enable(DiagnosticCode::MapsGetWithoutDefault);
+        check_fix_with_config(config, fixture_before, fixture_after)
+    }
+
+    #[test]
+    fn maps_get_2_is_reported() {
+        check_diagnostics(
+            r#"
+//- /src/main.erl
+   -module(main).
+   f(Map) ->
+     maps:get(key, Map).
+%%   ^^^^^^^^^^^^^^^^^^ 💡 weak: `maps:get/2` raises an exception if the key is missing.
+%%                    | Consider using `maps:get/3` with a default value.
+//- /src/maps.erl
+   -module(maps).
+   -export([get/2, get/3]).
+   get(_Key, _Map) -> ok.
+   get(_Key, _Map, _Default) -> ok.
+            "#,
+        )
+    }
+
+    #[test]
+    fn maps_get_3_is_not_reported() {
+        check_diagnostics(
+            r#"
+//- /src/main.erl
+   -module(main).
+   f(Map) ->
+     maps:get(key, Map, undefined).
+//- /src/maps.erl
+   -module(maps).
+   -export([get/2, get/3]).
+   get(_Key, _Map) -> ok.
+   get(_Key, _Map, _Default) -> ok.
+            "#,
+        )
+    }
+
+    #[test]
+    fn maps_get_2_after_is_map_key_is_not_reported() {
+        check_diagnostics(
+            r#"
+//- /src/main.erl
+   -module(main).
+   f(Key, Map) when is_map_key(Key, Map) ->
+     maps:get(Key, Map);
+   f(_Key, _Map) ->
+     undefined.
+//- /src/maps.erl
+   -module(maps).
+   -export([get/2, get/3]).
+   get(_Key, _Map) -> ok.
+   get(_Key, _Map, _Default) -> ok.
+            "#,
+        )
+    }
+
+    #[test]
+    fn maps_get_2_in_guarded_case_clause_is_not_reported() {
+        check_diagnostics(
+            r#"
+//- /src/main.erl
+   -module(main).
+   f(Key, Map) ->
+     case Key of
+       _ when is_map_key(Key, Map) -> maps:get(Key, Map);
+       _ -> undefined
+     end.
+//- /src/maps.erl
+   -module(maps).
+   -export([get/2, get/3]).
+   get(_Key, _Map) -> ok.
+   get(_Key, _Map, _Default) -> ok.
+            "#,
+        )
+    }
+
+    #[test]
+    fn maps_get_2_not_dominated_by_is_map_key_is_reported() {
+        check_diagnostics(
+            r#"
+//- /src/main.erl
+   -module(main).
+   f(Key, Map) ->
+     case Key of
+       a when is_map_key(Key, Map) -> ok;
+       _ -> maps:get(Key, Map)
+%%          ^^^^^^^^^^^^^^^^^^ 💡 weak: `maps:get/2` raises an exception if the key is missing.
+%%                           | Consider using `maps:get/3` with a default value.
+     end.
+   g(Key, Map) when is_map_key(Key, Map); is_atom(Key) ->
+     maps:get(Key, Map).
+%%   ^^^^^^^^^^^^^^^^^^ 💡 weak: `maps:get/2` raises an exception if the key is missing.
+%%                    | Consider using `maps:get/3` with a default value.
+//- /src/maps.erl
+   -module(maps).
+   -export([get/2, get/3]).
+   get(_Key, _Map) -> ok.
+   get(_Key, _Map, _Default) -> ok.
+            "#,
+        )
+    }
+
+    #[test]
+    fn maps_get_add_default() {
+        check_fix(
+            r#"
+//- /src/main.erl
+   -module(main).
+   f(Map) ->
+     maps:g~et(key, Map).
+//- /src/maps.erl
+   -module(maps).
+   -export([get/2, get/3]).
+   get(_Key, _Map) -> ok.
+   get(_Key, _Map, _Default) -> ok.
+            "#,
+            expect![[r#"
+   -module(main).
+   f(Map) ->
+     maps:get(key, Map, undefined).
+            "#]],
+        )
+    }
+}
@@ -205,9 +205,14 @@ fn make_diagnostic(
 #[cfg(test)]
 mod tests {
 
+    use elp_ide_db::elp_base_db::assert_eq_text;
+    use elp_project_model::test_fixture::trim_indent;
+
     use crate::diagnostics::Diagnostic;
     use crate::diagnostics::DiagnosticCode;
+    use crate::fixture;
     use crate::tests;
+    use crate::DiagnosticsConfig;
 
     fn filter(d: &Diagnostic) -> bool {
         d.code == DiagnosticCode::MeckMissingNoLinkInInitPerSuite
@@ -450,4 +455,60 @@ a(_Config) ->
 "#,
         );
     }
+
+    #[test]
+    fn test_fix_missing_no_link_option_preview() {
+        let (analysis, pos, diagnostics_enabled) = fixture::position(
+            r#"
+//- common_test
+//- /my_app/test/missing_no_link9_SUITE.erl
+-module(missing_no_link9_SUITE).
+-export([all/0, init_per_suite/1]).
+-export([a/1]).
+all() -> [a].
+init_per_suite(Config) ->
+  m~eck:new(my_module, [passthrough]).
+
+a(_Config) ->
+  ok.
+//- /my_app/src/meck.erl
+-module(meck).
+-export([new/2]).
+new(_Module, _Options) -> ok.
+            "#,
+        );
+        let config = DiagnosticsConfig::default().disable(DiagnosticCode::UndefinedFunction);
+        let diagnostics = fixture::diagnostics_for(
+            &analysis,
+            pos.file_id,
+            &config,
+            &vec![],
+            &diagnostics_enabled,
+        );
+        let fix = diagnostics
+            .diagnostics_for(pos.file_id)
+            .into_iter()
+            .filter(filter)
+            .flat_map(|d| d.fixes.unwrap_or_default())
+            .find(|a| a.id.0 == "meck_add_missing_no_link_option")
+            .expect("no fix");
+        let text = analysis.file_text(pos.file_id).unwrap();
+        let preview = fix.source_change.unwrap().preview(pos.file_id, &text);
+        let expected = trim_indent(
+            r#"
+-module(missing_no_link9_SUITE).
+-export([all/0, init_per_suite/1]).
+-export([a/1]).
+all() -> [a].
+init_per_suite(Config) ->
+  meck:new(my_module, [passthrough, no_link]).
+
+a(_Config) ->
+  ok.
+"#,
+        );
+        assert_eq_text!(&expected, &preview);
+        // The database is left untouched
+        assert_eq!(analysis.file_text(pos.file_id).unwrap(), text);
+    }
 }
@@ -0,0 +1,164 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+// Diagnostic: non-contractive-type
+//
+// Return a warning on a `-type` or `-opaque` declaration which eqWAlizer
+// rejects because it is recursive without being contractive, such as
+// `-type t() :: t().`, or whose contractivity check failed. A recursive
+// type is contractive when each recursive reference is guarded by a
+// constructor, such as a list, tuple or map, as in
+// `-type t() :: [t()] | leaf.`
+
+use elp_eqwalizer::ast::db::EqwalizerASTDatabase;
+use elp_eqwalizer::ast::Error;
+use elp_eqwalizer::ast::Pos;
+use elp_ide_db::elp_base_db::FileId;
+use elp_ide_db::EqwalizerDatabase;
+use elp_syntax::ast;
+use elp_syntax::AstNode;
+use elp_syntax::TextRange;
+use elp_types_db::eqwalizer::form::InvalidForm;
+use elp_types_db::eqwalizer::form::InvalidTypeDecl;
+use elp_types_db::eqwalizer::invalid_diagnostics::Invalid;
+use elp_types_db::IncludeGenerated;
+use hir::Semantic;
+
+use super::Diagnostic;
+use crate::diagnostics::DiagnosticCode;
+use crate::diagnostics::Severity;
+use crate::RootDatabase;
+
+pub(crate) fn non_contractive_types(
+    sema: &Semantic,
+    db: &RootDatabase,
+    diagnostics: &mut Vec<Diagnostic>,
+    file_id: FileId,
+) -> Option<()> {
+    // Building the stub needs the erlang service, so only do it when
+    // the module declares a type.
+    if sema.db.def_map(file_id).get_types().is_empty() {
+        return None;
+    }
+    if !db.is_eqwalizer_enabled(file_id, IncludeGenerated::Yes) {
+        return None;
+    }
+    let project_id = db.file_app_data(file_id)?.project_id;
+    let module = db
+        .module_index(project_id)
+        .module_for_file(file_id)?
+        .clone();
+
+    let source = db.parse(file_id).tree();
+    match db.contractive_stub(project_id, module) {
+        Ok(stub) => {
+            for form in &stub.invalid_forms {
+                if let InvalidForm::InvalidTypeDecl(InvalidTypeDecl {
+                    location,
+                    id,
+                    te: Invalid::NonProductiveRecursiveTypeAlias(_),
+                }) = form
+                {
+                    let message = format!(
+                        "This type is not contractive: every recursive use of `{id}` must be nested in a tuple, list, map, record or fun type."
+                    );
+                    report(&source, location, message, diagnostics);
+                }
+            }
+        }
+        Err(Error::ContractivityError(err)) => {
+            if let Some((_, location)) = err.type_decl() {
+                let message =
+                    format!("eqWAlizer could not check whether this type is contractive: {err}");
+                report(&source, location, message, diagnostics);
+            }
+        }
+        Err(_) => {}
+    }
+    Some(())
+}
+
+fn report(
+    source: &ast::SourceFile,
+    location: &Pos,
+    message: String,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Option<()> {
+    let range = type_name_at(source, location)?;
+    diagnostics.push(
+        Diagnostic::new(DiagnosticCode::NonContractiveType, message, range)
+            .with_severity(Severity::Warning),
+    );
+    Some(())
+}
+
+/// The range of the name of the type declaration at `location`.
+fn type_name_at(source: &ast::SourceFile, location: &Pos) -> Option<TextRange> {
+    let range: TextRange = match location {
+        Pos::TextRange(range) => range.clone().into(),
+        Pos::LineAndColumn(_) => return None,
+    };
+    source.forms().find_map(|form| {
+        let name = match &form {
+            ast::Form::TypeAlias(alias) => alias.name(),
+            ast::Form::Opaque(opaque) => opaque.name(),
+            _ => return None,
+        };
+        if form.syntax().text_range().contains(range.start()) {
+            Some(name?.syntax().text_range())
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use elp_project_model::otp::otp_supported_by_eqwalizer;
+
+    use crate::diagnostics::DiagnosticCode;
+    use crate::tests::check_filtered_diagnostics;
+
+    #[track_caller]
+    fn check_diagnostics(fixture: &str) {
+        check_filtered_diagnostics(fixture, &|d| d.code == DiagnosticCode::NonContractiveType)
+    }
+
+    #[test]
+    fn non_contractive_type() {
+        if otp_supported_by_eqwalizer() {
+            check_diagnostics(
+                r#"
+            //- eqwalizer
+            //- native
+            //- /play/src/main.erl app:play
+            -module(main).
+            -type t() :: t().
+            %%    ^^^ warning: This type is not contractive: every recursive use of `t/0` must be nested in a tuple, list, map, record or fun type.
+            "#,
+            )
+        }
+    }
+
+    #[test]
+    fn contractive_type() {
+        if otp_supported_by_eqwalizer() {
+            check_diagnostics(
+                r#"
+            //- eqwalizer
+            //- native
+            //- /play/src/main.erl app:play
+            -module(main).
+            -type list_of(T) :: nil | {cons, T, list_of(T)}.
+            -opaque tree() :: [tree()].
+            "#,
+            )
+        }
+    }
+}
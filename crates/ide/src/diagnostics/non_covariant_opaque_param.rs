@@ -0,0 +1,211 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+// Diagnostic: non-covariant-opaque-param
+//
+// Return a warning on a parameter of an `-opaque` declaration which
+// eqWAlizer rejects because it occurs in a contravariant position, such
+// as the argument of a fun type, or on the declaration itself if its
+// variance check failed. eqWAlizer needs every parameter of an opaque
+// type to be covariant, so that `t(A)` is a subtype of `t(B)` whenever
+// `A` is a subtype of `B`.
+
+use elp_eqwalizer::ast::db::EqwalizerASTDatabase;
+use elp_eqwalizer::ast::Error;
+use elp_eqwalizer::ast::Pos;
+use elp_ide_db::elp_base_db::FileId;
+use elp_ide_db::EqwalizerDatabase;
+use elp_syntax::ast;
+use elp_syntax::AstNode;
+use elp_syntax::TextRange;
+use elp_types_db::eqwalizer::form::InvalidForm;
+use elp_types_db::eqwalizer::form::InvalidTypeDecl;
+use elp_types_db::eqwalizer::invalid_diagnostics::AliasWithNonCovariantParam;
+use elp_types_db::eqwalizer::invalid_diagnostics::Invalid;
+use elp_types_db::IncludeGenerated;
+use hir::Semantic;
+
+use super::Diagnostic;
+use crate::diagnostics::DiagnosticCode;
+use crate::diagnostics::Severity;
+use crate::RootDatabase;
+
+pub(crate) fn non_covariant_opaque_params(
+    sema: &Semantic,
+    db: &RootDatabase,
+    diagnostics: &mut Vec<Diagnostic>,
+    file_id: FileId,
+) -> Option<()> {
+    // Building the stub needs the erlang service, so only do it when
+    // the module declares a parameterised type.
+    if sema
+        .db
+        .def_map(file_id)
+        .get_types()
+        .keys()
+        .all(|name| name.arity() == 0)
+    {
+        return None;
+    }
+    if !db.is_eqwalizer_enabled(file_id, IncludeGenerated::Yes) {
+        return None;
+    }
+    let project_id = db.file_app_data(file_id)?.project_id;
+    let module = db
+        .module_index(project_id)
+        .module_for_file(file_id)?
+        .clone();
+
+    let source = db.parse(file_id).tree();
+    match db.covariant_stub(project_id, module) {
+        Ok(stub) => {
+            for form in &stub.invalid_forms {
+                if let InvalidForm::InvalidTypeDecl(InvalidTypeDecl {
+                    location,
+                    id,
+                    te:
+                        Invalid::AliasWithNonCovariantParam(AliasWithNonCovariantParam {
+                            type_var, ..
+                        }),
+                }) = form
+                {
+                    let message = format!(
+                        "Parameter `{type_var}` of opaque type `{id}` occurs in a contravariant position, such as the argument of a fun type, but opaque type parameters must be covariant."
+                    );
+                    report(
+                        &source,
+                        location,
+                        Some(type_var.as_str()),
+                        message,
+                        diagnostics,
+                    );
+                }
+            }
+        }
+        Err(Error::VarianceCheckError(err)) => {
+            if let Some((_, location)) = err.type_decl() {
+                let message = format!(
+                    "eqWAlizer could not check whether the parameters of this type are covariant: {err}"
+                );
+                report(&source, location, None, message, diagnostics);
+            }
+        }
+        Err(_) => {}
+    }
+    Some(())
+}
+
+fn report(
+    source: &ast::SourceFile,
+    location: &Pos,
+    type_var: Option<&str>,
+    message: String,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Option<()> {
+    let range = opaque_range_at(source, location, type_var)?;
+    diagnostics.push(
+        Diagnostic::new(DiagnosticCode::NonCovariantOpaqueParam, message, range)
+            .with_severity(Severity::Warning),
+    );
+    Some(())
+}
+
+/// The range of the parameter `type_var` of the opaque declaration at
+/// `location`, or of its name if there is no such parameter.
+fn opaque_range_at(
+    source: &ast::SourceFile,
+    location: &Pos,
+    type_var: Option<&str>,
+) -> Option<TextRange> {
+    let range: TextRange = match location {
+        Pos::TextRange(range) => range.clone().into(),
+        Pos::LineAndColumn(_) => return None,
+    };
+    let name = source.forms().find_map(|form| match form {
+        ast::Form::Opaque(opaque) if opaque.syntax().text_range().contains(range.start()) => {
+            opaque.name()
+        }
+        _ => None,
+    })?;
+    let param = type_var.and_then(|type_var| {
+        name.args()?
+            .args()
+            .find(|var| var.text().as_str() == type_var)
+    });
+    match param {
+        Some(var) => Some(var.syntax().text_range()),
+        None => Some(name.syntax().text_range()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use elp_project_model::otp::otp_supported_by_eqwalizer;
+
+    use crate::diagnostics::DiagnosticCode;
+    use crate::tests::check_filtered_diagnostics;
+
+    #[track_caller]
+    fn check_diagnostics(fixture: &str) {
+        check_filtered_diagnostics(fixture, &|d| {
+            d.code == DiagnosticCode::NonCovariantOpaqueParam
+        })
+    }
+
+    #[test]
+    fn contravariant_param() {
+        if otp_supported_by_eqwalizer() {
+            check_diagnostics(
+                r#"
+            //- eqwalizer
+            //- native
+            //- /play/src/main.erl app:play
+            -module(main).
+            -export_type([handler/1]).
+            -opaque handler(T) :: fun((T) -> ok).
+            %%              ^ warning: Parameter `T` of opaque type `handler/1` occurs in a contravariant position, such as the argument of a fun type, but opaque type parameters must be covariant.
+            "#,
+            )
+        }
+    }
+
+    #[test]
+    fn contravariant_param_through_alias() {
+        if otp_supported_by_eqwalizer() {
+            check_diagnostics(
+                r#"
+            //- eqwalizer
+            //- native
+            //- /play/src/main.erl app:play
+            -module(main).
+            -export_type([handler/1]).
+            -type callback(T) :: fun((T) -> ok).
+            -opaque handler(T) :: {handler, callback(T)}.
+            %%              ^ warning: Parameter `T` of opaque type `handler/1` occurs in a contravariant position, such as the argument of a fun type, but opaque type parameters must be covariant.
+            "#,
+            )
+        }
+    }
+
+    #[test]
+    fn covariant_param() {
+        if otp_supported_by_eqwalizer() {
+            check_diagnostics(
+                r#"
+            //- eqwalizer
+            //- native
+            //- /play/src/main.erl app:play
+            -module(main).
+            -export_type([producer/1]).
+            -opaque producer(T) :: fun(() -> T).
+            "#,
+            )
+        }
+    }
+}
@@ -0,0 +1,212 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+// Diagnostic: quadratic-append
+//
+// Return a weak warning if an accumulator is extended with `Acc ++ [X]`
+// on every step of a recursion or a fold. Each append copies the whole
+// accumulator, so building a list this way is quadratic. Prepending with
+// `[X | Acc]` and reversing the result at the end is linear.
+
+use elp_ide_db::elp_base_db::FileId;
+use elp_ide_db::DiagnosticCode;
+use elp_syntax::ast::BinaryOp;
+use elp_syntax::ast::ListOp;
+use hir::fold::MacroStrategy;
+use hir::fold::ParenStrategy;
+use hir::AnyExpr;
+use hir::Body;
+use hir::CallTarget;
+use hir::Clause;
+use hir::Expr;
+use hir::ExprId;
+use hir::FunctionDef;
+use hir::Pat;
+use hir::Semantic;
+use hir::Strategy;
+use hir::Var;
+use text_edit::TextRange;
+
+use super::Diagnostic;
+use super::DiagnosticConditions;
+use super::DiagnosticDescriptor;
+use super::Severity;
+
+pub(crate) static DESCRIPTOR: DiagnosticDescriptor = DiagnosticDescriptor {
+    conditions: DiagnosticConditions {
+        experimental: false,
+        include_generated: false,
+        include_tests: false,
+        default_disabled: false,
+    },
+    checker: &|diags, sema, file_id, _ext| {
+        quadratic_append(diags, sema, file_id);
+    },
+};
+
+fn quadratic_append(diagnostics: &mut Vec<Diagnostic>, sema: &Semantic, file_id: FileId) {
+    sema.for_each_function(file_id, |def| check_function(diagnostics, sema, def));
+}
+
+fn check_function(diagnostics: &mut Vec<Diagnostic>, sema: &Semantic, def: &FunctionDef) {
+    let def_fb = def.in_function_body(sema, def);
+    def_fb.clone().fold_function(
+        Strategy {
+            macros: MacroStrategy::Expand,
+            parens: ParenStrategy::InvisibleParens,
+        },
+        (),
+        &mut |_acc, clause_id, ctx| {
+            let (target, args) = match &ctx.item {
+                AnyExpr::Expr(Expr::Call { target, args }) => (target, args),
+                _ => return,
+            };
+            let body = def_fb.body(clause_id);
+            let appends = if is_self_call(sema, &body, def, target, args.len()) {
+                recursive_appends(&body, &def_fb[clause_id].clause, args)
+            } else {
+                fold_appends(sema, &body, target, args)
+            };
+            for append in appends {
+                if let Some(range) = def_fb.range_for_expr(clause_id, append) {
+                    diagnostics.push(make_diagnostic(range));
+                }
+            }
+        },
+    )
+}
+
+/// Whether `target/arity` is the function being checked.
+fn is_self_call(
+    sema: &Semantic,
+    body: &Body,
+    def: &FunctionDef,
+    target: &CallTarget<ExprId>,
+    arity: usize,
+) -> bool {
+    match target {
+        CallTarget::Local { name } => {
+            def.name.arity() as usize == arity
+                && body[*name]
+                    .as_atom()
+                    .map_or(false, |atom| sema.db.lookup_atom(atom) == *def.name.name())
+        }
+        CallTarget::Remote { .. } => false,
+    }
+}
+
+/// Arguments of a recursive call of the form `Acc ++ [X]`, where `Acc`
+/// is the parameter in the same position of the enclosing clause.
+fn recursive_appends(body: &Body, clause: &Clause, args: &[ExprId]) -> Vec<ExprId> {
+    args.iter()
+        .zip(&clause.pats)
+        .filter(|(arg, pat)| match &body[**pat] {
+            Pat::Var(param) => appended_to(body, **arg) == Some(*param),
+            _ => false,
+        })
+        .map(|(arg, _)| *arg)
+        .collect()
+}
+
+/// Results of the fun passed to `lists:foldl/3` or `lists:foldr/3` of the
+/// form `Acc ++ [X]`, where `Acc` is the accumulator parameter of the fun.
+fn fold_appends(
+    sema: &Semantic,
+    body: &Body,
+    target: &CallTarget<ExprId>,
+    args: &[ExprId],
+) -> Vec<ExprId> {
+    match target.label(args.len() as u32, sema, body).as_deref() {
+        Some("lists:foldl/3" | "lists:foldr/3") => {}
+        _ => return vec![],
+    }
+    let clauses = match &body[args[0]] {
+        Expr::Closure { clauses, .. } => clauses,
+        _ => return vec![],
+    };
+    clauses
+        .iter()
+        .filter_map(|clause| {
+            let result = *clause.exprs.last()?;
+            match &body[*clause.pats.get(1)?] {
+                Pat::Var(acc) if appended_to(body, result) == Some(*acc) => Some(result),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// The variable `Acc` if `expr` is `Acc ++ [X]`.
+fn appended_to(body: &Body, expr: ExprId) -> Option<Var> {
+    match &body[expr] {
+        Expr::BinaryOp {
+            lhs,
+            rhs,
+            op: BinaryOp::ListOp(ListOp::Append),
+        } => match (&body[*lhs], &body[*rhs]) {
+            (Expr::Var(acc), Expr::List { exprs, tail: None }) if exprs.len() == 1 => Some(*acc),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn make_diagnostic(range: TextRange) -> Diagnostic {
+    let message = "Appending to the end of an accumulator copies it on every step, which is quadratic.\nConsider prepending with `[X | Acc]` and calling `lists:reverse/1` on the result.".to_string();
+    Diagnostic::new(DiagnosticCode::QuadraticAppend, message, range)
+        .with_severity(Severity::WeakWarning)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::check_diagnostics;
+
+    #[test]
+    fn recursive_append_is_reported() {
+        check_diagnostics(
+            r#"
+             -module(main).
+
+             double([], Acc) -> Acc;
+             double([X | Xs], Acc) -> double(Xs, Acc ++ [2 * X]).
+             %%                                  ^^^^^^^^^^^^^^ weak: Appending to the end of an accumulator copies it on every step, which is quadratic.
+             %%                                               | Consider prepending with `[X | Acc]` and calling `lists:reverse/1` on the result.
+            "#,
+        );
+    }
+
+    #[test]
+    fn fold_append_is_reported() {
+        check_diagnostics(
+            r#"
+             -module(main).
+
+             double(Xs) ->
+                 % elp:ignore W0017 (undefined_function)
+                 lists:foldl(fun(X, Acc) -> Acc ++ [2 * X] end, [], Xs).
+             %%                             ^^^^^^^^^^^^^^ weak: Appending to the end of an accumulator copies it on every step, which is quadratic.
+             %%                                          | Consider prepending with `[X | Acc]` and calling `lists:reverse/1` on the result.
+            "#,
+        );
+    }
+
+    #[test]
+    fn benign_append_is_not_reported() {
+        check_diagnostics(
+            r#"
+             -module(main).
+
+             join(Xs, Ys) -> Xs ++ Ys.
+             add(X, Xs) -> Xs ++ [X].
+             walk([], Acc) -> Acc;
+             walk([X | Xs], Acc) -> walk(Xs, [X | Acc] ++ [X]).
+            "#,
+        );
+    }
+}
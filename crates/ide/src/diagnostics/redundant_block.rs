@@ -0,0 +1,211 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Lint/fix: redundant_block
+//!
+//! Return a diagnostic if a `begin ... end` block contains a single
+//! expression, and offer to replace the block with that expression.
+
+use elp_ide_db::elp_base_db::FileId;
+use elp_ide_db::source_change::SourceChange;
+use elp_syntax::ast;
+use elp_syntax::AstNode;
+use elp_syntax::SyntaxKind;
+use hir::fold::MacroStrategy;
+use hir::fold::ParenStrategy;
+use hir::AnyExpr;
+use hir::AnyExprId;
+use hir::Expr;
+use hir::FunctionDef;
+use hir::Semantic;
+use hir::Strategy;
+use text_edit::TextEdit;
+
+use super::Category;
+use super::Diagnostic;
+use super::DiagnosticConditions;
+use super::DiagnosticDescriptor;
+use super::Severity;
+use crate::diagnostics::DiagnosticCode;
+use crate::fix;
+
+pub(crate) static DESCRIPTOR: DiagnosticDescriptor = DiagnosticDescriptor {
+    conditions: DiagnosticConditions {
+        experimental: false,
+        include_generated: false,
+        include_tests: true,
+        default_disabled: false,
+    },
+    checker: &|diags, sema, file_id, _ext| {
+        redundant_block(diags, sema, file_id);
+    },
+};
+
+fn redundant_block(diags: &mut Vec<Diagnostic>, sema: &Semantic, file_id: FileId) {
+    sema.for_each_function(file_id, |def| process_blocks(diags, sema, file_id, def));
+}
+
+fn process_blocks(
+    diags: &mut Vec<Diagnostic>,
+    sema: &Semantic,
+    file_id: FileId,
+    def: &FunctionDef,
+) {
+    let source_file = sema.parse(file_id);
+    let def_fb = def.in_function_body(sema, def);
+    def_fb.clone().fold_function(
+        Strategy {
+            macros: MacroStrategy::Expand,
+            parens: ParenStrategy::InvisibleParens,
+        },
+        (),
+        &mut |_acc, clause_id, ctx| {
+            let expr_id = match (ctx.item_id, &ctx.item) {
+                (AnyExprId::Expr(expr_id), AnyExpr::Expr(Expr::Block { exprs }))
+                    if exprs.len() == 1 =>
+                {
+                    expr_id
+                }
+                _ => return,
+            };
+            let block = match def_fb
+                .get_body_map(clause_id)
+                .expr(expr_id)
+                .and_then(|ptr| ptr.to_node(&source_file))
+            {
+                Some(ast::Expr::ExprMax(ast::ExprMax::BlockExpr(block))) => block,
+                _ => return,
+            };
+            if let Some(diag) = make_diagnostic(file_id, &block) {
+                diags.push(diag);
+            }
+        },
+    );
+}
+
+/// Whether `expr` can replace the block without changing how the
+/// surrounding code parses.
+fn can_unwrap(block: &ast::BlockExpr, expr: &ast::Expr) -> bool {
+    let standalone = block.syntax().parent().map_or(false, |parent| {
+        matches!(
+            parent.kind(),
+            SyntaxKind::CLAUSE_BODY
+                | SyntaxKind::EXPR_ARGS
+                | SyntaxKind::BLOCK_EXPR
+                | SyntaxKind::LIST
+                | SyntaxKind::TUPLE
+        )
+    });
+    standalone
+        || !matches!(
+            expr,
+            ast::Expr::AnnType(_)
+                | ast::Expr::BinaryOpExpr(_)
+                | ast::Expr::CatchExpr(_)
+                | ast::Expr::CondMatchExpr(_)
+                | ast::Expr::MatchExpr(_)
+                | ast::Expr::Pipe(_)
+                | ast::Expr::UnaryOpExpr(_)
+        )
+}
+
+fn make_diagnostic(file_id: FileId, block: &ast::BlockExpr) -> Option<Diagnostic> {
+    let mut exprs = block.exprs();
+    let expr = exprs.next()?;
+    if exprs.next().is_some() || !can_unwrap(block, &expr) {
+        return None;
+    }
+    let range = block.syntax().text_range();
+    let edit = TextEdit::replace(range, expr.syntax().text().to_string());
+    Some(
+        Diagnostic::new(
+            DiagnosticCode::RedundantBlock,
+            "This `begin ... end` block contains a single expression.",
+            range,
+        )
+        .with_severity(Severity::WeakWarning)
+        .add_categories([Category::SimplificationRule])
+        .with_fixes(Some(vec![fix(
+            "remove_redundant_block",
+            "Remove the `begin ... end` block",
+            SourceChange::from_text_edit(file_id, edit),
+            range,
+        )])),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::expect;
+
+    use crate::tests::check_diagnostics;
+    use crate::tests::check_fix;
+
+    #[test]
+    fn single_expression_block_is_reported() {
+        check_diagnostics(
+            r#"
+            -module(main).
+            f(X) ->
+                Y = begin g(X) end,
+            %%      ^^^^^^^^^^^^^^ 💡 weak: This `begin ... end` block contains a single expression.
+                Y.
+            g(X) -> X.
+            "#,
+        );
+    }
+
+    #[test]
+    fn multi_statement_block_is_not_reported() {
+        check_diagnostics(
+            r#"
+            -module(main).
+            f(X) ->
+                Y = begin
+                    Z = g(X),
+                    g(Z)
+                end,
+                Y.
+            g(X) -> X.
+            "#,
+        );
+    }
+
+    #[test]
+    fn block_changing_precedence_is_not_reported() {
+        check_diagnostics(
+            r#"
+            -module(main).
+            f(X) -> 2 * begin X + 1 end.
+            "#,
+        );
+    }
+
+    #[test]
+    fn unwrap_single_expression_block() {
+        check_fix(
+            r#"
+            -module(main).
+            f(X) ->
+                Y = beg~in
+                    g(X)
+                end,
+                Y.
+            g(X) -> X.
+            "#,
+            expect![[r#"
+            -module(main).
+            f(X) ->
+                Y = g(X),
+                Y.
+            g(X) -> X.
+            "#]],
+        );
+    }
+}
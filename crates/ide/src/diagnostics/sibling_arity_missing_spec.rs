@@ -0,0 +1,135 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+// Diagnostic: sibling-arity-missing-spec
+//
+// Return a weak warning if an exported function has no spec, while an
+// exported function with the same name and a different arity has one.
+// eqWAlizer treats the function without a spec as dynamic, which is easy
+// to miss when its siblings are typed.
+
+use elp_ide_db::elp_base_db::FileId;
+use elp_syntax::AstNode;
+use elp_syntax::TextRange;
+use fxhash::FxHashMap;
+use hir::FunctionDef;
+use hir::Name;
+use hir::NameArity;
+use hir::Semantic;
+
+use super::Diagnostic;
+use super::DiagnosticConditions;
+use super::DiagnosticDescriptor;
+use super::Severity;
+use crate::diagnostics::DiagnosticCode;
+
+pub(crate) static DESCRIPTOR: DiagnosticDescriptor = DiagnosticDescriptor {
+    conditions: DiagnosticConditions {
+        experimental: false,
+        include_generated: false,
+        include_tests: false,
+        default_disabled: false,
+    },
+    checker: &|diags, sema, file_id, _ext| {
+        sibling_arity_missing_spec(diags, sema, file_id);
+    },
+};
+
+fn sibling_arity_missing_spec(diags: &mut Vec<Diagnostic>, sema: &Semantic, file_id: FileId) {
+    let def_map = sema.def_map(file_id);
+    let mut by_name: FxHashMap<&Name, Vec<(&NameArity, &FunctionDef)>> = FxHashMap::default();
+    for (name_arity, def) in def_map.get_functions() {
+        if def.file.file_id == file_id && def.exported {
+            by_name
+                .entry(name_arity.name())
+                .or_default()
+                .push((name_arity, def));
+        }
+    }
+    for siblings in by_name.values_mut() {
+        siblings.sort_by_key(|(name_arity, _)| name_arity.arity());
+        let specced = match siblings.iter().find(|(_, def)| def.spec.is_some()) {
+            Some((name_arity, _)) => *name_arity,
+            None => continue,
+        };
+        for (name_arity, def) in siblings.iter() {
+            if def.spec.is_some() {
+                continue;
+            }
+            if let Some(name) = def.first_clause_name(sema.db.upcast()) {
+                let range = name.syntax().text_range();
+                diags.push(make_diagnostic(sema, file_id, range, name_arity, specced));
+            }
+        }
+    }
+}
+
+fn make_diagnostic(
+    sema: &Semantic,
+    file_id: FileId,
+    range: TextRange,
+    name_arity: &NameArity,
+    specced: &NameArity,
+) -> Diagnostic {
+    let message = format!(
+        "Function `{name_arity}` has no spec, but `{specced}` has one.\nConsider adding a spec, so that eqWAlizer does not treat it as dynamic."
+    );
+    Diagnostic::new(DiagnosticCode::SiblingArityMissingSpec, message, range)
+        .with_severity(Severity::WeakWarning)
+        .with_ignore_fix(sema, file_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::check_diagnostics;
+
+    #[test]
+    fn mixed_specs_are_reported() {
+        check_diagnostics(
+            r#"
+            -module(main).
+            -export([foo/1, foo/2, foo/3]).
+            -spec foo(integer()) -> ok.
+            foo(_) -> ok.
+            foo(_, _) -> ok.
+         %% ^^^ 💡 weak: Function `foo/2` has no spec, but `foo/1` has one.
+         %%   | Consider adding a spec, so that eqWAlizer does not treat it as dynamic.
+            -spec foo(integer(), integer(), integer()) -> ok.
+            foo(_, _, _) -> ok.
+            "#,
+        );
+    }
+
+    #[test]
+    fn all_specced_is_not_reported() {
+        check_diagnostics(
+            r#"
+            -module(main).
+            -export([foo/1, foo/2]).
+            -spec foo(integer()) -> ok.
+            foo(_) -> ok.
+            -spec foo(integer(), integer()) -> ok.
+            foo(_, _) -> ok.
+            "#,
+        );
+    }
+
+    #[test]
+    fn unexported_sibling_is_not_reported() {
+        check_diagnostics(
+            r#"
+            -module(main).
+            -export([foo/1]).
+            -spec foo(integer()) -> ok.
+            foo(X) -> foo(X, 1).
+            foo(_, _) -> ok.
+            "#,
+        );
+    }
+}
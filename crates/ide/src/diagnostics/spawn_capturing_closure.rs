@@ -0,0 +1,217 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+// Diagnostic: spawn-capturing-closure
+//
+// Return a weak warning if a closure passed to `spawn/1` and friends
+// captures variables from the enclosing function. Everything the
+// closure captures is copied to the new process, which can be
+// expensive if it includes large terms.
+
+use elp_ide_db::elp_base_db::FileId;
+use hir::Expr;
+use hir::FunctionDef;
+use hir::Semantic;
+use lazy_static::lazy_static;
+use text_edit::TextRange;
+
+use super::Diagnostic;
+use super::DiagnosticConditions;
+use super::DiagnosticDescriptor;
+use crate::codemod_helpers::find_call_in_function;
+use crate::codemod_helpers::CheckCallCtx;
+use crate::codemod_helpers::MakeDiagCtx;
+use crate::diagnostics::DiagnosticCode;
+use crate::diagnostics::Severity;
+use crate::FunctionMatch;
+
+pub(crate) static DESCRIPTOR: DiagnosticDescriptor = DiagnosticDescriptor {
+    conditions: DiagnosticConditions {
+        experimental: false,
+        include_generated: false,
+        include_tests: false,
+        default_disabled: false,
+    },
+    checker: &|diags, sema, file_id, _ext| {
+        spawn_capturing_closure(diags, sema, file_id);
+    },
+};
+
+fn spawn_capturing_closure(diagnostics: &mut Vec<Diagnostic>, sema: &Semantic, file_id: FileId) {
+    lazy_static! {
+        static ref SPAWN_CALLS: Vec<FunctionMatch> = vec![
+            FunctionMatch::mfa("erlang", "spawn", 1),
+            FunctionMatch::mfa("erlang", "spawn_link", 1),
+            FunctionMatch::mfa("erlang", "spawn_monitor", 1),
+        ];
+        static ref SPAWN_CALLS_MFAS: Vec<(&'static FunctionMatch, ())> = SPAWN_CALLS
+            .iter()
+            .map(|matcher| (matcher, ()))
+            .collect::<Vec<_>>();
+    }
+
+    sema.def_map(file_id)
+        .get_functions()
+        .for_each(|(_arity, def)| {
+            if def.file.file_id == file_id {
+                check_function(diagnostics, sema, def, &SPAWN_CALLS_MFAS);
+            }
+        });
+}
+
+fn check_function(
+    diags: &mut Vec<Diagnostic>,
+    sema: &Semantic,
+    def: &FunctionDef,
+    mfas: &[(&FunctionMatch, ())],
+) {
+    find_call_in_function(
+        diags,
+        sema,
+        def,
+        mfas,
+        &move |CheckCallCtx {
+                   args, in_clause, ..
+               }: CheckCallCtx<'_, ()>| {
+            let fun = args.get(0)?;
+            if !matches!(in_clause.body()[fun], Expr::Closure { .. }) {
+                return None;
+            }
+            let scopes = sema.free_vars(&in_clause.with_value(fun))?;
+            let mut captured: Vec<String> = scopes
+                .free
+                .iter()
+                .map(|(var, _defs)| var.as_string(sema.db.upcast()))
+                .collect();
+            if captured.is_empty() {
+                return None;
+            }
+            captured.sort();
+            captured.dedup();
+            Some(captured)
+        },
+        &move |MakeDiagCtx {
+                   sema,
+                   def_fb,
+                   args,
+                   extra,
+                   range,
+                   ..
+               }| {
+            let range = args
+                .get(0)
+                .and_then(|fun| def_fb.range_for_expr(fun))
+                .unwrap_or(range);
+            let diag = make_diagnostic(sema, def.file.file_id, range, extra);
+            Some(diag)
+        },
+    );
+}
+
+fn make_diagnostic(
+    sema: &Semantic,
+    file_id: FileId,
+    range: TextRange,
+    captured: &[String],
+) -> Diagnostic {
+    let vars = captured
+        .iter()
+        .map(|var| format!("`{var}`"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let message =
+        format!("The spawned fun captures {vars}, which will be copied to the new process.");
+    Diagnostic::new(DiagnosticCode::SpawnCapturingClosure, message, range)
+        .with_severity(Severity::WeakWarning)
+        .with_ignore_fix(sema, file_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::check_diagnostics;
+
+    #[test]
+    fn capturing_fun_is_reported() {
+        check_diagnostics(
+            r#"
+//- /src/main.erl
+   -module(main).
+   -export([run/2]).
+   run(State, Config) ->
+     spawn(fun() -> loop(State, Config) end).
+%%         ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^ 💡 weak: The spawned fun captures `Config`, `State`, which will be copied to the new process.
+   loop(_State, _Config) -> ok.
+
+//- /opt/lib/stdlib-3.17/src/erlang.erl otp_app:/opt/lib/stdlib-3.17
+   -module(erlang).
+   -export([spawn/1]).
+   spawn(_) -> ok.
+            "#,
+        )
+    }
+
+    #[test]
+    fn qualified_spawn_link_is_reported() {
+        check_diagnostics(
+            r#"
+//- /src/main.erl
+   -module(main).
+   -export([run/1]).
+   run(State) ->
+     erlang:spawn_link(fun() -> loop(State) end).
+%%                     ^^^^^^^^^^^^^^^^^^^^^^^^ 💡 weak: The spawned fun captures `State`, which will be copied to the new process.
+   loop(_State) -> ok.
+
+//- /opt/lib/stdlib-3.17/src/erlang.erl otp_app:/opt/lib/stdlib-3.17
+   -module(erlang).
+   -export([spawn_link/1]).
+   spawn_link(_) -> ok.
+            "#,
+        )
+    }
+
+    #[test]
+    fn non_capturing_fun_is_not_reported() {
+        check_diagnostics(
+            r#"
+//- /src/main.erl
+   -module(main).
+   -export([run/0]).
+   run() ->
+     spawn(fun() -> X = 1, loop(X) end).
+   loop(_X) -> ok.
+
+//- /opt/lib/stdlib-3.17/src/erlang.erl otp_app:/opt/lib/stdlib-3.17
+   -module(erlang).
+   -export([spawn/1]).
+   spawn(_) -> ok.
+            "#,
+        )
+    }
+
+    #[test]
+    fn fun_reference_is_not_reported() {
+        check_diagnostics(
+            r#"
+//- /src/main.erl
+   -module(main).
+   -export([run/0, loop/0]).
+   run() ->
+     spawn(fun main:loop/0),
+     spawn(fun loop/0).
+   loop() -> ok.
+
+//- /opt/lib/stdlib-3.17/src/erlang.erl otp_app:/opt/lib/stdlib-3.17
+   -module(erlang).
+   -export([spawn/1]).
+   spawn(_) -> ok.
+            "#,
+        )
+    }
+}
@@ -0,0 +1,216 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+// Diagnostic: spawn-undefined-function
+//
+// Return a warning if `spawn/3` and friends are given a module and
+// function which are literal atoms, and a literal argument list, but
+// the function is not exported from the module, or does not exist.
+// Such a process crashes with `undef` as soon as it starts. Only
+// modules known to the project are checked.
+
+use elp_ide_db::elp_base_db::FileId;
+use hir::known;
+use hir::Expr;
+use hir::ExprId;
+use hir::FunctionDef;
+use hir::NameArity;
+use hir::Semantic;
+use lazy_static::lazy_static;
+use text_edit::TextRange;
+
+use super::undefined_function;
+use super::Diagnostic;
+use super::DiagnosticConditions;
+use super::DiagnosticDescriptor;
+use crate::codemod_helpers::find_call_in_function;
+use crate::codemod_helpers::CheckCallCtx;
+use crate::codemod_helpers::MakeDiagCtx;
+use crate::FunctionMatch;
+
+pub(crate) static DESCRIPTOR: DiagnosticDescriptor = DiagnosticDescriptor {
+    conditions: DiagnosticConditions {
+        experimental: false,
+        include_generated: false,
+        include_tests: true,
+        default_disabled: false,
+    },
+    checker: &|diags, sema, file_id, _ext| {
+        spawn_undefined_function(diags, sema, file_id);
+    },
+};
+
+/// The spawned function, and whether it exists but is not exported.
+struct Target {
+    label: String,
+    is_private: bool,
+    function_def: Option<FunctionDef>,
+    module: ExprId,
+    args: ExprId,
+}
+
+fn spawn_undefined_function(diagnostics: &mut Vec<Diagnostic>, sema: &Semantic, file_id: FileId) {
+    lazy_static! {
+        // Each call is paired with the position of the module argument
+        static ref SPAWN_CALLS: Vec<(FunctionMatch, usize)> = vec![
+            (FunctionMatch::mfa("erlang", "spawn", 3), 0),
+            (FunctionMatch::mfa("erlang", "spawn_link", 3), 0),
+            (FunctionMatch::mfa("erlang", "spawn_monitor", 3), 0),
+            (FunctionMatch::mfa("erlang", "spawn_opt", 4), 0),
+            (FunctionMatch::mfa("erlang", "spawn", 4), 1),
+            (FunctionMatch::mfa("erlang", "spawn_link", 4), 1),
+        ];
+        static ref SPAWN_CALLS_MFAS: Vec<(&'static FunctionMatch, usize)> = SPAWN_CALLS
+            .iter()
+            .map(|(matcher, module_arg)| (matcher, *module_arg))
+            .collect::<Vec<_>>();
+    }
+
+    sema.def_map(file_id)
+        .get_functions()
+        .for_each(|(_arity, def)| {
+            if def.file.file_id == file_id {
+                check_function(diagnostics, sema, def, &SPAWN_CALLS_MFAS);
+            }
+        });
+}
+
+fn check_function(
+    diags: &mut Vec<Diagnostic>,
+    sema: &Semantic,
+    def: &FunctionDef,
+    mfas: &[(&FunctionMatch, usize)],
+) {
+    find_call_in_function(
+        diags,
+        sema,
+        def,
+        mfas,
+        &move |CheckCallCtx {
+                   t: module_arg,
+                   args,
+                   in_clause,
+                   ..
+               }: CheckCallCtx<'_, usize>| {
+            let module = args.get(*module_arg)?;
+            let function = args.get(*module_arg + 1)?;
+            let spawn_args = args.get(*module_arg + 2)?;
+            let body = in_clause.body();
+            let arity = match &body[spawn_args] {
+                Expr::List { exprs, tail: None } => exprs.len() as u32,
+                _ => return None,
+            };
+            let module_name = in_clause.as_atom_name(&module)?;
+            let function_name = in_clause.as_atom_name(&function)?;
+            if sema.is_atom_named(&body[function], known::module_info) && arity <= 1 {
+                return None;
+            }
+            let target_module = sema.resolve_module_expr(in_clause.file_id(), &body[module])?;
+            let target_def_map = sema.def_map(target_module.file.file_id);
+            let name = NameArity::new(function_name.clone(), arity);
+            if target_def_map.is_function_exported(&name) {
+                return None;
+            }
+            let function_def = target_def_map.get_function(&name).cloned();
+            Some(Target {
+                label: format!("{module_name}:{function_name}/{arity}"),
+                is_private: function_def.is_some(),
+                function_def,
+                module,
+                args: spawn_args,
+            })
+        },
+        &move |MakeDiagCtx {
+                   sema,
+                   def_fb,
+                   extra,
+                   range,
+                   ..
+               }| {
+            let range = match (
+                def_fb.range_for_expr(extra.module),
+                def_fb.range_for_expr(extra.args),
+            ) {
+                (Some(start), Some(end)) => TextRange::new(start.start(), end.end()),
+                _ => range,
+            };
+            let diag = undefined_function::make_diagnostic(
+                sema,
+                def.file.file_id,
+                range,
+                &extra.label,
+                extra.is_private,
+                extra.function_def.clone(),
+            );
+            Some(diag)
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::check_diagnostics;
+
+    #[test]
+    fn unexported_target_is_reported() {
+        check_diagnostics(
+            r#"
+//- /src/main.erl
+   -module(main).
+   -export([run/0]).
+   run() ->
+     spawn(worker, loop, [1]),
+%%         ^^^^^^^^^^^^^^^^^ 💡 warning: Function 'worker:loop/1' is not exported.
+     spawn_link(worker, missing, []).
+%%              ^^^^^^^^^^^^^^^^^^^ 💡 warning: Function 'worker:missing/0' is undefined.
+
+//- /src/worker.erl
+   -module(worker).
+   -export([start/0]).
+   start() -> ok.
+   loop(_) -> ok.
+
+//- /opt/lib/stdlib-3.17/src/erlang.erl otp_app:/opt/lib/stdlib-3.17
+   -module(erlang).
+   -export([spawn/3, spawn_link/3]).
+   spawn(_, _, _) -> ok.
+   spawn_link(_, _, _) -> ok.
+            "#,
+        )
+    }
+
+    #[test]
+    fn exported_target_is_not_reported() {
+        check_diagnostics(
+            r#"
+//- /src/main.erl
+   -module(main).
+   -export([run/1]).
+   run(Args) ->
+     spawn(worker, loop, [1]),
+     erlang:spawn(node(), worker, start, []),
+     spawn(worker, loop, Args),
+     spawn(unknown, loop, [1]).
+
+//- /src/worker.erl
+   -module(worker).
+   -export([start/0, loop/1]).
+   start() -> ok.
+   loop(_) -> ok.
+
+//- /opt/lib/stdlib-3.17/src/erlang.erl otp_app:/opt/lib/stdlib-3.17
+   -module(erlang).
+   -export([spawn/3, spawn/4, node/0]).
+   spawn(_, _, _) -> ok.
+   spawn(_, _, _, _) -> ok.
+   node() -> ok.
+            "#,
+        )
+    }
+}
@@ -0,0 +1,163 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+// Diagnostic: spec-with-any-argument
+//
+// Return a weak warning if the spec of an exported function uses `any()`
+// or `term()` for an argument. Such an argument accepts every value, so
+// eqWAlizer cannot check the calls made from other modules. Disabled by
+// default.
+
+use elp_ide_db::elp_base_db::FileId;
+use elp_ide_db::DiagnosticCode;
+use hir::fold::FoldCtx;
+use hir::fold::MacroStrategy;
+use hir::fold::ParenStrategy;
+use hir::AnyExpr;
+use hir::InFile;
+use hir::Semantic;
+use hir::Strategy;
+use hir::TypeExpr;
+use text_edit::TextRange;
+
+use super::Diagnostic;
+use super::DiagnosticConditions;
+use super::DiagnosticDescriptor;
+use super::Severity;
+
+pub(crate) static DESCRIPTOR: DiagnosticDescriptor = DiagnosticDescriptor {
+    conditions: DiagnosticConditions {
+        experimental: false,
+        include_generated: false,
+        include_tests: false,
+        default_disabled: true,
+    },
+    checker: &|diags, sema, file_id, _file_kind| {
+        spec_with_any_argument(diags, sema, file_id);
+    },
+};
+
+fn spec_with_any_argument(diags: &mut Vec<Diagnostic>, sema: &Semantic, file_id: FileId) {
+    sema.def_map(file_id)
+        .get_functions()
+        .for_each(|(_name, def)| {
+            if def.file.file_id != file_id || !def.exported {
+                return;
+            }
+            if let Some(spec) = &def.spec {
+                let spec_id = InFile::new(spec.file.file_id, spec.spec_id);
+                let spec_body = sema.db.spec_body(spec_id);
+                for sig in &spec_body.sigs {
+                    FoldCtx::fold_type_exprs(
+                        Strategy {
+                            macros: MacroStrategy::Expand,
+                            parens: ParenStrategy::InvisibleParens,
+                        },
+                        &spec_body.body,
+                        &sig.args,
+                        (),
+                        &mut |_acc, ctx| {
+                            if let AnyExpr::TypeExpr(TypeExpr::Call { target, args }) = &ctx.item {
+                                let label = target.label(args.len() as u32, sema, &spec_body.body);
+                                if let Some(
+                                    label @ ("any/0" | "term/0" | "erlang:any/0" | "erlang:term/0"),
+                                ) = label.as_deref()
+                                {
+                                    if let Some(range) =
+                                        spec_body.body.range_for_any(sema, ctx.item_id)
+                                    {
+                                        diags.push(make_diagnostic(sema, file_id, range, label));
+                                    }
+                                }
+                            }
+                        },
+                    );
+                }
+            }
+        });
+}
+
+fn make_diagnostic(sema: &Semantic, file_id: FileId, range: TextRange, label: &str) -> Diagnostic {
+    let type_name = label.trim_start_matches("erlang:").trim_end_matches("/0");
+    let message = format!(
+        "Exported function argument has type `{type_name}()`, so calls to it cannot be type checked.\nConsider a more precise type."
+    );
+    Diagnostic::new(DiagnosticCode::SpecWithAnyArgument, message, range)
+        .with_severity(Severity::WeakWarning)
+        .with_ignore_fix(sema, file_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use elp_ide_db::DiagnosticCode;
+
+    use crate::diagnostics::DiagnosticsConfig;
+    use crate::tests::check_diagnostics_with_config;
+
+    #[track_caller]
+    fn check_diagnostics(fixture: &str) {
+        let config = DiagnosticsConfig::default().enable(DiagnosticCode::SpecWithAnyArgument);
+        check_diagnostics_with_config(config, fixture)
+    }
+
+    #[test]
+    fn any_argument_is_reported() {
+        check_diagnostics(
+            r#"
+            -module(main).
+            -export([f/2]).
+            -spec f(any(),
+            %%      ^^^^^ 💡 weak: Exported function argument has type `any()`, so calls to it cannot be type checked.
+            %%          | Consider a more precise type.
+                    [term()]) -> ok.
+            %%       ^^^^^^ 💡 weak: Exported function argument has type `term()`, so calls to it cannot be type checked.
+            %%            | Consider a more precise type.
+            f(_, _) -> ok.
+            "#,
+        );
+    }
+
+    #[test]
+    fn precise_spec_is_not_reported() {
+        check_diagnostics(
+            r#"
+            -module(main).
+            -export([f/1]).
+            -spec f(integer()) -> any().
+            f(_) -> ok.
+            "#,
+        );
+    }
+
+    #[test]
+    fn unexported_function_is_not_reported() {
+        check_diagnostics(
+            r#"
+            -module(main).
+            -export([g/0]).
+            -spec f(any()) -> ok.
+            f(_) -> ok.
+            g() -> f(1).
+            "#,
+        );
+    }
+
+    #[test]
+    fn ignore_is_honoured() {
+        check_diagnostics(
+            r#"
+            -module(main).
+            -export([f/1]).
+            % elp:ignore W0051 (spec_with_any_argument)
+            -spec f(any()) -> ok.
+            f(_) -> ok.
+            "#,
+        );
+    }
+}
@@ -211,7 +211,7 @@ fn matches_trivially(
                 } else {
                     let expr_fields_map = expr_fields
                         .iter()
-                        .filter_map(|(field, val)| {
+                        .filter_map(|(field, _op, val)| {
                             let lit = as_literal(in_clause, field)?;
                             Some((lit, val))
                         })
@@ -0,0 +1,130 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+// Diagnostic: undefined-export-type
+//
+// Return a warning if an `-export_type` attribute lists a type which is
+// not defined in the module, typically because the type was renamed or
+// removed. Offer to remove the entry.
+
+use elp_ide_db::elp_base_db::FileId;
+use elp_ide_db::source_change::SourceChangeBuilder;
+use elp_syntax::AstNode;
+use hir::Semantic;
+
+use super::Diagnostic;
+use crate::codemod_helpers::element_range;
+use crate::diagnostics::DiagnosticCode;
+use crate::diagnostics::Severity;
+use crate::fix;
+
+pub(crate) fn undefined_export_types(
+    sema: &Semantic,
+    diagnostics: &mut Vec<Diagnostic>,
+    file_id: FileId,
+) {
+    let form_list = sema.db.file_form_list(file_id);
+    // The types declared in the module or its includes
+    let def_map = sema.db.def_map(file_id);
+    let defined = def_map.get_types();
+
+    let source = sema.db.parse(file_id).tree();
+    for (_, export) in form_list.type_exports() {
+        let export_ast = export.form_id.get(&source);
+        for entry in export.entries.clone() {
+            let entry = &form_list[entry];
+            if defined.contains_key(&entry.name) {
+                continue;
+            }
+            if let Some(fa) = export_ast.types().nth(entry.idx as usize) {
+                let range = fa.syntax().text_range();
+                let mut builder = SourceChangeBuilder::new(file_id);
+                builder.delete(element_range(fa.syntax()));
+                let diagnostic = Diagnostic::new(
+                    DiagnosticCode::UndefinedExportType,
+                    format!("Type `{}` is exported, but not defined.", entry.name),
+                    range,
+                )
+                .with_severity(Severity::Warning)
+                .with_fixes(Some(vec![fix(
+                    "remove_export_type",
+                    &format!("Remove the export of `{}`", entry.name),
+                    builder.finish(),
+                    range,
+                )]));
+                diagnostics.push(diagnostic);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::expect;
+
+    use crate::tests::check_diagnostics;
+    use crate::tests::check_fix;
+
+    #[test]
+    fn stale_export_type() {
+        check_diagnostics(
+            r#"
+            -module(main).
+            -export_type([t/0, old/1]).
+            %%                 ^^^^^ 💡 warning: Type `old/1` is exported, but not defined.
+            -type t() :: ok.
+            "#,
+        )
+    }
+
+    #[test]
+    fn defined_export_type() {
+        check_diagnostics(
+            r#"
+            -module(main).
+            -export_type([t/0, o/1]).
+            -type t() :: ok.
+            -opaque o(X) :: {X}.
+            "#,
+        )
+    }
+
+    #[test]
+    fn export_type_defined_in_include() {
+        check_diagnostics(
+            r#"
+            //- /include/main.hrl include_path:/include
+            -type t() :: ok.
+            //- /src/main.erl
+            -module(main).
+            -include("main.hrl").
+            -export_type([t/0]).
+            -export([f/0]).
+            -spec f() -> t().
+            f() -> ok.
+            "#,
+        )
+    }
+
+    #[test]
+    fn remove_stale_export_type() {
+        check_fix(
+            r#"
+            -module(main).
+            -export_type([o~ld/1, t/0]).
+            -type t() :: ok.
+            "#,
+            expect![[r#"
+            -module(main).
+            -export_type([t/0]).
+            -type t() :: ok.
+            "#]],
+        )
+    }
+}
@@ -151,7 +151,7 @@ fn in_exclusion_list(sema: &Semantic, module: &Expr, function: &Expr, arity: u32
         || sema.is_atom_named(module, known::thrift_parser)
 }
 
-fn make_diagnostic(
+pub(crate) fn make_diagnostic(
     sema: &Semantic,
     file_id: FileId,
     range: TextRange,
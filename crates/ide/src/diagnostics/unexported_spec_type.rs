@@ -0,0 +1,261 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+// Diagnostic: unexported-spec-type
+//
+// Return a warning if the `-spec` of an exported function refers to a
+// type declared in the module but not exported with `-export_type`.
+// Callers cannot name such a type, so the spec is opaque to them.
+
+use elp_eqwalizer::ast::db::EqwalizerASTDatabase;
+use elp_eqwalizer::ast::stub::ModuleStub;
+use elp_eqwalizer::ast::Id;
+use elp_eqwalizer::ast::Pos;
+use elp_ide_assists::helpers;
+use elp_ide_assists::helpers::ExportForm;
+use elp_ide_db::elp_base_db::FileId;
+use elp_ide_db::source_change::SourceChangeBuilder;
+use elp_ide_db::EqwalizerDatabase;
+use elp_syntax::ast;
+use elp_syntax::ast::HasArity;
+use elp_syntax::AstNode;
+use elp_syntax::TextRange;
+use elp_types_db::eqwalizer::types::FunType;
+use elp_types_db::eqwalizer::types::Type;
+use elp_types_db::IncludeGenerated;
+use fxhash::FxHashSet;
+use hir::Name;
+use hir::NameArity;
+use hir::Semantic;
+
+use super::Diagnostic;
+use crate::diagnostics::DiagnosticCode;
+use crate::diagnostics::Severity;
+use crate::fix;
+use crate::RootDatabase;
+
+pub(crate) fn unexported_spec_types(
+    sema: &Semantic,
+    db: &RootDatabase,
+    diagnostics: &mut Vec<Diagnostic>,
+    file_id: FileId,
+) -> Option<()> {
+    // Building the stub needs the erlang service, so only do it when
+    // there is a local type that could be reported.
+    if sema
+        .db
+        .def_map(file_id)
+        .get_types()
+        .values()
+        .all(|type_alias| type_alias.exported)
+    {
+        return None;
+    }
+    if !db.is_eqwalizer_enabled(file_id, IncludeGenerated::Yes) {
+        return None;
+    }
+    let project_id = db.file_app_data(file_id)?.project_id;
+    let module = db
+        .module_index(project_id)
+        .module_for_file(file_id)?
+        .clone();
+    let stub = db.expanded_stub(project_id, module).ok()?;
+
+    let source = db.parse(file_id).tree();
+    let specs = stub
+        .specs
+        .values()
+        .map(|spec| (&spec.id, &spec.location, vec![&spec.ty]))
+        .chain(
+            stub.overloaded_specs
+                .values()
+                .map(|spec| (&spec.id, &spec.location, spec.tys.iter().collect())),
+        );
+    for (fun_id, location, tys) in specs {
+        if !stub.exports.contains(fun_id) {
+            continue;
+        }
+        let unexported = unexported_local_types(&stub, &tys);
+        if unexported.is_empty() {
+            continue;
+        }
+        if let Some(spec) = spec_at(&source, location) {
+            for id in unexported {
+                report(sema, file_id, &spec, fun_id, &id, diagnostics);
+            }
+        }
+    }
+    Some(())
+}
+
+/// Local types referred to by the given function types which are
+/// declared in the module, but not exported.
+fn unexported_local_types(stub: &ModuleStub, tys: &[&FunType]) -> FxHashSet<Id> {
+    let mut res = FxHashSet::default();
+    let mut check = |ty: &Type| {
+        let remote_id = match ty {
+            Type::RemoteType(ty) => &ty.id,
+            Type::OpaqueType(ty) => &ty.id,
+            _ => return Ok::<(), ()>(()),
+        };
+        if remote_id.module == stub.module {
+            let id: Id = remote_id.clone().into();
+            let declared = stub.types.contains_key(&id) || stub.public_opaques.contains_key(&id);
+            if declared && !stub.export_types.contains(&id) {
+                res.insert(id);
+            }
+        }
+        Ok(())
+    };
+    for ty in tys {
+        let _ = ty.res_ty.traverse(&mut check);
+        for arg_ty in &ty.arg_tys {
+            let _ = arg_ty.traverse(&mut check);
+        }
+    }
+    res
+}
+
+fn spec_at(source: &ast::SourceFile, location: &Pos) -> Option<ast::Spec> {
+    let range: TextRange = match location {
+        Pos::TextRange(range) => range.clone().into(),
+        Pos::LineAndColumn(_) => return None,
+    };
+    source.forms().find_map(|form| match form {
+        ast::Form::Spec(spec) if spec.syntax().text_range().contains(range.start()) => Some(spec),
+        _ => None,
+    })
+}
+
+fn report(
+    sema: &Semantic,
+    file_id: FileId,
+    spec: &ast::Spec,
+    fun_id: &Id,
+    type_id: &Id,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let name = NameArity::new(
+        Name::from_erlang_service(type_id.name.as_unquoted_str()),
+        type_id.arity,
+    );
+    let uses = spec
+        .syntax()
+        .descendants()
+        .filter_map(ast::Call::cast)
+        .filter(|call| match call.expr() {
+            Some(ast::Expr::ExprMax(ast::ExprMax::Atom(atom))) => {
+                atom.text().as_deref() == Some(type_id.name.as_unquoted_str())
+                    && call.arity_value() == Some(type_id.arity as usize)
+            }
+            _ => false,
+        });
+    for call in uses {
+        let range = call.syntax().text_range();
+        let mut builder = SourceChangeBuilder::new(file_id);
+        helpers::ExportBuilder::new(
+            sema,
+            file_id,
+            ExportForm::Types,
+            &[name.clone()],
+            &mut builder,
+        )
+        .finish();
+        let diagnostic = Diagnostic::new(
+            DiagnosticCode::UnexportedSpecType,
+            format!("Type `{name}` is used in the spec of exported function `{fun_id}`, but is not exported."),
+            range,
+        )
+        .with_severity(Severity::Warning)
+        .with_fixes(Some(vec![fix(
+            "export_type",
+            &format!("Export the type `{name}`"),
+            builder.finish(),
+            range,
+        )]));
+        diagnostics.push(diagnostic);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use elp_project_model::otp::otp_supported_by_eqwalizer;
+    use expect_test::expect;
+
+    use crate::tests::check_diagnostics;
+    use crate::tests::check_fix;
+
+    #[test]
+    fn unexported_type_in_exported_spec() {
+        if otp_supported_by_eqwalizer() {
+            check_diagnostics(
+                r#"
+            //- eqwalizer
+            //- native
+            //- /play/src/main.erl app:play
+            -module(main).
+            -export([f/0]).
+            -type t() :: ok.
+            -spec f() -> t().
+            %%           ^^^ 💡 warning: Type `t/0` is used in the spec of exported function `f/0`, but is not exported.
+            f() -> ok.
+            "#,
+            )
+        }
+    }
+
+    #[test]
+    fn exported_type_in_exported_spec() {
+        if otp_supported_by_eqwalizer() {
+            check_diagnostics(
+                r#"
+            //- eqwalizer
+            //- native
+            //- /play/src/main.erl app:play
+            -module(main).
+            -export([f/0]).
+            -export_type([t/0]).
+            -type t() :: ok.
+            -type u() :: ok.
+            -spec f() -> t().
+            f() -> g().
+            -spec g() -> u().
+            g() -> ok.
+            "#,
+            )
+        }
+    }
+
+    #[test]
+    fn fix_unexported_type_in_exported_spec() {
+        if otp_supported_by_eqwalizer() {
+            check_fix(
+                r#"
+            //- eqwalizer
+            //- native
+            //- /play/src/main.erl app:play
+            -module(main).
+            -export([f/1]).
+            -type t(X) :: {X}.
+            -spec f(t~(ok)) -> ok.
+            f({ok}) -> ok.
+            "#,
+                expect![[r#"
+                    -module(main).
+                    -export([f/1]).
+
+                    -export_type([t/1]).
+                    -type t(X) :: {X}.
+                    -spec f(t(ok)) -> ok.
+                    f({ok}) -> ok.
+            "#]],
+            )
+        }
+    }
+}
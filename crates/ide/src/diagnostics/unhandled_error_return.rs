@@ -0,0 +1,239 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+// Diagnostic: unhandled-error-return
+//
+// Return a warning if a call is used as a statement, so its result is
+// discarded, but the `-spec` of the called function says it can
+// return `{error, _}`. Such errors go unnoticed.
+
+use elp_eqwalizer::ast::db::EqwalizerASTDatabase;
+use elp_eqwalizer::ast::Id;
+use elp_ide_db::elp_base_db::FileId;
+use elp_ide_db::elp_base_db::ProjectId;
+use elp_ide_db::EqwalizerDatabase;
+use elp_syntax::AstNode;
+use elp_syntax::SyntaxKind;
+use elp_types_db::eqwalizer::types::Type;
+use elp_types_db::IncludeGenerated;
+use hir::fold::MacroStrategy;
+use hir::fold::ParenStrategy;
+use hir::AnyExpr;
+use hir::AnyExprId;
+use hir::Expr;
+use hir::FunctionDef;
+use hir::Semantic;
+use hir::Strategy;
+
+use super::effect_free_statement::is_followed_by;
+use super::effect_free_statement::is_statement;
+use super::Diagnostic;
+use crate::diagnostics::DiagnosticCode;
+use crate::diagnostics::Severity;
+use crate::RootDatabase;
+
+pub(crate) fn unhandled_error_returns(
+    sema: &Semantic,
+    db: &RootDatabase,
+    diagnostics: &mut Vec<Diagnostic>,
+    file_id: FileId,
+) -> Option<()> {
+    // Specs are read from the eqWAlizer stubs, which need the erlang
+    // service to be built.
+    if !db.is_eqwalizer_enabled(file_id, IncludeGenerated::Yes) {
+        return None;
+    }
+    let project_id = db.file_app_data(file_id)?.project_id;
+    let source_file = sema.parse(file_id);
+    sema.for_each_function(file_id, |def| {
+        let def_fb = def.in_function_body(sema, def);
+        def_fb.fold_function(
+            Strategy {
+                macros: MacroStrategy::Expand,
+                parens: ParenStrategy::InvisibleParens,
+            },
+            (),
+            &mut |_acc, clause_id, ctx| {
+                let expr_id = match ctx.item_id {
+                    AnyExprId::Expr(expr_id) => expr_id,
+                    _ => return,
+                };
+                let (target, args) = match ctx.item {
+                    AnyExpr::Expr(Expr::Call { target, args }) => (target, args),
+                    _ => return,
+                };
+                let expr_ast = match def_fb
+                    .get_body_map(clause_id)
+                    .expr(expr_id)
+                    .and_then(|ptr| ptr.to_node(&source_file))
+                {
+                    Some(expr_ast) => expr_ast,
+                    None => return,
+                };
+                if !is_statement(&expr_ast) || !is_followed_by(SyntaxKind::ANON_COMMA, &expr_ast) {
+                    return;
+                }
+                let arity = args.len() as u32;
+                if let Some(callee) =
+                    target.resolve_call(arity, sema, file_id, &def_fb.body(clause_id))
+                {
+                    if can_return_error(db, project_id, &callee) {
+                        let range = expr_ast.syntax().text_range();
+                        let message = format!(
+                            "The result of `{}` is ignored, but it can return `{{error, _}}`.",
+                            callee.name
+                        );
+                        diagnostics.push(
+                            Diagnostic::new(DiagnosticCode::UnhandledErrorReturn, message, range)
+                                .with_severity(Severity::Warning)
+                                .with_ignore_fix(sema, file_id),
+                        );
+                    }
+                }
+            },
+        );
+    });
+    Some(())
+}
+
+/// Whether the spec of the function allows it to return `{error, _}`.
+fn can_return_error(db: &RootDatabase, project_id: ProjectId, def: &FunctionDef) -> bool {
+    let stub = match def
+        .module
+        .clone()
+        .and_then(|module| db.expanded_stub(project_id, module).ok())
+    {
+        Some(stub) => stub,
+        None => return false,
+    };
+    let id = Id {
+        name: def.name.name().as_str().into(),
+        arity: def.name.arity(),
+    };
+    if let Some(spec) = stub.specs.get(&id) {
+        return is_error_type(db, project_id, &spec.ty.res_ty, 0);
+    }
+    if let Some(spec) = stub.overloaded_specs.get(&id) {
+        return spec
+            .tys
+            .iter()
+            .any(|ty| is_error_type(db, project_id, &ty.res_ty, 0));
+    }
+    false
+}
+
+/// How many type aliases are followed looking for `{error, _}`, as an
+/// alias can refer to itself.
+const MAX_ALIAS_DEPTH: u32 = 8;
+
+fn is_error_type(db: &RootDatabase, project_id: ProjectId, ty: &Type, depth: u32) -> bool {
+    match ty {
+        Type::TupleType(tuple) => {
+            matches!(tuple.arg_tys.first(), Some(Type::AtomLitType(atom)) if atom.atom == "error")
+        }
+        Type::UnionType(union) => union
+            .tys
+            .iter()
+            .any(|ty| is_error_type(db, project_id, ty, depth)),
+        Type::RemoteType(remote) if depth < MAX_ALIAS_DEPTH => {
+            let id = Id {
+                name: remote.id.name.clone(),
+                arity: remote.id.arity,
+            };
+            match db.expanded_stub(project_id, remote.id.module.clone()) {
+                Ok(stub) => stub
+                    .types
+                    .get(&id)
+                    .is_some_and(|decl| is_error_type(db, project_id, &decl.body, depth + 1)),
+                Err(_) => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use elp_project_model::otp::otp_supported_by_eqwalizer;
+
+    use crate::tests::check_diagnostics;
+
+    #[test]
+    fn ignored_error_return() {
+        if otp_supported_by_eqwalizer() {
+            check_diagnostics(
+                r#"
+            //- eqwalizer
+            //- native
+            //- /play/src/main.erl app:play
+            -module(main).
+            -export([f/0]).
+            -spec write() -> ok | {error, term()}.
+            write() -> ok.
+            f() ->
+                write(),
+            %%  ^^^^^^^ 💡 warning: The result of `write/0` is ignored, but it can return `{error, _}`.
+                ok.
+            "#,
+            )
+        }
+    }
+
+    #[test]
+    fn ignored_error_return_through_alias() {
+        if otp_supported_by_eqwalizer() {
+            check_diagnostics(
+                r#"
+            //- eqwalizer
+            //- native
+            //- /play/src/main.erl app:play
+            -module(main).
+            -export([f/0]).
+            -type my_error() :: {error, term()}.
+            -spec write() -> ok | my_error().
+            write() -> ok.
+            f() ->
+                write(),
+            %%  ^^^^^^^ 💡 warning: The result of `write/0` is ignored, but it can return `{error, _}`.
+                ok.
+            "#,
+            )
+        }
+    }
+
+    #[test]
+    fn handled_error_return() {
+        if otp_supported_by_eqwalizer() {
+            check_diagnostics(
+                r#"
+            //- eqwalizer
+            //- native
+            //- /play/src/main.erl app:play
+            -module(main).
+            -export([f/0, g/0, h/0]).
+            -spec write() -> ok | {error, term()}.
+            write() -> ok.
+            -spec log() -> ok.
+            log() -> ok.
+            f() ->
+                ok = write(),
+                ok.
+            g() ->
+                case write() of
+                    ok -> ok;
+                    {error, _} -> error
+                end.
+            h() ->
+                log(),
+                write().
+            "#,
+            )
+        }
+    }
+}
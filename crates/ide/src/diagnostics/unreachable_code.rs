@@ -0,0 +1,251 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+// Diagnostic: unreachable-code
+//
+// Return a warning if an expression follows a call to `throw/1`,
+// `exit/1` or `error/1` in the same sequence of expressions. The call
+// always raises an exception, so the expressions after it are never
+// evaluated. Offer to remove them.
+
+use elp_ide_db::elp_base_db::FileId;
+use elp_ide_db::source_change::SourceChangeBuilder;
+use hir::known;
+use hir::Body;
+use hir::CallTarget;
+use hir::Expr;
+use hir::ExprId;
+use hir::FunctionDef;
+use hir::NameArity;
+use hir::Semantic;
+use text_edit::TextRange;
+
+use super::Diagnostic;
+use super::DiagnosticConditions;
+use super::DiagnosticDescriptor;
+use super::Severity;
+use crate::diagnostics::DiagnosticCode;
+use crate::fix;
+
+pub(crate) static DESCRIPTOR: DiagnosticDescriptor = DiagnosticDescriptor {
+    conditions: DiagnosticConditions {
+        experimental: false,
+        include_generated: false,
+        include_tests: true,
+        default_disabled: false,
+    },
+    checker: &|diags, sema, file_id, _ext| {
+        unreachable_code(diags, sema, file_id);
+    },
+};
+
+fn unreachable_code(diagnostics: &mut Vec<Diagnostic>, sema: &Semantic, file_id: FileId) {
+    sema.for_each_function(file_id, |def| {
+        check_function(diagnostics, sema, file_id, def)
+    });
+}
+
+fn check_function(
+    diagnostics: &mut Vec<Diagnostic>,
+    sema: &Semantic,
+    file_id: FileId,
+    def: &FunctionDef,
+) {
+    let def_fb = def.in_function_body(sema, def);
+    for (clause_id, clause) in def_fb.clauses() {
+        let body = def_fb.body(clause_id);
+        let mut sequences = vec![clause.clause.exprs.as_slice()];
+        for (_, expr) in body.exprs.iter() {
+            sequences.extend(sequences_in(expr));
+        }
+        for exprs in sequences {
+            let position = match exprs[..exprs.len().saturating_sub(1)]
+                .iter()
+                .position(|expr| always_raises(sema, file_id, &body, *expr))
+            {
+                Some(position) => position,
+                None => continue,
+            };
+            let raise = def_fb.range_for_expr(clause_id, exprs[position]);
+            let first = def_fb.range_for_expr(clause_id, exprs[position + 1]);
+            let last = exprs
+                .last()
+                .and_then(|last| def_fb.range_for_expr(clause_id, *last));
+            if let (Some(raise), Some(first), Some(last)) = (raise, first, last) {
+                let unreachable = TextRange::new(raise.end(), last.end());
+                diagnostics.push(make_diagnostic(sema, file_id, first, unreachable));
+            }
+        }
+    }
+}
+
+/// The sequences of expressions directly contained in `expr`.
+fn sequences_in(expr: &Expr) -> Vec<&[ExprId]> {
+    match expr {
+        Expr::Block { exprs } => vec![&exprs[..]],
+        Expr::If { clauses } => clauses.iter().map(|clause| &clause.exprs[..]).collect(),
+        Expr::Case { clauses, .. } => clauses.iter().map(|clause| &clause.exprs[..]).collect(),
+        Expr::Receive { clauses, after } => clauses
+            .iter()
+            .map(|clause| &clause.exprs[..])
+            .chain(after.iter().map(|after| &after.exprs[..]))
+            .collect(),
+        Expr::Try {
+            exprs,
+            of_clauses,
+            catch_clauses,
+            after,
+        } => [&exprs[..], &after[..]]
+            .into_iter()
+            .chain(of_clauses.iter().map(|clause| &clause.exprs[..]))
+            .chain(catch_clauses.iter().map(|clause| &clause.exprs[..]))
+            .collect(),
+        Expr::Closure { clauses, .. } => clauses.iter().map(|clause| &clause.exprs[..]).collect(),
+        Expr::Maybe { else_clauses, .. } => else_clauses
+            .iter()
+            .map(|clause| &clause.exprs[..])
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// Whether `expr` is a call to `erlang:throw/1`, `erlang:exit/1` or
+/// `erlang:error/1`. A local call only refers to the BIF if no local or
+/// imported function of the same name shadows it, as it can with
+/// `-compile({no_auto_import, ...})`.
+fn always_raises(sema: &Semantic, file_id: FileId, body: &Body, expr: ExprId) -> bool {
+    match &body[expr] {
+        Expr::Call { target, args } if args.len() == 1 => {
+            let name = match target {
+                CallTarget::Local { name } => *name,
+                CallTarget::Remote { module, name, .. } => match body[*module].as_atom() {
+                    Some(module) if sema.db.lookup_atom(module) == known::erlang => *name,
+                    _ => return false,
+                },
+            };
+            let name = match body[name].as_atom() {
+                Some(name) => sema.db.lookup_atom(name),
+                None => return false,
+            };
+            if !matches!(name.as_str(), "throw" | "exit" | "error") {
+                return false;
+            }
+            match target {
+                CallTarget::Local { .. } => {
+                    let def_map = sema.db.def_map(file_id);
+                    let name_arity = NameArity::new(name, 1);
+                    def_map.get_function(&name_arity).is_none()
+                        && !def_map.get_imports().contains_key(&name_arity)
+                }
+                CallTarget::Remote { .. } => true,
+            }
+        }
+        _ => false,
+    }
+}
+
+fn make_diagnostic(
+    sema: &Semantic,
+    file_id: FileId,
+    range: TextRange,
+    unreachable: TextRange,
+) -> Diagnostic {
+    let mut builder = SourceChangeBuilder::new(file_id);
+    builder.delete(unreachable);
+    let fixes = vec![fix(
+        "remove_unreachable_code",
+        "Remove unreachable code",
+        builder.finish(),
+        range,
+    )];
+    Diagnostic::new(
+        DiagnosticCode::UnreachableCode,
+        "This code is unreachable, because the expression before it always raises an exception.",
+        range,
+    )
+    .with_severity(Severity::Warning)
+    .with_ignore_fix(sema, file_id)
+    .with_fixes(Some(fixes))
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::expect;
+
+    use crate::tests::check_diagnostics;
+    use crate::tests::check_fix;
+
+    #[test]
+    fn code_after_throw_is_reported() {
+        check_diagnostics(
+            r#"
+            -module(main).
+            f(X) ->
+                throw(oops),
+                X + 1,
+            %%  ^^^^^ 💡 warning: This code is unreachable, because the expression before it always raises an exception.
+                X.
+            g(X) ->
+                case X of
+                    ok -> erlang:error(badarg), ok;
+            %%                                  ^^ 💡 warning: This code is unreachable, because the expression before it always raises an exception.
+                    _ -> X
+                end.
+            "#,
+        );
+    }
+
+    #[test]
+    fn final_throw_is_not_reported() {
+        check_diagnostics(
+            r#"
+            -module(main).
+            f(X) ->
+                Y = X + 1,
+                throw(Y).
+            g(X) ->
+                begin
+                    exit(X)
+                end.
+            "#,
+        );
+    }
+
+    #[test]
+    fn shadowed_raise_is_not_reported() {
+        check_diagnostics(
+            r#"
+            -module(main).
+            -compile({no_auto_import, [error/1]}).
+            f(X) ->
+                error(X),
+                X.
+            error(_) -> ok.
+            "#,
+        );
+    }
+
+    #[test]
+    fn remove_unreachable_code() {
+        check_fix(
+            r#"
+            -module(main).
+            f(X) ->
+                throw(oops),
+                X~ + 1,
+                X.
+            "#,
+            expect![[r#"
+            -module(main).
+            f(X) ->
+                throw(oops).
+            "#]],
+        );
+    }
+}
@@ -91,6 +91,27 @@ pub fn db_annotations(
     )
 }
 
+/// Creates analysis from a multi-file fixture, returns the fixture files
+/// and annotations marked with sequence of %% ^^^ in any of them
+pub fn multi_file_annotations(
+    fixture: &str,
+) -> (
+    Analysis,
+    Vec<FileId>,
+    DiagnosticsEnabled,
+    Vec<(FileRange, String)>,
+) {
+    let (db, fixture) = RootDatabase::with_fixture(fixture);
+    let annotations = fixture.annotations(&db);
+    let analysis = AnalysisHost { db }.analysis();
+    (
+        analysis,
+        fixture.files,
+        fixture.diagnostics_enabled,
+        annotations,
+    )
+}
+
 pub fn check_no_parse_errors(analysis: &Analysis, file_id: FileId) -> Option<()> {
     // Check that we have a syntactically valid starting point
     let text = analysis.file_text(file_id).ok()?;
@@ -890,6 +890,25 @@ bar() -> ok.
         );
     }
 
+    #[test]
+    fn remote_call_to_extra_src_dir() {
+        check(
+            r#"
+//- /my_app/test/main_SUITE.erl app:my_app extra:test
+-module(main_SUITE).
+
+foo(Config) -> test_helper:set~up(Config).
+
+//- /my_app/test/test_helper.erl app:my_app extra:test
+-module(test_helper).
+-export([setup/1]).
+
+  setup(Config) -> Config.
+%%^^^^^
+"#,
+        );
+    }
+
     #[test]
     fn remote_call() {
         check(
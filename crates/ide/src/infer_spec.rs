@@ -0,0 +1,232 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Offer to add a `-spec` to a function that lacks one, using the types
+//! eqWAlizer inferred for its arguments and results.
+//!
+//! This lives here rather than in `elp_ide_assists` because it needs the
+//! eqWAlizer database, which assists cannot reach.
+
+use elp_ide_assists::Assist;
+use elp_ide_assists::AssistConfig;
+use elp_ide_assists::AssistId;
+use elp_ide_assists::AssistKind;
+use elp_ide_db::elp_base_db::FileRange;
+use elp_ide_db::elp_base_db::SourceDatabase;
+use elp_ide_db::eqwalizer::function_arg_types;
+use elp_ide_db::eqwalizer::type_to_spec_source;
+use elp_ide_db::source_change::SourceChangeBuilder;
+use elp_ide_db::EqwalizerDatabase;
+use elp_ide_db::RootDatabase;
+use elp_syntax::ast;
+use elp_syntax::label::Label;
+use elp_syntax::AstNode;
+use elp_types_db::eqwalizer::types::Type;
+use elp_types_db::eqwalizer::types::UnionType;
+use hir::db::DefDatabase;
+
+// Assist: infer_spec
+//
+// Adds a spec above a function that doesn't have one, built from the
+// types eqWAlizer inferred for it. Only offered when every argument and
+// result type is known and not `dynamic()`.
+//
+// ```
+// foo() -> {ok, <<"foo">>}.
+// ```
+// ->
+// ```
+// -spec foo() -> {ok, binary()}.
+// foo() -> {ok, <<"foo">>}.
+// ```
+pub(crate) fn infer_spec(
+    db: &RootDatabase,
+    config: &AssistConfig,
+    frange: FileRange,
+) -> Option<Assist> {
+    if let Some(allowed) = &config.allowed {
+        if !allowed.contains(&AssistKind::Generate) {
+            return None;
+        }
+    }
+    let file_id = frange.file_id;
+    let def_map = db.def_map(file_id);
+    let (function, source) = def_map.get_functions().find_map(|(_, function)| {
+        let source = function.source(db);
+        let name = source.first()?.name()?;
+        if name.syntax().text_range().contains(frange.range.start()) {
+            Some((function, source))
+        } else {
+            None
+        }
+    })?;
+    if function.spec.is_some() {
+        return None;
+    }
+    let project_id = db.file_app_data(file_id)?.project_id;
+    let module = db
+        .module_index(project_id)
+        .module_for_file(file_id)?
+        .clone();
+    let arg_tys = function_arg_types(db, project_id, &module, &function.name)?;
+    let res_ty = result_type(db, frange, &source)?;
+    if arg_tys
+        .iter()
+        .chain(Some(&res_ty))
+        .any(Type::contains_dynamic)
+    {
+        return None;
+    }
+
+    let render = |idx: usize, ty: &Type| {
+        let text = type_to_spec_source(ty, &module);
+        match config.snippet_cap {
+            Some(_) => format!("${{{}:{}}}", idx + 1, escape_snippet(&text)),
+            None => text,
+        }
+    };
+    let args = arg_tys
+        .iter()
+        .enumerate()
+        .map(|(idx, ty)| render(idx, ty))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let spec = format!(
+        "-spec {}({}) -> {}.\n",
+        function.name.name().to_quoted_string(),
+        args,
+        render(arg_tys.len(), &res_ty)
+    );
+
+    let first = source.first()?;
+    let target = first.name()?.syntax().text_range();
+    let insert = first.syntax().text_range().start();
+    let mut builder = SourceChangeBuilder::new(file_id);
+    match config.snippet_cap {
+        Some(cap) => builder.insert_snippet(cap, insert, spec),
+        None => builder.insert(insert, spec),
+    }
+    Some(Assist {
+        id: AssistId("infer_spec", AssistKind::Generate),
+        label: Label::new("Add spec inferred by eqWAlizer"),
+        group: None,
+        target,
+        source_change: Some(builder.finish()),
+        user_input: None,
+        original_diagnostic: None,
+    })
+}
+
+/// The union of the types of the last expression of each clause.
+fn result_type(db: &RootDatabase, frange: FileRange, source: &[ast::FunDecl]) -> Option<Type> {
+    let mut tys: Vec<Type> = vec![];
+    for fun_decl in source {
+        let clause = match fun_decl.clause()? {
+            ast::FunctionOrMacroClause::FunctionClause(clause) => clause,
+            ast::FunctionOrMacroClause::MacroCallExpr(_) => return None,
+        };
+        let last = clause.body()?.exprs().last()?;
+        let range = FileRange {
+            file_id: frange.file_id,
+            range: last.syntax().text_range(),
+        };
+        let type_info = db.type_at_position(range)?;
+        if type_info.1 != range {
+            return None;
+        }
+        if !tys.contains(&type_info.0) {
+            tys.push(type_info.0.clone());
+        }
+    }
+    match tys.len() {
+        0 => None,
+        1 => tys.pop(),
+        _ => Some(Type::UnionType(UnionType { tys })),
+    }
+}
+
+fn escape_snippet(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('$', "\\$")
+        .replace('}', "\\}")
+}
+
+#[cfg(test)]
+mod tests {
+    use elp_ide_assists::AssistConfig;
+    use elp_ide_db::elp_base_db::assert_eq_text;
+    use elp_ide_db::elp_base_db::FileRange;
+    use elp_project_model::otp::otp_supported_by_eqwalizer;
+    use elp_project_model::test_fixture::trim_indent;
+    use elp_syntax::TextRange;
+
+    use super::infer_spec;
+    use crate::fixture;
+
+    #[track_caller]
+    fn check(fixture: &str, expected: Option<&str>) {
+        let (analysis, position, _) = fixture::position(fixture);
+        let config = AssistConfig {
+            snippet_cap: None,
+            allowed: None,
+        };
+        let frange = FileRange {
+            file_id: position.file_id,
+            range: TextRange::empty(position.offset),
+        };
+        let assist = infer_spec(&analysis.db, &config, frange);
+        match (assist, expected) {
+            (Some(assist), Some(expected)) => {
+                let text = analysis.file_text(position.file_id).unwrap();
+                let actual = assist
+                    .source_change
+                    .unwrap()
+                    .preview(position.file_id, &text);
+                assert_eq_text!(&trim_indent(expected), &actual);
+            }
+            (None, None) => {}
+            (assist, _) => panic!("unexpected assist: {:?}", assist),
+        }
+    }
+
+    #[test]
+    fn infers_spec() {
+        if otp_supported_by_eqwalizer() {
+            check(
+                r#"
+                //- eqwalizer
+                //- /play/src/main.erl app:play
+                -module(main).
+                -export([pair/0]).
+                pa~ir() -> {ok, <<"pair">>}.
+                "#,
+                Some(
+                    r#"
+                    -module(main).
+                    -export([pair/0]).
+                    -spec pair() -> {ok, binary()}.
+                    pair() -> {ok, <<"pair">>}.
+                    "#,
+                ),
+            )
+        }
+    }
+
+    #[test]
+    fn not_offered_with_existing_spec() {
+        check(
+            r#"
+            -module(main).
+            -spec pair() -> {ok, binary()}.
+            pa~ir() -> {ok, <<"pair">>}.
+            "#,
+            None,
+        )
+    }
+}
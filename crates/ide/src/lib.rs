@@ -19,6 +19,7 @@ use diagnostics::LabeledDiagnostics;
 use diagnostics::RemoveElpReported;
 use diagnostics_collection::DiagnosticCollection;
 use elp_eqwalizer::ast::Pos;
+use elp_eqwalizer::EqwalizerDiagnostic;
 use elp_ide_assists::Assist;
 use elp_ide_assists::AssistConfig;
 use elp_ide_assists::AssistId;
@@ -65,8 +66,10 @@ use elp_syntax::SmolStr;
 use elp_types_db::eqwalizer;
 use elp_types_db::eqwalizer::types::Type;
 use elp_types_db::IncludeGenerated;
+use elp_types_db::TypedSemantic;
 use erlang_service::CompileOption;
 use expand_macro::ExpandedMacro;
+use fxhash::FxHashMap;
 use handlers::get_docs;
 use handlers::goto_definition;
 use handlers::goto_type_definition;
@@ -76,6 +79,7 @@ use hir::DefMap;
 use hir::File;
 use hir::FormList;
 use hir::Module;
+use hir::NameArity;
 use hir::Semantic;
 use navigation_target::ToNav;
 use rayon::prelude::*;
@@ -91,6 +95,7 @@ mod extend_selection;
 mod folding_ranges;
 mod handlers;
 mod hover;
+mod infer_spec;
 mod inlay_hints;
 mod navigation_target;
 mod rename;
@@ -253,6 +258,17 @@ impl Analysis {
         self.with_db(|db| diagnostics::eqwalizer_diagnostics(db, file_id, include_generated))
     }
 
+    /// Like `eqwalizer_diagnostics_for_file`, but also reports whether
+    /// eqWAlizer is unavailable, so that a file which could not be
+    /// checked is not mistaken for one without errors.
+    pub fn eqwalizer_status_for_file(
+        &self,
+        file_id: FileId,
+        include_generated: IncludeGenerated,
+    ) -> Cancellable<diagnostics::EqwalizerStatus> {
+        self.with_db(|db| diagnostics::eqwalizer_status(db, file_id, include_generated))
+    }
+
     /// Computes the set of eqwalizer diagnostics for the given project and files,
     /// including checking for disabled. Returns standard diagnostics.
     pub fn eqwalizer_diagnostics_by_project(
@@ -298,6 +314,35 @@ impl Analysis {
         })
     }
 
+    /// Computes the eqwalizer diagnostics for the given file which
+    /// overlap the given range. Returns EqwalizerDiagnostics
+    pub fn eqwalizer_diagnostics_in_range(
+        &self,
+        file_id: FileId,
+        range: TextRange,
+    ) -> Cancellable<Vec<EqwalizerDiagnostic>> {
+        self.with_db(|db| diagnostics::eqwalizer_diagnostics_in_range(db, file_id, range))
+    }
+
+    /// Computes the eqwalizer diagnostics for the given file, grouped
+    /// by the function containing them
+    pub fn eqwalizer_diagnostics_by_function(
+        &self,
+        file_id: FileId,
+    ) -> Cancellable<FxHashMap<NameArity, Vec<EqwalizerDiagnostic>>> {
+        self.with_db(|db| {
+            let diagnostics = db
+                .eqwalizer_diagnostics(file_id, IncludeGenerated::Yes)
+                .unwrap_or_default();
+            diagnostics::eqwalizer_diagnostics_by_function(
+                db,
+                file_id,
+                &db.def_map(file_id),
+                diagnostics,
+            )
+        })
+    }
+
     pub fn eqwalizer_stats(
         &self,
         project_id: ProjectId,
@@ -418,6 +463,15 @@ impl Analysis {
         self.with_db(|db| db.module_index(project_id))
     }
 
+    /// Returns the app type of each module in the project, and
+    /// whether it belongs to OTP
+    pub fn module_app_types(
+        &self,
+        project_id: ProjectId,
+    ) -> Cancellable<Arc<FxHashMap<FileId, (AppType, bool)>>> {
+        self.with_db(|db| db.module_app_types(project_id))
+    }
+
     pub fn module_file_id(
         &self,
         project_id: ProjectId,
@@ -533,6 +587,7 @@ impl Analysis {
             let mut res = diagnostic_assists;
             res.extend(assists);
             res.extend(eqwalizer_assists);
+            res.extend(infer_spec::infer_spec(db, assist_config, frange));
 
             res
         })
@@ -542,6 +597,10 @@ impl Analysis {
         self.with_db(|db| db.is_generated(file_id))
     }
 
+    pub fn is_applicable_file(&self, file_id: FileId) -> Cancellable<bool> {
+        self.with_db(|db| db.is_applicable_file(file_id))
+    }
+
     pub fn is_test_suite_or_test_helper(&self, file_id: FileId) -> Cancellable<Option<bool>> {
         self.with_db(|db| db.is_test_suite_or_test_helper(file_id))
     }
@@ -10,6 +10,10 @@
 // To run the tests via cargo
 // cargo test --package elp_ide --lib
 
+use std::sync::Arc;
+
+use elp_eqwalizer::EqwalizerConfig;
+use elp_eqwalizer::EqwalizerDiagnosticsDatabase;
 use elp_ide_assists::Assist;
 use elp_ide_db::elp_base_db::assert_eq_text;
 use elp_ide_db::elp_base_db::fixture::extract_annotations;
@@ -108,12 +112,7 @@ pub(crate) fn check_filtered_ct_fix_with_config(
     let actual = {
         let source_change = fix.source_change.as_ref().unwrap();
         let file_id = *source_change.source_file_edits.keys().next().unwrap();
-        let mut actual = analysis.db.file_text(file_id).to_string();
-
-        for edit in source_change.source_file_edits.values() {
-            edit.apply(&mut actual);
-        }
-        actual
+        source_change.preview(file_id, &analysis.db.file_text(file_id))
     };
     assert!(
         fix.target.contains_inclusive(pos.offset),
@@ -221,12 +220,7 @@ pub(crate) fn check_nth_fix(
     let actual = {
         let source_change = fix.source_change.as_ref().unwrap();
         let file_id = *source_change.source_file_edits.keys().next().unwrap();
-        let mut actual = analysis.db.file_text(file_id).to_string();
-
-        for edit in source_change.source_file_edits.values() {
-            edit.apply(&mut actual);
-        }
-        actual
+        source_change.preview(file_id, &analysis.db.file_text(file_id))
     };
     let actual = remove_annotations(None, &actual);
     assert!(
@@ -322,12 +316,7 @@ pub(crate) fn check_specific_fix_with_config_and_adhoc(
     let actual = {
         let source_change = fix.source_change.as_ref().unwrap();
         let file_id = *source_change.source_file_edits.keys().next().unwrap();
-        let mut actual = analysis.db.file_text(file_id).to_string();
-
-        for edit in source_change.source_file_edits.values() {
-            edit.apply(&mut actual);
-        }
-        actual
+        source_change.preview(file_id, &analysis.db.file_text(file_id))
     };
     let actual = remove_annotations(None, &actual);
     let actual = trim_indent(&actual);
@@ -422,6 +411,117 @@ pub(crate) fn check_diagnostics_with_config_and_ad_hoc(
     }
 }
 
+/// Check the diagnostics of every file in the fixture against the
+/// annotations in all of them at once. Unlike
+/// `check_diagnostics_with_config`, a diagnostic reported against
+/// another file while checking a file, such as an error in an
+/// included header, is matched against the annotations of the file it
+/// is reported in. A diagnostic reported identically while checking
+/// several files is only expected once.
+#[track_caller]
+pub(crate) fn check_diagnostics_across_files(elp_fixture: &str) {
+    let config = DiagnosticsConfig::default().set_experimental(true);
+    let (analysis, files, diagnostics_enabled, expected) =
+        fixture::multi_file_annotations(elp_fixture);
+    let mut actual: Vec<(FileRange, String)> = files
+        .iter()
+        .flat_map(|&checked_file_id| {
+            let diagnostics = fixture::diagnostics_for(
+                &analysis,
+                checked_file_id,
+                &config,
+                &vec![],
+                &diagnostics_enabled,
+            );
+            files
+                .iter()
+                .flat_map(|&file_id| {
+                    convert_diagnostics_to_annotations(diagnostics.diagnostics_for(file_id))
+                        .into_iter()
+                        .map(move |(range, annotation)| (FileRange { file_id, range }, annotation))
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    actual.sort_by_key(|(r, a)| (r.file_id.index(), r.range.start(), a.clone()));
+    actual.dedup();
+    let mut expected = expected;
+    expected.sort_by_key(|(r, a)| (r.file_id.index(), r.range.start(), a.clone()));
+    assert_eq!(expected, actual);
+}
+
+/// Like `check_filtered_diagnostics`, but with the given eqWAlizer
+/// config set on the database.
+#[track_caller]
+pub(crate) fn check_filtered_diagnostics_with_eqwalizer_config(
+    eqwalizer_config: EqwalizerConfig,
+    elp_fixture: &str,
+    filter: &dyn Fn(&Diagnostic) -> bool,
+) {
+    let config = DiagnosticsConfig::default().set_experimental(true);
+    let (mut db, files, diagnostics_enabled) = RootDatabase::with_many_files(elp_fixture);
+    db.set_eqwalizer_config(Arc::new(eqwalizer_config));
+    let host = AnalysisHost { db };
+    let analysis = host.analysis();
+    for file_id in files {
+        let diagnostics =
+            fixture::diagnostics_for(&analysis, file_id, &config, &vec![], &diagnostics_enabled);
+        let diagnostics = diagnostics
+            .diagnostics_for(file_id)
+            .into_iter()
+            .filter(filter)
+            .collect();
+        let mut expected = extract_annotations(&analysis.db.file_text(file_id));
+        expected.sort_by_key(|(r1, _)| r1.start());
+        let actual = convert_diagnostics_to_annotations(diagnostics);
+        assert_eq!(expected, actual);
+    }
+}
+
+/// Like `check_fix`, but with the given eqWAlizer config set on the
+/// database.
+#[track_caller]
+pub(crate) fn check_fix_with_eqwalizer_config(
+    eqwalizer_config: EqwalizerConfig,
+    fixture_before: &str,
+    fixture_after: Expect,
+) {
+    let config = DiagnosticsConfig::default().set_experimental(true);
+    let (mut db, fixture_data) = RootDatabase::with_fixture(fixture_before);
+    db.set_eqwalizer_config(Arc::new(eqwalizer_config));
+    let pos = fixture_data.position();
+    let host = AnalysisHost { db };
+    let analysis = host.analysis();
+    let diagnostics = fixture::diagnostics_for(
+        &analysis,
+        pos.file_id,
+        &config,
+        &vec![],
+        &fixture_data.diagnostics_enabled,
+    );
+    let diagnostic = diagnostics
+        .diagnostics_for(pos.file_id)
+        .into_iter()
+        .last()
+        .expect("no diagnostics")
+        .clone();
+    let fixes = diagnostic.fixes.expect("diagnostic misses fixes");
+    let fix = &fixes[0];
+    let actual = {
+        let source_change = fix.source_change.as_ref().unwrap();
+        let file_id = *source_change.source_file_edits.keys().next().unwrap();
+        source_change.preview(file_id, &analysis.db.file_text(file_id))
+    };
+    let actual = remove_annotations(None, &actual);
+    assert!(
+        fix.target.contains_inclusive(pos.offset),
+        "diagnostic fix range {:?} does not touch cursor position {:?}",
+        fix.target,
+        pos.offset
+    );
+    fixture_after.assert_eq(&actual);
+}
+
 #[track_caller]
 pub(crate) fn check_filtered_diagnostics(elp_fixture: &str, filter: &dyn Fn(&Diagnostic) -> bool) {
     let config = DiagnosticsConfig::default();
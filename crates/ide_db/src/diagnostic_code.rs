@@ -68,6 +68,27 @@ pub enum DiagnosticCode {
     UnnecessaryFoldToBuildMapFromList,
     UnnecessaryMapFromListAroundComprehension,
     UnspecificInclude,
+    IfToCase,
+    UnexportedSpecType,
+    SpawnCapturingClosure,
+    GenServerCallWithoutTimeout,
+    UnhandledErrorReturn,
+    CaseMissingCatchAll,
+    NonContractiveType,
+    NonCovariantOpaqueParam,
+    QuadraticAppend,
+    RedundantBlock,
+    AfterMayRaise,
+    MapsGetWithoutDefault,
+    DuplicateClause,
+    SpecWithAnyArgument,
+    MissingTestFunction,
+    SiblingArityMissingSpec,
+    UnreachableCode,
+    UndefinedExportType,
+    ComprehensionToListsCall,
+    CaseOnBoolean,
+    LiteralReturnContradictsSpec,
 
     // Wrapper for erlang service diagnostic codes
     ErlangService(String),
@@ -203,6 +224,27 @@ impl DiagnosticCode {
             DiagnosticCode::UnnecessaryFoldToBuildMapFromList => "W0035".to_string(),
             DiagnosticCode::UnnecessaryMapFromListAroundComprehension => "W0036".to_string(),
             DiagnosticCode::UnspecificInclude => "W0037".to_string(),
+            DiagnosticCode::IfToCase => "W0038".to_string(),
+            DiagnosticCode::UnexportedSpecType => "W0039".to_string(),
+            DiagnosticCode::SpawnCapturingClosure => "W0040".to_string(),
+            DiagnosticCode::GenServerCallWithoutTimeout => "W0041".to_string(),
+            DiagnosticCode::UnhandledErrorReturn => "W0042".to_string(),
+            DiagnosticCode::CaseMissingCatchAll => "W0043".to_string(),
+            DiagnosticCode::NonContractiveType => "W0044".to_string(),
+            DiagnosticCode::NonCovariantOpaqueParam => "W0045".to_string(),
+            DiagnosticCode::QuadraticAppend => "W0046".to_string(),
+            DiagnosticCode::RedundantBlock => "W0047".to_string(),
+            DiagnosticCode::AfterMayRaise => "W0048".to_string(),
+            DiagnosticCode::MapsGetWithoutDefault => "W0049".to_string(),
+            DiagnosticCode::DuplicateClause => "W0050".to_string(),
+            DiagnosticCode::SpecWithAnyArgument => "W0051".to_string(),
+            DiagnosticCode::MissingTestFunction => "W0052".to_string(),
+            DiagnosticCode::SiblingArityMissingSpec => "W0053".to_string(),
+            DiagnosticCode::UnreachableCode => "W0054".to_string(),
+            DiagnosticCode::UndefinedExportType => "W0055".to_string(),
+            DiagnosticCode::ComprehensionToListsCall => "W0056".to_string(),
+            DiagnosticCode::CaseOnBoolean => "W0057".to_string(),
+            DiagnosticCode::LiteralReturnContradictsSpec => "W0058".to_string(),
             DiagnosticCode::ErlangService(c) => c.to_string(),
             DiagnosticCode::Eqwalizer(c) => format!("eqwalizer: {c}"),
             DiagnosticCode::AdHoc(c) => format!("ad-hoc: {c}"),
@@ -276,6 +318,31 @@ impl DiagnosticCode {
                 "unnecessary_map_from_list_around_comprehension".to_string()
             }
             DiagnosticCode::UnspecificInclude => "unspecific_include".to_string(),
+            DiagnosticCode::IfToCase => "if_to_case".to_string(),
+            DiagnosticCode::UnexportedSpecType => "unexported_spec_type".to_string(),
+            DiagnosticCode::SpawnCapturingClosure => "spawn_capturing_closure".to_string(),
+            DiagnosticCode::GenServerCallWithoutTimeout => {
+                "gen_server_call_without_timeout".to_string()
+            }
+            DiagnosticCode::UnhandledErrorReturn => "unhandled_error_return".to_string(),
+            DiagnosticCode::CaseMissingCatchAll => "case_missing_catch_all".to_string(),
+            DiagnosticCode::NonContractiveType => "non_contractive_type".to_string(),
+            DiagnosticCode::NonCovariantOpaqueParam => "non_covariant_opaque_param".to_string(),
+            DiagnosticCode::QuadraticAppend => "quadratic_append".to_string(),
+            DiagnosticCode::RedundantBlock => "redundant_block".to_string(),
+            DiagnosticCode::AfterMayRaise => "after_may_raise".to_string(),
+            DiagnosticCode::MapsGetWithoutDefault => "maps_get_without_default".to_string(),
+            DiagnosticCode::DuplicateClause => "duplicate_clause".to_string(),
+            DiagnosticCode::SpecWithAnyArgument => "spec_with_any_argument".to_string(),
+            DiagnosticCode::MissingTestFunction => "missing_test_function".to_string(),
+            DiagnosticCode::SiblingArityMissingSpec => "sibling_arity_missing_spec".to_string(),
+            DiagnosticCode::UnreachableCode => "unreachable_code".to_string(),
+            DiagnosticCode::UndefinedExportType => "undefined_export_type".to_string(),
+            DiagnosticCode::ComprehensionToListsCall => "comprehension_to_lists_call".to_string(),
+            DiagnosticCode::CaseOnBoolean => "case_on_boolean".to_string(),
+            DiagnosticCode::LiteralReturnContradictsSpec => {
+                "literal_return_contradicts_spec".to_string()
+            }
             DiagnosticCode::RecordTupleMatch => "record_tuple_match".to_string(),
             DiagnosticCode::ErlangService(c) => c.to_string(),
             DiagnosticCode::Eqwalizer(c) => c.to_string(),
@@ -406,6 +473,27 @@ impl DiagnosticCode {
             DiagnosticCode::BooleanPrecedence => false,
             DiagnosticCode::UnexportedFunction => false,
             DiagnosticCode::UnspecificInclude => false,
+            DiagnosticCode::IfToCase => false,
+            DiagnosticCode::UnexportedSpecType => false,
+            DiagnosticCode::SpawnCapturingClosure => false,
+            DiagnosticCode::GenServerCallWithoutTimeout => false,
+            DiagnosticCode::UnhandledErrorReturn => false,
+            DiagnosticCode::CaseMissingCatchAll => false,
+            DiagnosticCode::NonContractiveType => false,
+            DiagnosticCode::NonCovariantOpaqueParam => false,
+            DiagnosticCode::QuadraticAppend => false,
+            DiagnosticCode::RedundantBlock => false,
+            DiagnosticCode::AfterMayRaise => false,
+            DiagnosticCode::MapsGetWithoutDefault => false,
+            DiagnosticCode::DuplicateClause => false,
+            DiagnosticCode::SpecWithAnyArgument => false,
+            DiagnosticCode::MissingTestFunction => false,
+            DiagnosticCode::SiblingArityMissingSpec => false,
+            DiagnosticCode::UnreachableCode => false,
+            DiagnosticCode::UndefinedExportType => false,
+            DiagnosticCode::ComprehensionToListsCall => false,
+            DiagnosticCode::CaseOnBoolean => false,
+            DiagnosticCode::LiteralReturnContradictsSpec => false,
             DiagnosticCode::ErlangService(_) => false,
             DiagnosticCode::Eqwalizer(_) => false,
             DiagnosticCode::AdHoc(_) => false,
@@ -10,6 +10,8 @@
 use std::sync::Arc;
 
 use elp_base_db::salsa;
+use elp_base_db::AtomName;
+use elp_base_db::AtomNameInterner;
 use elp_base_db::FileId;
 use elp_base_db::FileRange;
 use elp_base_db::FileSource;
@@ -21,24 +23,34 @@ use elp_base_db::VfsPath;
 use elp_eqwalizer::analyses::EqwalizerAnalysesDatabase;
 use elp_eqwalizer::ast::db::EqwalizerASTDatabase;
 use elp_eqwalizer::ast::db::EqwalizerErlASTStorage;
+use elp_eqwalizer::ast::db::StubBytesCache;
 use elp_eqwalizer::ast::Error;
 use elp_eqwalizer::ast::Pos;
 use elp_eqwalizer::ast::RemoteId;
 use elp_eqwalizer::ipc::IpcHandle;
+use elp_eqwalizer::ConfigValue;
 use elp_eqwalizer::EqwalizerDiagnostic;
 use elp_eqwalizer::EqwalizerDiagnostics;
 use elp_eqwalizer::EqwalizerDiagnosticsDatabase;
 use elp_project_model::otp::otp_supported_by_eqwalizer;
 use elp_syntax::ast;
+use elp_syntax::AstNode;
 use elp_syntax::SmolStr;
+use elp_syntax::TextRange;
 use elp_types_db::eqwalizer;
 use elp_types_db::eqwalizer::types::Type;
+use elp_types_db::eqwalizer::types::UnionType;
 use elp_types_db::IncludeGenerated;
 use fxhash::FxHashSet;
+use hir::db::DefDatabase;
+use hir::Expr;
+use hir::NameArity;
+use hir::Semantic;
 use parking_lot::Mutex;
 
 use crate::ErlAstDatabase;
 use crate::LineCol;
+use crate::RootDatabase;
 
 pub trait EqwalizerLoader {
     fn typecheck(&self, project_id: ProjectId, modules: Vec<FileId>) -> EqwalizerDiagnostics;
@@ -166,6 +178,147 @@ fn types_for_file(db: &dyn EqwalizerDatabase, file_id: FileId) -> Option<Arc<Vec
     None
 }
 
+/// The project modules, with eqWAlizer enabled, for which eqWAlizer
+/// produced no results: either their AST could not be produced, usually
+/// because of a parse error, or eqWAlizer failed on them.
+pub fn modules_without_eqwalizer_results(
+    db: &dyn EqwalizerDatabase,
+    project_id: ProjectId,
+) -> Vec<ModuleName> {
+    let module_index = db.module_index(project_id);
+    let (modules, file_ids): (Vec<ModuleName>, Vec<FileId>) = module_index
+        .iter_own()
+        .filter(|(_, _, file_id)| db.is_eqwalizer_enabled(*file_id, IncludeGenerated::No))
+        .map(|(module, _, file_id)| (module.clone(), file_id))
+        .unzip();
+    if file_ids.is_empty() {
+        return vec![];
+    }
+    let mut res: Vec<ModuleName> =
+        match &*eqwalizer_diagnostics_by_project(db, project_id, file_ids) {
+            EqwalizerDiagnostics::Diagnostics { errors, no_ast, .. } => modules
+                .into_iter()
+                .filter(|module| no_ast.contains(module) || !errors.contains_key(module))
+                .collect(),
+            EqwalizerDiagnostics::NoAst { module } => vec![module.clone()],
+            EqwalizerDiagnostics::Error(_) => modules,
+        };
+    res.sort();
+    res
+}
+
+/// The eqWAlizer coverage of `module`: the fraction of the variables
+/// used in its expressions which eqWAlizer gave a type not involving
+/// `dynamic()`. eqWAlizer only records the types of variables, and
+/// variables without type information count as not covered. `None` if
+/// eqWAlizer produced no types for the module, or it uses no variables.
+pub fn eqwalizer_coverage(
+    db: &RootDatabase,
+    project_id: ProjectId,
+    module: &ModuleName,
+) -> Option<f32> {
+    let file_id = db.module_file(project_id, module.clone())?;
+    let types = db.types_for_file(file_id)?;
+    let typed: FxHashSet<TextRange> = types
+        .iter()
+        .filter_map(|(pos, ty)| match pos {
+            Pos::TextRange(range) if !ty.contains_dynamic() => Some(range.clone().into()),
+            _ => None,
+        })
+        .collect();
+    let sema = Semantic::new(db);
+    let mut total = 0;
+    let mut covered = 0;
+    sema.for_each_function(file_id, |def| {
+        let def_fb = def.in_function_body(&sema, def);
+        for (clause_id, _) in def_fb.clauses() {
+            let body = def_fb.body(clause_id);
+            for (expr_id, expr) in body.exprs.iter() {
+                if !matches!(expr, Expr::Var(_)) {
+                    continue;
+                }
+                if let Some(range) = def_fb.range_for_expr(clause_id, expr_id) {
+                    total += 1;
+                    if typed.contains(&range) {
+                        covered += 1;
+                    }
+                }
+            }
+        }
+    });
+    if total == 0 {
+        return None;
+    }
+    Some(covered as f32 / total as f32)
+}
+
+/// The eqWAlizer type of each argument of `function`, taken from its
+/// spec. For an overloaded spec, each argument type is the union of
+/// the argument types of its clauses. For a function without a spec,
+/// fall back to the types eqWAlizer inferred for the parameters of its
+/// first clause, if they are all known.
+pub fn function_arg_types(
+    db: &RootDatabase,
+    project_id: ProjectId,
+    module: &ModuleName,
+    function: &NameArity,
+) -> Option<Vec<Type>> {
+    let id = eqwalizer::Id {
+        name: function.name().as_str().into(),
+        arity: function.arity(),
+    };
+    if let Ok(stub) = db.transitive_stub(project_id, module.clone()) {
+        if let Some(spec) = stub.specs.get(&id) {
+            return Some(spec.ty.arg_tys.clone());
+        }
+        if let Some(spec) = stub.overloaded_specs.get(&id) {
+            return Some(
+                (0..function.arity() as usize)
+                    .map(|i| {
+                        let mut tys: Vec<Type> = vec![];
+                        for ty in spec.tys.iter().filter_map(|ty| ty.arg_tys.get(i)) {
+                            if !tys.contains(ty) {
+                                tys.push(ty.clone());
+                            }
+                        }
+                        match tys.len() {
+                            1 => tys.remove(0),
+                            _ => Type::UnionType(UnionType { tys }),
+                        }
+                    })
+                    .collect(),
+            );
+        }
+    }
+    let file_id = db.module_file(project_id, module.clone())?;
+    let def_map = db.def_map(file_id);
+    let fun_decl = def_map
+        .get_function(function)?
+        .source(db)
+        .into_iter()
+        .next()?;
+    let clause = match fun_decl.clause()? {
+        ast::FunctionOrMacroClause::FunctionClause(clause) => clause,
+        ast::FunctionOrMacroClause::MacroCallExpr(_) => return None,
+    };
+    clause
+        .args()?
+        .args()
+        .map(|arg| {
+            let range = FileRange {
+                file_id,
+                range: arg.syntax().text_range(),
+            };
+            let type_info = db.type_at_position(range)?;
+            if type_info.1 == range {
+                Some(type_info.0.clone())
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
 fn is_eqwalizer_enabled(
     db: &dyn EqwalizerDatabase,
     file_id: FileId,
@@ -376,13 +529,134 @@ pub fn type_references(
     links.into_iter().collect()
 }
 
+/// Render an eqWAlizer type as Erlang type syntax, suitable for
+/// inserting into a `-spec` in `module`. Types from `module` are not
+/// qualified, and eqWAlizer-only types without a source form are
+/// widened to the closest type that has one, such as `dynamic(T)` to
+/// `dynamic()`.
+pub fn type_to_spec_source(ty: &Type, module: &ModuleName) -> String {
+    let render_all = |tys: &[Type]| {
+        tys.iter()
+            .map(|ty| type_to_spec_source(ty, module))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    match ty {
+        Type::AtomLitType(lit) => AtomName::from(lit.atom.clone()).to_string(),
+        Type::AnyType => "term()".to_string(),
+        Type::AnyFunType => "fun()".to_string(),
+        Type::AnyTupleType => "tuple()".to_string(),
+        Type::AtomType => "atom()".to_string(),
+        Type::NilType => "[]".to_string(),
+        Type::BinaryType => "binary()".to_string(),
+        Type::NoneType => "none()".to_string(),
+        Type::DynamicType | Type::BoundedDynamicType(_) => "dynamic()".to_string(),
+        Type::PidType => "pid()".to_string(),
+        Type::PortType => "port()".to_string(),
+        Type::ReferenceType => "reference()".to_string(),
+        Type::NumberType => "number()".to_string(),
+        Type::VarType(var) => {
+            if var.name.starts_with(|c: char| c.is_uppercase() || c == '_') {
+                var.name.to_string()
+            } else {
+                format!("T{}", var.n)
+            }
+        }
+        Type::FunType(fun) => format!(
+            "fun(({}) -> {})",
+            render_all(&fun.arg_tys),
+            type_to_spec_source(&fun.res_ty, module)
+        ),
+        Type::AnyArityFunType(fun) => {
+            format!("fun((...) -> {})", type_to_spec_source(&fun.res_ty, module))
+        }
+        Type::TupleType(tuple) => format!("{{{}}}", render_all(&tuple.arg_tys)),
+        Type::ListType(list) => format!("[{}]", type_to_spec_source(&list.t, module)),
+        Type::UnionType(union) => union
+            .tys
+            .iter()
+            .map(|ty| type_to_spec_source(ty, module))
+            .collect::<Vec<_>>()
+            .join(" | "),
+        Type::RemoteType(eqwalizer::types::RemoteType { id, arg_tys })
+        | Type::OpaqueType(eqwalizer::types::OpaqueType { id, arg_tys }) => {
+            let is_builtin_alias = id.module == "erlang"
+                && Type::builtin_type_alias(id.name.as_unquoted_str()).is_some();
+            if &id.module == module || is_builtin_alias {
+                format!("{}({})", id.name, render_all(arg_tys))
+            } else {
+                format!("{}:{}({})", id.module, id.name, render_all(arg_tys))
+            }
+        }
+        Type::RecordType(rec) => format!("#{}{{}}", rec.name),
+        Type::RefinedRecordType(rec) => {
+            let mut fields: Vec<_> = rec.fields.iter().collect();
+            fields.sort_by(|(a, _), (b, _)| a.cmp(b));
+            format!(
+                "#{}{{{}}}",
+                rec.rec_type.name,
+                fields
+                    .into_iter()
+                    .map(|(name, ty)| format!("{} :: {}", name, type_to_spec_source(ty, module)))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        }
+        Type::MapType(map) => {
+            let mut props: Vec<_> = map.props.iter().collect();
+            props.sort_by(|(a, _), (b, _)| a.cmp(b));
+            let mut entries: Vec<String> = props
+                .into_iter()
+                .map(|(key, prop)| {
+                    format!(
+                        "{} {} {}",
+                        key_to_spec_source(key),
+                        if prop.req { ":=" } else { "=>" },
+                        type_to_spec_source(&prop.tp, module)
+                    )
+                })
+                .collect();
+            if *map.k_type != Type::NoneType {
+                entries.push(format!(
+                    "{} => {}",
+                    type_to_spec_source(&map.k_type, module),
+                    type_to_spec_source(&map.v_type, module)
+                ));
+            }
+            format!("#{{{}}}", entries.join(", "))
+        }
+    }
+}
+
+fn key_to_spec_source(key: &eqwalizer::types::Key) -> String {
+    match key {
+        eqwalizer::types::Key::AtomKey(atom) => AtomName::from(atom.name.clone()).to_string(),
+        eqwalizer::types::Key::TupleKey(tuple) => format!(
+            "{{{}}}",
+            tuple
+                .keys
+                .iter()
+                .map(key_to_spec_source)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
 impl EqwalizerErlASTStorage for crate::RootDatabase {
     fn get_erl_ast_bytes(
         &self,
         project_id: ProjectId,
         module: ModuleName,
     ) -> Result<Arc<Vec<u8>>, Error> {
-        if let Some(file_id) = self.module_index(project_id).file_for_module(&module) {
+        // Reading from `ast_dir` is not tracked by salsa, it is only
+        // meant for replaying a set of dumped ASTs.
+        if let Some(ast_dir) = &self.eqwalizer_config().ast_dir {
+            if let Ok(bytes) = std::fs::read(ast_dir.join(format!("{module}.etf"))) {
+                return Ok(Arc::new(bytes));
+            }
+        }
+        if let Some(file_id) = self.module_file(project_id, module.clone()) {
             let result = self.module_ast(
                 file_id,
                 elp_erlang_service::Format::OffsetEtf,
@@ -398,6 +672,18 @@ impl EqwalizerErlASTStorage for crate::RootDatabase {
             Err(Error::ModuleNotFound(module.to_string()))
         }
     }
+
+    fn preprocess_predicates(&self) -> bool {
+        self.eqwalizer_config().preprocess_predicates != ConfigValue::Off
+    }
+
+    fn atom_interner(&self) -> &AtomNameInterner {
+        &self.atom_interner
+    }
+
+    fn stub_bytes_cache(&self) -> &StubBytesCache {
+        &self.stub_bytes_cache
+    }
 }
 
 impl elp_eqwalizer::DbApi for crate::RootDatabase {
@@ -413,6 +699,12 @@ impl elp_eqwalizer::DbApi for crate::RootDatabase {
         }
     }
 
+    fn eqwalizing_result(&self, module: &ModuleName, diagnostics: &EqwalizerDiagnostics) {
+        if let Some(reporter) = self.eqwalizer_progress_reporter.lock().as_mut() {
+            reporter.module_diagnostics(module, diagnostics);
+        }
+    }
+
     fn set_module_ipc_handle(&self, module: &ModuleName, handle: Option<Arc<Mutex<IpcHandle>>>) {
         match handle {
             Some(handle) => {
@@ -462,10 +754,40 @@ fn is_ignore_or_fixme_atom(expr: ast::Expr) -> bool {
 
 #[cfg(test)]
 mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
     use elp_base_db::fixture::WithFixture;
+    use elp_base_db::SourceDatabaseExt;
+    use elp_eqwalizer::ast::db::beam_path;
+    use elp_eqwalizer::ast::preprocess::PreprocessRule;
+    use elp_eqwalizer::ast::stub::ModuleStub;
+    use elp_eqwalizer::ast::stub::STUB_FORMAT_VERSION;
+    use elp_eqwalizer::ast::stub::STUB_MAGIC;
+    use elp_eqwalizer::Eqwalizer;
+    use elp_eqwalizer::EqwalizerConfig;
+    use elp_eqwalizer::EqwalizerError;
+    use elp_eqwalizer::EqwalizerExe;
+    use elp_eqwalizer::ModuleFilter;
+    use elp_syntax::TextSize;
+    use elp_types_db::eqwalizer::expr::Expr;
+    use elp_types_db::eqwalizer::form::ExternalForm;
+    use elp_types_db::eqwalizer::pat::Pat;
+    use elp_types_db::eqwalizer::transformer;
+    use elp_types_db::eqwalizer::transformer::Transformer;
+    use elp_types_db::eqwalizer::types::AtomKey;
+    use elp_types_db::eqwalizer::types::FunType;
+    use elp_types_db::eqwalizer::types::Key;
+    use elp_types_db::eqwalizer::types::ListType;
+    use elp_types_db::eqwalizer::types::MapType;
+    use elp_types_db::eqwalizer::types::Prop;
+    use elp_types_db::eqwalizer::types::RemoteType;
+    use elp_types_db::eqwalizer::types::TupleType;
+    use elp_types_db::eqwalizer::types::VarType;
+    use fxhash::FxHashMap;
 
     use super::*;
-    use crate::RootDatabase;
 
     #[test]
     fn test_has_eqwalizer_module_marker() {
@@ -542,4 +864,1455 @@ mod tests {
 
         assert!(db.has_eqwalizer_ignore_marker(file_id));
     }
+
+    #[test]
+    fn test_eqwalizer_config_builder() {
+        let config = EqwalizerConfig::builder()
+            .fault_tolerance(true)
+            .occurrence_typing(false)
+            .report_bad_maps(true)
+            .build();
+        assert_eq!(
+            config,
+            EqwalizerConfig {
+                fault_tolerance: ConfigValue::On,
+                occurrence_typing: ConfigValue::Off,
+                report_bad_maps: ConfigValue::On,
+                ..EqwalizerConfig::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_eqwalizer_config_inherit() {
+        let project = EqwalizerConfig {
+            fault_tolerance: ConfigValue::On,
+            report_bad_maps: ConfigValue::On,
+            ..EqwalizerConfig::default()
+        };
+        let module = EqwalizerConfig {
+            report_bad_maps: ConfigValue::Off,
+            ..EqwalizerConfig::default()
+        };
+        let config = module.inherit(&project);
+        assert_eq!(config.fault_tolerance, ConfigValue::On);
+        assert_eq!(config.report_bad_maps, ConfigValue::Off);
+        assert_eq!(config.occurrence_typing, ConfigValue::Inherit);
+    }
+
+    #[test]
+    fn test_eqwalizer_config_inherit_settings() {
+        let project = EqwalizerConfig::builder()
+            .skip_failed_modules(true)
+            .related_information(true)
+            .ast_dir(PathBuf::from("/tmp/asts"))
+            .ipc_trace(PathBuf::from("/tmp/trace"))
+            .ipc_read_timeout(Duration::from_secs(10))
+            .ipc_write_timeout(Duration::from_secs(20))
+            .build();
+        let module = EqwalizerConfig::builder()
+            .related_information(false)
+            .ipc_write_timeout(Duration::from_secs(5))
+            .build();
+        let config = module.inherit(&project);
+        assert_eq!(config.skip_failed_modules, ConfigValue::On);
+        assert_eq!(config.related_information, ConfigValue::Off);
+        assert_eq!(config.preprocess_predicates, ConfigValue::Inherit);
+        assert_eq!(config.ast_dir, Some(PathBuf::from("/tmp/asts")));
+        assert_eq!(config.ipc_trace, Some(PathBuf::from("/tmp/trace")));
+        assert_eq!(config.ipc_read_timeout, Some(Duration::from_secs(10)));
+        assert_eq!(config.ipc_write_timeout, Some(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn test_config_value_json() {
+        for (value, json) in [
+            (ConfigValue::On, "true"),
+            (ConfigValue::Off, "false"),
+            (ConfigValue::Inherit, "null"),
+        ] {
+            assert_eq!(serde_json::to_string(&value).unwrap(), json);
+            assert_eq!(serde_json::from_str::<ConfigValue>(json).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn test_eqwalizer_config_cmd_env() {
+        let config = EqwalizerConfig {
+            occurrence_typing: ConfigValue::Off,
+            report_bad_maps: ConfigValue::On,
+            ..EqwalizerConfig::default()
+        };
+        assert_eq!(
+            config.cmd_env(),
+            vec![
+                ("EQWALIZER_EQWATER", "false"),
+                ("EQWALIZER_REPORT_BAD_MAPS", "true"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_no_ast_does_not_abort_the_session() {
+        if otp_supported_by_eqwalizer() {
+            let (db, file_ids, _) = RootDatabase::with_many_files(
+                r#"
+//- eqwalizer
+//- /src/no_ast.erl
+-module(no_ast).
+-export([foo/0]).
+foo() -> ok;
+foon() -> ok.
+//- /src/checkable.erl
+-module(checkable).
+-export([bar/0]).
+-spec bar() -> atom().
+bar() -> 1.
+"#,
+            );
+            let project_id = db.file_project_id(file_ids[0]).unwrap();
+            let diagnostics = db.eqwalizer_diagnostics_by_project(project_id, file_ids);
+            match &*diagnostics {
+                EqwalizerDiagnostics::Diagnostics { errors, no_ast, .. } => {
+                    assert_eq!(no_ast, &vec![ModuleName::new("no_ast")]);
+                    let checkable = errors
+                        .get(&ModuleName::new("checkable"))
+                        .expect("checkable should have been type-checked");
+                    assert_eq!(checkable.len(), 1);
+                }
+                other => panic!("expected diagnostics, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_module_filter_skips_unlisted_modules() {
+        if otp_supported_by_eqwalizer() {
+            let (mut db, file_ids, _) = RootDatabase::with_many_files(
+                r#"
+//- eqwalizer
+//- /src/app_checked.erl
+-module(app_checked).
+-export([foo/0]).
+-spec foo() -> atom().
+foo() -> 1.
+//- /src/app_skipped.erl
+-module(app_skipped).
+-export([bar/0]).
+-spec bar() -> atom().
+bar() -> 1.
+"#,
+            );
+            db.set_eqwalizer_config(Arc::new(EqwalizerConfig {
+                module_filter: Some(ModuleFilter {
+                    allow: vec!["*_checked".to_string()],
+                    deny: vec![],
+                }),
+                ..EqwalizerConfig::default_test()
+            }));
+            let project_id = db.file_project_id(file_ids[0]).unwrap();
+            let diagnostics = db.eqwalizer_diagnostics_by_project(project_id, file_ids);
+            match &*diagnostics {
+                EqwalizerDiagnostics::Diagnostics { errors, .. } => {
+                    let mut modules: Vec<&ModuleName> = errors.keys().collect();
+                    modules.sort();
+                    assert_eq!(modules, vec![&ModuleName::new("app_checked")]);
+                }
+                other => panic!("expected diagnostics, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_ipc_read_timeout() {
+        // A process which never writes anything
+        let mut cmd = std::process::Command::new("sleep");
+        cmd.arg("10");
+        let timeout = std::time::Duration::from_millis(100);
+        let mut handle = IpcHandle::from_command_with_timeouts(&mut cmd, timeout, timeout).unwrap();
+        let start = std::time::Instant::now();
+        let err = handle.receive().unwrap_err();
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+        assert!(format!("{:?}", err).contains("receiving message"));
+    }
+
+    #[test]
+    fn test_ipc_process_exit_code() {
+        let mut cmd = std::process::Command::new("sh");
+        cmd.args(["-c", "exit 3"]);
+        let mut handle = IpcHandle::from_command(&mut cmd).unwrap();
+        assert!(handle.receive().is_err());
+        assert_eq!(handle.exit_code(), Some(Some(3)));
+    }
+
+    #[test]
+    fn test_ipc_process_killed_mid_session() {
+        // A process which exits as soon as it receives a message
+        let mut cmd = std::process::Command::new("sh");
+        cmd.args(["-c", "read line; exit 7"]);
+        let mut handle = IpcHandle::from_command(&mut cmd).unwrap();
+        assert!(handle.is_alive());
+        handle
+            .send(&elp_eqwalizer::ipc::MsgToEqWAlizer::ELPExitingModule)
+            .unwrap();
+        assert!(handle.receive().is_err());
+        assert_eq!(handle.exit_code(), Some(Some(7)));
+        assert!(!handle.is_alive());
+    }
+
+    #[test]
+    fn test_compressed_ast_bytes_round_trip() {
+        let mut text = "-module(big).\n-export_type([".to_string();
+        let types: Vec<String> = (0..2000).map(|i| format!("t{i}/0")).collect();
+        text.push_str(&types.join(", "));
+        text.push_str("]).\n");
+        for i in 0..2000 {
+            text.push_str(&format!(
+                "-type t{i}() :: #{{key{i} => {{atom(), [binary()]}}}} | t{}().\n",
+                (i + 1) % 2000
+            ));
+        }
+        let (db, file_id) = RootDatabase::with_single_file(&text);
+        let project_id = db.file_project_id(file_id).unwrap();
+        let bytes = db
+            .transitive_stub_bytes(project_id, ModuleName::new("big"))
+            .unwrap();
+        assert!(bytes.len() > elp_eqwalizer::ipc::COMPRESSION_THRESHOLD);
+        let compressed = elp_eqwalizer::ipc::compress_bytes(&bytes).unwrap();
+        assert!(compressed.len() < bytes.len() / 2);
+        assert_eq!(
+            elp_eqwalizer::ipc::decompress_bytes(&compressed).unwrap(),
+            *bytes
+        );
+    }
+
+    #[test]
+    fn test_stubs_share_atom_names() {
+        let atoms = [
+            "a_long_atom_name_shared_by_every_module",
+            "another_long_atom_name_in_every_module",
+        ];
+        let modules = 50;
+        let mut fixture = String::new();
+        for i in 0..modules {
+            fixture.push_str(&format!(
+                "//- /src/mod{i}.erl\n-module(mod{i}).\n-export_type([t/0]).\n-type t() :: '{}' | '{}'.\n",
+                atoms[0], atoms[1]
+            ));
+        }
+        let (db, file_ids, _) = RootDatabase::with_many_files(&fixture);
+        let project_id = db.file_project_id(file_ids[0]).unwrap();
+        for i in 0..modules {
+            db.converted_stub(project_id, ModuleName::new(&format!("mod{i}")))
+                .unwrap();
+        }
+        // Every module after the first shares the names of the first
+        let shared: usize = atoms.iter().map(|atom| atom.len()).sum();
+        assert!(db.atom_interner().saved_bytes() >= (modules - 1) * shared);
+    }
+
+    #[test]
+    fn test_uncompressed_ast_bytes_reply_is_unchanged() {
+        let reply = |compressed| {
+            serde_json::to_string(&elp_eqwalizer::ipc::MsgToEqWAlizer::GetAstBytesReply {
+                ast_bytes_len: 10,
+                compressed,
+            })
+            .unwrap()
+        };
+        assert_eq!(
+            reply(false),
+            r#"{"tag":"GetAstBytesReply","content":{"ast_bytes_len":10}}"#
+        );
+        assert_eq!(
+            reply(true),
+            r#"{"tag":"GetAstBytesReply","content":{"ast_bytes_len":10,"compressed":true}}"#
+        );
+    }
+
+    #[test]
+    fn test_eqwalizer_error_display() {
+        let exited = EqwalizerError::ProcessExited {
+            code: Some(1),
+            message: "reading from eqWAlizer: broken pipe".to_string(),
+        };
+        assert_eq!(
+            exited.to_string(),
+            "eqWAlizer process exited with code 1: reading from eqWAlizer: broken pipe"
+        );
+        let protocol = EqwalizerError::Protocol("unexpected message".to_string());
+        assert_eq!(protocol.to_string(), "unexpected message");
+    }
+
+    #[test]
+    fn test_ipc_trace() {
+        if otp_supported_by_eqwalizer() {
+            let (mut db, file_ids, _) = RootDatabase::with_many_files(
+                r#"
+//- eqwalizer
+//- /src/traced.erl
+-module(traced).
+-export([foo/0]).
+-spec foo() -> atom().
+foo() -> ok.
+"#,
+            );
+            let trace = tempfile::NamedTempFile::new().unwrap();
+            db.set_eqwalizer_config(Arc::new(EqwalizerConfig {
+                ipc_trace: Some(trace.path().to_path_buf()),
+                ..EqwalizerConfig::default_test()
+            }));
+            let project_id = db.file_project_id(file_ids[0]).unwrap();
+            db.eqwalizer_diagnostics_by_project(project_id, file_ids);
+
+            let trace = std::fs::read_to_string(trace.path()).unwrap();
+            let lines: Vec<&str> = trace.lines().collect();
+            let tag = |line: &str| {
+                let start = line.find(r#""tag":""#)? + r#""tag":""#.len();
+                let len = line[start..].find('"')?;
+                Some(line[start..start + len].to_string())
+            };
+            let tags: Vec<Option<String>> = lines.iter().map(|line| tag(line)).collect();
+            assert_eq!(tags[0].as_deref(), Some("EnteringModule"));
+            assert_eq!(tags[1].as_deref(), Some("ELPEnteringModule"));
+            assert_eq!(tags[tags.len() - 2].as_deref(), Some("ELPExitingModule"));
+            assert_eq!(tags[tags.len() - 1].as_deref(), Some("Done"));
+            // AST payloads are summarized by their length
+            for (i, line) in lines.iter().enumerate() {
+                if tags[i].as_deref() == Some("GetAstBytesReply") && !line.contains(":0}") {
+                    assert!(lines[i + 1].starts_with("-> <") && lines[i + 1].ends_with(" bytes>"));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_ipc_trace_unopenable_file() {
+        if otp_supported_by_eqwalizer() {
+            let (mut db, file_ids, _) = RootDatabase::with_many_files(
+                r#"
+//- eqwalizer
+//- /src/traced.erl
+-module(traced).
+-export([foo/0]).
+-spec foo() -> atom().
+foo() -> 1.
+"#,
+            );
+            let dir = tempfile::tempdir().unwrap();
+            db.set_eqwalizer_config(Arc::new(EqwalizerConfig {
+                ipc_trace: Some(dir.path().join("missing").join("trace.log")),
+                ..EqwalizerConfig::default_test()
+            }));
+            let project_id = db.file_project_id(file_ids[0]).unwrap();
+            let diagnostics = db.eqwalizer_diagnostics_by_project(project_id, file_ids);
+            match &*diagnostics {
+                EqwalizerDiagnostics::Diagnostics { errors, .. } => {
+                    let traced = errors.get(&ModuleName::new("traced"));
+                    assert_eq!(traced.map(|diags| diags.len()), Some(1));
+                }
+                other => panic!("expected diagnostics, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_moved_ast_reuses_results() {
+        if otp_supported_by_eqwalizer() {
+            let text = r#"-module(cached).
+-export([foo/0]).
+-spec foo() -> atom().
+foo() -> 1.
+"#;
+            let (mut db, file_ids, _) = RootDatabase::with_many_files(&format!(
+                "//- eqwalizer\n//- /src/cached.erl\n{text}"
+            ));
+            let file_id = file_ids[0];
+            let trace = tempfile::NamedTempFile::new().unwrap();
+            db.set_eqwalizer_config(Arc::new(EqwalizerConfig {
+                ipc_trace: Some(trace.path().to_path_buf()),
+                ..EqwalizerConfig::default_test()
+            }));
+            let project_id = db.file_project_id(file_id).unwrap();
+            let trace_len = || std::fs::read_to_string(trace.path()).unwrap().len();
+            let before = db.eqwalizer_diagnostics_by_project(project_id, vec![file_id]);
+            let first_run = trace_len();
+            assert!(first_run > 0);
+
+            // Text after the last form does not move any code, so the AST
+            // is unchanged and eqWAlizer is not run again
+            db.set_file_text(file_id, Arc::from(format!("{text}\n\n")));
+            let after = db.eqwalizer_diagnostics_by_project(project_id, vec![file_id]);
+            assert_eq!(trace_len(), first_run);
+            assert_eq!(before, after);
+
+            // A comment and a line break inside the function move its
+            // body, so the cached diagnostic moves with it
+            let moved = text.replace("foo() -> 1.", "% the answer\nfoo() ->\n    1.");
+            db.set_file_text(file_id, Arc::from(moved.clone()));
+            let after = db.eqwalizer_diagnostics_by_project(project_id, vec![file_id]);
+            assert_eq!(trace_len(), first_run);
+            let ranges = |diagnostics: &EqwalizerDiagnostics| match diagnostics {
+                EqwalizerDiagnostics::Diagnostics { errors, .. } => errors
+                    .get(&ModuleName::new("cached"))
+                    .map(|diags| diags.iter().map(|d| d.range).collect::<Vec<_>>()),
+                other => panic!("expected diagnostics, got {:?}", other),
+            };
+            let start = TextSize::from(moved.find("1.").unwrap() as u32);
+            assert_eq!(
+                ranges(&after),
+                Some(vec![TextRange::at(start, TextSize::from(1))])
+            );
+
+            db.set_file_text(file_id, Arc::from(text.replace("1.", "ok.")));
+            db.eqwalizer_diagnostics_by_project(project_id, vec![file_id]);
+            assert!(trace_len() > first_run);
+        }
+    }
+
+    #[test]
+    fn test_module_config_override() {
+        let (mut db, file_ids, _) = RootDatabase::with_many_files(
+            r#"
+//- eqwalizer
+//- /src/legacy.erl
+-module(legacy).
+-export([foo/0]).
+-spec foo() -> atom().
+foo() -> 1.
+//- /src/strict.erl
+-module(strict).
+-export([bar/0]).
+-spec bar() -> atom().
+bar() -> ok.
+"#,
+        );
+        let trace = tempfile::NamedTempFile::new().unwrap();
+        let config = EqwalizerConfig {
+            ipc_trace: Some(trace.path().to_path_buf()),
+            ..EqwalizerConfig::default_test()
+        };
+        db.set_eqwalizer_config(Arc::new(config.clone()));
+        let legacy = ModuleName::new("legacy");
+        let strict = ModuleName::new("strict");
+        let project_id = db.file_project_id(file_ids[0]).unwrap();
+        if otp_supported_by_eqwalizer() {
+            db.eqwalizer_diagnostics_by_project(project_id, file_ids.clone());
+        }
+        let first_run = std::fs::read_to_string(trace.path()).unwrap().len();
+
+        let legacy_config = Arc::new(EqwalizerConfig {
+            fault_tolerance: ConfigValue::On,
+            ..config.clone()
+        });
+        let mut overrides = FxHashMap::default();
+        overrides.insert(legacy.clone(), legacy_config.clone());
+        db.set_eqwalizer_module_configs(Arc::new(overrides));
+        assert_eq!(db.eqwalizer_module_config(legacy), legacy_config);
+        assert_eq!(*db.eqwalizer_module_config(strict), config);
+
+        // Only the module whose config changed is checked again
+        if otp_supported_by_eqwalizer() {
+            db.eqwalizer_diagnostics_by_project(project_id, file_ids);
+            let trace = std::fs::read_to_string(trace.path()).unwrap();
+            let second_run = &trace[first_run..];
+            assert!(second_run.contains("legacy"));
+            assert!(!second_run.contains("strict"));
+        }
+    }
+
+    #[test]
+    fn test_skip_failed_modules() {
+        if otp_supported_by_eqwalizer() {
+            let (mut db, file_ids, _) = RootDatabase::with_many_files(
+                r#"
+//- eqwalizer
+//- /src/broken.erl
+-module(broken).
+-export([foo/0]).
+foo() -> ok.
+//- /src/good.erl
+-module(good).
+-export([bar/0]).
+-spec bar() -> atom().
+bar() -> 1.
+"#,
+            );
+            // An AST which cannot be decoded makes eqWAlizer fail on the module
+            let ast_dir = tempfile::tempdir().unwrap();
+            std::fs::write(ast_dir.path().join("broken.etf"), b"not an AST").unwrap();
+            let config = EqwalizerConfig {
+                ast_dir: Some(ast_dir.path().to_path_buf()),
+                ..EqwalizerConfig::default_test()
+            };
+            let project_id = db.file_project_id(file_ids[0]).unwrap();
+
+            db.set_eqwalizer_config(Arc::new(config.clone()));
+            let strict = db.eqwalizer_diagnostics_by_project(project_id, file_ids.clone());
+            assert!(matches!(*strict, EqwalizerDiagnostics::Error(_)));
+
+            db.set_eqwalizer_config(Arc::new(EqwalizerConfig {
+                skip_failed_modules: ConfigValue::On,
+                ..config
+            }));
+            let lenient = db.eqwalizer_diagnostics_by_project(project_id, file_ids);
+            match &*lenient {
+                EqwalizerDiagnostics::Diagnostics { errors, .. } => {
+                    assert!(errors.contains_key(&ModuleName::new("good")));
+                    assert!(!errors.contains_key(&ModuleName::new("broken")));
+                }
+                other => panic!("expected diagnostics, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_cached_modules_are_reported() {
+        struct Recorder(Arc<Mutex<Vec<String>>>);
+        impl crate::EqwalizerProgressReporter for Recorder {
+            fn start_module(&mut self, module: ModuleName) {
+                self.0.lock().push(format!("start {module}"));
+            }
+            fn done_module(&mut self, module: &ModuleName) {
+                self.0.lock().push(format!("done {module}"));
+            }
+        }
+
+        if otp_supported_by_eqwalizer() {
+            let text = r#"-module(cached).
+-export([foo/0]).
+-spec foo() -> atom().
+foo() -> 1.
+"#;
+            let (mut db, file_ids, _) = RootDatabase::with_many_files(&format!(
+                "//- eqwalizer\n//- /src/cached.erl\n{text}"
+            ));
+            let project_id = db.file_project_id(file_ids[0]).unwrap();
+            db.eqwalizer_diagnostics_by_project(project_id, file_ids.clone());
+
+            // The second run reuses the result, and still reports progress
+            db.set_file_text(file_ids[0], Arc::from(format!("{text}\n\n")));
+            let events = Arc::new(Mutex::new(vec![]));
+            db.set_eqwalizer_progress_reporter(Some(Box::new(Recorder(events.clone()))));
+            db.eqwalizer_diagnostics_by_project(project_id, file_ids);
+            assert_eq!(
+                *events.lock(),
+                vec!["start cached".to_string(), "done cached".to_string()]
+            );
+        }
+    }
+
+    #[test]
+    fn test_module_diagnostics_are_streamed() {
+        struct Recorder(Arc<Mutex<Vec<String>>>);
+        impl crate::EqwalizerProgressReporter for Recorder {
+            fn start_module(&mut self, _module: ModuleName) {}
+            fn done_module(&mut self, module: &ModuleName) {
+                self.0.lock().push(format!("done {module}"));
+            }
+            fn module_diagnostics(
+                &mut self,
+                module: &ModuleName,
+                _diagnostics: &EqwalizerDiagnostics,
+            ) {
+                self.0.lock().push(format!("diagnostics {module}"));
+            }
+        }
+
+        if otp_supported_by_eqwalizer() {
+            let (db, file_ids, _) = RootDatabase::with_many_files(
+                r#"
+//- eqwalizer
+//- /src/first.erl
+-module(first).
+-export([foo/0]).
+-spec foo() -> atom().
+foo() -> 1.
+//- /src/second.erl
+-module(second).
+-export([bar/0]).
+-spec bar() -> atom().
+bar() -> ok.
+"#,
+            );
+            let events = Arc::new(Mutex::new(vec![]));
+            db.set_eqwalizer_progress_reporter(Some(Box::new(Recorder(events.clone()))));
+            let project_id = db.file_project_id(file_ids[0]).unwrap();
+            let diagnostics = db.eqwalizer_diagnostics_by_project(project_id, file_ids);
+
+            // Each module is delivered once, as soon as it is done, and
+            // before the next module is checked
+            let events = events.lock().clone();
+            let modules: Vec<&str> = events
+                .iter()
+                .filter_map(|event| event.strip_prefix("done "))
+                .collect();
+            let mut sorted = modules.clone();
+            sorted.sort();
+            assert_eq!(sorted, vec!["first", "second"]);
+            let expected: Vec<String> = modules
+                .iter()
+                .flat_map(|module| [format!("done {module}"), format!("diagnostics {module}")])
+                .collect();
+            assert_eq!(events, expected);
+
+            // The combined result is still returned
+            match &*diagnostics {
+                EqwalizerDiagnostics::Diagnostics { errors, .. } => {
+                    assert!(errors.contains_key(&ModuleName::new("first")));
+                }
+                other => panic!("expected diagnostics, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_typecheck_function() {
+        if otp_supported_by_eqwalizer() {
+            let (db, file_ids, _) = RootDatabase::with_many_files(
+                r#"
+//- eqwalizer
+//- /src/first.erl
+-module(first).
+-export([foo/0, bar/0]).
+-spec foo() -> atom().
+foo() -> 1.
+-spec bar() -> atom().
+bar() -> 2.
+"#,
+            );
+            let file_id = file_ids[0];
+            let project_id = db.file_project_id(file_id).unwrap();
+            let first = ModuleName::new("first");
+            let bar = eqwalizer::Id {
+                name: "bar".into(),
+                arity: 0,
+            };
+            let text = db.file_text(file_id);
+            let bar_start = text.find("bar() -> 2").unwrap() as u32;
+            // Once the whole module is checked, its cached result is
+            // restricted to the function
+            db.eqwalizer().typecheck(&db, project_id, vec![&first]);
+            match db
+                .eqwalizer()
+                .typecheck_function(&db, project_id, &first, &bar)
+            {
+                Some(EqwalizerDiagnostics::Diagnostics { errors, .. }) => {
+                    assert_eq!(errors[&first].len(), 1);
+                    assert!(u32::from(errors[&first][0].range.start()) >= bar_start);
+                }
+                other => panic!("expected diagnostics, got {:?}", other),
+            }
+            let missing = eqwalizer::Id {
+                name: "missing".into(),
+                arity: 0,
+            };
+            assert!(db
+                .eqwalizer()
+                .typecheck_function(&db, project_id, &first, &missing)
+                .is_none());
+        }
+    }
+
+    #[test]
+    fn test_typecheck_function_scopes_the_check() {
+        if otp_supported_by_eqwalizer() {
+            let dir = tempfile::tempdir().unwrap();
+            let log = dir.path().join("log");
+            // Stands in for eqWAlizer, and logs the messages ELP sends
+            // when entering the module
+            let script = format!(
+                r#"
+shift
+for module in "$@"; do
+    echo '{{"tag":"EnteringModule","content":{{"module":"'$module'"}}}}'
+    read line
+    echo "$line" >> {log}
+    read line
+    echo "$line" >> {log}
+    echo '{{"tag":"Done","content":{{"diagnostics":{{"'$module'":[]}},"type_info":{{}}}}}}'
+    read line
+done
+echo '{{"tag":"Done","content":{{"diagnostics":{{}},"type_info":{{}}}}}}'
+"#,
+                log = log.display(),
+            );
+            let exe = EqwalizerExe::from_command(
+                PathBuf::from("sh"),
+                vec!["-c".into(), script.into(), "sh".into()],
+            );
+            let eqwalizer = Eqwalizer::with_exe(exe);
+            let (db, file_ids, _) = RootDatabase::with_many_files(
+                r#"
+//- eqwalizer
+//- /src/first.erl
+-module(first).
+-export([foo/0]).
+foo() -> ok.
+"#,
+            );
+            let project_id = db.file_project_id(file_ids[0]).unwrap();
+            let first = ModuleName::new("first");
+            let foo = eqwalizer::Id {
+                name: "foo".into(),
+                arity: 0,
+            };
+            match eqwalizer.typecheck_function(&db, project_id, &first, &foo) {
+                Some(EqwalizerDiagnostics::Diagnostics { errors, .. }) => {
+                    assert!(errors[&first].is_empty());
+                }
+                other => panic!("expected diagnostics, got {:?}", other),
+            }
+            let log = fs::read_to_string(&log).unwrap();
+            let lines: Vec<&str> = log.lines().collect();
+            assert_eq!(lines.len(), 2, "{log}");
+            assert!(lines[0].contains(r#""tag":"ELPEnteringModule""#));
+            assert_eq!(
+                lines[1],
+                r#"{"tag":"ELPScopeFunction","content":{"name":"foo","arity":0}}"#
+            );
+        }
+    }
+
+    #[test]
+    fn test_typecheck_recovers_deleted_exe() {
+        if otp_supported_by_eqwalizer() {
+            // A dedicated executable, so that the one shared by other
+            // tests is left alone
+            let Some(exe) = EqwalizerExe::new() else {
+                return;
+            };
+            let Some(path) = exe.temp_path().map(|path| path.to_path_buf()) else {
+                return;
+            };
+            let eqwalizer = Eqwalizer::with_exe(exe);
+            fs::remove_file(&path).unwrap();
+            let (db, file_ids, _) = RootDatabase::with_many_files(
+                r#"
+//- eqwalizer
+//- /src/first.erl
+-module(first).
+-export([foo/0]).
+-spec foo() -> atom().
+foo() -> 1.
+"#,
+            );
+            let project_id = db.file_project_id(file_ids[0]).unwrap();
+            let first = ModuleName::new("first");
+            match eqwalizer.typecheck(&db, project_id, vec![&first]) {
+                EqwalizerDiagnostics::Diagnostics { errors, .. } => {
+                    assert_eq!(errors[&first].len(), 1);
+                }
+                other => panic!("expected diagnostics, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_typecheck_restarts_exited_process() {
+        if otp_supported_by_eqwalizer() {
+            let dir = tempfile::tempdir().unwrap();
+            let log = dir.path().join("log");
+            let marker = dir.path().join("exited");
+            // Stands in for eqWAlizer, and exits while checking `second`
+            // the first time it is run
+            let script = format!(
+                r#"
+shift
+echo "$*" >> {log}
+for module in "$@"; do
+    echo '{{"tag":"EnteringModule","content":{{"module":"'$module'"}}}}'
+    read line
+    if [ "$module" = second ] && [ ! -e {marker} ]; then
+        touch {marker}
+        exit 3
+    fi
+    echo '{{"tag":"Done","content":{{"diagnostics":{{"'$module'":[]}},"type_info":{{}}}}}}'
+    read line
+done
+echo '{{"tag":"Done","content":{{"diagnostics":{{}},"type_info":{{}}}}}}'
+"#,
+                log = log.display(),
+                marker = marker.display(),
+            );
+            let exe = EqwalizerExe::from_command(
+                PathBuf::from("sh"),
+                vec!["-c".into(), script.into(), "sh".into()],
+            );
+            let eqwalizer = Eqwalizer::with_exe(exe);
+            let (db, file_ids, _) = RootDatabase::with_many_files(
+                r#"
+//- eqwalizer
+//- /src/first.erl
+-module(first).
+//- /src/second.erl
+-module(second).
+"#,
+            );
+            let project_id = db.file_project_id(file_ids[0]).unwrap();
+            let first = ModuleName::new("first");
+            let second = ModuleName::new("second");
+            match eqwalizer.typecheck(&db, project_id, vec![&first, &second]) {
+                EqwalizerDiagnostics::Diagnostics { errors, .. } => {
+                    let mut modules: Vec<&ModuleName> = errors.keys().collect();
+                    modules.sort();
+                    assert_eq!(modules, vec![&first, &second]);
+                }
+                other => panic!("expected diagnostics, got {:?}", other),
+            }
+            // Only the module the process did not finish is checked again
+            assert_eq!(fs::read_to_string(&log).unwrap(), "first second\nsecond\n");
+        }
+    }
+
+    #[test]
+    fn test_module_configs_share_one_process() {
+        if otp_supported_by_eqwalizer() {
+            let dir = tempfile::tempdir().unwrap();
+            let log = dir.path().join("log");
+            // Stands in for eqWAlizer, and logs its arguments and the
+            // message ELP sends when entering each module
+            let script = format!(
+                r#"
+shift
+echo "$*" >> {log}
+for module in "$@"; do
+    echo '{{"tag":"EnteringModule","content":{{"module":"'$module'"}}}}'
+    read line
+    echo "$line" >> {log}
+    echo '{{"tag":"Done","content":{{"diagnostics":{{"'$module'":[]}},"type_info":{{}}}}}}'
+    read line
+done
+echo '{{"tag":"Done","content":{{"diagnostics":{{}},"type_info":{{}}}}}}'
+"#,
+                log = log.display(),
+            );
+            let exe = EqwalizerExe::from_command(
+                PathBuf::from("sh"),
+                vec!["-c".into(), script.into(), "sh".into()],
+            );
+            let eqwalizer = Eqwalizer::with_exe(exe);
+            let (mut db, file_ids, _) = RootDatabase::with_many_files(
+                r#"
+//- eqwalizer
+//- /src/legacy.erl
+-module(legacy).
+//- /src/strict.erl
+-module(strict).
+"#,
+            );
+            let legacy = ModuleName::new("legacy");
+            let strict = ModuleName::new("strict");
+            let mut overrides = FxHashMap::default();
+            overrides.insert(
+                legacy.clone(),
+                Arc::new(EqwalizerConfig {
+                    fault_tolerance: ConfigValue::On,
+                    ..EqwalizerConfig::default()
+                }),
+            );
+            db.set_eqwalizer_module_configs(Arc::new(overrides));
+            let project_id = db.file_project_id(file_ids[0]).unwrap();
+            eqwalizer.typecheck(&db, project_id, vec![&legacy, &strict]);
+
+            // A single process checks both modules, and each gets its
+            // own flags when it is entered
+            let log = fs::read_to_string(&log).unwrap();
+            let lines: Vec<&str> = log.lines().collect();
+            assert_eq!(lines.len(), 3, "{log}");
+            assert_eq!(lines[0], "legacy strict");
+            assert!(lines[1].contains(r#""tag":"ELPEnteringModule""#));
+            assert!(lines[1].contains(r#""fault_tolerance":true"#));
+            assert!(lines[2].contains(r#""fault_tolerance":null"#));
+        }
+    }
+
+    #[test]
+    fn test_elixir_module_beam_path() {
+        let (db, file_ids, _) = RootDatabase::with_many_files(
+            r#"
+//- /my_app/lib/foo_bar.ex app:my_app
+-module('Elixir.FooBar').
+"#,
+        );
+        let project_id = db.file_project_id(file_ids[0]).unwrap();
+        let module = db
+            .module_index(project_id)
+            .module_for_file(file_ids[0])
+            .unwrap()
+            .clone();
+        assert_eq!(module.to_string(), "'Elixir.FooBar'");
+        let path = beam_path(&db, project_id, module).unwrap();
+        assert_eq!(path.file_name(), Some("Elixir.FooBar.beam"));
+    }
+
+    #[test]
+    fn test_eqwalizer_coverage() {
+        if otp_supported_by_eqwalizer() {
+            let (db, file_ids, _) = RootDatabase::with_many_files(
+                r#"
+//- eqwalizer
+//- /src/typed.erl
+-module(typed).
+-export([area/1]).
+-spec area({square, number()} | {rect, number(), number()}) -> number().
+area(Shape) ->
+    case Shape of
+        {square, Side} -> Side * Side;
+        {rect, W, H} -> W * H
+    end.
+//- /src/untyped.erl
+-module(untyped).
+-export([area/1]).
+-spec area(dynamic()) -> number().
+area(Shape) ->
+    case Shape of
+        {square, Side} -> Side * Side;
+        {rect, W, H} -> W * H
+    end.
+"#,
+            );
+            let project_id = db.file_project_id(file_ids[0]).unwrap();
+            let typed = eqwalizer_coverage(&db, project_id, &ModuleName::new("typed")).unwrap();
+            let untyped = eqwalizer_coverage(&db, project_id, &ModuleName::new("untyped")).unwrap();
+            assert!(typed > 0.99, "typed coverage: {}", typed);
+            assert!(untyped < typed, "untyped coverage: {}", untyped);
+        }
+    }
+
+    #[test]
+    fn test_modules_without_eqwalizer_results() {
+        if otp_supported_by_eqwalizer() {
+            let (db, file_ids, _) = RootDatabase::with_many_files(
+                r#"
+//- eqwalizer
+//- /src/no_ast.erl
+-module(no_ast).
+-export([foo/0]).
+foo() -> ok;
+foon() -> ok.
+//- /src/checkable.erl
+-module(checkable).
+-export([bar/0]).
+-spec bar() -> atom().
+bar() -> 1.
+"#,
+            );
+            let project_id = db.file_project_id(file_ids[0]).unwrap();
+            assert_eq!(
+                modules_without_eqwalizer_results(&db, project_id),
+                vec![ModuleName::new("no_ast")]
+            );
+        }
+    }
+
+    #[test]
+    fn test_function_arg_types() {
+        let (db, file_id) = RootDatabase::with_single_file(
+            r#"
+-module(test).
+-export([f/2, g/1]).
+-spec f(atom(), [integer()]) -> ok.
+f(_A, _L) -> ok.
+-spec g(a) -> ok; (b) -> ok.
+g(_) -> ok.
+"#,
+        );
+        let project_id = db.file_project_id(file_id).unwrap();
+        let arg_types = |name: &str, arity: u32| {
+            function_arg_types(
+                &db,
+                project_id,
+                &ModuleName::new("test"),
+                &NameArity::new(hir::Name::from_erlang_service(name), arity),
+            )
+            .map(|tys| tys.iter().map(|ty| ty.to_string()).collect::<Vec<_>>())
+        };
+        assert_eq!(
+            arg_types("f", 2),
+            Some(vec!["atom()".to_string(), "[number()]".to_string()])
+        );
+        assert_eq!(arg_types("g", 1), Some(vec!["a | b".to_string()]));
+    }
+
+    #[test]
+    fn test_type_to_spec_source() {
+        let module = ModuleName::new("test");
+        let check = |ty: Type, expected: &str| {
+            assert_eq!(type_to_spec_source(&ty, &module), expected);
+        };
+        check(
+            Type::UnionType(UnionType {
+                tys: vec![
+                    Type::atom_lit_type("ok".into()),
+                    Type::atom_lit_type("Not Found".into()),
+                ],
+            }),
+            "ok | 'Not Found'",
+        );
+        check(
+            Type::TupleType(TupleType {
+                arg_tys: vec![Type::atom_lit_type("error".into()), Type::BinaryType],
+            }),
+            "{error, binary()}",
+        );
+        check(
+            Type::FunType(FunType {
+                forall: vec![1],
+                arg_tys: vec![Type::VarType(VarType {
+                    n: 1,
+                    name: "".into(),
+                })],
+                res_ty: Box::new(Type::ListType(ListType {
+                    t: Box::new(Type::VarType(VarType {
+                        n: 1,
+                        name: "".into(),
+                    })),
+                })),
+            }),
+            "fun((T1) -> [T1])",
+        );
+        check(
+            Type::MapType(MapType {
+                props: [(
+                    Key::AtomKey(AtomKey { name: "id".into() }),
+                    Prop {
+                        req: true,
+                        tp: Type::NumberType,
+                    },
+                )]
+                .into_iter()
+                .collect(),
+                k_type: Box::new(Type::AtomType),
+                v_type: Box::new(Type::AnyType),
+            }),
+            "#{id := number(), atom() => term()}",
+        );
+        check(
+            Type::RemoteType(RemoteType {
+                id: RemoteId {
+                    module: "other".into(),
+                    name: "t".into(),
+                    arity: 1,
+                },
+                arg_tys: vec![Type::RemoteType(RemoteType {
+                    id: RemoteId {
+                        module: "test".into(),
+                        name: "local".into(),
+                        arity: 0,
+                    },
+                    arg_tys: vec![],
+                })],
+            }),
+            "other:t(local())",
+        );
+        check(Type::string_type(), "string()");
+    }
+
+    #[test]
+    fn test_type_contains_dynamic() {
+        let map_type = |v_type: Type| {
+            Type::MapType(MapType {
+                props: [(
+                    Key::AtomKey(AtomKey { name: "id".into() }),
+                    Prop {
+                        req: true,
+                        tp: Type::TupleType(TupleType {
+                            arg_tys: vec![Type::NumberType, v_type],
+                        }),
+                    },
+                )]
+                .into_iter()
+                .collect(),
+                k_type: Box::new(Type::AtomType),
+                v_type: Box::new(Type::UnionType(UnionType {
+                    tys: vec![Type::BinaryType, Type::NilType],
+                })),
+            })
+        };
+        assert!(map_type(Type::DynamicType).contains_dynamic());
+        assert!(!map_type(Type::AtomType).contains_dynamic());
+        assert!(Type::FunType(FunType {
+            forall: vec![],
+            arg_tys: vec![Type::AtomType],
+            res_ty: Box::new(Type::UnionType(UnionType {
+                tys: vec![Type::NilType, Type::cls_exn_stack_type_dynamic()],
+            })),
+        })
+        .contains_dynamic());
+        assert!(!Type::cls_exn_stack_type().contains_dynamic());
+    }
+
+    #[test]
+    fn test_preprocessing_log() {
+        let (db, file_id) = RootDatabase::with_single_file(
+            r#"
+-module(test).
+-export([split/1, split_lambda/1]).
+split(L) -> lists:partition(fun erlang:is_atom/1, L).
+split_lambda(L) -> lists:partition(fun(X) -> erlang:is_integer(X) end, L).
+"#,
+        );
+        let project_id = db.file_project_id(file_id).unwrap();
+        let bytes = db
+            .get_erl_ast_bytes(project_id, ModuleName::new("test"))
+            .unwrap();
+        let text = db.file_text(file_id);
+        let log: Vec<(PreprocessRule, &str)> = elp_eqwalizer::ast::preprocessing_log(&bytes)
+            .unwrap()
+            .into_iter()
+            .map(|event| match event.location {
+                Pos::TextRange(range) => (
+                    event.rule,
+                    &text[range.start_byte as usize..range.end_byte as usize],
+                ),
+                Pos::LineAndColumn(_) => panic!("expected a text range"),
+            })
+            .collect();
+        assert_eq!(
+            log,
+            vec![
+                (
+                    PreprocessRule::ListsPartitionEtaExpansion,
+                    "lists:partition(fun erlang:is_atom/1, L)"
+                ),
+                (
+                    PreprocessRule::ListsPartitionLambdaToGuard,
+                    "lists:partition(fun(X) -> erlang:is_integer(X) end, L)"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_preprocess_predicates_disabled() {
+        let (mut db, file_id) = RootDatabase::with_single_file(
+            r#"
+-module(test).
+-export([split/1]).
+split(L) -> lists:partition(fun erlang:is_atom/1, L).
+"#,
+        );
+        let project_id = db.file_project_id(file_id).unwrap();
+        let partition_fun = |db: &RootDatabase| {
+            let ast = db
+                .converted_ast(project_id, ModuleName::new("test"))
+                .unwrap();
+            ast.iter()
+                .find_map(|form| match form {
+                    ExternalForm::FunDecl(decl) => match &decl.clauses[0].body.exprs[..] {
+                        [Expr::RemoteCall(call)] => Some(call.args[0].clone()),
+                        _ => None,
+                    },
+                    _ => None,
+                })
+                .unwrap()
+        };
+        assert!(matches!(partition_fun(&db), Expr::Lambda(_)));
+
+        db.set_eqwalizer_config(Arc::new(EqwalizerConfig {
+            preprocess_predicates: ConfigValue::Off,
+            ..EqwalizerConfig::default()
+        }));
+        assert!(matches!(partition_fun(&db), Expr::RemoteFun(_)));
+    }
+
+    #[test]
+    fn test_preprocess_maybe() {
+        let (db, file_id) = RootDatabase::with_single_file(
+            r#"
+-module(test).
+-feature(maybe_expr, enable).
+-export([first/0, last/0]).
+first() -> maybe {ok, X} ?= g(), X end.
+last() -> maybe X ?= g() end.
+g() -> {ok, 1}.
+"#,
+        );
+        let project_id = db.file_project_id(file_id).unwrap();
+        let ast = db
+            .converted_ast(project_id, ModuleName::new("test"))
+            .unwrap();
+        let body = |name: &str| {
+            ast.iter()
+                .find_map(|form| match form {
+                    ExternalForm::FunDecl(decl) if decl.id.name == name => {
+                        Some(decl.clauses[0].body.exprs.clone())
+                    }
+                    _ => None,
+                })
+                .unwrap()
+        };
+        let var_name = |expr: &Expr| match expr {
+            Expr::Var(var) => var.n.to_string(),
+            other => panic!("expected a variable, got {:?}", other),
+        };
+        let pat_var_name = |pat: &Pat| match pat {
+            Pat::PatVar(var) => var.n.to_string(),
+            other => panic!("expected a variable, got {:?}", other),
+        };
+        let case = |name: &str| match &body(name)[..] {
+            [Expr::Case(case)] => case.clone(),
+            other => panic!("expected a case, got {:?}", other),
+        };
+
+        // `maybe {ok, X} ?= g(), X end` becomes
+        // `case g() of {ok, X} -> X; V -> V end`
+        let first = case("first");
+        assert!(matches!(*first.expr, Expr::LocalCall(_)));
+        match &first.clauses[..] {
+            [matched, unmatched] => {
+                assert!(matches!(matched.pats[..], [Pat::PatTuple(_)]));
+                assert_eq!(var_name(&matched.body.exprs[0]), "X");
+                assert_eq!(
+                    pat_var_name(&unmatched.pats[0]),
+                    var_name(&unmatched.body.exprs[0])
+                );
+            }
+            other => panic!("expected two clauses, got {:?}", other),
+        }
+
+        // A trailing `X ?= g()` evaluates to the value of `g()` either way
+        let last = case("last");
+        match &last.clauses[..] {
+            [matched, unmatched] => {
+                match &matched.pats[..] {
+                    [Pat::PatMatch(pat_match)] => assert_eq!(
+                        pat_var_name(&pat_match.pat),
+                        var_name(&matched.body.exprs[0])
+                    ),
+                    other => panic!("expected a match pattern, got {:?}", other),
+                }
+                assert!(matches!(unmatched.pats[..], [Pat::PatVar(_)]));
+            }
+            other => panic!("expected two clauses, got {:?}", other),
+        }
+
+        let bytes = db
+            .get_erl_ast_bytes(project_id, ModuleName::new("test"))
+            .unwrap();
+        let rules: Vec<PreprocessRule> = elp_eqwalizer::ast::preprocessing_log(&bytes)
+            .unwrap()
+            .into_iter()
+            .map(|event| event.rule)
+            .collect();
+        assert_eq!(
+            rules,
+            vec![PreprocessRule::MaybeToCase, PreprocessRule::MaybeToCase]
+        );
+    }
+
+    #[test]
+    fn test_module_index_built_once_for_stubs() {
+        let (mut db, file_ids, _) = RootDatabase::with_many_files(
+            r#"
+//- /src/a.erl
+-module(a).
+-export_type([t/0]).
+-type t() :: b:t().
+//- /src/b.erl
+-module(b).
+-export_type([t/0]).
+-type t() :: c:t() | atom().
+//- /src/c.erl
+-module(c).
+-export_type([t/0]).
+-type t() :: integer().
+"#,
+        );
+        let project_id = db.file_project_id(file_ids[0]).unwrap();
+        let module_index = db.module_index(project_id);
+        for (module, file_id) in ["a", "b", "c"].iter().zip(&file_ids) {
+            let module = ModuleName::new(module);
+            assert!(db.transitive_stub(project_id, module.clone()).is_ok());
+            assert_eq!(db.module_file(project_id, module), Some(*file_id));
+        }
+        assert!(Arc::ptr_eq(&module_index, &db.module_index(project_id)));
+
+        // Editing a module does not rebuild the index
+        db.set_file_text(
+            file_ids[2],
+            Arc::from("-module(c).\n-export_type([t/0]).\n-type t() :: float().\n"),
+        );
+        assert!(db.transitive_stub(project_id, ModuleName::new("a")).is_ok());
+        assert!(Arc::ptr_eq(&module_index, &db.module_index(project_id)));
+    }
+
+    #[test]
+    fn test_stub_bytes_reused_after_header_edit() {
+        let (mut db, file_ids, _) = RootDatabase::with_many_files(
+            r#"
+//- /src/a.erl
+-module(a).
+-include("a.hrl").
+-export_type([t/0, u/0]).
+-type t() :: b:t().
+//- /src/a.hrl
+-type u() :: ok.
+//- /src/b.erl
+-module(b).
+-export_type([t/0]).
+-type t() :: c:t() | atom().
+//- /src/c.erl
+-module(c).
+-export_type([t/0]).
+-type t() :: integer().
+"#,
+        );
+        let project_id = db.file_project_id(file_ids[0]).unwrap();
+        let stub_bytes = |db: &RootDatabase| {
+            ["a", "b", "c"].map(|module| {
+                db.transitive_stub_bytes(project_id, ModuleName::new(module))
+                    .unwrap()
+            })
+        };
+        let before = stub_bytes(&db);
+
+        // Only the stub of the module including the header changes
+        db.set_file_text(file_ids[1], Arc::from("-type u() :: error.\n"));
+        let edited = stub_bytes(&db);
+        assert!(!Arc::ptr_eq(&before[0], &edited[0]));
+        assert!(Arc::ptr_eq(&before[1], &edited[1]));
+        assert!(Arc::ptr_eq(&before[2], &edited[2]));
+
+        // Undoing the edit reuses the bytes serialized before it
+        db.set_file_text(file_ids[1], Arc::from("-type u() :: ok.\n"));
+        let undone = stub_bytes(&db);
+        assert!(Arc::ptr_eq(&before[0], &undone[0]));
+    }
+
+    #[test]
+    fn test_stub_versioned_bytes_round_trip() {
+        let (db, file_id) = RootDatabase::with_single_file(
+            r#"
+-module(test).
+-export([f/1]).
+-export_type([t/0, o/0]).
+-record(r, {a :: atom(), b = 1 :: integer()}).
+-type t() :: #r{} | [t()].
+-opaque o() :: {o, binary()}.
+-spec f(t()) -> o().
+f(_) -> {o, <<>>}.
+-callback cb(t()) -> ok.
+"#,
+        );
+        let project_id = db.file_project_id(file_id).unwrap();
+        let stub = db
+            .transitive_stub(project_id, ModuleName::new("test"))
+            .unwrap();
+        let bytes = stub.to_versioned_bytes();
+        assert!(bytes.starts_with(STUB_MAGIC));
+        assert_eq!(
+            ModuleStub::from_versioned_bytes(&bytes),
+            Ok((*stub).clone())
+        );
+    }
+
+    #[test]
+    fn test_stub_versioned_bytes_reject_other_version() {
+        let stub = ModuleStub::new(ModuleName::new("test"));
+        let mut bytes = stub.to_versioned_bytes();
+        let version = STUB_MAGIC.len()..STUB_MAGIC.len() + 4;
+        bytes[version].copy_from_slice(&(STUB_FORMAT_VERSION + 1).to_be_bytes());
+        assert_eq!(
+            ModuleStub::from_versioned_bytes(&bytes),
+            Err(Error::DecodeError(format!(
+                "module stub has format version {}, expected {}",
+                STUB_FORMAT_VERSION + 1,
+                STUB_FORMAT_VERSION
+            )))
+        );
+
+        // Bytes sent to eqWAlizer have no header
+        assert!(ModuleStub::from_versioned_bytes(&stub.to_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_stub_from_ast_dir() {
+        let (mut db, file_id) = RootDatabase::with_single_file(
+            r#"
+-module(test).
+-export_type([t/0]).
+-type t() :: atom().
+"#,
+        );
+        let project_id = db.file_project_id(file_id).unwrap();
+        let dumped = db.module_ast(
+            file_id,
+            elp_erlang_service::Format::OffsetEtf,
+            vec![],
+            vec![],
+        );
+        assert!(dumped.is_ok());
+        let ast_dir = tempfile::tempdir().unwrap();
+        std::fs::write(ast_dir.path().join("test.etf"), &*dumped.ast).unwrap();
+
+        // The module no longer defines `t/0`, but the dumped AST does.
+        db.set_file_text(
+            file_id,
+            Arc::from("-module(test).\n-export_type([u/0]).\n-type u() :: atom().\n"),
+        );
+        let has_t = |db: &RootDatabase| {
+            db.transitive_stub(project_id, ModuleName::new("test"))
+                .unwrap()
+                .types
+                .keys()
+                .any(|id| id.name == "t")
+        };
+        assert!(!has_t(&db));
+
+        db.set_eqwalizer_config(Arc::new(EqwalizerConfig {
+            ast_dir: Some(ast_dir.path().to_path_buf()),
+            ..EqwalizerConfig::default()
+        }));
+        assert!(has_t(&db));
+    }
+
+    #[test]
+    fn test_identity_transformer_preserves_exprs() {
+        struct Identity;
+        impl Transformer<()> for Identity {}
+
+        let (db, file_id) = RootDatabase::with_single_file(
+            r#"
+-module(test).
+-export([f/2]).
+-record(r, {a, b = 1}).
+f(X, #r{a = A} = R) when is_integer(X), X > 0 ->
+    Y = [E * 2 || E <- X, E =/= A],
+    M = #{k => Y, <<"b">> => <<X:8, "s">>},
+    case catch g(M#{k := R#r.b}) of
+        {ok, V} -> fun(Z) -> {Z, V} end;
+        _ -> receive {msg, W} -> W after 10 -> -X end
+    end,
+    try lists:map(fun g/1, Y) of
+        L -> begin [H | _] = L, H end
+    catch
+        error:Reason:St -> {Reason, St}
+    after
+        ok
+    end.
+g(X) -> X.
+"#,
+        );
+        let project_id = db.file_project_id(file_id).unwrap();
+        let ast = db
+            .converted_ast(project_id, ModuleName::new("test"))
+            .unwrap();
+        let exprs: Vec<&Expr> = ast
+            .iter()
+            .filter_map(|form| match form {
+                ExternalForm::FunDecl(decl) => Some(decl),
+                _ => None,
+            })
+            .flat_map(|decl| decl.clauses.iter().flat_map(|clause| &clause.body.exprs))
+            .collect();
+        assert!(!exprs.is_empty());
+        for expr in exprs {
+            assert_eq!(
+                transformer::walk_expr(&mut Identity, expr.clone()),
+                Ok(expr.clone())
+            );
+        }
+    }
 }
@@ -16,6 +16,7 @@ use std::sync::Arc;
 use elp_base_db::limit_logged_string;
 use elp_base_db::salsa;
 use elp_base_db::AbsPathBuf;
+use elp_base_db::AtomNameInterner;
 use elp_base_db::FileId;
 use elp_base_db::FileLoader;
 use elp_base_db::FileLoaderDelegate;
@@ -25,10 +26,12 @@ use elp_base_db::ModuleName;
 use elp_base_db::ProjectId;
 use elp_base_db::SourceDatabase;
 use elp_base_db::Upcast;
+use elp_eqwalizer::ast::db::StubBytesCache;
 use elp_eqwalizer::ipc::IpcHandle;
 use elp_eqwalizer::EqwalizerConfig;
 use elp_eqwalizer::EqwalizerDiagnosticsDatabase;
 use elp_eqwalizer::Mode;
+use elp_eqwalizer::TimingSink;
 use elp_syntax::AstNode;
 use elp_syntax::SyntaxKind;
 use elp_syntax::SyntaxToken;
@@ -99,6 +102,9 @@ type EqwalizerProgressReporterBox =
 pub trait EqwalizerProgressReporter: Send + Sync + RefUnwindSafe {
     fn start_module(&mut self, module: ModuleName);
     fn done_module(&mut self, module: &ModuleName);
+    /// Receives the diagnostics of each module as soon as it has been
+    /// checked, so that they can be shown before the batch completes.
+    fn module_diagnostics(&mut self, _module: &ModuleName, _diagnostics: &EqwalizerDiagnostics) {}
 }
 
 #[salsa::database(
@@ -122,6 +128,11 @@ pub struct RootDatabase {
     eqwalizer: Eqwalizer,
     eqwalizer_progress_reporter: EqwalizerProgressReporterBox,
     ipc_handles: Arc<AssertUnwindSafe<RwLock<FxHashMap<ModuleName, Arc<Mutex<IpcHandle>>>>>>,
+    stub_bytes_cache: Arc<AssertUnwindSafe<StubBytesCache>>,
+    atom_interner: Arc<AtomNameInterner>,
+    /// The salsa events, while a test is logging them.
+    #[cfg(test)]
+    events: Arc<AssertUnwindSafe<Mutex<Option<Vec<salsa::Event>>>>>,
 }
 impl Default for RootDatabase {
     fn default() -> Self {
@@ -131,8 +142,13 @@ impl Default for RootDatabase {
             eqwalizer: Eqwalizer::default(),
             eqwalizer_progress_reporter: EqwalizerProgressReporterBox::default(),
             ipc_handles: Arc::default(),
+            stub_bytes_cache: Arc::default(),
+            atom_interner: Arc::default(),
+            #[cfg(test)]
+            events: Arc::default(),
         };
         db.set_eqwalizer_config(Arc::new(EqwalizerConfig::default()));
+        db.set_eqwalizer_module_configs(Arc::default());
         db
     }
 }
@@ -167,7 +183,14 @@ impl fmt::Debug for RootDatabase {
     }
 }
 
-impl salsa::Database for RootDatabase {}
+impl salsa::Database for RootDatabase {
+    #[cfg(test)]
+    fn salsa_event(&self, event: salsa::Event) {
+        if let Some(events) = &mut *self.events.lock() {
+            events.push(event);
+        }
+    }
+}
 
 impl salsa::ParallelDatabase for RootDatabase {
     fn snapshot(&self) -> salsa::Snapshot<RootDatabase> {
@@ -177,11 +200,32 @@ impl salsa::ParallelDatabase for RootDatabase {
             eqwalizer: self.eqwalizer.clone(),
             eqwalizer_progress_reporter: self.eqwalizer_progress_reporter.clone(),
             ipc_handles: self.ipc_handles.clone(),
+            stub_bytes_cache: self.stub_bytes_cache.clone(),
+            atom_interner: self.atom_interner.clone(),
+            #[cfg(test)]
+            events: self.events.clone(),
         })
     }
 }
 
 impl RootDatabase {
+    /// The queries executed, rather than reused, while running `f`.
+    #[cfg(test)]
+    fn log_executed(&self, f: impl FnOnce()) -> Vec<String> {
+        *self.events.lock() = Some(Vec::new());
+        f();
+        let events = self.events.lock().take().unwrap_or_default();
+        events
+            .into_iter()
+            .filter_map(|event| match event.kind {
+                salsa::EventKind::WillExecute { database_key } => {
+                    Some(format!("{:?}", database_key.debug(self)))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
     pub fn request_cancellation(&mut self) {
         let _p = tracing::info_span!("RootDatabase::request_cancellation").entered();
         self.salsa_runtime_mut()
@@ -244,6 +288,14 @@ impl RootDatabase {
         self.eqwalizer.mode = mode
     }
 
+    pub fn disable_eqwalizer_exe(&mut self) {
+        self.eqwalizer.disable_exe()
+    }
+
+    pub fn set_eqwalizer_timing_sink(&mut self, sink: Option<TimingSink>) {
+        self.eqwalizer.set_timing_sink(sink)
+    }
+
     pub fn resolved_includes(&self, file_id: FileId) -> Option<Includes> {
         let source_file = self.parse(file_id).tree();
         // Context for T171541590
@@ -403,7 +455,7 @@ impl TypedSemantic for RootDatabase {
                 log::error!(
                     "EqWAlizer failed for {}: {}",
                     module,
-                    limit_logged_string(err)
+                    limit_logged_string(&err.to_string())
                 );
                 Some(vec![])
             }
@@ -420,6 +472,9 @@ impl TypedSemantic for RootDatabase {
 #[cfg(test)]
 mod tests {
     use elp_base_db::fixture::WithFixture;
+    use elp_base_db::AppType;
+    use elp_base_db::FileSource;
+    use elp_base_db::ProjectId;
     use elp_base_db::SourceDatabase;
     use text_edit::TextRange;
 
@@ -448,4 +503,119 @@ mod tests {
         let position = fixture.position();
         debug_assert_eq!(db.clamp_offset(position.file_id, 2000.into()), 15.into())
     }
+
+    #[test]
+    fn only_applicable_files_are_processed() {
+        let fixture = r#"
+//- /main/src/listed.erl app:main applicable:true
+-module(listed).
+//- /main/src/unlisted.erl app:main
+-module(unlisted).
+//- /foo/src/foo.erl app:foo
+-module(foo).
+"#;
+        let (db, fixture) = RootDatabase::with_fixture(fixture);
+        let (listed, unlisted, foo) = (fixture.files[0], fixture.files[1], fixture.files[2]);
+        assert!(db.is_applicable_file(listed));
+        assert!(!db.is_applicable_file(unlisted));
+        assert!(db.is_applicable_file(foo));
+    }
+
+    #[test]
+    fn extra_src_dir_modules_are_indexed() {
+        let fixture = r#"
+//- /my_app/src/main.erl app:my_app
+-module(main).
+//- /my_app/test/main_SUITE.erl app:my_app extra:test
+-module(main_SUITE).
+//- /my_app/test/test_helper.erl app:my_app extra:test
+-module(test_helper).
+"#;
+        let (db, fixture) = RootDatabase::with_fixture(fixture);
+        let (main, suite, helper) = (fixture.files[0], fixture.files[1], fixture.files[2]);
+
+        let module_index = db.module_index(ProjectId(0));
+        assert_eq!(module_index.file_for_module("test_helper"), Some(helper));
+        assert_eq!(module_index.file_for_module("main_SUITE"), Some(suite));
+        assert_eq!(
+            module_index.file_source_for_file(main),
+            Some(FileSource::Src)
+        );
+        assert_eq!(
+            module_index.file_source_for_file(suite),
+            Some(FileSource::Extra)
+        );
+        assert_eq!(
+            module_index.file_source_for_file(helper),
+            Some(FileSource::Extra)
+        );
+        assert_eq!(db.is_test_suite_or_test_helper(helper), Some(true));
+        assert_eq!(db.is_test_suite_or_test_helper(main), Some(false));
+    }
+
+    #[test]
+    fn module_app_types() {
+        let fixture = r#"
+//- /src/main.erl app:main
+-module(main).
+//- /foo/src/foo.erl app:foo
+-module(foo).
+//- /opt/lib/stdlib-3.17/src/lists.erl otp_app:/opt/lib/stdlib-3.17
+-module(lists).
+"#;
+        let (db, fixture) = RootDatabase::with_fixture(fixture);
+        let (main, foo, lists) = (fixture.files[0], fixture.files[1], fixture.files[2]);
+
+        let app_types = db.module_app_types(ProjectId(0));
+        assert_eq!(app_types.len(), 2);
+        assert_eq!(app_types.get(&main), Some(&(AppType::App, false)));
+        assert_eq!(app_types.get(&foo), Some(&(AppType::App, false)));
+        assert_eq!(app_types.get(&lists), None);
+
+        let otp_app_types = db.module_app_types(ProjectId(1));
+        assert_eq!(otp_app_types.get(&lists), Some(&(AppType::Otp, true)));
+    }
+
+    #[test]
+    fn module_app_types_replace_per_file_queries() {
+        let text = r#"
+//- /src/a.erl app:main
+-module(a).
+//- /src/b.erl app:main
+-module(b).
+//- /src/c.erl app:main
+-module(c).
+//- /foo/src/foo.erl app:foo
+-module(foo).
+"#;
+        let (db, fixture) = RootDatabase::with_fixture(text);
+        let count = |executed: &[String], query: &str| {
+            executed
+                .iter()
+                .filter(|key| key.starts_with(&format!("{query}(")))
+                .count()
+        };
+
+        // Filtering modules as parse-all does, one lookup per file
+        let executed = db.log_executed(|| {
+            for file_id in &fixture.files {
+                db.file_app_type(*file_id);
+                db.is_otp(*file_id);
+            }
+        });
+        assert_eq!(count(&executed, "file_app_type"), 4);
+        assert_eq!(count(&executed, "is_otp"), 4);
+
+        // With the map, neither runs for any file
+        let (db, fixture) = RootDatabase::with_fixture(text);
+        let executed = db.log_executed(|| {
+            let app_types = db.module_app_types(ProjectId(0));
+            for file_id in &fixture.files {
+                app_types.get(file_id);
+            }
+        });
+        assert_eq!(count(&executed, "module_app_types"), 1);
+        assert_eq!(count(&executed, "file_app_type"), 0);
+        assert_eq!(count(&executed, "is_otp"), 0);
+    }
 }
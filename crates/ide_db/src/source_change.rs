@@ -81,6 +81,17 @@ impl SourceChange {
         self.source_file_edits.get(&file_id)
     }
 
+    /// Returns `text`, the current contents of `file_id`, with the
+    /// edits for that file applied. The database is not modified, so
+    /// this can be used to preview a change, e.g. in a diff UI.
+    pub fn preview(&self, file_id: FileId, text: &str) -> String {
+        let mut result = text.to_string();
+        if let Some(edit) = self.source_file_edits.get(&file_id) {
+            edit.apply(&mut result);
+        }
+        result
+    }
+
     pub fn merge(mut self, other: SourceChange) -> SourceChange {
         self.extend(other.source_file_edits);
         self.extend(other.file_system_edits);
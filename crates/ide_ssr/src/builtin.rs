@@ -0,0 +1,52 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Curated sets of rules, shipped with ELP, which can be applied by
+//! name rather than written out by the user.
+
+use hir::db::DefDatabase;
+
+use crate::SsrError;
+use crate::SsrRule;
+
+/// A named set of rules, to be added together to a `MatchFinder`.
+#[derive(Debug)]
+pub struct BuiltinRuleSet {
+    pub name: &'static str,
+    pub description: &'static str,
+    rules: &'static [&'static str],
+}
+
+pub static BUILTIN_RULE_SETS: &[BuiltinRuleSet] = &[BuiltinRuleSet {
+    name: "deprecated-random",
+    description: "Replace calls to the deprecated `random` module with their `rand` equivalents",
+    rules: &[
+        "ssr: random:uniform() ==>> rand:uniform().",
+        "ssr: random:uniform(_@N) ==>> rand:uniform(_@N).",
+        // `rand` needs an explicit algorithm when seeding
+        "ssr: random:seed() ==>> rand:seed(exsss).",
+        "ssr: random:seed(_@Seed) ==>> rand:seed(exsss, _@Seed).",
+        "ssr: random:seed(_@A, _@B, _@C) ==>> rand:seed(exsss, {_@A, _@B, _@C}).",
+    ],
+}];
+
+/// Look up a built-in rule set by name.
+pub fn builtin_rule_set(name: &str) -> Option<&'static BuiltinRuleSet> {
+    BUILTIN_RULE_SETS.iter().find(|set| set.name == name)
+}
+
+impl BuiltinRuleSet {
+    /// Parse the rules of the set, in the order they are declared.
+    pub fn rules(&self, db: &dyn DefDatabase) -> Result<Vec<SsrRule>, SsrError> {
+        self.rules
+            .iter()
+            .map(|rule| SsrRule::parse_str(db, rule))
+            .collect()
+    }
+}
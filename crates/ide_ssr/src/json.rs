@@ -0,0 +1,63 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! A serializable form of SSR matches, so that tools outside ELP, such
+//! as editors or CI jobs, can post-process codemod candidates.
+
+use std::collections::BTreeMap;
+
+use elp_ide_db::elp_base_db::path_for_file;
+use elp_ide_db::elp_base_db::SourceDatabaseExt;
+use hir::Semantic;
+use serde::Serialize;
+
+use crate::Match;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MatchJson {
+    /// The path of the file containing the match, if it is known.
+    pub path: Option<String>,
+    /// Byte offsets of the matched code in the file.
+    pub start: u32,
+    pub end: u32,
+    pub text: String,
+    /// The code bound to each placeholder, keyed by placeholder name.
+    pub placeholders: BTreeMap<String, String>,
+    /// The index of the rule which matched, in the order the rules
+    /// were added to the `MatchFinder`.
+    pub rule_index: usize,
+}
+
+impl MatchJson {
+    pub fn new(sema: &Semantic, m: &Match) -> MatchJson {
+        let file_text = sema.db.file_text(m.range.file_id);
+        let placeholders = m
+            .placeholders_by_var
+            .iter()
+            .filter_map(|(var, sub_ids)| {
+                // Every occurrence of a placeholder binds the same code
+                let value = sub_ids
+                    .iter()
+                    .find_map(|sub_id| m.placeholder_values.get(sub_id))?;
+                Some((
+                    var.as_string(sema.db.upcast()),
+                    file_text[value.range()].to_string(),
+                ))
+            })
+            .collect();
+        MatchJson {
+            path: path_for_file(sema.db.upcast(), m.range.file_id).map(|path| path.to_string()),
+            start: m.range().start().into(),
+            end: m.range().end().into(),
+            text: file_text[m.range()].to_string(),
+            placeholders,
+            rule_index: m.rule_index,
+        }
+    }
+}
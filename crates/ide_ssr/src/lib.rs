@@ -55,18 +55,23 @@
 // %% ssr: foo(_@A, _@B) ==>> foo(_@B, _@A).
 // ```
 
+use std::iter;
 use std::sync::Arc;
 
 use elp_ide_db::elp_base_db::FileId;
 use elp_ide_db::elp_base_db::FileRange;
 use elp_ide_db::elp_base_db::SourceDatabaseExt;
+use elp_ide_db::source_change::SourceChange;
+use elp_ide_db::source_change::SourceChangeBuilder;
 use elp_ide_db::RootDatabase;
 use elp_syntax::ast;
 use elp_syntax::ast::CompOp;
+use elp_syntax::ast::Ordering;
 use elp_syntax::AstNode;
 use elp_syntax::SyntaxNode;
 use elp_syntax::TextRange;
 use fxhash::FxHashMap;
+use fxhash::FxHashSet;
 use hir::db::DefDatabase;
 use hir::db::InternDatabase;
 use hir::fold::fold_body;
@@ -78,6 +83,7 @@ use hir::fold::ParenStrategy;
 use hir::AnyExprId;
 use hir::AnyExprRef;
 use hir::Body;
+use hir::CallTarget;
 use hir::Expr;
 use hir::ExprId;
 use hir::FoldBody;
@@ -86,36 +92,54 @@ use hir::InFile;
 use hir::InSsr;
 use hir::Literal;
 use hir::Name;
+use hir::NameArity;
 use hir::On;
 use hir::Pat;
+use hir::PatId;
 use hir::Semantic;
 use hir::SsrBody;
 use hir::SsrPatternIds;
 use hir::SsrPlaceholder;
 use hir::SsrSource;
+use hir::TypeExpr;
+use hir::Var;
 
 #[macro_use]
 mod errors;
 
+mod builtin;
+mod json;
 mod matching;
 mod nester;
+mod replacing;
 mod search;
 #[cfg(test)]
 mod tests;
 
+pub use builtin::builtin_rule_set;
+pub use builtin::BuiltinRuleSet;
+pub use builtin::BUILTIN_RULE_SETS;
 pub use errors::SsrError;
 use hir::Strategy;
+pub use json::MatchJson;
 pub use matching::Match;
 pub use matching::MatchFailureReason;
 pub use matching::PlaceholderMatch;
 pub use matching::SubId;
+pub use replacing::resolve_conflicts;
+pub use replacing::Conflict;
+pub use replacing::ConflictPolicy;
+pub use replacing::Replacement;
+pub use replacing::ResolvedReplacements;
 
 // ---------------------------------------------------------------------
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SsrSearchScope {
     WholeFile(FileId),
     FunctionsOnly(FileId),
+    /// The clauses of a single function of the file.
+    Function(FileId, NameArity),
 }
 
 impl SsrSearchScope {
@@ -134,6 +158,50 @@ impl SsrSearchScope {
             SsrSearchScope::FunctionsOnly(file_id) => {
                 fold_file_functions(sema, strategy, *file_id, initial, callback)
             }
+            SsrSearchScope::Function(file_id, name) => {
+                match sema.def_map_local(*file_id).get_function(name) {
+                    Some(def) => sema.fold_function(
+                        strategy,
+                        InFile::new(*file_id, def.function_id),
+                        initial,
+                        &mut |acc, _, ctx| callback(acc, ctx),
+                    ),
+                    None => initial,
+                }
+            }
+        }
+    }
+}
+
+/// Which kind of code node a rule is allowed to match. The search
+/// pattern is lowered as an expression, a pattern and a type, and by
+/// default it will match either an expression or a pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SsrMatchKind {
+    /// Match both expressions and patterns.
+    #[default]
+    Any,
+    /// Only match pattern positions, such as function head arguments,
+    /// `case` clause patterns or the left hand side of a match.
+    Patterns,
+    /// Only match expressions.
+    Expressions,
+    /// Only match type expressions, such as those in `-spec`, `-type`
+    /// and `-callback` attributes, or in record field types. The
+    /// search pattern is read as a type, so `ssr: queue:queue(_@T).`
+    /// matches uses of the remote type, binding `_@T` to its argument.
+    Types,
+}
+
+impl SsrMatchKind {
+    /// Whether a code node with the given id may be matched.
+    pub(crate) fn admits(&self, code: &AnyExprId) -> bool {
+        match (self, code) {
+            (SsrMatchKind::Any, AnyExprId::Expr(_) | AnyExprId::Pat(_)) => true,
+            (SsrMatchKind::Patterns, AnyExprId::Pat(_)) => true,
+            (SsrMatchKind::Expressions, AnyExprId::Expr(_)) => true,
+            (SsrMatchKind::Types, AnyExprId::TypeExpr(_)) => true,
+            _ => false,
         }
     }
 }
@@ -204,14 +272,113 @@ pub fn is_placeholder_a_var_from_sema_and_match(
 #[derive(Debug)]
 pub struct SsrRule {
     parsed_rule: Arc<SsrBody>,
-    conditions: FxHashMap<SsrPlaceholder, Condition>,
+    /// One entry for each `;`-separated alternative of the `when`
+    /// clause. Empty if the rule has no conditions.
+    conditions: Vec<Conditions>,
+    /// Placeholders written as `_@Name...`, which match the remaining
+    /// statements of a `begin ... end` block.
+    tails: FxHashSet<Var>,
+    /// Set if the search pattern is a function clause rather than an
+    /// expression.
+    clause: Option<ClauseRule>,
+    /// Records written as `#foo{k = _@V, ...}`, which also match
+    /// records with fields other than those listed.
+    open_records: FxHashSet<AnyExprId>,
 }
 
+/// The function names of a rule whose search pattern is a function
+/// clause, such as `ssr: _@Name(_@X) -> _@X ==>> _@Name(_@X) -> ok.`
+/// Both the search pattern and the template are lowered as the only
+/// clause of a `fun`, which has no name.
+#[derive(Debug, Clone)]
+pub(crate) struct ClauseRule {
+    /// The functions whose clauses are matched.
+    pub(crate) name: ClauseName,
+    /// The function name written in the template, if the rule has
+    /// one. Either an atom or a placeholder.
+    pub(crate) template_name: Option<String>,
+}
+
+/// The functions whose clauses are matched by a clause rule.
+#[derive(Debug, Clone)]
+pub(crate) enum ClauseName {
+    /// The function name is a placeholder, so any function matches,
+    /// and the placeholder is bound to the name.
+    Placeholder(Var),
+    /// Only clauses of functions with this name match.
+    Named(Name),
+}
+
+impl ClauseName {
+    pub(crate) fn admits(&self, name: &Name) -> bool {
+        match self {
+            ClauseName::Placeholder(_) => true,
+            ClauseName::Named(expected) => expected == name,
+        }
+    }
+}
+
+/// The conditions on each placeholder in one alternative of a `when`
+/// clause, all of which must hold.
+pub(crate) type Conditions = FxHashMap<SsrPlaceholder, Vec<Condition>>;
+
 /// A possible condition extracted from the ssr rule `when` clause
 #[derive(Debug)]
 pub enum Condition {
     Literal(hir::Literal),
     Not(Box<Condition>),
+    TypeTest(TypeTest),
+    /// The placeholder is a number literal which compares to the
+    /// given bound, such as `_@N > 100`.
+    Compare {
+        ordering: Ordering,
+        strict: bool,
+        bound: f64,
+    },
+    /// The placeholder is the arity of a function reference, and is
+    /// equal to the given one. Written `fun _@F/_@A=2`.
+    Arity(u32),
+}
+
+/// A guard type test on a placeholder, such as `is_atom(_@X)`.
+/// Since matching is purely syntactic, it only holds if the
+/// placeholder matches a literal of the given type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TypeTest {
+    Atom,
+    Integer,
+    Float,
+    Number,
+}
+
+impl TypeTest {
+    fn from_guard_bif(name: &str) -> Option<TypeTest> {
+        match name {
+            "is_atom" => Some(TypeTest::Atom),
+            "is_integer" => Some(TypeTest::Integer),
+            "is_float" => Some(TypeTest::Float),
+            "is_number" => Some(TypeTest::Number),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn holds_for(&self, literal: &Literal) -> bool {
+        match (self, literal) {
+            (TypeTest::Atom, Literal::Atom(_)) => true,
+            (TypeTest::Integer | TypeTest::Number, Literal::Integer(_)) => true,
+            (TypeTest::Float | TypeTest::Number, Literal::Float(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+/// The value of an integer or float literal.
+pub(crate) fn literal_number(literal: &Literal) -> Option<f64> {
+    match literal {
+        Literal::Integer(int) => Some(*int as f64),
+        Literal::Float(bits) => Some(f64::from_bits(*bits)),
+        _ => None,
+    }
 }
 
 impl SsrRule {
@@ -220,7 +387,11 @@ impl SsrRule {
         self.parsed_rule.tree_print(db)
     }
 
-    fn parse_ssr_source(db: &dyn DefDatabase, ssr_source: SsrSource) -> Result<SsrRule, SsrError> {
+    fn parse_ssr_source(
+        db: &dyn DefDatabase,
+        ssr_source: SsrSource,
+        tails: &[String],
+    ) -> Result<SsrRule, SsrError> {
         if let Some((ssr_body, _)) = db.ssr_body_with_source(ssr_source) {
             // Using a `FoldBody` with invisible parens is fine for
             // conditions, because either way we only check a
@@ -234,10 +405,16 @@ impl SsrRule {
                 },
                 &ssr_body.body,
             );
-            let conditions = SsrRule::make_conditions(&ssr_body, &body)?;
+            let conditions = SsrRule::make_conditions(db.upcast(), &ssr_body, &body)?;
             Ok(SsrRule {
                 parsed_rule: ssr_body.clone(),
                 conditions,
+                tails: tails
+                    .iter()
+                    .map(|name| db.var(Name::from_erlang_service(name)))
+                    .collect(),
+                clause: None,
+                open_records: FxHashSet::default(),
             })
         } else {
             Err(SsrError("Could not lower rule".to_string()))
@@ -245,8 +422,132 @@ impl SsrRule {
     }
 
     pub fn parse_str(db: &dyn DefDatabase, pattern_str: &str) -> Result<SsrRule, SsrError> {
-        let ssr_source = db.ssr(Arc::from(pattern_str));
-        Self::parse_ssr_source(db, ssr_source)
+        let (pattern_str, tails) = strip_tail_markers(pattern_str);
+        let (pattern_str, arities) = strip_arity_constraints(&pattern_str)?;
+        let (pattern_str, mut open_records) = strip_open_record_markers(&pattern_str)?;
+        if !open_records.is_empty() && find_in_code(&pattern_str, "==>>").is_some() {
+            return Err(SsrError::new(
+                "Invalid `...`, a rule with a template cannot have open records",
+            ));
+        }
+        let ssr_source = db.ssr(Arc::from(pattern_str.as_str()));
+        let mut rule = match Self::parse_ssr_source(db, ssr_source, &tails) {
+            Ok(rule) => rule,
+            Err(err) => {
+                let (fun_str, clause) = function_clause_as_fun(db, &pattern_str).ok_or(err)?;
+                // The clause from its `(` on is the same in both, so
+                // move the open records along with it.
+                if let (Some(from), Some(to)) = (pattern_str.find('('), fun_str.find('(')) {
+                    for end in &mut open_records {
+                        *end = *end + to - from;
+                    }
+                }
+                let mut rule =
+                    Self::parse_ssr_source(db, db.ssr(Arc::from(fun_str.as_str())), &tails)?;
+                rule.clause = Some(clause);
+                rule
+            }
+        };
+        for name in &tails {
+            let var = db.var(Name::from_erlang_service(name));
+            if !rule.is_tail_position(&var) {
+                return Err(SsrError::new(format!(
+                    "Invalid tail placeholder `{name}...`, it must be the last element of a `begin ... end` block or a tuple"
+                )));
+            }
+        }
+        rule.open_records = rule.open_records_ending_at(db, &open_records);
+        for (name, arity) in arities {
+            let var = db.var(Name::from_erlang_service(&name));
+            if !rule.is_capture_fun_arity(&var) {
+                return Err(SsrError::new(format!(
+                    "Invalid arity constraint on `{name}`, it must be the arity of a function reference, such as `fun _@F/{name}={arity}`"
+                )));
+            }
+            // The arity is part of the search pattern, so it must
+            // hold in every alternative of the `when` clause.
+            if rule.conditions.is_empty() {
+                rule.conditions.push(Conditions::default());
+            }
+            for conditions in &mut rule.conditions {
+                conditions
+                    .entry(SsrPlaceholder { var: var.clone() })
+                    .or_default()
+                    .push(Condition::Arity(arity));
+            }
+        }
+        Ok(rule)
+    }
+
+    /// The records of the rule whose source ends at one of the given
+    /// offsets, which are those written with `...`.
+    fn open_records_ending_at(&self, db: &dyn DefDatabase, ends: &[usize]) -> FxHashSet<AnyExprId> {
+        let mut records = FxHashSet::default();
+        if ends.is_empty() {
+            return records;
+        }
+        let Some((ssr_body, source_map)) = db.ssr_body_with_source(self.parsed_rule.ssr_source)
+        else {
+            return records;
+        };
+        let body = &ssr_body.body;
+        let exprs = body.exprs.iter().filter_map(|(id, expr)| {
+            matches!(expr, Expr::Record { .. } | Expr::RecordUpdate { .. })
+                .then_some(AnyExprId::Expr(id))
+        });
+        let pats = body.pats.iter().filter_map(|(id, pat)| {
+            matches!(pat, Pat::Record { .. }).then_some(AnyExprId::Pat(id))
+        });
+        let type_exprs = body.type_exprs.iter().filter_map(|(id, type_expr)| {
+            matches!(type_expr, TypeExpr::Record { .. }).then_some(AnyExprId::TypeExpr(id))
+        });
+        for id in exprs.chain(pats).chain(type_exprs) {
+            if let Some(source) = source_map.any(id) {
+                if ends.contains(&usize::from(source.range().end())) {
+                    records.insert(id);
+                }
+            }
+        }
+        records
+    }
+
+    /// Whether the placeholder for `var` is the last element of a
+    /// `begin ... end` block or a tuple, or, for a clause rule, of the
+    /// arguments or the body of the clause.
+    fn is_tail_position(&self, var: &Var) -> bool {
+        let body = &self.parsed_rule.body;
+        let is_var_expr = |id: Option<&ExprId>| match id.map(|id| &body[*id]) {
+            Some(Expr::SsrPlaceholder(placeholder)) => &placeholder.var == var,
+            _ => false,
+        };
+        let is_var_pat = |id: Option<&PatId>| match id.map(|id| &body[*id]) {
+            Some(Pat::SsrPlaceholder(placeholder)) => &placeholder.var == var,
+            _ => false,
+        };
+        let in_exprs = body.exprs.iter().any(|(_, expr)| match expr {
+            Expr::Block { exprs } | Expr::Tuple { exprs } => is_var_expr(exprs.last()),
+            Expr::Closure { clauses, .. } if self.clause.is_some() => clauses
+                .iter()
+                .any(|clause| is_var_pat(clause.pats.last()) || is_var_expr(clause.exprs.last())),
+            _ => false,
+        });
+        in_exprs
+            || body.pats.iter().any(|(_, pat)| match pat {
+                Pat::Tuple { pats } => is_var_pat(pats.last()),
+                _ => false,
+            })
+    }
+
+    /// Whether the placeholder for `var` is the arity of a function
+    /// reference in the search pattern, such as `fun _@F/_@A`.
+    fn is_capture_fun_arity(&self, var: &Var) -> bool {
+        let body = &self.parsed_rule.body;
+        body.exprs.iter().any(|(_, expr)| match expr {
+            Expr::CaptureFun { arity, .. } => {
+                matches!(&body[*arity], Expr::SsrPlaceholder(placeholder) if &placeholder.var == var)
+            }
+            _ => false,
+        })
     }
 
     /// The `when` clause is lowered as HIR guards.
@@ -258,64 +559,353 @@ impl SsrRule {
     /// ```erlang
     /// ssr: _@X when _@X == foo.
     /// ```
+    ///
+    /// or that it is a literal of a given type, such as
+    ///
+    /// ```erlang
+    /// ssr: _@X when is_atom(_@X).
+    /// ```
+    ///
+    /// or that it is a number literal in a given range, such as
+    ///
+    /// ```erlang
+    /// ssr: _@X when is_integer(_@X), _@X > 100.
+    /// ```
+    ///
+    /// Conditions separated by a comma form a conjunction, and all
+    /// of them must hold for a match. Alternatives separated by a
+    /// semicolon form a disjunction, and a match needs only one of
+    /// them to hold.
     fn make_conditions(
+        db: &dyn InternDatabase,
         ssr_body: &SsrBody,
         body: &FoldBody,
-    ) -> Result<FxHashMap<SsrPlaceholder, Condition>, SsrError> {
-        let mut conditions: FxHashMap<SsrPlaceholder, Condition> = FxHashMap::default();
+    ) -> Result<Vec<Conditions>, SsrError> {
+        let mut alternatives: Vec<Conditions> = Vec::new();
         let mut error = None;
         ssr_body.when.as_ref().map(|w| {
             w.iter().for_each(|conds| {
+                let mut conditions = Conditions::default();
                 conds.iter().for_each(|cond| {
-                    extract_condition(body, cond, &mut conditions, &mut error);
+                    if let Some((placeholder, condition)) =
+                        extract_condition(db, body, cond, &mut error)
+                    {
+                        conditions.entry(placeholder).or_default().push(condition);
+                    }
                 });
+                alternatives.push(conditions);
             })
         });
         if let Some(error) = error {
             Err(error)
         } else {
-            Ok(conditions)
+            Ok(alternatives)
+        }
+    }
+}
+
+/// A tail placeholder, such as `begin _@A, _@Rest... end`, is not
+/// valid Erlang syntax, so remove the `...` marker before parsing the
+/// rule and return the names of the placeholders it was attached to.
+/// Strings, quoted atoms and comments are left alone.
+fn strip_tail_markers(pattern_str: &str) -> (String, Vec<String>) {
+    let mut stripped = String::with_capacity(pattern_str.len());
+    let mut tails = Vec::new();
+    let mut rest = pattern_str;
+    while let Some(start) = find_in_code(rest, "_@") {
+        let name_len = rest[start + 2..]
+            .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '@'))
+            .unwrap_or(rest.len() - start - 2);
+        let end = start + 2 + name_len;
+        stripped.push_str(&rest[..end]);
+        rest = &rest[end..];
+        if let Some(after) = rest.strip_prefix("...") {
+            tails.push(stripped[stripped.len() - name_len - 2..].to_string());
+            rest = after;
         }
     }
+    stripped.push_str(rest);
+    (stripped, tails)
+}
+
+/// The byte offset of the first `needle` in `text` which is part of
+/// the Erlang code itself, rather than of a string, a quoted atom, a
+/// character literal or a comment.
+fn find_in_code(text: &str, needle: &str) -> Option<usize> {
+    let mut chars = text.char_indices();
+    while let Some((i, c)) = chars.next() {
+        if text[i..].starts_with(needle) {
+            return Some(i);
+        }
+        match c {
+            '"' | '\'' => {
+                while let Some((_, d)) = chars.next() {
+                    if d == '\\' {
+                        chars.next();
+                    } else if d == c {
+                        break;
+                    }
+                }
+            }
+            '$' => {
+                if let Some((_, '\\')) = chars.next() {
+                    chars.next();
+                }
+            }
+            '%' => {
+                for (_, d) in chars.by_ref() {
+                    if d == '\n' {
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// A function clause such as `_@Name(_@Args) -> _@Body` is not an
+/// expression, so a rule with one as its search pattern does not
+/// lower. Rewrite the search pattern, and the template if there is
+/// one, as a `fun` with the same clause, which does, and return the
+/// function names separately. A `when` before the `->` of a clause is
+/// its guard, and one after the last clause of the rule starts the
+/// conditions of the rule.
+fn function_clause_as_fun(db: &dyn DefDatabase, pattern_str: &str) -> Option<(String, ClauseRule)> {
+    let rule = pattern_str
+        .trim()
+        .strip_prefix("ssr:")?
+        .strip_suffix('.')?
+        .trim();
+    let (pattern, template) = match find_in_code(rule, "==>>") {
+        Some(arrow) => (&rule[..arrow], Some(&rule[arrow + 4..])),
+        None => (rule, None),
+    };
+    let last = template.unwrap_or(pattern);
+    // Only one of the places the conditions could start at gives a
+    // rule which parses, so try them all, starting with none.
+    iter::once(last.len())
+        .chain(keyword_offsets(last, "when"))
+        .find_map(|at| {
+            let (last, conditions) = last.split_at(at);
+            let (pattern, template) = match template {
+                Some(_) => (pattern, Some(last)),
+                None => (last, None),
+            };
+            let (name, pattern_fun) = clause_as_fun(pattern)?;
+            let name = match name.strip_prefix("_@") {
+                Some(_) => ClauseName::Placeholder(db.var(Name::from_erlang_service(name))),
+                None => ClauseName::Named(Name::from_erlang_service(name)),
+            };
+            let mut fun_str = format!("ssr: {pattern_fun}");
+            let template_name = match template {
+                Some(template) => {
+                    let (template_name, template_fun) = clause_as_fun(template)?;
+                    fun_str.push_str(&format!(" ==>> {template_fun}"));
+                    Some(template_name.to_string())
+                }
+                None => None,
+            };
+            if !conditions.is_empty() {
+                fun_str.push_str(&format!(" {conditions}"));
+            }
+            fun_str.push('.');
+            if !ast::SourceFile::parse_text(&fun_str).errors().is_empty() {
+                return None;
+            }
+            let clause = ClauseRule {
+                name,
+                template_name,
+            };
+            Some((fun_str, clause))
+        })
+}
+
+/// Split a function clause such as `foo(_@A) -> _@A` into its name,
+/// which is an atom or a placeholder, and a `fun` with the rest of
+/// the clause.
+fn clause_as_fun(clause: &str) -> Option<(&str, String)> {
+    let clause = clause.trim();
+    let (name, rest) = clause.split_at(clause.find('(')?);
+    let name = name.trim();
+    let is_name_char = |c: char| c.is_alphanumeric() || c == '_' || c == '@';
+    let is_name = match name.strip_prefix("_@") {
+        Some(placeholder) => !placeholder.is_empty() && placeholder.chars().all(is_name_char),
+        None => {
+            name.starts_with(|c: char| c.is_ascii_lowercase()) && name.chars().all(is_name_char)
+        }
+    };
+    is_name.then(|| (name, format!("fun{rest} end")))
+}
+
+/// The byte offsets of the occurrences of `keyword` in `text` which
+/// are part of the Erlang code, and not of a longer name.
+fn keyword_offsets(text: &str, keyword: &str) -> Vec<usize> {
+    let is_name_char = |c: char| c.is_alphanumeric() || c == '_' || c == '@';
+    let mut offsets = Vec::new();
+    let mut start = 0;
+    while let Some(found) = find_in_code(&text[start..], keyword) {
+        let at = start + found;
+        start = at + keyword.len();
+        if !text[..at].ends_with(is_name_char) && !text[start..].starts_with(is_name_char) {
+            offsets.push(at);
+        }
+    }
+    offsets
+}
+
+/// Remove the `...` markers from records which may have more fields
+/// than those listed, written `#foo{k = _@V, ...}`, and return the
+/// offsets in the stripped rule just past the `}` of those records.
+/// The marker only applies to the record it is written in.
+fn strip_open_record_markers(pattern_str: &str) -> Result<(String, Vec<usize>), SsrError> {
+    let mut stripped = String::with_capacity(pattern_str.len());
+    let mut records = Vec::new();
+    let mut rest = pattern_str;
+    while let Some(start) = find_in_code(rest, "...") {
+        let before = rest[..start].trim_end();
+        let after = &rest[start + 3..];
+        if !after.trim_start().starts_with('}') {
+            stripped.push_str(&rest[..start + 3]);
+            rest = after;
+            continue;
+        }
+        stripped.push_str(before.strip_suffix(',').unwrap_or(before));
+        rest = after;
+        match open_record_name(&stripped) {
+            Some(_) => {
+                let brace = after.len() - after.trim_start().len();
+                records.push(stripped.len() + brace + 1);
+            }
+            None => {
+                return Err(SsrError::new(
+                    "Invalid `...`, it can only end the fields of a record, such as `#foo{k = _@V, ...}`",
+                ));
+            }
+        }
+    }
+    stripped.push_str(rest);
+    Ok((stripped, records))
+}
+
+/// The name of the record whose fields are still open at the end of
+/// `text`, such as `foo` for `#foo{k = _@V`.
+fn open_record_name(text: &str) -> Option<&str> {
+    let mut depth = 0;
+    let brace = text.char_indices().rev().find_map(|(i, c)| {
+        match c {
+            '}' => depth += 1,
+            '{' if depth == 0 => return Some(i),
+            '{' => depth -= 1,
+            _ => {}
+        }
+        None
+    })?;
+    let (_, name) = text[..brace].rsplit_once('#')?;
+    let name = name.trim();
+    let is_name_char = |c: char| c.is_alphanumeric() || c == '_' || c == '@';
+    if name.is_empty() || !name.chars().all(is_name_char) {
+        return None;
+    }
+    Some(name)
+}
+
+/// Remove the arity constraints, written `fun _@F/_@A=2`, from the
+/// rule and return the names of the placeholders with their arity.
+fn strip_arity_constraints(pattern_str: &str) -> Result<(String, Vec<(String, u32)>), SsrError> {
+    let is_name_char = |c: char| c.is_alphanumeric() || c == '_' || c == '@';
+    let mut stripped = String::with_capacity(pattern_str.len());
+    let mut arities = Vec::new();
+    let mut rest = pattern_str;
+    while let Some(start) = find_in_code(rest, "/_@") {
+        let name_len = rest[start + 3..]
+            .find(|c: char| !is_name_char(c))
+            .unwrap_or(rest.len() - start - 3);
+        let end = start + 3 + name_len;
+        let name = &rest[start + 1..end];
+        stripped.push_str(&rest[..end]);
+        rest = &rest[end..];
+        // `=:=`, `==`, `=/=` and `=<` are comparisons, not constraints
+        let constraint = match rest.strip_prefix('=') {
+            Some(after) if !after.starts_with(['=', ':', '/', '<']) => after,
+            _ => continue,
+        };
+        let arity_len = constraint
+            .find(|c: char| !is_name_char(c))
+            .unwrap_or(constraint.len());
+        match constraint[..arity_len].parse::<u32>() {
+            Ok(arity) => arities.push((name.to_string(), arity)),
+            Err(_) => {
+                return Err(SsrError::new(format!(
+                    "Invalid arity constraint on `{name}`, expecting an integer"
+                )));
+            }
+        }
+        rest = &constraint[arity_len..];
+    }
+    stripped.push_str(rest);
+    Ok((stripped, arities))
 }
 
 fn extract_condition(
+    db: &dyn InternDatabase,
     body: &FoldBody,
     cond: &ExprId,
-    conditions: &mut FxHashMap<SsrPlaceholder, Condition>,
     error: &mut Option<SsrError>,
-) {
-    match body[*cond] {
+) -> Option<(SsrPlaceholder, Condition)> {
+    match &body[*cond] {
         Expr::BinaryOp { lhs, rhs, op } => {
-            match &body[lhs] {
+            match &body[*lhs] {
                 Expr::SsrPlaceholder(ssr_placeholder) => {
                     // We have a condition on the current placeholder, store it if valid
                     match op {
                         ast::BinaryOp::CompOp(CompOp::Eq { strict: _, negated }) => {
                             if let Some(lit_rhs) =
-                                get_literal_subid(&body, &SubId::AnyExprId(AnyExprId::Expr(rhs)))
+                                get_literal_subid(&body, &SubId::AnyExprId(AnyExprId::Expr(*rhs)))
                             {
-                                if negated {
-                                    conditions.insert(
+                                if *negated {
+                                    return Some((
                                         ssr_placeholder.clone(),
                                         Condition::Not(Box::new(Condition::Literal(
                                             lit_rhs.clone(),
                                         ))),
-                                    );
+                                    ));
                                 } else {
-                                    conditions.insert(
+                                    return Some((
                                         ssr_placeholder.clone(),
                                         Condition::Literal(lit_rhs.clone()),
-                                    );
+                                    ));
                                 }
                             } else {
                                 *error =
                                     Some(SsrError::new("Invalid `when` RHS, expecting a literal"));
                             }
                         }
+                        ast::BinaryOp::CompOp(CompOp::Ord { ordering, strict }) => {
+                            match get_literal_subid(&body, &SubId::AnyExprId(AnyExprId::Expr(*rhs)))
+                                .and_then(literal_number)
+                            {
+                                Some(bound) => {
+                                    return Some((
+                                        ssr_placeholder.clone(),
+                                        Condition::Compare {
+                                            ordering: *ordering,
+                                            strict: *strict,
+                                            bound,
+                                        },
+                                    ));
+                                }
+                                None => {
+                                    *error = Some(SsrError::new(
+                                        "Invalid `when` RHS, expecting a number",
+                                    ));
+                                }
+                            }
+                        }
                         _ => {
                             *error = Some(SsrError::new(
-                                "Invalid `when` condition, must use `==`, `/=`, `=:=` or `=/=`",
+                                "Invalid `when` condition, must use `==`, `/=`, `=:=`, `=/=`, `<`, `=<`, `>` or `>=`",
                             ))
                         }
                     }
@@ -323,18 +913,46 @@ fn extract_condition(
                 _ => {}
             }
         }
+        Expr::Call {
+            target: CallTarget::Local { name },
+            args,
+        } => {
+            let type_test = match &body[*name] {
+                Expr::Literal(Literal::Atom(atom)) => TypeTest::from_guard_bif(&atom.as_string(db)),
+                _ => None,
+            };
+            match (type_test, args.as_slice()) {
+                (Some(type_test), [arg]) => match &body[*arg] {
+                    Expr::SsrPlaceholder(ssr_placeholder) => {
+                        return Some((ssr_placeholder.clone(), Condition::TypeTest(type_test)));
+                    }
+                    _ => {
+                        *error = Some(SsrError::new(
+                            "Invalid `when` type test, expecting a placeholder argument",
+                        ));
+                    }
+                },
+                _ => {
+                    *error = Some(SsrError::new("Invalid `when` condition"));
+                }
+            }
+        }
         _ => {
             *error = Some(SsrError::new("Invalid `when` condition"));
         }
     }
+    None
 }
 
 #[derive(Debug)]
 pub(crate) struct SsrPattern {
     pub(crate) ssr_source: SsrSource,
-    pub(crate) conditions: FxHashMap<SsrPlaceholder, Condition>,
+    pub(crate) conditions: Vec<Conditions>,
     pub(crate) pattern_node: SsrPatternIds,
     pub(crate) index: usize,
+    pub(crate) tails: FxHashSet<Var>,
+    pub(crate) clause: Option<ClauseRule>,
+    pub(crate) open_records: FxHashSet<AnyExprId>,
 }
 
 impl SsrPattern {
@@ -344,6 +962,9 @@ impl SsrPattern {
             conditions: rule.conditions,
             pattern_node: rule.parsed_rule.pattern.clone(),
             index,
+            tails: rule.tails,
+            clause: rule.clause,
+            open_records: rule.open_records,
         }
     }
 
@@ -352,7 +973,7 @@ impl SsrPattern {
         Some(body.body.clone())
     }
 
-    /// The pattern is lowered both as a HIR Pat and an Expr.
+    /// The pattern is lowered as a HIR Pat, an Expr and a TypeExpr.
     /// Choose which of these to use for matching the given `code` node.
     pub(crate) fn pattern_sub_id_for_code(
         &self,
@@ -365,6 +986,12 @@ impl SsrPattern {
                 Pat::Missing => SubId::Constant("Cannot match pattern Pat::Missing".to_string()),
                 _ => SubId::AnyExprId(AnyExprId::Pat(self.pattern_node.pat.clone())),
             },
+            AnyExprId::TypeExpr(_) => match pattern_body[self.pattern_node.type_expr] {
+                TypeExpr::Missing => {
+                    SubId::Constant("Cannot match pattern TypeExpr::Missing".to_string())
+                }
+                _ => SubId::AnyExprId(AnyExprId::TypeExpr(self.pattern_node.type_expr)),
+            },
             _ => SubId::Constant("not implemented yet".to_string()),
         }
     }
@@ -384,6 +1011,11 @@ pub struct MatchFinder<'a> {
     scope: SsrSearchScope,
     pub debug_print: bool,
     strategy: Strategy,
+    match_kind: SsrMatchKind,
+    expanded_only: bool,
+    /// The macro strategy in force before `set_expanded_only(true)`,
+    /// restored when expanded-only matching is turned off again.
+    saved_macros: Option<MacroStrategy>,
 }
 
 impl<'a> MatchFinder<'a> {
@@ -400,6 +1032,43 @@ impl<'a> MatchFinder<'a> {
             scope,
             debug_print: false,
             strategy,
+            match_kind: SsrMatchKind::default(),
+            expanded_only: false,
+            saved_macros: None,
+        }
+    }
+
+    /// Constructs a new instance which only searches the clauses of
+    /// the function `function` in `file_id`.
+    pub fn in_function(
+        sema: &'a Semantic<'a>,
+        strategy: Strategy,
+        file_id: FileId,
+        function: NameArity,
+    ) -> MatchFinder<'a> {
+        MatchFinder::in_context(sema, strategy, SsrSearchScope::Function(file_id, function))
+    }
+
+    /// Restrict the kind of code node the rules will be matched
+    /// against.
+    pub fn set_match_kind(&mut self, match_kind: SsrMatchKind) {
+        self.match_kind = match_kind;
+    }
+
+    /// Match the rules purely against the macro-expanded HIR, so a
+    /// rule written in terms of expanded code also matches where the
+    /// source uses a macro. The bodies of `-define` attributes are
+    /// not searched, and matches inside an expansion are reported at
+    /// the range of the macro call.
+    pub fn set_expanded_only(&mut self, expanded_only: bool) {
+        self.expanded_only = expanded_only;
+        if expanded_only {
+            if self.saved_macros.is_none() {
+                self.saved_macros = Some(self.strategy.macros);
+            }
+            self.strategy.macros = MacroStrategy::Expand;
+        } else if let Some(macros) = self.saved_macros.take() {
+            self.strategy.macros = macros;
         }
     }
 
@@ -423,6 +1092,61 @@ impl<'a> MatchFinder<'a> {
         nester::nest_and_remove_collisions(matches, &self.sema)
     }
 
+    /// Returns matches for all added rules, including nested ones, as
+    /// a JSON array of `MatchJson`.
+    pub fn matches_json(&self) -> serde_json::Result<String> {
+        let matches: Vec<MatchJson> = self
+            .matches()
+            .flattened()
+            .matches
+            .iter()
+            .map(|m| MatchJson::new(self.sema, m))
+            .collect();
+        serde_json::to_string(&matches)
+    }
+
+    /// Computes the replacement for each of the given matches, by
+    /// substituting the values bound to its placeholders into the
+    /// template of the rule that matched. A replacement that would
+    /// introduce a syntax error into the file is returned as an
+    /// error, rather than as invalid code.
+    pub fn replacements(&self, matches: &SsrMatches) -> Vec<Result<Replacement, SsrError>> {
+        matches
+            .matches
+            .iter()
+            .map(|m| {
+                let rule = self
+                    .rules
+                    .get(m.rule_index)
+                    .ok_or_else(|| SsrError::new("Match refers to an unknown rule"))?;
+                replacing::replacement(self.sema, rule, m)
+            })
+            .collect()
+    }
+
+    /// Applies the template of each rule to its matches in `file_id`,
+    /// returning the edited source as a `SourceChange`. A match
+    /// nested inside another one is skipped, as the outer match is
+    /// rewritten, and so are matches whose replacement cannot be
+    /// computed. Returns `None` if there is nothing to rewrite.
+    pub fn rewrite(&self, file_id: FileId) -> Option<SourceChange> {
+        let replacements: Vec<Replacement> = self
+            .replacements(&self.matches())
+            .into_iter()
+            .filter_map(|r| r.ok())
+            .filter(|r| r.range.file_id == file_id)
+            .collect();
+        let resolved = resolve_conflicts(replacements, ConflictPolicy::OuterWins).ok()?;
+        if resolved.replacements.is_empty() {
+            return None;
+        }
+        let mut builder = SourceChangeBuilder::new(file_id);
+        for replacement in resolved.replacements {
+            builder.replace(replacement.range.range, replacement.text);
+        }
+        Some(builder.finish())
+    }
+
     /// Finds all nodes in `file_id` whose text is exactly equal to
     /// `snippet` and attempts to match them, while recording reasons
     /// why they don't match. This API is useful for command
@@ -645,7 +1369,10 @@ fn get_literal_subid<'a>(body: &'a FoldBody, code: &'a SubId) -> Option<&'a Lite
                 Pat::Literal(literal) => Some(literal),
                 _ => None,
             },
-            AnyExprRef::TypeExpr(_) => None,
+            AnyExprRef::TypeExpr(type_expr) => match type_expr {
+                TypeExpr::Literal(literal) => Some(literal),
+                _ => None,
+            },
             AnyExprRef::Term(_) => None,
         },
         _ => None,
@@ -23,6 +23,7 @@ use elp_syntax::ast::ListOp;
 use elp_syntax::ast::LogicOp;
 use elp_syntax::ast::Ordering;
 use elp_syntax::ast::UnaryOp;
+use elp_syntax::AstNode;
 use elp_syntax::TextRange;
 use fxhash::FxHashMap;
 use fxhash::FxHashSet;
@@ -34,13 +35,17 @@ use hir::Body;
 use hir::BodyOrigin;
 use hir::CRClause;
 use hir::CallTarget;
+use hir::Clause;
 use hir::ComprehensionBuilder;
 use hir::ComprehensionExpr;
 use hir::Expr;
 use hir::ExprId;
 use hir::FoldBody;
+use hir::FunType;
+use hir::FunctionClauseId;
 use hir::IfClause;
 use hir::InFileAstPtr;
+use hir::ListType;
 use hir::Literal;
 use hir::MacroCallName;
 use hir::MapOp;
@@ -50,10 +55,14 @@ use hir::PatId;
 use hir::Semantic;
 use hir::SsrPlaceholder;
 use hir::TypeExpr;
+use hir::TypeExprId;
 use hir::Var;
 
 use crate::get_literal_subid;
+use crate::literal_number;
+use crate::ClauseName;
 use crate::Condition;
+use crate::Conditions;
 use crate::SsrMatches;
 use crate::SsrPattern;
 
@@ -132,15 +141,42 @@ impl PlaceholderMatch {
     }
 
     pub fn text(&self, sema: &Semantic, body: &Body) -> Option<String> {
-        let placeholder_match_id = self.code_id.any_expr_id()?;
+        let source_file = sema.parse(body.origin.file_id());
+        let placeholder_match_id = match self.code_id.any_expr_id() {
+            Some(placeholder_match_id) => placeholder_match_id,
+            // A placeholder bound to something which is not an
+            // expression, such as the name of a function clause.
+            None => {
+                return Some(
+                    source_file
+                        .value
+                        .syntax()
+                        .text()
+                        .slice(self.range.range)
+                        .to_string(),
+                );
+            }
+        };
         let placeholder_match_src: InFileAstPtr<ast::Expr> =
             body.get_body_map(sema)?.any(placeholder_match_id)?;
 
-        Some(
-            placeholder_match_src
-                .to_node(&sema.parse(body.origin.file_id()))?
-                .to_string(),
-        )
+        let node = placeholder_match_src.to_node(&source_file)?;
+        let node_range = node.syntax().text_range();
+        if node_range.start() == self.range.range.start()
+            && node_range.end() < self.range.range.end()
+        {
+            // A tail placeholder covers a run of statements, or of
+            // tuple elements, starting at the matched node.
+            return Some(
+                source_file
+                    .value
+                    .syntax()
+                    .text()
+                    .slice(self.range.range)
+                    .to_string(),
+            );
+        }
+        Some(node.to_string())
     }
 
     // Check if our `code_id` and the `other` represent equivalent
@@ -153,6 +189,11 @@ impl PlaceholderMatch {
         other: &SubId,
     ) -> bool {
         let debug_print = false;
+        if let SubId::Atom(name) = self.code_id {
+            // The name of a function clause is only equivalent to the
+            // same atom.
+            return get_literal_subid(body, other) == Some(&Literal::Atom(name));
+        }
         if let SubId::AnyExprId(code_id) = self.code_id {
             get_match(
                 debug_print,
@@ -239,6 +280,46 @@ pub(crate) fn get_match(
     })
 }
 
+/// Checks if the function clause `code_clause` matches the search
+/// pattern of a clause rule, which is lowered as the only clause of a
+/// `fun`. The match covers the whole of the function clause, at
+/// `clause_range`. If the rule has a placeholder for the function
+/// name, it is bound to `code_name`, the name of the function and its
+/// range in the clause.
+pub(crate) fn get_clause_match(
+    debug_active: bool,
+    rule: &SsrPattern,
+    pattern: &SubId,
+    pattern_clause: &Clause,
+    code_body_origin: &BodyOrigin,
+    code_clause_id: FunctionClauseId,
+    code_clause: &Clause,
+    clause_range: FileRange,
+    code_name: Option<(Atom, FileRange)>,
+    sema: &Semantic,
+    code_body: &FoldBody,
+    pattern_body: &FoldBody,
+) -> Result<Match, MatchFailed> {
+    record_match_fails_reasons_scope(debug_active, || {
+        Matcher::new(
+            sema,
+            rule,
+            None,
+            pattern,
+            code_body_origin,
+            &pattern_body,
+            &code_body,
+        )
+        .try_match_clause(
+            pattern_clause,
+            code_clause_id,
+            code_clause,
+            clause_range,
+            code_name,
+        )
+    })
+}
+
 /// Which phase of matching we're currently performing. We do two
 /// phases because most attempted matches will fail and it means we
 /// can defer more expensive checks to the second phase.
@@ -324,10 +405,97 @@ impl<'a> Matcher<'a> {
         // ignored comments and maybe do any other more expensive
         // checks that we didn't want to do on the first pass.
         self.attempt_match_node(&mut Phase::Second(&mut the_match), &self.pattern, code)?;
+        self.check_conditions(&the_match)?;
 
         Ok(the_match)
     }
 
+    fn try_match_clause(
+        &self,
+        pattern: &Clause,
+        code_id: FunctionClauseId,
+        code: &Clause,
+        range: FileRange,
+        code_name: Option<(Atom, FileRange)>,
+    ) -> Result<Match, MatchFailed> {
+        self.attempt_match_clause(&mut Phase::First, pattern, code)?;
+        let mut the_match = Match {
+            range,
+            matched_node_body: self.code_body_origin.clone(),
+            matched_node: SubId::FunctionClause(code_id),
+            placeholder_values: FxHashMap::default(),
+            placeholders_by_var: FxHashMap::default(),
+            rule_index: self.rule.index,
+            depth: 0,
+        };
+        // Bind the name first, so that any other occurrence of its
+        // placeholder in the clause must be the same atom.
+        let name_placeholder = self
+            .rule
+            .clause
+            .as_ref()
+            .and_then(|clause| match clause.name {
+                ClauseName::Placeholder(var) => Some(var),
+                ClauseName::Named(_) => None,
+            });
+        if let (Some(var), Some((name, name_range))) = (name_placeholder, code_name) {
+            let pattern = SubId::Var(var);
+            the_match.placeholder_values.insert(
+                pattern.clone(),
+                PlaceholderMatch::new(name_range, SubId::Atom(name)),
+            );
+            the_match
+                .placeholders_by_var
+                .entry(var)
+                .or_default()
+                .insert(pattern);
+        }
+        self.attempt_match_clause(&mut Phase::Second(&mut the_match), pattern, code)?;
+        self.check_conditions(&the_match)?;
+        Ok(the_match)
+    }
+
+    /// Match the arguments, guards and body of two clauses in turn.
+    /// The arguments and the body of the pattern may end with a tail
+    /// placeholder.
+    fn attempt_match_clause(
+        &self,
+        phase: &mut Phase<'_>,
+        pattern: &Clause,
+        code: &Clause,
+    ) -> Result<(), MatchFailed> {
+        let pats = |clause: &Clause| -> PatternList {
+            clause
+                .pats
+                .iter()
+                .map(|p| (*p).into())
+                .collect::<Vec<_>>()
+                .into()
+        };
+        // Each guard is marked, so that `when A; B` does not match `when A, B`
+        let guards = |clause: &Clause| -> PatternList {
+            clause
+                .guards
+                .iter()
+                .flat_map(|guard| {
+                    iter::once("guard".into()).chain(guard.iter().map(|g| (*g).into()))
+                })
+                .collect::<Vec<_>>()
+                .into()
+        };
+        let exprs = |clause: &Clause| -> PatternList {
+            clause
+                .exprs
+                .iter()
+                .map(|e| (*e).into())
+                .collect::<Vec<_>>()
+                .into()
+        };
+        self.attempt_match_with_tail(phase, pats(pattern), pats(code))?;
+        self.attempt_match_pattern_lists(phase, guards(pattern), guards(code))?;
+        self.attempt_match_with_tail(phase, exprs(pattern), exprs(code))
+    }
+
     /// Checks that `range` is within the permitted range if any. This
     /// is applicable when we're processing a macro expansion and we
     /// want to fail the match if we're working with a node that
@@ -390,10 +558,6 @@ impl<'a> Matcher<'a> {
         if self.is_placeholder(pattern) {
             if let Some(placeholder) = self.get_placeholder_for_node(pattern) {
                 if let Phase::Second(matches_out) = phase {
-                    // check constraints
-                    if let Some(condition) = self.rule.conditions.get(placeholder) {
-                        self.check_condition(code, condition)?;
-                    }
                     if let Some(range) = self.get_code_range(code) {
                         let file_id = self.code_body.body.origin.file_id();
                         let original_range = FileRange { file_id, range };
@@ -440,10 +604,56 @@ impl<'a> Matcher<'a> {
         return Ok(false);
     }
 
+    /// Check the `when` conditions of the rule against the values
+    /// bound to the placeholders of `the_match`. It is enough for the
+    /// conditions of one of the alternatives to hold.
+    fn check_conditions(&self, the_match: &Match) -> Result<(), MatchFailed> {
+        let mut result = Ok(());
+        for conditions in &self.rule.conditions {
+            result = self.check_alternative(the_match, conditions);
+            if result.is_ok() {
+                break;
+            }
+        }
+        result
+    }
+
+    fn check_alternative(
+        &self,
+        the_match: &Match,
+        conditions: &Conditions,
+    ) -> Result<(), MatchFailed> {
+        for (placeholder, conditions) in conditions {
+            // Every occurrence of a placeholder is bound to equivalent
+            // code, so checking one of them is enough.
+            let code = the_match
+                .placeholders_by_var
+                .get(&placeholder.var)
+                .and_then(|ids| ids.iter().next())
+                .and_then(|id| the_match.placeholder_values.get(id));
+            if let Some(placeholder_match) = code {
+                for condition in conditions {
+                    self.check_condition(&placeholder_match.code_id, condition)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
     fn check_condition(&self, code: &SubId, condition: &Condition) -> Result<(), MatchFailed> {
+        // The name of a function clause is bound as an atom, which is
+        // not a node of the code body.
+        let name_literal;
+        let code_literal = match code {
+            SubId::Atom(name) => {
+                name_literal = Literal::Atom(*name);
+                Some(&name_literal)
+            }
+            _ => get_literal_subid(&self.code_body, code),
+        };
         match condition {
             Condition::Literal(literal) => {
-                if let Some(code_literal) = get_literal_subid(&self.code_body, code) {
+                if let Some(code_literal) = code_literal {
                     if code_literal != literal {
                         fail_match!("literal match condition failed: literals different");
                     }
@@ -456,6 +666,34 @@ impl<'a> Matcher<'a> {
                     fail_match!("condition matched when it was expected not to");
                 }
             }
+            Condition::TypeTest(type_test) => match code_literal {
+                Some(code_literal) if type_test.holds_for(code_literal) => {}
+                Some(_) => fail_match!("type test condition failed: literal of a different type"),
+                None => fail_match!("type test condition failed: placeholder not a literal"),
+            },
+            Condition::Compare {
+                ordering,
+                strict,
+                bound,
+            } => {
+                let value = match code_literal.and_then(literal_number) {
+                    Some(value) => value,
+                    None => fail_match!("compare condition failed: placeholder not a number"),
+                };
+                let holds = match (ordering, strict) {
+                    (Ordering::Less, true) => value < *bound,
+                    (Ordering::Less, false) => value <= *bound,
+                    (Ordering::Greater, true) => value > *bound,
+                    (Ordering::Greater, false) => value >= *bound,
+                };
+                if !holds {
+                    fail_match!("compare condition failed: number out of range");
+                }
+            }
+            Condition::Arity(arity) => match code_literal {
+                Some(Literal::Integer(code_arity)) if *code_arity == *arity as i128 => {}
+                _ => fail_match!("arity condition failed: different arity"),
+            },
         }
         Ok(())
     }
@@ -470,10 +708,15 @@ impl<'a> Matcher<'a> {
         let ci = self.code_iterator(code);
         match (pi, ci) {
             (PatternIterator::List(pl), PatternIterator::List(cl)) => {
-                self.attempt_match_pattern_lists(phase, pl, cl)
+                if self.accepts_tail(pattern) {
+                    self.attempt_match_with_tail(phase, pl, cl)
+                } else {
+                    self.attempt_match_pattern_lists(phase, pl, cl)
+                }
             }
             (PatternIterator::Map(pm), PatternIterator::Map(cm)) => {
-                self.attempt_match_pattern_maps(phase, pm, cm)
+                let allow_extra = self.is_open_record(pattern);
+                self.attempt_match_pattern_maps(phase, pm, cm, allow_extra)
             }
             _ => fail_match!("Expecting two PatternMaps or two PatternLists"),
         }
@@ -517,11 +760,77 @@ impl<'a> Matcher<'a> {
         }
     }
 
+    /// Match the statements of a `begin ... end` block, or the elements
+    /// of a tuple.  If the last one of the pattern is a tail
+    /// placeholder, such as `_@Rest...`, it matches all the remaining
+    /// ones of the code, of which there must be at least one.
+    fn attempt_match_with_tail(
+        &self,
+        phase: &mut Phase<'_>,
+        pattern_it: PatternList,
+        code_it: PatternList,
+    ) -> Result<(), MatchFailed> {
+        let pattern_exprs: Vec<SubId> = pattern_it.collect();
+        let code_exprs: Vec<SubId> = code_it.collect();
+        match pattern_exprs.split_last() {
+            Some((tail, init)) if self.is_tail_placeholder(tail) => {
+                if code_exprs.len() <= init.len() {
+                    fail_match!("Nothing left for the tail placeholder");
+                }
+                let (code_init, code_rest) = code_exprs.split_at(init.len());
+                self.attempt_match_pattern_lists(
+                    phase,
+                    init.to_vec().into(),
+                    code_init.to_vec().into(),
+                )?;
+                self.attempt_match_tail_placeholder(phase, tail, code_rest)
+            }
+            _ => self.attempt_match_pattern_lists(phase, pattern_exprs.into(), code_exprs.into()),
+        }
+    }
+
+    /// Bind a tail placeholder to the range covering all of `code`.
+    fn attempt_match_tail_placeholder(
+        &self,
+        phase: &mut Phase<'_>,
+        pattern: &SubId,
+        code: &[SubId],
+    ) -> Result<(), MatchFailed> {
+        if let Phase::Second(matches_out) = phase {
+            let placeholder = match self.get_placeholder_for_node(pattern) {
+                Some(placeholder) => placeholder,
+                None => fail_match!("Tail pattern is not a placeholder"),
+            };
+            let (first, last) = match (code.first(), code.last()) {
+                (Some(first), Some(last)) => (first, last),
+                _ => fail_match!("Nothing left for the tail placeholder"),
+            };
+            let range = match (self.get_code_range(first), self.get_code_range(last)) {
+                (Some(first_range), Some(last_range)) => first_range.cover(last_range),
+                _ => fail_match!("No range for the tail placeholder"),
+            };
+            let file_id = self.code_body.body.origin.file_id();
+            let original_range = FileRange { file_id, range };
+            self.validate_range(&original_range)?;
+            matches_out.placeholder_values.insert(
+                pattern.clone(),
+                PlaceholderMatch::new(original_range, first.clone()),
+            );
+            matches_out
+                .placeholders_by_var
+                .entry(placeholder.var)
+                .or_default()
+                .insert(pattern.clone());
+        }
+        Ok(())
+    }
+
     fn attempt_match_pattern_maps(
         &self,
         phase: &mut Phase<'_>,
         pattern_it: PatternMap,
         code_it: PatternMap,
+        allow_extra: bool,
     ) -> Result<(), MatchFailed> {
         self.attempt_match_pattern_lists(phase, pattern_it.prefix, code_it.prefix)?;
         // We have a map of fields that can map in any order.  Each is
@@ -568,7 +877,7 @@ impl<'a> Matcher<'a> {
                 fail_match!("no placeholder match in child vector");
             };
         }
-        if !code_keys.is_empty() {
+        if !code_keys.is_empty() && !allow_extra {
             fail_match!("unmatched fields");
         }
         Ok(())
@@ -647,6 +956,22 @@ impl<'a> Matcher<'a> {
                     );
                 }
             }
+            (
+                SubIdRef::AnyExprRef(AnyExprRef::TypeExpr(TypeExpr::Literal(pat_lit))),
+                SubIdRef::AnyExprRef(AnyExprRef::TypeExpr(TypeExpr::Literal(code_lit))),
+            ) => {
+                let pat_lit_str = render_str(self.sema, pat_lit);
+                let code_lit_str = render_str(self.sema, code_lit);
+                if pat_lit_str == code_lit_str {
+                    return Ok(());
+                } else {
+                    fail_match!(
+                        "Pattern had `{}`, code had `{}`",
+                        &pat_lit_str,
+                        &code_lit_str
+                    );
+                }
+            }
             (
                 SubIdRef::AnyExprRef(AnyExprRef::Expr(Expr::Var(code_var))),
                 SubIdRef::AnyExprRef(AnyExprRef::Expr(Expr::Var(pat_var))),
@@ -775,11 +1100,40 @@ impl<'a> Matcher<'a> {
             || pattern_str == "Term::SsrPlaceholder"
     }
 
+    fn is_tail_placeholder(&self, id: &SubId) -> bool {
+        self.get_placeholder_for_node(id)
+            .map_or(false, |placeholder| {
+                self.rule.tails.contains(&placeholder.var)
+            })
+    }
+
+    /// Whether `id` is a record in the pattern which was written with
+    /// `...`, so that it also matches records with more fields.
+    fn is_open_record(&self, id: &SubId) -> bool {
+        match id {
+            SubId::AnyExprId(any_expr_id) => self.rule.open_records.contains(any_expr_id),
+            _ => false,
+        }
+    }
+
+    /// Whether the children of `id` may end with a tail placeholder.
+    fn accepts_tail(&self, id: &SubId) -> bool {
+        match id {
+            SubId::AnyExprId(any_expr_id) => matches!(
+                self.pattern_body.get_any(*any_expr_id),
+                AnyExprRef::Expr(Expr::Block { .. } | Expr::Tuple { .. })
+                    | AnyExprRef::Pat(Pat::Tuple { .. })
+            ),
+            _ => false,
+        }
+    }
+
     fn is_placeholder_expr(&self, id: &SubId) -> bool {
         match id {
             SubId::AnyExprId(any_expr_id) => match self.pattern_body.get_any(*any_expr_id) {
                 AnyExprRef::Expr(Expr::SsrPlaceholder(_)) => true,
                 AnyExprRef::Pat(Pat::SsrPlaceholder(_)) => true,
+                AnyExprRef::TypeExpr(TypeExpr::SsrPlaceholder(_)) => true,
                 _ => false,
             },
             _ => false,
@@ -812,6 +1166,8 @@ pub enum SubId {
     UnaryOp(UnaryOp),
     BinaryOp(BinaryOp),
     MapOp(MapOp),
+    /// A whole function clause, matched by a clause rule.
+    FunctionClause(FunctionClauseId),
     // Used to mark specific syntax such as a `|` in a list, to
     // clearly separate the optional tail
     Constant(String),
@@ -830,6 +1186,7 @@ impl SubId {
             SubId::AnyExprId(e) => body.get_any(*e).variant_str(),
             SubId::Atom(_) => "Atom",
             SubId::Var(_) => "Var",
+            SubId::FunctionClause(_) => "FunctionClause",
             SubId::UnaryOp(op) => match op {
                 UnaryOp::Plus => "UnaryOp::Plus",
                 UnaryOp::Minus => "UnaryOp::Minus",
@@ -923,6 +1280,7 @@ impl SubId {
             SubId::UnaryOp(op) => SubIdRef::UnaryOp(*op),
             SubId::BinaryOp(op) => SubIdRef::BinaryOp(*op),
             SubId::MapOp(op) => SubIdRef::MapOp(*op),
+            SubId::FunctionClause(id) => SubIdRef::FunctionClause(*id),
             SubId::Constant(v) => SubIdRef::Constant(v.clone()),
         }
     }
@@ -940,6 +1298,12 @@ impl From<ExprId> for SubId {
     }
 }
 
+impl From<TypeExprId> for SubId {
+    fn from(value: TypeExprId) -> Self {
+        SubId::AnyExprId(value.into())
+    }
+}
+
 impl From<Atom> for SubId {
     fn from(value: Atom) -> Self {
         SubId::Atom(value)
@@ -1004,6 +1368,7 @@ pub enum SubIdRef<'a> {
     UnaryOp(UnaryOp),
     BinaryOp(BinaryOp),
     MapOp(MapOp),
+    FunctionClause(FunctionClauseId),
     Constant(String),
 }
 
@@ -1088,16 +1453,13 @@ impl PatternIterator {
                         .collect();
                     PatternIterator::as_pattern_map(vec![(*name).into()], children)
                 }
-                Expr::RecordUpdate { expr, name, fields } => PatternIterator::as_pattern_list(
-                    vec![(*name).into(), (*expr).into()]
-                        .into_iter()
-                        .chain(
-                            fields
-                                .iter()
-                                .flat_map(|(name, val)| vec![(*name).into(), (*val).into()]),
-                        )
-                        .collect(),
-                ),
+                Expr::RecordUpdate { expr, name, fields } => {
+                    let children: FxHashMap<SubId, Vec<SubId>> = fields
+                        .iter()
+                        .map(|(name, val)| ((*name).into(), vec![(*val).into()]))
+                        .collect();
+                    PatternIterator::as_pattern_map(vec![(*name).into(), (*expr).into()], children)
+                }
                 Expr::RecordIndex { name, field } => {
                     PatternIterator::as_pattern_list(vec![(*name).into(), (*field).into()])
                 }
@@ -1109,7 +1471,7 @@ impl PatternIterator {
                 Expr::Map { fields } => {
                     let children: FxHashMap<SubId, Vec<SubId>> = fields
                         .iter()
-                        .map(|(name, val)| ((*name).into(), vec![(*val).into()]))
+                        .map(|(name, op, val)| ((*name).into(), vec![(*op).into(), (*val).into()]))
                         .collect();
                     PatternIterator::as_pattern_map(vec![], children)
                 }
@@ -1334,7 +1696,84 @@ impl PatternIterator {
                 Pat::Paren { pat } => PatternIterator::as_pattern_list(vec![(*pat).into()]),
                 Pat::SsrPlaceholder(_) => PatternIterator::Leaf,
             },
-            AnyExprRef::TypeExpr(_) => todo!(),
+            AnyExprRef::TypeExpr(it) => match it {
+                TypeExpr::Missing => PatternIterator::Leaf,
+                TypeExpr::Literal(_) => PatternIterator::Leaf,
+                TypeExpr::Var(var) => PatternIterator::as_pattern_list(vec![(*var).into()]),
+                TypeExpr::AnnType { var, ty } => {
+                    PatternIterator::as_pattern_list(vec![(*var).into(), (*ty).into()])
+                }
+                TypeExpr::BinaryOp { lhs, rhs, op } => PatternIterator::as_pattern_list(vec![
+                    (*op).into(),
+                    (*lhs).into(),
+                    (*rhs).into(),
+                ]),
+                TypeExpr::UnaryOp { type_expr, op } => {
+                    PatternIterator::as_pattern_list(vec![(*op).into(), (*type_expr).into()])
+                }
+                TypeExpr::Call { target, args } => PatternIterator::as_pattern_list({
+                    let mut res = Vec::default();
+                    match target {
+                        CallTarget::Local { name } => res.push((*name).into()),
+                        CallTarget::Remote { module, name, .. } => {
+                            res.push((*module).into());
+                            res.push((*name).into());
+                        }
+                    }
+                    args.iter().for_each(|arg| res.push((*arg).into()));
+                    res
+                }),
+                // Mark the kind of `fun()` and list type, so that for
+                // instance `list()` does not match `nonempty_list()`.
+                TypeExpr::Fun(fun) => PatternIterator::as_pattern_list(match fun {
+                    FunType::Any => vec!["any".into()],
+                    FunType::AnyArgs { result } => vec!["any_args".into(), (*result).into()],
+                    FunType::Full { params, result } => iter::once("params".into())
+                        .chain(params.iter().map(|p| (*p).into()))
+                        .chain(iter::once("result".into()))
+                        .chain(iter::once((*result).into()))
+                        .collect(),
+                }),
+                TypeExpr::List(list) => PatternIterator::as_pattern_list(match list {
+                    ListType::Empty => vec!["empty".into()],
+                    ListType::Regular(ty) => vec!["regular".into(), (*ty).into()],
+                    ListType::NonEmpty(ty) => vec!["non_empty".into(), (*ty).into()],
+                }),
+                TypeExpr::Map { fields } => {
+                    let children: FxHashMap<SubId, Vec<SubId>> = fields
+                        .iter()
+                        .map(|(name, op, val)| ((*name).into(), vec![(*op).into(), (*val).into()]))
+                        .collect();
+                    PatternIterator::as_pattern_map(vec![], children)
+                }
+                TypeExpr::Union { types } => {
+                    PatternIterator::as_pattern_list(types.iter().map(|id| (*id).into()).collect())
+                }
+                TypeExpr::Range { lhs, rhs } => {
+                    PatternIterator::as_pattern_list(vec![(*lhs).into(), (*rhs).into()])
+                }
+                TypeExpr::Record { name, fields } => {
+                    let children: FxHashMap<SubId, Vec<SubId>> = fields
+                        .iter()
+                        .map(|(name, val)| ((*name).into(), vec![(*val).into()]))
+                        .collect();
+                    PatternIterator::as_pattern_map(vec![(*name).into()], children)
+                }
+                TypeExpr::Tuple { args } => {
+                    PatternIterator::as_pattern_list(args.iter().map(|id| (*id).into()).collect())
+                }
+                TypeExpr::MacroCall {
+                    expansion: _,
+                    args,
+                    macro_def: _,
+                    macro_name,
+                } => PatternIterator::as_pattern_list(
+                    iter::once(macro_name.into())
+                        .chain(args.iter().map(|id| (*id).into()))
+                        .collect(),
+                ),
+                TypeExpr::SsrPlaceholder(_) => PatternIterator::Leaf,
+            },
             AnyExprRef::Term(_) => todo!(),
         }
     }
@@ -1364,10 +1803,16 @@ impl PatternIterator {
     }
 }
 
+/// Each clause and each guard of an `if` is marked, so that `if A; B ->`
+/// does not match `if A, B ->`, and a guard cannot match across a clause
+/// boundary.
 fn if_clause_iter(ifc: &IfClause) -> Vec<SubId> {
-    ifc.guards
-        .iter()
-        .flat_map(|g| g.into_iter().map(|e| (*e).into()))
+    iter::once("clause".into())
+        .chain(
+            ifc.guards
+                .iter()
+                .flat_map(|g| iter::once("guard".into()).chain(g.into_iter().map(|e| (*e).into()))),
+        )
         .chain(iter::once("exprs".into()))
         .chain(ifc.exprs.iter().map(|e| (*e).into()))
         .collect()
@@ -0,0 +1,359 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Code for applying the template of a rule to a previously found
+//! match.
+
+use std::cmp::Ordering;
+use std::cmp::Reverse;
+use std::ops::Range;
+
+use elp_ide_db::elp_base_db::FileRange;
+use elp_ide_db::elp_base_db::SourceDatabaseExt;
+use elp_syntax::ast;
+use elp_syntax::ast::ArithOp;
+use elp_syntax::ast::BinaryOp;
+use elp_syntax::ast::LogicOp;
+use elp_syntax::ast::SourceFile;
+use elp_syntax::AstNode;
+use elp_syntax::SyntaxNode;
+use elp_syntax::TextRange;
+use elp_syntax::TextSize;
+use hir::db::InternDatabase;
+use hir::Semantic;
+
+use crate::errors::bail;
+use crate::errors::error;
+use crate::Match;
+use crate::SsrError;
+use crate::SsrPattern;
+
+/// The text to replace a match with, produced from the template of
+/// the rule that matched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Replacement {
+    pub range: FileRange,
+    pub text: String,
+    /// The index of the rule that produced the replacement, in the
+    /// order the rules were added to the `MatchFinder`.
+    pub rule_index: usize,
+}
+
+/// How to handle replacements whose ranges overlap, which cannot
+/// both be applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// Keep the replacement of the rule added first, or the one
+    /// earlier in the file if they come from the same rule.
+    FirstWins,
+    /// Keep the outermost of overlapping replacements, so a match
+    /// nested inside another one is skipped when the other is
+    /// applied.
+    OuterWins,
+    /// Apply none of the replacements if any of them conflict.
+    Error,
+}
+
+/// Two replacements whose ranges overlap.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    pub first: Replacement,
+    pub second: Replacement,
+}
+
+/// The replacements left to apply once conflicts have been resolved,
+/// and the conflicts that were found.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ResolvedReplacements {
+    pub replacements: Vec<Replacement>,
+    pub conflicts: Vec<Conflict>,
+}
+
+/// Detect replacements whose ranges overlap, and resolve them
+/// according to `policy`. With `ConflictPolicy::Error`, any conflict
+/// is returned as an error.
+pub fn resolve_conflicts(
+    mut replacements: Vec<Replacement>,
+    policy: ConflictPolicy,
+) -> Result<ResolvedReplacements, Vec<Conflict>> {
+    match policy {
+        ConflictPolicy::OuterWins => replacements.sort_by(|a, b| {
+            a.range
+                .file_id
+                .cmp(&b.range.file_id)
+                .then_with(|| a.range.range.start().cmp(&b.range.range.start()))
+                .then_with(|| b.range.range.len().cmp(&a.range.range.len()))
+                .then_with(|| a.rule_index.cmp(&b.rule_index))
+        }),
+        ConflictPolicy::FirstWins | ConflictPolicy::Error => replacements.sort_by(|a, b| {
+            a.rule_index
+                .cmp(&b.rule_index)
+                .then_with(|| a.range.file_id.cmp(&b.range.file_id))
+                .then_with(|| a.range.range.start().cmp(&b.range.range.start()))
+        }),
+    }
+    let mut resolved = ResolvedReplacements::default();
+    for replacement in replacements {
+        match resolved
+            .replacements
+            .iter()
+            .find(|kept| overlaps(&kept.range, &replacement.range))
+        {
+            Some(kept) => resolved.conflicts.push(Conflict {
+                first: kept.clone(),
+                second: replacement,
+            }),
+            None => resolved.replacements.push(replacement),
+        }
+    }
+    if policy == ConflictPolicy::Error && !resolved.conflicts.is_empty() {
+        return Err(resolved.conflicts);
+    }
+    resolved.replacements.sort_by(|a, b| {
+        a.range
+            .file_id
+            .cmp(&b.range.file_id)
+            .then_with(|| a.range.range.start().cmp(&b.range.range.start()))
+    });
+    Ok(resolved)
+}
+
+fn overlaps(a: &FileRange, b: &FileRange) -> bool {
+    a.file_id == b.file_id
+        && a.range
+            .intersect(b.range)
+            .map_or(false, |range| !range.is_empty())
+}
+
+pub(crate) fn replacement(
+    sema: &Semantic,
+    rule: &SsrPattern,
+    m: &Match,
+) -> Result<Replacement, SsrError> {
+    let mut text = template_text(sema, rule, m)?;
+    if rule.clause.is_none() {
+        if let Some(code) = replaced_expr(sema, m.range) {
+            if needs_parens(code.syntax(), &text) {
+                text = format!("({text})");
+            }
+        }
+    }
+    let replacement = Replacement {
+        range: m.range,
+        text,
+        rule_index: m.rule_index,
+    };
+    validate(sema, &replacement)?;
+    Ok(replacement)
+}
+
+/// Substitute the placeholder values bound by the match into the
+/// template text of the rule.
+fn template_text(sema: &Semantic, rule: &SsrPattern, m: &Match) -> Result<String, SsrError> {
+    let rule_text = sema.db.lookup_ssr(rule.ssr_source);
+    let template = SourceFile::parse_text(&rule_text)
+        .tree()
+        .forms()
+        .find_map(|form| ast::SsrDefinition::cast(form.syntax().clone()))
+        .and_then(|ssr| ssr.rhs()?.expr())
+        .ok_or_else(|| error!("Rule has no template"))?;
+    let template_start = template.syntax().text_range().start();
+    let mut text = template.syntax().text().to_string();
+
+    let mut placeholders: Vec<ast::Var> = template
+        .syntax()
+        .descendants()
+        .filter_map(ast::Var::cast)
+        .filter(|var| var.is_ssr_placeholder())
+        .collect();
+    // Substitute from the end, so the earlier ranges remain valid.
+    placeholders.sort_by_key(|var| Reverse(var.syntax().text_range().start()));
+    for var in placeholders {
+        let name = var.syntax().text().to_string();
+        let value = m
+            .placeholder_texts(sema, &name)
+            .and_then(|texts| texts.into_iter().next())
+            .ok_or_else(|| error!("Placeholder `{}` is not bound by the search pattern", name))?;
+        let range: Range<usize> = (var.syntax().text_range() - template_start).into();
+        if needs_parens(var.syntax(), &value) {
+            text.replace_range(range, &format!("({value})"));
+        } else {
+            text.replace_range(range, &value);
+        }
+    }
+    let clause_name = rule
+        .clause
+        .as_ref()
+        .and_then(|clause| clause.template_name.as_deref());
+    match clause_name {
+        Some(name) => clause_template_text(sema, m, name, &text),
+        None => Ok(text),
+    }
+}
+
+/// The template of a clause rule is lowered as a `fun`, so turn its
+/// text back into a function clause named `name`, which is an atom
+/// or a placeholder bound by the search pattern.
+fn clause_template_text(
+    sema: &Semantic,
+    m: &Match,
+    name: &str,
+    fun_text: &str,
+) -> Result<String, SsrError> {
+    let name = if name.starts_with("_@") {
+        m.placeholder_texts(sema, name)
+            .and_then(|texts| texts.into_iter().next())
+            .ok_or_else(|| error!("Placeholder `{}` is not bound by the search pattern", name))?
+    } else {
+        name.to_string()
+    };
+    let clause = fun_text
+        .strip_prefix("fun")
+        .and_then(|text| text.strip_suffix("end"))
+        .ok_or_else(|| error!("Template is not a function clause"))?;
+    Ok(format!("{name}{}", clause.trim_end()))
+}
+
+/// The outermost expression of the code at `range`, which is the one
+/// a replacement takes the place of.
+fn replaced_expr(sema: &Semantic, range: FileRange) -> Option<ast::Expr> {
+    sema.parse(range.file_id)
+        .value
+        .syntax()
+        .descendants()
+        .filter_map(ast::Expr::cast)
+        .find(|expr| expr.syntax().text_range() == range.range)
+}
+
+/// Whether `value`, put in the place of `node`, must be wrapped in
+/// parentheses to keep its meaning. This is the case both for the
+/// value of a placeholder in a template, for example `_@A * 2` with
+/// `_@A` bound to `X + 1` must become `(X + 1) * 2`, and for a whole
+/// replacement in the code, for example `2 * foo(X)` with the rule
+/// `foo(_@A) ==>> _@A + 1` must become `2 * (X + 1)`.
+fn needs_parens(node: &SyntaxNode, value: &str) -> bool {
+    let value = match parse_expr(value) {
+        Some(value) => value,
+        None => return false,
+    };
+    let inner = binding_power(&value);
+    let parent = match node.parent().and_then(ast::Expr::cast) {
+        Some(parent) => parent,
+        None => return false,
+    };
+    let outer = binding_power(&parent);
+    let is_lhs = |lhs: Option<ast::Expr>| lhs.map_or(false, |lhs| lhs.syntax() == node);
+    match &parent {
+        ast::Expr::BinaryOpExpr(binary) => {
+            let assoc = binary
+                .op()
+                .map_or(Assoc::Left, |(op, _)| binary_op_assoc(op));
+            needs_parens_in_operand(inner, outer, assoc, is_lhs(binary.lhs()))
+        }
+        ast::Expr::MatchExpr(match_expr) => {
+            needs_parens_in_operand(inner, outer, Assoc::Right, is_lhs(match_expr.lhs()))
+        }
+        ast::Expr::UnaryOpExpr(_) => inner < outer,
+        _ => false,
+    }
+}
+
+fn needs_parens_in_operand(inner: u8, outer: u8, assoc: Assoc, is_lhs: bool) -> bool {
+    match inner.cmp(&outer) {
+        Ordering::Less => true,
+        Ordering::Equal => match assoc {
+            Assoc::Left => !is_lhs,
+            Assoc::Right => is_lhs,
+            Assoc::None => true,
+        },
+        Ordering::Greater => false,
+    }
+}
+
+/// Parse `text` as a single expression.
+fn parse_expr(text: &str) -> Option<ast::Expr> {
+    let prefix = "f() -> ";
+    let file = SourceFile::parse_text(&format!("{prefix}{text}."));
+    let range = TextRange::at(TextSize::of(prefix), TextSize::of(text));
+    file.tree()
+        .syntax()
+        .descendants()
+        .filter_map(ast::Expr::cast)
+        .find(|expr| expr.syntax().text_range() == range)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Assoc {
+    Left,
+    Right,
+    None,
+}
+
+/// How tightly an expression binds, following the operator
+/// precedence of the Erlang reference manual. Higher binds tighter,
+/// and expressions which are not operators bind tightest of all.
+fn binding_power(expr: &ast::Expr) -> u8 {
+    match expr {
+        ast::Expr::CatchExpr(_) => 1,
+        ast::Expr::MatchExpr(_) => 2,
+        ast::Expr::BinaryOpExpr(binary) => binary.op().map_or(10, |(op, _)| binary_op_power(op)),
+        ast::Expr::UnaryOpExpr(_) => 9,
+        _ => 10,
+    }
+}
+
+fn binary_op_power(op: BinaryOp) -> u8 {
+    match op {
+        BinaryOp::Send => 2,
+        BinaryOp::LogicOp(LogicOp::Or { lazy: true }) => 3,
+        BinaryOp::LogicOp(LogicOp::And { lazy: true }) => 4,
+        BinaryOp::CompOp(_) => 5,
+        BinaryOp::ListOp(_) => 6,
+        BinaryOp::LogicOp(LogicOp::Or { lazy: false } | LogicOp::Xor)
+        | BinaryOp::ArithOp(
+            ArithOp::Add
+            | ArithOp::Sub
+            | ArithOp::Bor
+            | ArithOp::Bxor
+            | ArithOp::Bsl
+            | ArithOp::Bsr,
+        ) => 7,
+        BinaryOp::LogicOp(LogicOp::And { lazy: false })
+        | BinaryOp::ArithOp(
+            ArithOp::Mul | ArithOp::FloatDiv | ArithOp::Div | ArithOp::Rem | ArithOp::Band,
+        ) => 8,
+    }
+}
+
+fn binary_op_assoc(op: BinaryOp) -> Assoc {
+    match op {
+        BinaryOp::Send | BinaryOp::ListOp(_) => Assoc::Right,
+        BinaryOp::CompOp(_) => Assoc::None,
+        BinaryOp::LogicOp(_) | BinaryOp::ArithOp(_) => Assoc::Left,
+    }
+}
+
+/// Re-parse the file with the replacement applied, and reject it if
+/// it introduces a syntax error.
+fn validate(sema: &Semantic, replacement: &Replacement) -> Result<(), SsrError> {
+    let file_text = sema.db.file_text(replacement.range.file_id);
+    let errors_before = SourceFile::parse_text(&file_text).errors().len();
+    let mut edited = file_text.to_string();
+    let range: Range<usize> = replacement.range.range.into();
+    edited.replace_range(range, &replacement.text);
+    let errors_after = SourceFile::parse_text(&edited).errors().len();
+    if errors_after > errors_before {
+        bail!(
+            "Replacing `{}` with `{}` introduces a syntax error",
+            &file_text[replacement.range.range],
+            replacement.text
+        );
+    }
+    Ok(())
+}
@@ -10,16 +10,23 @@
 //! Searching for matches.
 
 use elp_ide_db::elp_base_db::FileRange;
+use elp_syntax::ast;
+use elp_syntax::AstNode;
 use hir::fold::fold_body;
 use hir::AnyExprId;
 use hir::BodyOrigin;
+use hir::Expr;
 use hir::FoldBody;
 use hir::FormIdx;
+use hir::InFile;
 
 use crate::matching;
 use crate::matching::Match;
+use crate::matching::SubId;
+use crate::ClauseName;
 use crate::MatchFinder;
 use crate::SsrPattern;
+use crate::SsrSearchScope;
 
 impl MatchFinder<'_> {
     /// Adds all matches for `rule` to `matches_out`. Matches may
@@ -30,7 +37,85 @@ impl MatchFinder<'_> {
     pub(crate) fn find_matches_for_rule(&self, rule: &SsrPattern, matches_out: &mut Vec<Match>) {
         let pattern_body = rule.get_body(self.sema).expect("Cannot get pattern_body");
         let pattern_body = fold_body(self.strategy, &pattern_body);
-        self.slow_scan_node(rule, &None, matches_out, &pattern_body);
+        match &rule.clause {
+            Some(clause) => {
+                self.scan_function_clauses(rule, &clause.name, matches_out, &pattern_body)
+            }
+            None => self.slow_scan_node(rule, &None, matches_out, &pattern_body),
+        }
+    }
+
+    /// Match a clause rule against each clause of the functions in
+    /// scope, independently of the other clauses of the function.
+    fn scan_function_clauses(
+        &self,
+        rule: &SsrPattern,
+        clause_name: &ClauseName,
+        matches_out: &mut Vec<Match>,
+        pattern_body: &FoldBody,
+    ) {
+        let pattern_expr = rule.pattern_node.expr;
+        let pattern_clause = match &pattern_body[pattern_expr] {
+            Expr::Closure {
+                clauses,
+                name: None,
+            } if clauses.len() == 1 => &clauses[0],
+            _ => return,
+        };
+        let (file_id, only_function) = match &self.scope {
+            SsrSearchScope::WholeFile(file_id) | SsrSearchScope::FunctionsOnly(file_id) => {
+                (*file_id, None)
+            }
+            SsrSearchScope::Function(file_id, name) => (*file_id, Some(name)),
+        };
+        let source = self.sema.parse(file_id).value;
+        let def_map = self.sema.def_map_local(file_id);
+        for (name, def) in def_map.get_functions() {
+            if only_function.map_or(false, |only| only != name) || !clause_name.admits(name.name())
+            {
+                continue;
+            }
+            let name_atom = self.sema.db.atom(name.name().clone());
+            for (clause_id, clause) in def.function_clause_ids.iter().zip(&def.function_clauses) {
+                let ast_clause = match clause.form_id.get(&source).clause() {
+                    Some(ast_clause) => ast_clause,
+                    None => continue,
+                };
+                let range = ast_clause.syntax().text_range();
+                // The name of a clause produced by a macro is not
+                // written in it, so a placeholder cannot be bound to it.
+                let name_range = match &ast_clause {
+                    ast::FunctionOrMacroClause::FunctionClause(function_clause) => {
+                        function_clause.name().map(|name| FileRange {
+                            file_id,
+                            range: name.syntax().text_range(),
+                        })
+                    }
+                    ast::FunctionOrMacroClause::MacroCallExpr(_) => None,
+                };
+                let clause_body = self
+                    .sema
+                    .db
+                    .function_clause_body(InFile::new(file_id, *clause_id));
+                let code_body = fold_body(self.strategy, &clause_body.body);
+                if let Ok(m) = matching::get_clause_match(
+                    self.debug_print,
+                    rule,
+                    &SubId::AnyExprId(AnyExprId::Expr(pattern_expr)),
+                    pattern_clause,
+                    &BodyOrigin::new(file_id, FormIdx::FunctionClause(*clause_id)),
+                    *clause_id,
+                    &clause_body.clause,
+                    FileRange { file_id, range },
+                    name_range.map(|name_range| (name_atom, name_range)),
+                    self.sema,
+                    &code_body,
+                    pattern_body,
+                ) {
+                    matches_out.push(m);
+                }
+            }
+        }
     }
 
     fn slow_scan_node(
@@ -50,6 +135,12 @@ impl MatchFinder<'_> {
             self.strategy,
             (),
             &mut |_acc, ctx| {
+                if !self.match_kind.admits(&ctx.item_id) {
+                    return;
+                }
+                if self.expanded_only && matches!(ctx.body_origin, BodyOrigin::Define { .. }) {
+                    return;
+                }
                 let code_body = &ctx
                     .body_origin
                     .get_body(self.sema)
@@ -13,15 +13,23 @@ use elp_ide_db::elp_base_db::fixture::WithFixture;
 use elp_ide_db::elp_base_db::FileId;
 use elp_ide_db::elp_base_db::FilePosition;
 use elp_ide_db::elp_base_db::FileRange;
+use elp_ide_db::elp_base_db::SourceDatabaseExt;
 use elp_ide_db::RootDatabase;
 use expect_test::expect;
 use expect_test::Expect;
 use hir::fold::MacroStrategy;
 use hir::fold::ParenStrategy;
+use hir::Name;
+use hir::NameArity;
 use hir::Semantic;
 use hir::Strategy;
 
+use crate::builtin_rule_set;
+use crate::resolve_conflicts;
+use crate::ConflictPolicy;
 use crate::MatchFinder;
+use crate::Replacement;
+use crate::SsrMatchKind;
 use crate::SsrRule;
 use crate::SsrSearchScope;
 
@@ -138,6 +146,53 @@ fn parser_basic_query_with_cond() {
     );
 }
 
+#[test]
+fn parser_basic_query_with_multiple_conds() {
+    parse_good_text(
+        "ssr: _@V ==>> _@V + 1
+              when is_atom(_@V), _@V =/= foo
+         .
+        ",
+        expect![[r#"
+
+            SsrBody {
+                lhs
+                    expr
+                        Expr<0>:SsrPlaceholder {var: _@V, }
+                    pat
+                        Pat<0>:SsrPlaceholder {var: _@V, }
+                rhs
+                    Expr<3>:Expr::BinaryOp {
+                        lhs
+                            Expr<1>:SsrPlaceholder {var: _@V, }
+                        rhs
+                            Expr<2>:Literal(Integer(1))
+                        op
+                            ArithOp(Add),
+                    }
+                when
+                    guard
+                        Expr<6>:Expr::Call {
+                            target
+                                CallTarget::Local {
+                                    Expr<4>:Literal(Atom('is_atom'))
+                                }
+                            args
+                                Expr<5>:SsrPlaceholder {var: _@V, },
+                        },
+                        Expr<9>:Expr::BinaryOp {
+                            lhs
+                                Expr<7>:SsrPlaceholder {var: _@V, }
+                            rhs
+                                Expr<8>:Literal(Atom('foo'))
+                            op
+                                CompOp(Eq { strict: true, negated: true }),
+                        },
+            }
+        "#]],
+    );
+}
+
 // ---------------------------------------------------------------------
 
 /// `code` may optionally contain a cursor marker `~`. If it doesn't,
@@ -195,6 +250,31 @@ fn assert_matches(pattern: &str, code: &str, expected: &[&str]) {
 
 #[track_caller]
 fn assert_matches_with_strategy(strategy: Strategy, pattern: &str, code: &str, expected: &[&str]) {
+    assert_matches_with_strategy_and_kind(strategy, SsrMatchKind::Any, pattern, code, expected);
+}
+
+#[track_caller]
+fn assert_matches_of_kind(match_kind: SsrMatchKind, pattern: &str, code: &str, expected: &[&str]) {
+    assert_matches_with_strategy_and_kind(
+        Strategy {
+            macros: MacroStrategy::Expand,
+            parens: ParenStrategy::InvisibleParens,
+        },
+        match_kind,
+        pattern,
+        code,
+        expected,
+    );
+}
+
+#[track_caller]
+fn assert_matches_with_strategy_and_kind(
+    strategy: Strategy,
+    match_kind: SsrMatchKind,
+    pattern: &str,
+    code: &str,
+    expected: &[&str],
+) {
     let (db, position, _selections) = single_file(code);
     if expected.len() > 0 {
         if expected[0] == "" {
@@ -206,6 +286,7 @@ fn assert_matches_with_strategy(strategy: Strategy, pattern: &str, code: &str, e
     let mut match_finder =
         MatchFinder::in_context(&sema, strategy, SsrSearchScope::WholeFile(position.file_id));
     match_finder.debug_print = false;
+    match_finder.set_match_kind(match_kind);
     match_finder.add_search_pattern(pattern);
     let matched_strings: Vec<String> = match_finder
         .matches()
@@ -301,6 +382,33 @@ fn ssr_block_expr_match_multiple_statements() {
     );
 }
 
+#[test]
+fn ssr_block_expr_match_tail_two_statements() {
+    assert_matches(
+        "ssr: begin _@A = _@B, _@Rest... end.",
+        "foo() -> begin X = 10, X end.",
+        &["begin X = 10, X end"],
+    );
+}
+
+#[test]
+fn ssr_block_expr_match_tail_four_statements() {
+    assert_matches(
+        "ssr: begin _@A = _@B, _@Rest... end.",
+        "foo() -> begin X = 10, Y = 20, Z = X + Y, Z end.",
+        &["begin X = 10, Y = 20, Z = X + Y, Z end"],
+    );
+}
+
+#[test]
+fn ssr_block_expr_match_tail_needs_a_statement() {
+    assert_matches(
+        "ssr: begin _@A = _@B, _@Rest... end.",
+        "foo() -> begin X = 10 end, begin bar(), X end.",
+        &[],
+    );
+}
+
 #[test]
 fn ssr_expr_match_tuple() {
     assert_matches(
@@ -324,6 +432,63 @@ fn ssr_expr_match_tuple_nested() {
     );
 }
 
+#[test]
+fn ssr_expr_match_tuple_tail_three_elements() {
+    assert_matches(
+        "ssr: {foo, _@A, _@Rest...}.",
+        "fn() -> X = {foo, a, b}, X.",
+        &["{foo, a, b}"],
+    );
+}
+
+#[test]
+fn ssr_expr_match_tuple_tail_five_elements() {
+    assert_matches(
+        "ssr: {foo, _@A, _@Rest...}.",
+        "fn() -> X = {foo, a, b, c, d}, X.",
+        &["{foo, a, b, c, d}"],
+    );
+}
+
+#[test]
+fn ssr_expr_match_tuple_tail_needs_an_element() {
+    assert_matches(
+        "ssr: {foo, _@A, _@Rest...}.",
+        "fn() -> X = {foo, a}, Y = {bar, a, b}, {X, Y}.",
+        &[],
+    );
+}
+
+#[test]
+fn ssr_pat_match_tuple_tail() {
+    assert_matches(
+        "ssr: {foo, _@A, _@Rest...}.",
+        "fn({foo, A, B, C}) -> {A, B, C}.",
+        &["{foo, A, B, C}"],
+    );
+}
+
+#[test]
+fn ssr_expr_match_tuple_tail_ignores_strings() {
+    assert_matches(
+        "ssr: {\"_@A...\", '_@B...', _@Rest...}.",
+        "fn() -> X = {\"_@A...\", '_@B...', a, b}, Y = {\"_@A\", '_@B...', a}, {X, Y}.",
+        &["{\"_@A...\", '_@B...', a, b}"],
+    );
+}
+
+#[test]
+fn ssr_tail_placeholder_must_end_a_block_or_tuple() {
+    assert_eq!(
+        parse_error_text("ssr: {_@Rest..., foo}."),
+        "Parse error: Invalid tail placeholder `_@Rest...`, it must be the last element of a `begin ... end` block or a tuple"
+    );
+    assert_eq!(
+        parse_error_text("ssr: foo(_@Rest...)."),
+        "Parse error: Invalid tail placeholder `_@Rest...`, it must be the last element of a `begin ... end` block or a tuple"
+    );
+}
+
 #[test]
 fn ssr_record_expr_match() {
     assert_matches(
@@ -371,18 +536,77 @@ fn ssr_record_expr_match_record() {
 }
 
 #[test]
-#[ignore]
 fn ssr_record_expr_match_record_subset() {
-    // Note: this test currently fails.
-    // We need to extend the syntax to be able to say there are
-    // possibly don't care extra fields.
+    // Without `...`, extra fields are not allowed
     assert_matches(
         "ssr: #foo{k1 = _@A, k2 = _@B}.",
         "fn() -> X = #foo{k1 = a, k2 = <<\"blah\">>, k3 = {c, d}}, X.",
+        &[],
+    );
+    assert_matches(
+        "ssr: #foo{k1 = _@A, k2 = _@B, ...}.",
+        "fn() -> X = #foo{k1 = a, k2 = <<\"blah\">>, k3 = {c, d}}, X.",
         &["#foo{k1 = a, k2 = <<\"blah\">>, k3 = {c, d}}"],
     );
 }
 
+#[test]
+fn ssr_record_expr_match_record_subset_two_extra_fields() {
+    assert_matches(
+        "ssr: #foo{k2 = _@B, ...}.",
+        "fn() -> {#foo{k1 = a, k2 = b, k3 = c}, #foo{k1 = a}, #foo{k2 = b}}.",
+        &["#foo{k1 = a, k2 = b, k3 = c}", "#foo{k2 = b}"],
+    );
+    assert_matches(
+        "ssr: _@R#foo{k2 = _@B, ...}.",
+        "fn(R) -> R#foo{k3 = c, k2 = b, k1 = a}.",
+        &["R#foo{k3 = c, k2 = b, k1 = a}"],
+    );
+}
+
+#[test]
+fn ssr_record_subset_marker_only_applies_where_written() {
+    assert_matches(
+        "ssr: {#foo{k1 = _@A, ...}, #foo{k1 = _@B}}.",
+        "fn() -> {{#foo{k1 = a, k2 = b}, #foo{k1 = c}}, {#foo{k1 = a}, #foo{k1 = c, k2 = d}}}.",
+        &["{#foo{k1 = a, k2 = b}, #foo{k1 = c}}"],
+    );
+}
+
+#[test]
+fn ssr_record_subset_marker_in_string() {
+    assert_matches(
+        "ssr: #foo{k1 = \"...\"}.",
+        "fn() -> {#foo{k1 = \"...\"}, #foo{k1 = \"...\", k2 = b}}.",
+        &["#foo{k1 = \"...\"}"],
+    );
+}
+
+#[test]
+fn ssr_record_subset_in_function_clause() {
+    assert_matches(
+        "ssr: _@Name(#foo{k1 = _@A, ...}) -> _@B.",
+        "foo(#foo{k1 = a, k2 = b}) -> a; foo(#foo{k2 = b}) -> b.",
+        &["foo(#foo{k1 = a, k2 = b}) -> a"],
+    );
+}
+
+#[test]
+fn ssr_record_subset_marker_with_template() {
+    assert_eq!(
+        parse_error_text("ssr: #foo{k1 = _@A, ...} ==>> #foo{k1 = b}."),
+        "Parse error: Invalid `...`, a rule with a template cannot have open records"
+    );
+}
+
+#[test]
+fn ssr_record_subset_marker_outside_record() {
+    assert_eq!(
+        parse_error_text("ssr: {_@A, ...}."),
+        "Parse error: Invalid `...`, it can only end the fields of a record, such as `#foo{k = _@V, ...}`"
+    );
+}
+
 #[test]
 fn ssr_record_expr_match_unordered() {
     assert_matches(
@@ -439,6 +663,16 @@ fn ssr_expr_match_unary_op() {
     assert_matches("ssr: -_@A.", "fn(Y) -> -Y.", &["-Y"]);
 }
 
+#[test]
+fn ssr_expr_unary_op_does_not_match_binary_op() {
+    assert_matches("ssr: -_@A.", "fn(X, Y) -> X - Y.", &[]);
+    assert_matches("ssr: +_@A.", "fn(X, Y) -> X + Y.", &[]);
+    assert_matches("ssr: _@A - _@B.", "fn(Y) -> -Y.", &[]);
+    assert_matches("ssr: _@A + _@B.", "fn(Y) -> +Y.", &[]);
+    assert_matches("ssr: -_@A.", "fn(X, Y) -> X - (-Y).", &["-Y"]);
+    assert_matches("ssr: +_@A.", "fn(Y) -> -Y.", &[]);
+}
+
 #[test]
 fn ssr_expr_binary_op() {
     assert_matches("ssr: _@A + _@B.", "fn(X) -> Y = {X + 1}, Y.", &["X + 1"]);
@@ -499,6 +733,20 @@ fn ssr_expr_match_record_update() {
     );
 }
 
+#[test]
+fn ssr_expr_match_chained_record_update() {
+    assert_matches(
+        "ssr: _@R#rec1{a = _@A}#rec2{b = _@B}.",
+        "bar(R) -> R#rec1{a = 1}#rec2{b = 2}.",
+        &["R#rec1{a = 1}#rec2{b = 2}"],
+    );
+    assert_matches(
+        "ssr: _@R#rec1{a = _@A}#rec2{b = _@B}.",
+        "bar(R) -> R#rec2{b = 2}.",
+        &[],
+    );
+}
+
 #[test]
 fn ssr_expr_match_record_index() {
     assert_matches(
@@ -529,8 +777,6 @@ fn ssr_expr_match_record_field() {
 
 #[test]
 fn ssr_expr_match_map() {
-    // Note that the map operation is always Assoc (`=>`), as per the
-    // HIR lowering
     assert_matches(
         "ssr: #{ field => _@A }.",
         "bar() -> XX = 1, #{foo => XX}.",
@@ -549,6 +795,26 @@ fn ssr_expr_match_map() {
     assert_matches("ssr: #{ }.", "bar() -> #{}.", &["#{}"]);
 }
 
+#[test]
+fn ssr_expr_match_map_op() {
+    assert_matches(
+        "ssr: #{ field := _@A }.",
+        "bar() -> XX = 1, #{field => XX}.",
+        &[],
+    );
+    assert_matches(
+        "ssr: #{ field := _@A }.",
+        "bar() -> XX = 1, #{field := XX}.",
+        &["#{field := XX}"],
+    );
+    assert_matches(
+        "ssr: #{ field => _@A }.",
+        "bar() -> XX = 1, #{field := XX}.",
+        &[],
+    );
+    assert_matches("ssr: #{ }.", "bar() -> XX = 1, #{field := XX}.", &[]);
+}
+
 #[test]
 fn ssr_expr_match_map_update() {
     assert_matches(
@@ -571,6 +837,16 @@ fn ssr_expr_match_map_update() {
         "bar(List) -> XX = 1, List#{zz => 1, foo => XX}.",
         &[],
     );
+    assert_matches(
+        "ssr: _@A#{ foo := _@B }.",
+        "bar(List) -> XX = 1, List#{foo := XX}.",
+        &["List#{foo := XX}"],
+    );
+    assert_matches(
+        "ssr: _@A#{ foo := _@B }.",
+        "bar(List) -> XX = 1, List#{foo => XX}.",
+        &[],
+    );
 }
 
 #[test]
@@ -582,6 +858,21 @@ fn ssr_expr_match_catch() {
     );
 }
 
+#[test]
+fn ssr_expr_match_nested_catch() {
+    assert_matches(
+        "ssr: {ok, catch _@A}.",
+        "bar() -> {ok, catch foo()}.",
+        &["{ok, catch foo()}"],
+    );
+    assert_matches(
+        "ssr: catch _@A.",
+        "bar() -> {ok, [catch foo()]}.",
+        &["catch foo()"],
+    );
+    assert_matches("ssr: {ok, catch _@A}.", "bar() -> {ok, foo()}.", &[]);
+}
+
 #[test]
 fn ssr_expr_match_macro_call() {
     // TODO: fails because we do not have a visible macro call in the
@@ -639,6 +930,63 @@ fn ssr_expr_match_macro_call() {
     );
 }
 
+#[test]
+fn ssr_expr_match_expanded_only() {
+    let (db, position, _selections) = single_file(
+        "-define(BAR(X), {X}).
+         bar() -> ?BAR(4).",
+    );
+    let sema = Semantic::new(&db);
+    let pattern = SsrRule::parse_str(sema.db, "ssr: {_@X}.").unwrap();
+    let mut match_finder = MatchFinder::in_context(
+        &sema,
+        Strategy {
+            macros: MacroStrategy::DoNotExpand,
+            parens: ParenStrategy::InvisibleParens,
+        },
+        SsrSearchScope::WholeFile(position.file_id),
+    );
+    match_finder.set_expanded_only(true);
+    match_finder.add_search_pattern(pattern);
+    let matched_strings: Vec<String> = match_finder
+        .matches()
+        .flattened()
+        .matches
+        .iter()
+        .map(|m| m.matched_text(&db))
+        .collect();
+    assert_eq!(matched_strings, vec!["?BAR(4)"]);
+}
+
+#[test]
+fn ssr_expr_match_expanded_only_restores_strategy() {
+    let (db, position, _selections) = single_file(
+        "-define(BAR(X), {X}).
+         bar() -> ?BAR(4).",
+    );
+    let sema = Semantic::new(&db);
+    let pattern = SsrRule::parse_str(sema.db, "ssr: ?BAR(_@AA).").unwrap();
+    let mut match_finder = MatchFinder::in_context(
+        &sema,
+        Strategy {
+            macros: MacroStrategy::DoNotExpand,
+            parens: ParenStrategy::InvisibleParens,
+        },
+        SsrSearchScope::WholeFile(position.file_id),
+    );
+    match_finder.set_expanded_only(true);
+    match_finder.set_expanded_only(false);
+    match_finder.add_search_pattern(pattern);
+    let matched_strings: Vec<String> = match_finder
+        .matches()
+        .flattened()
+        .matches
+        .iter()
+        .map(|m| m.matched_text(&db))
+        .collect();
+    assert_eq!(matched_strings, vec!["?BAR(4)"]);
+}
+
 #[test]
 fn ssr_expr_list_comprehension() {
     assert_matches(
@@ -702,53 +1050,187 @@ fn ssr_expr_if() {
         "bar(F) -> if is_atom(F) -> 22 end.",
         &["if is_atom(F) -> 22 end"],
     );
+    assert_replacements(
+        "ssr: if _@Cond -> _@B end ==>> {_@Cond, _@B}.",
+        "bar(F) -> if is_atom(F) -> 22 end.",
+        &[Ok("{is_atom(F), 22}")],
+    );
 }
 
 #[test]
-fn ssr_expr_case() {
+fn ssr_expr_if_two_clauses() {
     assert_matches(
-        "ssr: case _@XX of _@A -> _@B end .",
-        "bar(F) -> XX = 1, case F of undefined -> XX end.",
-        &["case F of undefined -> XX end"],
+        "ssr: if _@C1 -> _@A; _@C2 -> _@B end.",
+        "bar(F) -> {if is_atom(F) -> 1; true -> 2 end, if is_atom(F) -> 1 end}.",
+        &["if is_atom(F) -> 1; true -> 2 end"],
+    );
+    assert_replacements(
+        "ssr: if _@C1 -> _@A; _@C2 -> _@B end ==>> {_@C1, _@A, _@C2, _@B}.",
+        "bar(F) -> if is_atom(F) -> 1; true -> 2 end.",
+        &[Ok("{is_atom(F), 1, true, 2}")],
     );
 }
 
 #[test]
-fn ssr_underscore_pattern_in_code_and_placeholder_in_ssr_do_not_match_if_atom_literal_pattens_do_no_match()
- {
-    assert_matches(
-        "ssr: case X of false -> _@BranchA; _@PatA -> _@BranchB end.",
-        "foo(X) -> case X of true -> a; _ -> b end.",
-        &[],
+fn ssr_replace_tuple_tail() {
+    assert_replacements(
+        "ssr: {foo, _@A, _@Rest...} ==>> {bar, _@Rest, _@A}.",
+        "bar() -> {foo, 1, 2, 3}.",
+        &[Ok("{bar, 2, 3, 1}")],
     );
 }
 
 #[test]
-fn ssr_expr_receive() {
+fn ssr_expr_if_guard_sequence() {
     assert_matches(
-        "ssr: receive _@XX -> 3 end.",
-        "bar(F) -> XX = 1, receive F -> 3 end.",
-        &["receive F -> 3 end"],
+        "ssr: if _@C1; _@C2 -> _@A end.",
+        "bar(F) -> {if is_atom(F); is_integer(F) -> 1 end, if is_atom(F), F =/= a -> 1 end}.",
+        &["if is_atom(F); is_integer(F) -> 1 end"],
     );
     assert_matches(
-        "ssr: receive _@XX -> 3 after _@MS -> ok end.",
-        "bar(F) -> XX = 1, receive F -> 3 end.",
-        &[],
+        "ssr: if _@C1, _@C2 -> _@A end.",
+        "bar(F) -> {if is_atom(F); is_integer(F) -> 1 end, if is_atom(F), F =/= a -> 1 end}.",
+        &["if is_atom(F), F =/= a -> 1 end"],
     );
+}
+
+#[test]
+fn ssr_expr_if_does_not_match_case() {
     assert_matches(
-        "ssr: receive _@XX -> 3 after _@MS -> ok end.",
-        "bar(F) -> XX = 1, receive F -> 3 after 1000 -> ok end.",
-        &["receive F -> 3 after 1000 -> ok end"],
+        "ssr: if _@Cond -> _@B end.",
+        "bar(F) -> case F of true -> 22 end.",
+        &[],
     );
     assert_matches(
-        "ssr: receive _@XX -> true; _@AA -> _@BB after _@MS -> timeout end.",
-        "bar() -> receive all_good -> true; X -> false after 1000 -> timeout end.",
-        &["receive all_good -> true; X -> false after 1000 -> timeout end"],
+        "ssr: case _@Cond of true -> _@B end.",
+        "bar(F) -> if F -> 22 end.",
+        &[],
     );
 }
 
 #[test]
-fn ssr_expr_try() {
+fn ssr_expr_case() {
+    assert_matches(
+        "ssr: case _@XX of _@A -> _@B end .",
+        "bar(F) -> XX = 1, case F of undefined -> XX end.",
+        &["case F of undefined -> XX end"],
+    );
+}
+
+#[test]
+fn ssr_function_clause() {
+    assert_matches(
+        "ssr: _@Name(_@A) -> _@B.",
+        "foo(undefined) -> none; foo(X) -> X + 1. bar(F, G) -> F.",
+        &["foo(undefined) -> none", "foo(X) -> X + 1"],
+    );
+}
+
+#[test]
+fn ssr_function_clause_named() {
+    assert_matches(
+        "ssr: foo(_@A) -> _@B.",
+        "foo(X) -> X. bar(X) -> X.",
+        &["foo(X) -> X"],
+    );
+}
+
+#[test]
+fn ssr_function_clause_with_guard() {
+    assert_matches(
+        "ssr: _@Name(_@A) when _@G -> _@B.",
+        "foo(X) when is_atom(X) -> X; foo(X) -> X. bar(F) when is_integer(F) -> F.",
+        &[
+            "foo(X) when is_atom(X) -> X",
+            "bar(F) when is_integer(F) -> F",
+        ],
+    );
+}
+
+#[test]
+fn ssr_function_clause_guard_alternatives() {
+    assert_matches(
+        "ssr: _@Name(_@X) when _@A; _@B -> _@Body.",
+        "foo(X) when is_atom(X); is_binary(X) -> X. bar(X) when is_atom(X), is_binary(X) -> X.",
+        &["foo(X) when is_atom(X); is_binary(X) -> X"],
+    );
+}
+
+#[test]
+fn ssr_function_clause_multiple_statements() {
+    assert_matches(
+        "ssr: _@Name(_@A) -> _@First, _@Rest... .",
+        "foo(X) -> Y = X + 1, Y. bar(X) -> X. baz(X) -> io:format(\"~p\", [X]), ok, X.",
+        &[
+            "foo(X) -> Y = X + 1, Y",
+            "baz(X) -> io:format(\"~p\", [X]), ok, X",
+        ],
+    );
+}
+
+#[test]
+fn ssr_function_clause_with_conditions() {
+    assert_matches(
+        "ssr: _@Name(_@A) when _@G -> _@B when is_atom(_@B).",
+        "foo(X) when X > 0 -> ok; foo(X) when X < 0 -> X. bar(X) -> ok.",
+        &["foo(X) when X > 0 -> ok"],
+    );
+}
+
+#[test]
+fn ssr_function_clause_name_condition() {
+    assert_matches(
+        "ssr: _@Name(_@A) -> _@B when _@Name == foo.",
+        "foo(X) -> X. bar(X) -> X.",
+        &["foo(X) -> X"],
+    );
+}
+
+#[test]
+fn ssr_function_clause_name_in_body() {
+    assert_matches(
+        "ssr: _@Name(_@A) -> _@Name(_@A).",
+        "loop(X) -> loop(X). other(X) -> loop(X).",
+        &["loop(X) -> loop(X)"],
+    );
+}
+
+#[test]
+fn ssr_underscore_pattern_in_code_and_placeholder_in_ssr_do_not_match_if_atom_literal_pattens_do_no_match()
+ {
+    assert_matches(
+        "ssr: case X of false -> _@BranchA; _@PatA -> _@BranchB end.",
+        "foo(X) -> case X of true -> a; _ -> b end.",
+        &[],
+    );
+}
+
+#[test]
+fn ssr_expr_receive() {
+    assert_matches(
+        "ssr: receive _@XX -> 3 end.",
+        "bar(F) -> XX = 1, receive F -> 3 end.",
+        &["receive F -> 3 end"],
+    );
+    assert_matches(
+        "ssr: receive _@XX -> 3 after _@MS -> ok end.",
+        "bar(F) -> XX = 1, receive F -> 3 end.",
+        &[],
+    );
+    assert_matches(
+        "ssr: receive _@XX -> 3 after _@MS -> ok end.",
+        "bar(F) -> XX = 1, receive F -> 3 after 1000 -> ok end.",
+        &["receive F -> 3 after 1000 -> ok end"],
+    );
+    assert_matches(
+        "ssr: receive _@XX -> true; _@AA -> _@BB after _@MS -> timeout end.",
+        "bar() -> receive all_good -> true; X -> false after 1000 -> timeout end.",
+        &["receive all_good -> true; X -> false after 1000 -> timeout end"],
+    );
+}
+
+#[test]
+fn ssr_expr_try() {
     assert_matches(
         r#"ssr:
              try _@AA of
@@ -788,6 +1270,71 @@ fn ssr_expr_capture_fun() {
     );
 }
 
+#[test]
+fn ssr_expr_capture_remote_fun() {
+    assert_matches(
+        "ssr: fun _@M:_@F/2.",
+        "bar(L) -> {fun lists:map/2, fun lists:sort/1, fun local/2, lists:map(fun local/2, L)}.",
+        &["fun lists:map/2"],
+    );
+    assert_replacements(
+        "ssr: fun _@M:_@F/_@A ==>> {_@M, _@F, _@A}.",
+        "bar() -> fun lists:map/2.",
+        &[Ok("{lists, map, 2}")],
+    );
+}
+
+#[test]
+fn ssr_expr_capture_local_fun() {
+    assert_matches(
+        "ssr: fun _@F/1.",
+        "bar(L) -> {fun local/1, fun local/2, fun lists:sort/1, local(L)}. local(L) -> L.",
+        &["fun local/1"],
+    );
+    assert_replacements(
+        "ssr: fun _@F/_@A ==>> {_@F, _@A}.",
+        "bar() -> fun local/1. local(L) -> L.",
+        &[Ok("{local, 1}")],
+    );
+}
+
+#[test]
+fn ssr_expr_capture_fun_arity_constraint() {
+    assert_matches(
+        "ssr: fun _@M:_@F/_@A=2.",
+        "bar(L) -> {fun lists:map/2, fun lists:sort/1, fun local/2, lists:map(fun local/2, L)}.",
+        &["fun lists:map/2"],
+    );
+    assert_matches(
+        "ssr: fun _@F/_@A=1.",
+        "bar(L) -> {fun local/1, fun local/2, local(L)}. local(L) -> L.",
+        &["fun local/1"],
+    );
+    assert_replacements(
+        "ssr: fun _@M:_@F/_@A=2 ==>> {_@M, _@F, _@A}.",
+        "bar() -> {fun lists:map/2, fun lists:sort/1}.",
+        &[Ok("{lists, map, 2}")],
+    );
+    // Only code is searched for constraints, not strings
+    assert_replacements(
+        "ssr: {_@X, _@Y} ==>> {_@Y, \"/_@A=two\"}.",
+        "bar() -> {a, b}.",
+        &[Ok("{b, \"/_@A=two\"}")],
+    );
+}
+
+#[test]
+fn ssr_expr_capture_fun_arity_constraint_errors() {
+    assert_eq!(
+        parse_error_text("ssr: fun _@M:_@F/_@A=two."),
+        "Parse error: Invalid arity constraint on `_@A`, expecting an integer"
+    );
+    assert_eq!(
+        parse_error_text("ssr: _@X/_@A=2."),
+        "Parse error: Invalid arity constraint on `_@A`, it must be the arity of a function reference, such as `fun _@F/_@A=2`"
+    );
+}
+
 #[test]
 fn ssr_expr_closure() {
     assert_matches(
@@ -1119,6 +1666,55 @@ fn ssr_pat_match_macro_call() {
     );
 }
 
+#[test]
+fn ssr_pat_kind_case_clause() {
+    let code = r#"
+         fn(R) ->
+             case R of
+                 {ok, X} -> {ok, X + 1};
+                 _ -> R
+             end.
+         "#;
+    assert_matches_of_kind(
+        SsrMatchKind::Any,
+        "ssr: {ok, _@V}.",
+        code,
+        &["{ok, X}", "{ok, X + 1}"],
+    );
+    assert_matches_of_kind(
+        SsrMatchKind::Patterns,
+        "ssr: {ok, _@V}.",
+        code,
+        &["{ok, X}"],
+    );
+    assert_matches_of_kind(
+        SsrMatchKind::Expressions,
+        "ssr: {ok, _@V}.",
+        code,
+        &["{ok, X + 1}"],
+    );
+}
+
+#[test]
+fn ssr_pat_kind_function_head() {
+    let code = r#"
+         fn({ok, X}, Y) -> {ok, Y};
+         fn({error, _}, Y) -> Y.
+         "#;
+    assert_matches_of_kind(
+        SsrMatchKind::Patterns,
+        "ssr: {ok, _@V}.",
+        code,
+        &["{ok, X}"],
+    );
+    assert_matches_of_kind(
+        SsrMatchKind::Patterns,
+        "ssr: {error, _@E}.",
+        code,
+        &["{error, _}"],
+    );
+}
+
 // ---------------------------------------------------------------------
 // Types.  Initially ensure we do not blow up on matches
 
@@ -1127,6 +1723,74 @@ fn ssr_spec_no_blowup() {
     assert_matches("ssr: _@X = 1.", "-spec foo() -> ok.", &[]);
 }
 
+#[test]
+fn ssr_type_match_remote_type() {
+    let code = r#"
+         -type q() :: queue:queue(integer()).
+         -spec f(queue:queue(atom()) | undefined) -> {ok, queue:queue()}.
+         f(_) -> queue:queue(1).
+         "#;
+    assert_matches_of_kind(
+        SsrMatchKind::Types,
+        "ssr: queue:queue(_@T).",
+        code,
+        &["queue:queue(integer())", "queue:queue(atom())"],
+    );
+    // The body of `f/1` calls a function of the same name
+    assert_matches_of_kind(
+        SsrMatchKind::Any,
+        "ssr: queue:queue(_@T).",
+        code,
+        &["queue:queue(1)"],
+    );
+}
+
+#[test]
+fn ssr_type_match_nested() {
+    assert_matches_of_kind(
+        SsrMatchKind::Types,
+        "ssr: {ok, _@T}.",
+        "-spec f() -> {ok, [{ok, atom()}]} | {error, {ok, term()}}.",
+        &["{ok, [{ok, atom()}]}", "{ok, atom()}", "{ok, term()}"],
+    );
+}
+
+#[test]
+fn ssr_type_match_kinds_of_list() {
+    assert_matches_of_kind(
+        SsrMatchKind::Types,
+        "ssr: [_@T].",
+        "-spec f([atom()], [integer(), ...]) -> [].",
+        &["[atom()]"],
+    );
+}
+
+#[test]
+fn ssr_type_match_placeholder() {
+    let (db, position, _selections) = single_file(
+        r#"
+        -record(r, {q :: queue:queue(binary())}).
+        "#,
+    );
+    let sema = Semantic::new(&db);
+    let mut match_finder = MatchFinder::in_context(
+        &sema,
+        Strategy {
+            macros: MacroStrategy::Expand,
+            parens: ParenStrategy::InvisibleParens,
+        },
+        SsrSearchScope::WholeFile(position.file_id),
+    );
+    match_finder.set_match_kind(SsrMatchKind::Types);
+    match_finder.add_search_pattern(SsrRule::parse_str(sema.db, "ssr: queue:queue(_@T).").unwrap());
+    let matches = match_finder.matches();
+    assert_eq!(matches.matches.len(), 1);
+    assert_eq!(
+        matches.matches[0].placeholder_text(&sema, "_@T"),
+        Some("binary()".to_string())
+    );
+}
+
 // ---------------------------------------------------------------------
 // Terms.  Initially ensure we do not blow up on matches
 
@@ -1156,6 +1820,76 @@ fn ssr_match_constant_negated_atom_placeholder() {
     );
 }
 
+#[test]
+fn ssr_match_multiple_conditions() {
+    assert_matches(
+        "ssr: {_@X = _@Y} when is_atom(_@X), _@X =/= foo.",
+        "foo() -> {foo = 3},{bar = 2},{4 = 1}.",
+        &["{bar = 2}"],
+    );
+}
+
+#[test]
+fn ssr_match_multiple_conditions_on_different_placeholders() {
+    assert_matches(
+        "ssr: {_@X = _@Y} when _@X =/= foo, _@Y == 2.",
+        "foo() -> {foo = 2},{bar = 2},{baz = 3}.",
+        &["{bar = 2}"],
+    );
+}
+
+#[test]
+fn ssr_match_alternative_conditions() {
+    assert_matches(
+        "ssr: {_@X = _@Y} when _@X == foo; _@Y == 3.",
+        "foo() -> {foo = 2},{bar = 2},{baz = 3}.",
+        &["{foo = 2}", "{baz = 3}"],
+    );
+}
+
+#[test]
+fn ssr_match_alternative_conjunctions() {
+    assert_matches(
+        "ssr: {_@X = _@Y} when _@X == foo, _@Y == 1; _@X == bar, _@Y == 2.",
+        "foo() -> {foo = 1},{foo = 2},{bar = 1},{bar = 2}.",
+        &["{foo = 1}", "{bar = 2}"],
+    );
+}
+
+#[test]
+fn ssr_match_integer_above_threshold() {
+    assert_matches(
+        "ssr: _@A = _@N when is_integer(_@N), _@N > 100.",
+        "foo() -> X = 10, Y = 100, Z = 1000, W = 200.5, {X, Y, Z, W}.",
+        &["Z = 1000"],
+    );
+}
+
+#[test]
+fn ssr_match_number_in_range() {
+    assert_matches(
+        "ssr: _@A = _@N when _@N >= 100, _@N =< 200.",
+        "foo() -> X = 10, Y = 100, Z = 1000, W = 200.5, V = 150.0, {X, Y, Z, W, V}.",
+        &["Y = 100", "V = 150.0"],
+    );
+}
+
+#[test]
+fn ssr_invalid_when_compare_not_number() {
+    expect![[r#"
+        "Parse error: Invalid `when` RHS, expecting a number"
+    "#]]
+    .assert_debug_eq(&parse_error_text("ssr: {_@X = _@Y} when _@X > foo."));
+}
+
+#[test]
+fn ssr_invalid_when_type_test_not_placeholder() {
+    expect![[r#"
+        "Parse error: Invalid `when` type test, expecting a placeholder argument"
+    "#]]
+    .assert_debug_eq(&parse_error_text("ssr: {_@X = _@Y} when is_atom(Y)."));
+}
+
 #[test]
 fn ssr_invalid_when_condition_not_literal() {
     expect![[r#"
@@ -1294,3 +2028,349 @@ fn ssr_complex_match() {
         &["lists:foldl(fun({K,V}, Acc) -> Acc#{K => V} end, #{}, List)"],
     );
 }
+
+#[track_caller]
+fn assert_replacements(pattern: &str, code: &str, expected: &[Result<&str, &str>]) {
+    let (db, position, _selections) = single_file(code);
+    let sema = Semantic::new(&db);
+    let pattern = SsrRule::parse_str(sema.db, pattern).unwrap();
+    let mut match_finder = MatchFinder::in_context(
+        &sema,
+        Strategy {
+            macros: MacroStrategy::Expand,
+            parens: ParenStrategy::InvisibleParens,
+        },
+        SsrSearchScope::WholeFile(position.file_id),
+    );
+    match_finder.add_search_pattern(pattern);
+    let replacements: Vec<Result<String, String>> = match_finder
+        .replacements(&match_finder.matches())
+        .into_iter()
+        .map(|r| r.map(|r| r.text).map_err(|e| e.0))
+        .collect();
+    let expected: Vec<Result<String, String>> = expected
+        .iter()
+        .map(|r| r.map(|s| s.to_string()).map_err(|s| s.to_string()))
+        .collect();
+    assert_eq!(replacements, expected);
+}
+
+#[test]
+fn ssr_replacement_substitutes_placeholders() {
+    assert_replacements(
+        "ssr: {_@A, _@B} ==>> {_@B, _@A, _@B}.",
+        "fn(X, Y) -> {X, foo(Y)}.",
+        &[Ok("{foo(Y), X, foo(Y)}")],
+    );
+}
+
+#[test]
+fn ssr_replacement_adds_parens_for_precedence() {
+    assert_replacements(
+        "ssr: double(_@A) ==>> _@A * 2.",
+        "fn(X, Y) -> {double(X + 1), double(Y * 3), double(f(X))}.",
+        &[Ok("(X + 1) * 2"), Ok("Y * 3 * 2"), Ok("f(X) * 2")],
+    );
+}
+
+#[test]
+fn ssr_replacement_adds_parens_for_associativity() {
+    assert_replacements(
+        "ssr: minus(_@A, _@B) ==>> _@A - _@B.",
+        "fn(X, Y, Z) -> minus(X - Y, Y - Z).",
+        &[Ok("X - Y - (Y - Z)")],
+    );
+}
+
+#[test]
+fn ssr_replacement_adds_parens_for_unary_operand() {
+    assert_replacements(
+        "ssr: negate(_@A) ==>> not _@A.",
+        "fn(X) -> negate(X andalso true).",
+        &[Ok("not (X andalso true)")],
+    );
+}
+
+#[test]
+fn ssr_replacement_adds_parens_in_code() {
+    assert_replacements(
+        "ssr: foo(_@A) ==>> _@A + 1.",
+        "fn(X) -> {2 * foo(X), foo(X) + 2, foo(X)}.",
+        &[Ok("2 * (X + 1)"), Ok("X + 1 + 2"), Ok("X + 1")],
+    );
+}
+
+#[test]
+fn ssr_replacement_changes_map_op() {
+    assert_replacements(
+        "ssr: _@M#{field => _@A} ==>> _@M#{field := _@A}.",
+        "fn(M, X) -> {M#{field => X}, M#{field := X}}.",
+        &[Ok("M#{field := X}")],
+    );
+}
+
+#[test]
+fn ssr_replacement_without_template() {
+    assert_replacements(
+        "ssr: {_@A, _@B}.",
+        "fn(X, Y) -> {X, Y}.",
+        &[Err("Rule has no template")],
+    );
+}
+
+#[test]
+fn ssr_replacement_conflicts_between_rules() {
+    let (db, position, _selections) = single_file("fn(X) -> {foo(X), 1}.");
+    let sema = Semantic::new(&db);
+    let mut match_finder = MatchFinder::in_context(
+        &sema,
+        Strategy {
+            macros: MacroStrategy::Expand,
+            parens: ParenStrategy::InvisibleParens,
+        },
+        SsrSearchScope::WholeFile(position.file_id),
+    );
+    for rule in [
+        "ssr: {_@A, _@B} ==>> {_@B, _@A}.",
+        "ssr: foo(_@X) ==>> bar(_@X).",
+    ] {
+        match_finder.add_search_pattern(SsrRule::parse_str(sema.db, rule).unwrap());
+    }
+    let replacements: Vec<Replacement> = match_finder
+        .replacements(&match_finder.matches())
+        .into_iter()
+        .map(|r| r.unwrap())
+        .collect();
+    let describe = |r: &Replacement| (r.rule_index, r.text.clone());
+
+    let conflicts = resolve_conflicts(replacements.clone(), ConflictPolicy::Error).unwrap_err();
+    let conflicts: Vec<_> = conflicts
+        .iter()
+        .map(|c| (describe(&c.first), describe(&c.second)))
+        .collect();
+    assert_eq!(
+        conflicts,
+        vec![((0, "{1, foo(X)}".to_string()), (1, "bar(X)".to_string()))]
+    );
+
+    let resolved = resolve_conflicts(replacements, ConflictPolicy::FirstWins).unwrap();
+    let kept: Vec<_> = resolved.replacements.iter().map(describe).collect();
+    assert_eq!(kept, vec![(0, "{1, foo(X)}".to_string())]);
+    assert_eq!(resolved.conflicts.len(), 1);
+}
+
+#[test]
+fn ssr_replacement_rejects_syntax_error() {
+    // A `catch` expression cannot be a binary element without
+    // parentheses, so the rewritten code would not parse.
+    assert_replacements(
+        "ssr: {_@A} ==>> catch _@A.",
+        "fn(X) -> {X}, <<{X}>>.",
+        &[
+            Ok("catch X"),
+            Err("Replacing `{X}` with `catch X` introduces a syntax error"),
+        ],
+    );
+}
+
+#[track_caller]
+fn assert_rewrite(pattern: &str, code: &str, expected: Option<&str>) {
+    let (db, position, _selections) = single_file(code);
+    let sema = Semantic::new(&db);
+    let pattern = SsrRule::parse_str(sema.db, pattern).unwrap();
+    let mut match_finder = MatchFinder::in_context(
+        &sema,
+        Strategy {
+            macros: MacroStrategy::Expand,
+            parens: ParenStrategy::InvisibleParens,
+        },
+        SsrSearchScope::WholeFile(position.file_id),
+    );
+    match_finder.add_search_pattern(pattern);
+    let text = db.file_text(position.file_id);
+    let rewritten = match_finder
+        .rewrite(position.file_id)
+        .map(|change| change.preview(position.file_id, &text));
+    assert_eq!(rewritten.as_deref(), expected);
+}
+
+#[test]
+fn ssr_rewrite_collapses_add_zero() {
+    assert_rewrite(
+        "ssr: _@V + 0 ==>> _@V.",
+        "fn(X, Y) -> {X + 0, Y + 1}.",
+        Some("fn(X, Y) -> {X, Y + 1}."),
+    );
+}
+
+#[test]
+fn ssr_rewrite_repeated_placeholder() {
+    assert_rewrite(
+        "ssr: double(_@A) ==>> _@A + _@A.",
+        "fn(X) -> double(foo(X)).",
+        Some("fn(X) -> foo(X) + foo(X)."),
+    );
+}
+
+#[test]
+fn ssr_rewrite_multi_line_placeholder() {
+    assert_rewrite(
+        "ssr: _@V + 0 ==>> _@V.",
+        "fn(X) ->\n    foo(X,\n        bar) + 0.",
+        Some("fn(X) ->\n    foo(X,\n        bar)."),
+    );
+}
+
+#[test]
+fn ssr_rewrite_skips_inner_match() {
+    // `X + 0 + 0` is `(X + 0) + 0`, so `X + 0` is matched both on its
+    // own and as the value of `_@V` in the outer match.
+    assert_rewrite(
+        "ssr: _@V + 0 ==>> _@V.",
+        "fn(X) -> X + 0 + 0.",
+        Some("fn(X) -> X + 0."),
+    );
+}
+
+#[test]
+fn ssr_rewrite_function_clause() {
+    assert_rewrite(
+        "ssr: foo(_@A) -> _@B ==>> bar(_@A) -> _@B.",
+        "foo(X) -> X + 1. baz(X) -> X.",
+        Some("bar(X) -> X + 1. baz(X) -> X."),
+    );
+}
+
+#[test]
+fn ssr_rewrite_function_clause_with_name_placeholder() {
+    assert_rewrite(
+        "ssr: _@Name(_@A, _@B) when _@G -> _@C ==>> _@Name(_@B, _@A) when _@G -> _@C when _@Name == add.",
+        "add(X, Y) when X > Y -> X + Y. sub(X, Y) when X > Y -> X - Y.",
+        Some("add(Y, X) when X > Y -> X + Y. sub(X, Y) when X > Y -> X - Y."),
+    );
+}
+
+#[test]
+fn ssr_rewrite_without_matches() {
+    assert_rewrite("ssr: _@V + 0 ==>> _@V.", "fn(X) -> X + 1.", None);
+}
+
+#[track_caller]
+fn assert_builtin_replacements(name: &str, code: &str, expected: &[&str]) {
+    let (db, position, _selections) = single_file(code);
+    let sema = Semantic::new(&db);
+    let mut match_finder = MatchFinder::in_context(
+        &sema,
+        Strategy {
+            macros: MacroStrategy::Expand,
+            parens: ParenStrategy::InvisibleParens,
+        },
+        SsrSearchScope::WholeFile(position.file_id),
+    );
+    for rule in builtin_rule_set(name).unwrap().rules(sema.db).unwrap() {
+        match_finder.add_search_pattern(rule);
+    }
+    let replacements: Vec<String> = match_finder
+        .replacements(&match_finder.matches())
+        .into_iter()
+        .map(|r| r.unwrap().text)
+        .collect();
+    assert_eq!(replacements, expected);
+}
+
+#[test]
+fn ssr_builtin_deprecated_random_uniform() {
+    assert_builtin_replacements(
+        "deprecated-random",
+        "fn(N) -> {random:uniform(), random:uniform(N + 1), rand:uniform(N)}.",
+        &["rand:uniform()", "rand:uniform(N + 1)"],
+    );
+}
+
+#[test]
+fn ssr_builtin_deprecated_random_seed() {
+    assert_builtin_replacements(
+        "deprecated-random",
+        "fn(A, B, C, S) -> random:seed(), random:seed(S), random:seed(A, B, C).",
+        &[
+            "rand:seed(exsss)",
+            "rand:seed(exsss, S)",
+            "rand:seed(exsss, {A, B, C})",
+        ],
+    );
+}
+
+#[test]
+fn ssr_builtin_rule_sets_parse() {
+    let (db, _position, _selections) = single_file("fn() -> ok.");
+    let sema = Semantic::new(&db);
+    for set in crate::BUILTIN_RULE_SETS {
+        assert!(set.rules(sema.db).is_ok(), "{} does not parse", set.name);
+    }
+    assert!(builtin_rule_set("no-such-rules").is_none());
+}
+
+#[test]
+fn ssr_in_function_only_matches_target() {
+    let (db, position, _selections) = single_file(
+        r#"
+        f(X) -> foo(X).
+        g(X) -> foo(X), foo(1).
+        h(Y) -> foo(Y).
+        "#,
+    );
+    let sema = Semantic::new(&db);
+    let mut match_finder = MatchFinder::in_function(
+        &sema,
+        Strategy {
+            macros: MacroStrategy::Expand,
+            parens: ParenStrategy::InvisibleParens,
+        },
+        position.file_id,
+        NameArity::new(Name::from_erlang_service("g"), 1),
+    );
+    match_finder.add_search_pattern(SsrRule::parse_str(sema.db, "ssr: foo(_@A).").unwrap());
+    let matched: Vec<String> = match_finder
+        .matches()
+        .flattened()
+        .matches
+        .iter()
+        .map(|m| m.matched_text(&db))
+        .collect();
+    // `f/1` contains the same code as `g/1`, but is not searched
+    assert_eq!(matched, vec!["foo(X)", "foo(1)"]);
+}
+
+#[test]
+fn ssr_matches_json() {
+    let (db, position, _selections) = single_file(
+        r#"
+//- /src/main.erl
+-module(main).
+f(X) -> foo(X, 1).
+"#,
+    );
+    let sema = Semantic::new(&db);
+    let mut match_finder = MatchFinder::in_context(
+        &sema,
+        Strategy {
+            macros: MacroStrategy::Expand,
+            parens: ParenStrategy::InvisibleParens,
+        },
+        SsrSearchScope::WholeFile(position.file_id),
+    );
+    match_finder.add_search_pattern(SsrRule::parse_str(sema.db, "ssr: foo(_@A, _@B).").unwrap());
+    let json = match_finder.matches_json().unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(
+        value,
+        serde_json::json!([{
+            "path": "/src/main.erl",
+            "start": 23,
+            "end": 32,
+            "text": "foo(X, 1)",
+            "placeholders": {"_@A": "X", "_@B": "1"},
+            "rule_index": 0,
+        }])
+    );
+}
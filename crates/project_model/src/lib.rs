@@ -65,6 +65,7 @@ pub mod test_fixture;
 
 pub const ELP_CONFIG_FILE: &str = ".elp.toml";
 pub const BUILD_INFO_FILE: &str = "build_info.json";
+pub const EQWALIZER_CONFIG_FILE: &str = ".eqwalizer";
 
 pub struct CommandProxy<'a> {
     _guard: MutexGuard<'a, ()>,
@@ -283,8 +284,8 @@ impl ProjectManifest {
         let _timer = timeit!("discover all projects");
         // First check for a json config file as the path.
         if let Some("json") = path.extension() {
-            let json = json::JsonConfig::try_parse(path)?;
-            return Ok((ElpConfig::default(), ProjectManifest::Json(json)));
+            let json = ProjectManifest::Json(json::JsonConfig::try_parse(path)?);
+            return Ok((ElpConfig::for_manifest(&json)?, json));
         }
 
         if let Some(elp_config) = Self::discover_toml(path)? {
@@ -320,15 +321,13 @@ impl ProjectManifest {
             }
         }
         if let Some(r) = Self::discover_rebar(path, None, IncludeParentDirs::Yes)? {
-            return Ok((ElpConfig::default(), r));
+            return Ok((ElpConfig::for_manifest(&r)?, r));
         }
         if let Some(s) = Self::discover_static(path, IncludeParentDirs::Yes)? {
-            return Ok((ElpConfig::default(), s));
+            return Ok((ElpConfig::for_manifest(&s)?, s));
         }
-        Ok((
-            ElpConfig::default(),
-            Self::discover_no_manifest(path, IncludeParentDirs::Yes),
-        ))
+        let no_manifest = Self::discover_no_manifest(path, IncludeParentDirs::Yes);
+        Ok((ElpConfig::for_manifest(&no_manifest)?, no_manifest))
     }
 
     /// Given the path of the `ELP_CONFIG_FILE` file, discover its configuration.
@@ -418,6 +417,75 @@ impl Default for EqwalizerConfig {
     }
 }
 
+impl EqwalizerConfig {
+    /// Resolve the eqWAlizer settings of the project rooted at `dir`.
+    /// The `explicit` settings, from the `[eqwalizer]` section of the
+    /// `ELP_CONFIG_FILE`, take precedence over those in the
+    /// `EQWALIZER_CONFIG_FILE`, which take precedence over the
+    /// defaults. Editor and CLI settings are applied on top of this
+    /// where the configuration is used.
+    pub fn resolve(dir: &AbsPath, explicit: &EqwalizerConfigFile) -> Result<EqwalizerConfig> {
+        let mut config = EqwalizerConfig::default();
+        if let Some(file) = EqwalizerConfigFile::try_parse(dir)? {
+            file.apply(&mut config);
+        }
+        explicit.apply(&mut config);
+        Ok(config)
+    }
+}
+
+/// Project-level eqWAlizer settings, read from the
+/// `EQWALIZER_CONFIG_FILE` at the root of the project. Each setting is
+/// optional, so that only those given override another source. The
+/// same file is used to opt an app in to eqWAlizer, so it may well be
+/// empty, or hold content which is not TOML at all.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct EqwalizerConfigFile {
+    pub enable_all: Option<bool>,
+    pub max_tasks: Option<usize>,
+}
+
+impl EqwalizerConfigFile {
+    /// Read the `EQWALIZER_CONFIG_FILE` in `dir`, if there is one. A
+    /// file which is not valid TOML is a plain marker, and holds no
+    /// settings.
+    pub fn try_parse(dir: &AbsPath) -> Result<Option<EqwalizerConfigFile>> {
+        let path = dir.join(EQWALIZER_CONFIG_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let config_content = fs::read_to_string(&path)?;
+        match toml::from_str(config_content.as_str()) {
+            Ok(config) => Ok(Some(config)),
+            Err(err) => {
+                log::warn!(
+                    "ignoring settings in {}, it is not valid TOML: {err}",
+                    path.as_path().as_os_str().to_string_lossy()
+                );
+                Ok(None)
+            }
+        }
+    }
+
+    /// Override the settings in `config` with those given here.
+    pub fn apply(&self, config: &mut EqwalizerConfig) {
+        if let Some(enable_all) = self.enable_all {
+            config.enable_all = enable_all;
+        }
+        if let Some(max_tasks) = self.max_tasks {
+            config.max_tasks = max_tasks;
+        }
+    }
+}
+
+/// The `[eqwalizer]` section of an `ELP_CONFIG_FILE`, keeping track of
+/// which settings are given explicitly.
+#[derive(Debug, Default, Deserialize)]
+struct ExplicitEqwalizerConfig {
+    #[serde(default)]
+    eqwalizer: EqwalizerConfigFile,
+}
+
 #[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct BuildInfoConfig {
     pub file: Option<PathBuf>,
@@ -503,6 +571,11 @@ impl ElpConfig {
         match toml::from_str(config_content.as_str()) {
             Ok(mut config) => {
                 BuckConfig::make_config(&path, &mut config)?;
+                if let Some(dir) = path.parent() {
+                    let explicit: ExplicitEqwalizerConfig =
+                        toml::from_str(config_content.as_str())?;
+                    config.eqwalizer = EqwalizerConfig::resolve(dir, &explicit.eqwalizer)?;
+                }
                 config.config_path = Some(path);
 
                 Ok(config)
@@ -514,6 +587,16 @@ impl ElpConfig {
         }
     }
 
+    /// The configuration of a project without an `ELP_CONFIG_FILE`,
+    /// which only picks up the `EQWALIZER_CONFIG_FILE` at its root.
+    fn for_manifest(manifest: &ProjectManifest) -> Result<ElpConfig> {
+        let mut config = ElpConfig::default();
+        if let Some(dir) = manifest.root().parent() {
+            config.eqwalizer = EqwalizerConfig::resolve(dir, &EqwalizerConfigFile::default())?;
+        }
+        Ok(config)
+    }
+
     pub fn buck_enabled(&self) -> bool {
         if let Some(buck) = &self.buck {
             buck.enabled
@@ -940,6 +1023,13 @@ impl ProjectAppData {
         self.macros.dedup();
         self.parse_transforms.extend(other.parse_transforms);
         self.parse_transforms.dedup();
+        self.is_test_target = self.is_test_target.or(other.is_test_target);
+        match (&mut self.applicable_files, other.applicable_files) {
+            (Some(files), Some(other_files)) => files.extend(other_files),
+            (files @ None, other_files) => *files = other_files,
+            (Some(_), None) => {}
+        }
+        self.ebin = self.ebin.take().or(other.ebin);
     }
 }
 
@@ -1484,6 +1574,101 @@ mod tests {
         .assert_eq(&debug_normalise_temp_dir(dir, &manifest));
     }
 
+    #[test]
+    fn test_eqwalizer_config_file() {
+        let spec = r#"
+        //- /build_info.json
+        {
+          "apps": [
+            {
+              "name": "app_a",
+              "dir": "app_a",
+              "src_dirs": ["src"]
+            }
+          ],
+          "deps": []
+        }
+        //- /.eqwalizer
+        enable_all = false
+        max_tasks = 8
+        //- /app_a/src/app.erl
+        -module(app).
+        "#;
+        let dir = FixtureWithProjectMeta::gen_project(spec);
+        let dir_path = AbsPathBuf::assert_utf8(fs::canonicalize(dir.path()).unwrap());
+        let (elp_config, _manifest) =
+            ProjectManifest::discover(&dir_path.join("build_info.json")).unwrap();
+        assert_eq!(
+            elp_config.eqwalizer,
+            EqwalizerConfig {
+                enable_all: false,
+                max_tasks: 8,
+            }
+        );
+    }
+
+    #[test]
+    fn test_eqwalizer_config_file_precedence() {
+        let spec = r#"
+        //- /.elp.toml
+        [eqwalizer]
+        max_tasks = 2
+        //- /.eqwalizer
+        enable_all = false
+        max_tasks = 8
+        //- /app_a/src/app.erl
+        -module(app).
+        "#;
+        let dir = FixtureWithProjectMeta::gen_project(spec);
+        let dir_path = AbsPathBuf::assert_utf8(fs::canonicalize(dir.path()).unwrap());
+        let elp_config = ElpConfig::try_parse(&dir_path).unwrap();
+        // The `.elp.toml` setting wins, the `.eqwalizer` one fills the gap
+        assert_eq!(
+            elp_config.eqwalizer,
+            EqwalizerConfig {
+                enable_all: false,
+                max_tasks: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn test_eqwalizer_config_file_empty() {
+        let spec = r#"
+        //- /.elp.toml
+        //- /.eqwalizer
+        //- /app_a/src/app.erl
+        -module(app).
+        "#;
+        let dir = FixtureWithProjectMeta::gen_project(spec);
+        let dir_path = AbsPathBuf::assert_utf8(fs::canonicalize(dir.path()).unwrap());
+        let elp_config = ElpConfig::try_parse(&dir_path).unwrap();
+        assert_eq!(elp_config.eqwalizer, EqwalizerConfig::default());
+    }
+
+    #[test]
+    fn test_eqwalizer_config_file_legacy_marker() {
+        let spec = r#"
+        //- /.elp.toml
+        [eqwalizer]
+        max_tasks = 2
+        //- /.eqwalizer
+        eqwalizer: enabled
+        //- /app_a/src/app.erl
+        -module(app).
+        "#;
+        let dir = FixtureWithProjectMeta::gen_project(spec);
+        let dir_path = AbsPathBuf::assert_utf8(fs::canonicalize(dir.path()).unwrap());
+        let elp_config = ElpConfig::try_parse(&dir_path).unwrap();
+        assert_eq!(
+            elp_config.eqwalizer,
+            EqwalizerConfig {
+                max_tasks: 2,
+                ..EqwalizerConfig::default()
+            }
+        );
+    }
+
     #[test]
     fn test_toml_empty() {
         // This one is a real worst-case. We force discovery to happen in
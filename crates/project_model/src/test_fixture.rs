@@ -79,11 +79,26 @@
 //! foo() -> ?BAR.
 //! "
 //! ```
+//!
+//! An app can declare the apps it depends on, in which case the include
+//! directories of those apps are added to its include path
+//! ```not_rust
+//! "
+//! //- /app_a/include/foo.hrl include_path:/app_a/include app:app_a
+//! -define(FOO,3).
+//! //- /app_b/src/bar.erl app:app_b deps:app_a
+//! -module(bar).
+//! -include("foo.hrl").
+//! bar() -> ?FOO.
+//! "
+//! ```
 
 use std::fs;
 use std::fs::File;
 use std::io::Write;
 
+use fxhash::FxHashMap;
+use fxhash::FxHashSet;
 use paths::AbsPath;
 use paths::AbsPathBuf;
 use paths::Utf8Path;
@@ -104,10 +119,39 @@ pub struct Fixture {
     pub text: String,
     pub app_data: ProjectAppData,
     pub otp: Option<Otp>,
+    pub deps: Vec<AppName>,
     pub tag: Option<String>,
     pub tags: Vec<(TextRange, Option<String>)>,
 }
 
+/// The on-disk location of each file of a fixture written by
+/// [`FixtureWithProjectMeta::gen_project_with_paths`], keyed by the
+/// `path` given in its meta line.
+#[derive(Clone, Debug, Default)]
+pub struct FixturePaths(FxHashMap<String, AbsPathBuf>);
+
+impl FixturePaths {
+    pub fn get(&self, fixture_path: &str) -> Option<&AbsPath> {
+        self.0.get(fixture_path).map(|path| path.as_path())
+    }
+
+    /// Map a fixture path to an id, once the project has been loaded,
+    /// for example using `Vfs::file_id`.
+    pub fn resolve<T>(
+        &self,
+        fixture_path: &str,
+        resolver: impl Fn(&AbsPath) -> Option<T>,
+    ) -> Option<T> {
+        self.get(fixture_path).and_then(resolver)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &AbsPath)> {
+        self.0
+            .iter()
+            .map(|(fixture_path, path)| (fixture_path.as_str(), path.as_path()))
+    }
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct DiagnosticsEnabled {
     pub use_native: bool,
@@ -138,6 +182,12 @@ impl DiagnosticsEnabled {
         *use_ct || *use_edoc
     }
 
+    /// eqWAlizer reads the project structure from a `build_info.json`
+    /// file, which is otherwise not needed to load a fixture.
+    pub fn needs_build_info(&self) -> bool {
+        self.use_eqwalizer
+    }
+
     #[track_caller]
     pub fn assert_ct_enabled(&self) {
         if !self.use_ct {
@@ -159,6 +209,30 @@ impl DiagnosticsEnabled {
         }
     }
 
+    /// Combine two configurations, enabling every diagnostic source
+    /// enabled in either. The temporary directory of `self` is kept
+    /// if it has one, otherwise that of `other`.
+    pub fn merge(self, other: DiagnosticsEnabled) -> DiagnosticsEnabled {
+        let DiagnosticsEnabled {
+            use_native,
+            use_erlang_service,
+            use_eqwalizer,
+            use_ct,
+            use_edoc,
+            tmp_dir,
+        } = other;
+        let mut merged = DiagnosticsEnabled {
+            use_native: self.use_native || use_native,
+            use_erlang_service: self.use_erlang_service || use_erlang_service,
+            use_eqwalizer: self.use_eqwalizer || use_eqwalizer,
+            use_ct: self.use_ct || use_ct,
+            use_edoc: self.use_edoc || use_edoc,
+            tmp_dir: self.tmp_dir.or(tmp_dir),
+        };
+        merged.set_default_native();
+        merged
+    }
+
     /// If no other diagnostics are enabled, enable native.
     /// If any are explicitly enabled, then native must also be
     /// explicitly enabled.
@@ -299,20 +373,36 @@ impl FixtureWithProjectMeta {
 
     /// Create an on-disk image of a test fixture in a temporary directory
     pub fn gen_project_from_fixture(fixtures: &FixtureWithProjectMeta) -> TempDir {
+        let (tmp_dir, _paths) = FixtureWithProjectMeta::gen_project_with_paths(fixtures);
+        tmp_dir
+    }
+
+    /// Create an on-disk image of a test fixture in a temporary
+    /// directory, returning where each fixture file was written, so that
+    /// tests can find specific files once the project is loaded.
+    pub fn gen_project_with_paths(fixtures: &FixtureWithProjectMeta) -> (TempDir, FixturePaths) {
         let tmp_dir = TempDir::new();
+        let mut paths = FxHashMap::default();
         for fixture in &fixtures.fixture {
             let path = tmp_dir.path().join(&fixture.path[1..]);
             let parent = path.parent().unwrap();
             fs::create_dir_all(parent).unwrap();
-            let mut tmp_file = File::create(path).unwrap();
+            let mut tmp_file = File::create(&path).unwrap();
             write!(tmp_file, "{}", &fixture.text).unwrap();
+            let abs_path = AbsPathBuf::assert(
+                Utf8PathBuf::from_path_buf(path).expect("could not decode UTF8"),
+            );
+            paths.insert(fixture.path.clone(), abs_path);
         }
-        tmp_dir
+        (tmp_dir, FixturePaths(paths))
     }
 
     //- /module.erl app:foo
     //- /opt/lib/comp-1.3/include/comp.hrl otp_app:/opt/lib/comp-1.3
     //- /my_app/test/file_SUITE.erl extra:test
+    //- /app_b/src/module.erl app:app_b deps:app_a,app_c
+    //- /my_app/lib/foo_bar.ex app:my_app
+    //- /my_app/src/listed.erl app:my_app applicable:true
     fn parse_meta_line(meta: &str) -> Fixture {
         assert!(meta.starts_with("//-"));
         let meta = meta["//-".len()..].trim();
@@ -324,12 +414,19 @@ impl FixtureWithProjectMeta {
             "fixture path does not start with `/`: {:?}",
             path
         );
+        let (path, is_elixir) = match elixir_module_path(&path) {
+            Some(path) => (path, true),
+            None => (path, false),
+        };
 
         let mut app_name = None;
         let mut include_dirs = Vec::new();
         let mut extra_dirs = Vec::new();
         let mut otp = None;
+        let mut deps = Vec::new();
         let mut tag = None;
+        let mut is_test_target = None;
+        let mut applicable = false;
 
         for component in components[1..].iter() {
             let (key, value) = component
@@ -354,9 +451,23 @@ impl FixtureWithProjectMeta {
                     let dir = value.to_string();
                     extra_dirs.push(dir);
                 }
+                "deps" => {
+                    deps.extend(value.split(',').map(|dep| AppName(dep.to_string())));
+                }
                 "tag" => {
                     tag = Some(value.to_string());
                 }
+                "is_test_target" => {
+                    let value = value
+                        .parse()
+                        .unwrap_or_else(|_| panic!("invalid is_test_target: {:?}", value));
+                    is_test_target = Some(value);
+                }
+                "applicable" => {
+                    applicable = value
+                        .parse()
+                        .unwrap_or_else(|_| panic!("invalid applicable: {:?}", value));
+                }
                 _ => panic!("bad component: {:?}", component),
             }
         }
@@ -373,15 +484,29 @@ impl FixtureWithProjectMeta {
             if let Some(ext) = abs_path.extension() {
                 if ext == "erl" {
                     if let Some(parent) = abs_path.parent() {
-                        let path = parent.to_path_buf();
-                        src_dirs.push(path)
+                        // Modules in an extra directory, such as test
+                        // helpers, are extra sources rather than
+                        // sources of the app, as in a rebar3 project.
+                        let is_extra_dir = parent.strip_prefix(&dir).map_or(false, |rel| {
+                            extra_dirs.iter().any(|extra| rel.as_str() == extra)
+                        });
+                        if !is_extra_dir {
+                            src_dirs.push(parent.to_path_buf())
+                        }
                     }
                 }
             }
-            (
-                None,
-                ProjectAppData::fixture_app_data(app_name, dir, include_dirs, src_dirs, extra_dirs),
-            )
+            let ebin = is_elixir.then(|| dir.join("ebin"));
+            let mut app_data =
+                ProjectAppData::fixture_app_data(app_name, dir, include_dirs, src_dirs, extra_dirs);
+            app_data.is_test_target = is_test_target;
+            if applicable {
+                // Restrict the app to the files marked as applicable,
+                // as for a Buck target
+                app_data.applicable_files = Some(FxHashSet::from_iter([abs_path]));
+            }
+            app_data.ebin = ebin;
+            (None, app_data)
         };
 
         Fixture {
@@ -389,12 +514,35 @@ impl FixtureWithProjectMeta {
             text: String::new(),
             app_data,
             otp,
+            deps,
             tag,
             tags: Vec::new(),
         }
     }
 }
 
+/// A fixture file with an `.ex` extension stands for the module the
+/// Elixir compiler would produce from it, so that tests can exercise
+/// `'Elixir.Foo'` module names. Its text is Erlang, and it is stored
+/// under the module name, so `/my_app/lib/foo_bar.ex` becomes
+/// `/my_app/lib/Elixir.FooBar.erl`. Its app gets an `ebin` directory,
+/// for the `.beam` file of the module.
+fn elixir_module_path(path: &str) -> Option<String> {
+    let (dir, file) = path.rsplit_once('/')?;
+    let stem = file.strip_suffix(".ex")?;
+    let module: String = stem
+        .split('_')
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect();
+    Some(format!("{dir}/Elixir.{module}.erl"))
+}
+
 /// Extracts ranges, marked with `<tag> </tag>` pairs from the `text`
 pub fn extract_tags(mut text: &str, tag: &str) -> (Vec<(TextRange, Option<String>)>, String) {
     let open = format!("<{tag}");
@@ -445,7 +593,9 @@ pub fn extract_tags(mut text: &str, tag: &str) -> (Vec<(TextRange, Option<String
 mod tests {
 
     use expect_test::expect;
+    use fxhash::FxHashSet;
     use paths::AbsPath;
+    use paths::AbsPathBuf;
     use paths::Utf8PathBuf;
 
     use super::FixtureWithProjectMeta;
@@ -493,6 +643,36 @@ bar() -> ok.
         assert_eq!("/bar.erl", meta1.path);
     }
 
+    #[test]
+    fn gen_project_with_paths_maps_fixture_paths() {
+        let fixture = FixtureWithProjectMeta::parse(
+            r#"
+//- /app_a/src/foo.erl
+-module(foo).
+//- /app_a/include/foo.hrl
+-define(FOO, 1).
+"#,
+        );
+        let (dir, paths) = FixtureWithProjectMeta::gen_project_with_paths(&fixture);
+
+        let foo_erl = paths.get("/app_a/src/foo.erl").unwrap();
+        assert_eq!(dir.path().join("app_a/src/foo.erl"), foo_erl.as_std_path());
+        assert_eq!("-module(foo).\n", std::fs::read_to_string(foo_erl).unwrap());
+
+        let foo_hrl = paths.get("/app_a/include/foo.hrl").unwrap();
+        assert_eq!(
+            dir.path().join("app_a/include/foo.hrl"),
+            foo_hrl.as_std_path()
+        );
+
+        assert_eq!(2, paths.iter().count());
+        assert_eq!(None, paths.get("/app_a/src/bar.erl"));
+        assert_eq!(
+            Some(foo_hrl.to_owned()),
+            paths.resolve("/app_a/include/foo.hrl", |path| Some(path.to_owned()))
+        );
+    }
+
     #[test]
     fn parse_fixture_erlang_service() {
         let fixture = FixtureWithProjectMeta::parse(
@@ -536,6 +716,104 @@ foo() -> ok.
         assert_eq!(fixture.diagnostics_enabled.use_erlang_service, false);
     }
 
+    #[test]
+    fn merge_diagnostics_enabled() {
+        let ct = FixtureWithProjectMeta::parse(
+            r#"
+//- common_test
+//- /foo.erl
+-module(foo).
+"#,
+        );
+        let eqwalizer = FixtureWithProjectMeta::parse(
+            r#"
+//- eqwalizer
+//- /foo.erl
+-module(foo).
+"#,
+        );
+        let merged = ct.diagnostics_enabled.merge(eqwalizer.diagnostics_enabled);
+        assert_eq!(merged.use_ct, true);
+        assert_eq!(merged.use_eqwalizer, true);
+        assert_eq!(merged.use_native, false);
+        assert_eq!(merged.use_erlang_service, false);
+    }
+
+    #[test]
+    fn parse_fixture_is_test_target() {
+        let fixture = FixtureWithProjectMeta::parse(
+            r#"
+//- /my_app/test/checks.erl app:my_app is_test_target:true
+-module(checks).
+//- /other_app/src/other.erl app:other_app
+-module(other).
+"#,
+        );
+        let parsed = fixture.fixture;
+        assert_eq!(Some(true), parsed[0].app_data.is_test_target);
+        assert_eq!(None, parsed[1].app_data.is_test_target);
+    }
+
+    #[test]
+    fn parse_fixture_applicable() {
+        let fixture = FixtureWithProjectMeta::parse(
+            r#"
+//- /my_app/src/listed.erl app:my_app applicable:true
+-module(listed).
+//- /other_app/src/other.erl app:other_app
+-module(other).
+"#,
+        );
+        let parsed = fixture.fixture;
+        assert_eq!(
+            Some(FxHashSet::from_iter([AbsPathBuf::assert(
+                "/my_app/src/listed.erl".into()
+            )])),
+            parsed[0].app_data.applicable_files
+        );
+        assert_eq!(None, parsed[1].app_data.applicable_files);
+    }
+
+    #[test]
+    fn parse_fixture_elixir_module() {
+        let fixture = FixtureWithProjectMeta::parse(
+            r#"
+//- /my_app/lib/foo_bar.ex app:my_app
+-module('Elixir.FooBar').
+"#,
+        );
+        let parsed = &fixture.fixture[0];
+        assert_eq!("/my_app/lib/Elixir.FooBar.erl", parsed.path);
+        assert_eq!(
+            vec![AbsPath::assert(&Utf8PathBuf::from("/my_app/lib")).normalize()],
+            parsed.app_data.abs_src_dirs
+        );
+        assert_eq!(
+            Some(AbsPath::assert(&Utf8PathBuf::from("/my_app/ebin")).normalize()),
+            parsed.app_data.ebin
+        );
+    }
+
+    #[test]
+    fn parse_fixture_extra_dir_is_not_a_src_dir() {
+        let fixture = FixtureWithProjectMeta::parse(
+            r#"
+//- /my_app/src/foo.erl app:my_app
+-module(foo).
+//- /my_app/test/helper.erl app:my_app extra:test
+-module(helper).
+"#,
+        );
+        let parsed = fixture.fixture;
+        assert_eq!(
+            vec![AbsPath::assert(&Utf8PathBuf::from("/my_app/src")).normalize()],
+            parsed[0].app_data.abs_src_dirs
+        );
+        assert_eq!(Vec::<String>::new(), parsed[0].app_data.extra_src_dirs);
+        assert!(parsed[1].app_data.abs_src_dirs.is_empty());
+        assert_eq!(vec!["test".to_string()], parsed[1].app_data.extra_src_dirs);
+    }
+
     #[test]
     fn parse_fixture_gets_app_data() {
         let fixture = FixtureWithProjectMeta::parse(
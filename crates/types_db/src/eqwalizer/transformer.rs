@@ -81,6 +81,9 @@ use super::pat::PatUnOp;
 use super::AST;
 use crate::eqwalizer::expr::RecordFieldNamed;
 
+/// Rewrites an AST. The default methods rebuild each node from its
+/// walked children, so a transformer which overrides nothing returns
+/// its input unchanged, positions included.
 pub trait Transformer<T>: Sized {
     fn transform_ast(&mut self, ast: AST) -> Result<AST, T> {
         ast.into_iter()
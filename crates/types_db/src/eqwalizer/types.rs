@@ -251,6 +251,15 @@ impl Type {
     pub fn is_dynamic(&self) -> bool {
         self == &Type::DynamicType
     }
+
+    /// Whether `dynamic()`, bounded or not, occurs anywhere within this type.
+    pub fn contains_dynamic(&self) -> bool {
+        self.traverse(&mut |ty| match ty {
+            Type::DynamicType | Type::BoundedDynamicType(_) => Err(()),
+            _ => Ok(()),
+        })
+        .is_err()
+    }
 }
 
 impl fmt::Display for Type {
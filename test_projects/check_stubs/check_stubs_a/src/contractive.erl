@@ -0,0 +1,5 @@
+-module(contractive).
+
+-export_type([list_of/1]).
+
+-type list_of(T) :: nil | {cons, T, list_of(T)}.
@@ -0,0 +1,5 @@
+-module(non_contractive).
+
+-export_type([t/0]).
+
+-type t() :: t().